@@ -0,0 +1,3 @@
+DROP TRIGGER contacts_log_change ON contacts;
+DROP FUNCTION log_contact_change;
+DROP TABLE contact_changes;
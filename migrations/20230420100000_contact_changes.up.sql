@@ -0,0 +1,19 @@
+CREATE TABLE IF NOT EXISTS contact_changes (
+    id BIGSERIAL PRIMARY KEY,
+    contact_id INTEGER NOT NULL,
+    deleted BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE OR REPLACE FUNCTION log_contact_change() RETURNS TRIGGER AS $$
+BEGIN
+    IF (TG_OP = 'DELETE') THEN
+        INSERT INTO contact_changes(contact_id, deleted) VALUES (OLD.id, TRUE);
+    ELSE
+        INSERT INTO contact_changes(contact_id, deleted) VALUES (NEW.id, FALSE);
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER contacts_log_change AFTER INSERT OR UPDATE OR DELETE ON contacts
+    FOR EACH ROW EXECUTE FUNCTION log_contact_change();
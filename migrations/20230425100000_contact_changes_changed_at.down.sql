@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS contact_changes_contact_id_idx;
+
+ALTER TABLE contact_changes DROP COLUMN IF EXISTS changed_at;
@@ -0,0 +1,3 @@
+ALTER TABLE contact_changes ADD COLUMN IF NOT EXISTS changed_at TIMESTAMPTZ NOT NULL DEFAULT now();
+
+CREATE INDEX IF NOT EXISTS contact_changes_contact_id_idx ON contact_changes(contact_id);
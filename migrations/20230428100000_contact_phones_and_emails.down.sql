@@ -0,0 +1,14 @@
+DROP VIEW IF EXISTS contacts_view;
+
+ALTER TABLE contacts ADD COLUMN phone_no BIGINT;
+ALTER TABLE contacts ADD COLUMN email VARCHAR (255);
+
+UPDATE contacts c SET
+    phone_no = COALESCE((SELECT p.number FROM contact_phones p WHERE p.contact_id = c.id ORDER BY p.is_primary DESC, p.id LIMIT 1), 0),
+    email = COALESCE((SELECT e.address FROM contact_emails e WHERE e.contact_id = c.id ORDER BY e.is_primary DESC, e.id LIMIT 1), '');
+
+ALTER TABLE contacts ALTER COLUMN phone_no SET NOT NULL;
+ALTER TABLE contacts ALTER COLUMN email SET NOT NULL;
+
+DROP TABLE IF EXISTS contact_emails;
+DROP TABLE IF EXISTS contact_phones;
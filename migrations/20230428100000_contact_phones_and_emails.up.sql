@@ -0,0 +1,45 @@
+CREATE TABLE IF NOT EXISTS contact_phones (
+    id serial PRIMARY KEY,
+    contact_id INTEGER NOT NULL REFERENCES contacts(id) ON DELETE CASCADE,
+    type VARCHAR (16) NOT NULL DEFAULT 'mobile' CHECK (type IN ('mobile', 'home', 'work', 'fax', 'other')),
+    number BIGINT NOT NULL,
+    is_primary BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE TABLE IF NOT EXISTS contact_emails (
+    id serial PRIMARY KEY,
+    contact_id INTEGER NOT NULL REFERENCES contacts(id) ON DELETE CASCADE,
+    type VARCHAR (16) NOT NULL DEFAULT 'other' CHECK (type IN ('home', 'work', 'other')),
+    address VARCHAR (255) NOT NULL,
+    is_primary BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE INDEX IF NOT EXISTS contact_phones_contact_id_idx ON contact_phones(contact_id);
+CREATE INDEX IF NOT EXISTS contact_emails_contact_id_idx ON contact_emails(contact_id);
+CREATE UNIQUE INDEX IF NOT EXISTS contact_phones_primary_idx ON contact_phones(contact_id) WHERE is_primary;
+CREATE UNIQUE INDEX IF NOT EXISTS contact_emails_primary_idx ON contact_emails(contact_id) WHERE is_primary;
+
+INSERT INTO contact_phones(contact_id, type, number, is_primary)
+    SELECT id, 'mobile', phone_no, TRUE FROM contacts;
+INSERT INTO contact_emails(contact_id, type, address, is_primary)
+    SELECT id, 'other', email, TRUE FROM contacts WHERE email <> '';
+
+ALTER TABLE contacts DROP COLUMN phone_no;
+ALTER TABLE contacts DROP COLUMN email;
+
+-- A contact along with its phones and emails, as JSON arrays with the primary entry first.
+CREATE OR REPLACE VIEW contacts_view AS
+    SELECT
+        c.id,
+        c.name,
+        COALESCE(
+            (SELECT json_agg(json_build_object('type', p.type, 'number', p.number, 'primary', p.is_primary) ORDER BY p.is_primary DESC, p.id)
+                FROM contact_phones p WHERE p.contact_id = c.id),
+            '[]'::json
+        ) AS phones,
+        COALESCE(
+            (SELECT json_agg(json_build_object('type', e.type, 'address', e.address, 'primary', e.is_primary) ORDER BY e.is_primary DESC, e.id)
+                FROM contact_emails e WHERE e.contact_id = c.id),
+            '[]'::json
+        ) AS emails
+    FROM contacts c;
@@ -0,0 +1,21 @@
+DROP VIEW IF EXISTS contacts_view;
+
+ALTER TABLE contact_phones ALTER COLUMN number TYPE BIGINT
+    USING COALESCE(NULLIF(regexp_replace(split_part(number, ';', 1), '[^0-9]', '', 'g'), ''), '0')::BIGINT;
+
+-- A contact along with its phones and emails, as JSON arrays with the primary entry first.
+CREATE OR REPLACE VIEW contacts_view AS
+    SELECT
+        c.id,
+        c.name,
+        COALESCE(
+            (SELECT json_agg(json_build_object('type', p.type, 'number', p.number, 'primary', p.is_primary) ORDER BY p.is_primary DESC, p.id)
+                FROM contact_phones p WHERE p.contact_id = c.id),
+            '[]'::json
+        ) AS phones,
+        COALESCE(
+            (SELECT json_agg(json_build_object('type', e.type, 'address', e.address, 'primary', e.is_primary) ORDER BY e.is_primary DESC, e.id)
+                FROM contact_emails e WHERE e.contact_id = c.id),
+            '[]'::json
+        ) AS emails
+    FROM contacts c;
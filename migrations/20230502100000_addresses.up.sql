@@ -0,0 +1,41 @@
+CREATE TABLE IF NOT EXISTS addresses (
+    id SERIAL PRIMARY KEY,
+    contact_id INTEGER NOT NULL REFERENCES contacts(id) ON DELETE CASCADE,
+    label VARCHAR (64),
+    street VARCHAR (255) NOT NULL DEFAULT '',
+    locality VARCHAR (255) NOT NULL DEFAULT '',
+    region VARCHAR (255) NOT NULL DEFAULT '',
+    postal_code VARCHAR (32) NOT NULL DEFAULT '',
+    -- ISO 3166 alpha-2 code
+    country VARCHAR (2) NOT NULL CHECK (country ~ '^[A-Z]{2}$'),
+    is_primary BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE INDEX IF NOT EXISTS addresses_contact_id_idx ON addresses(contact_id);
+CREATE INDEX IF NOT EXISTS addresses_country_idx ON addresses(country);
+CREATE INDEX IF NOT EXISTS addresses_locality_idx ON addresses(lower(locality));
+CREATE UNIQUE INDEX IF NOT EXISTS addresses_primary_idx ON addresses(contact_id) WHERE is_primary;
+
+DROP VIEW IF EXISTS contacts_view;
+
+-- A contact along with its phones, emails and addresses, as JSON arrays with the primary entry first.
+CREATE OR REPLACE VIEW contacts_view AS
+    SELECT
+        c.id,
+        c.name,
+        COALESCE(
+            (SELECT json_agg(json_build_object('type', p.type, 'number', p.number, 'primary', p.is_primary) ORDER BY p.is_primary DESC, p.id)
+                FROM contact_phones p WHERE p.contact_id = c.id),
+            '[]'::json
+        ) AS phones,
+        COALESCE(
+            (SELECT json_agg(json_build_object('type', e.type, 'address', e.address, 'primary', e.is_primary) ORDER BY e.is_primary DESC, e.id)
+                FROM contact_emails e WHERE e.contact_id = c.id),
+            '[]'::json
+        ) AS emails,
+        COALESCE(
+            (SELECT json_agg(json_build_object('label', a.label, 'street', a.street, 'locality', a.locality, 'region', a.region, 'postal_code', a.postal_code, 'country', a.country, 'primary', a.is_primary) ORDER BY a.is_primary DESC, a.id)
+                FROM addresses a WHERE a.contact_id = c.id),
+            '[]'::json
+        ) AS addresses
+    FROM contacts c;
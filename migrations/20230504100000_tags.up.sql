@@ -0,0 +1,42 @@
+-- Tag names are kept lower case, so they are case-insensitive.
+CREATE TABLE IF NOT EXISTS tags (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR (64) NOT NULL UNIQUE CHECK (name = lower(name))
+);
+
+CREATE TABLE IF NOT EXISTS contact_tags (
+    contact_id INTEGER NOT NULL REFERENCES contacts(id) ON DELETE CASCADE,
+    tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+    PRIMARY KEY (contact_id, tag_id)
+);
+
+CREATE INDEX IF NOT EXISTS contact_tags_tag_id_idx ON contact_tags(tag_id);
+
+DROP VIEW IF EXISTS contacts_view;
+
+-- A contact along with its phones, emails and addresses, as JSON arrays with the primary entry first, and its tags, sorted.
+CREATE OR REPLACE VIEW contacts_view AS
+    SELECT
+        c.id,
+        c.name,
+        COALESCE(
+            (SELECT json_agg(json_build_object('type', p.type, 'number', p.number, 'primary', p.is_primary) ORDER BY p.is_primary DESC, p.id)
+                FROM contact_phones p WHERE p.contact_id = c.id),
+            '[]'::json
+        ) AS phones,
+        COALESCE(
+            (SELECT json_agg(json_build_object('type', e.type, 'address', e.address, 'primary', e.is_primary) ORDER BY e.is_primary DESC, e.id)
+                FROM contact_emails e WHERE e.contact_id = c.id),
+            '[]'::json
+        ) AS emails,
+        COALESCE(
+            (SELECT json_agg(json_build_object('label', a.label, 'street', a.street, 'locality', a.locality, 'region', a.region, 'postal_code', a.postal_code, 'country', a.country, 'primary', a.is_primary) ORDER BY a.is_primary DESC, a.id)
+                FROM addresses a WHERE a.contact_id = c.id),
+            '[]'::json
+        ) AS addresses,
+        COALESCE(
+            (SELECT json_agg(t.name ORDER BY t.name)
+                FROM contact_tags ct JOIN tags t ON t.id = ct.tag_id WHERE ct.contact_id = c.id),
+            '[]'::json
+        ) AS tags
+    FROM contacts c;
@@ -0,0 +1,33 @@
+DROP VIEW IF EXISTS contacts_view;
+
+ALTER TABLE contacts DROP COLUMN job_title;
+ALTER TABLE contacts DROP COLUMN organization_id;
+
+DROP TABLE IF EXISTS organizations;
+
+-- A contact along with its phones, emails and addresses, as JSON arrays with the primary entry first, and its tags, sorted.
+CREATE OR REPLACE VIEW contacts_view AS
+    SELECT
+        c.id,
+        c.name,
+        COALESCE(
+            (SELECT json_agg(json_build_object('type', p.type, 'number', p.number, 'primary', p.is_primary) ORDER BY p.is_primary DESC, p.id)
+                FROM contact_phones p WHERE p.contact_id = c.id),
+            '[]'::json
+        ) AS phones,
+        COALESCE(
+            (SELECT json_agg(json_build_object('type', e.type, 'address', e.address, 'primary', e.is_primary) ORDER BY e.is_primary DESC, e.id)
+                FROM contact_emails e WHERE e.contact_id = c.id),
+            '[]'::json
+        ) AS emails,
+        COALESCE(
+            (SELECT json_agg(json_build_object('label', a.label, 'street', a.street, 'locality', a.locality, 'region', a.region, 'postal_code', a.postal_code, 'country', a.country, 'primary', a.is_primary) ORDER BY a.is_primary DESC, a.id)
+                FROM addresses a WHERE a.contact_id = c.id),
+            '[]'::json
+        ) AS addresses,
+        COALESCE(
+            (SELECT json_agg(t.name ORDER BY t.name)
+                FROM contact_tags ct JOIN tags t ON t.id = ct.tag_id WHERE ct.contact_id = c.id),
+            '[]'::json
+        ) AS tags
+    FROM contacts c;
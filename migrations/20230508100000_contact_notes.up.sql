@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS contact_notes (
+    id SERIAL PRIMARY KEY,
+    contact_id INTEGER NOT NULL REFERENCES contacts(id) ON DELETE CASCADE,
+    -- The username of the API user who wrote the note
+    author VARCHAR (255) NOT NULL,
+    body TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS contact_notes_contact_id_idx ON contact_notes(contact_id, created_at);
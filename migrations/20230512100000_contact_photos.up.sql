@@ -0,0 +1,8 @@
+-- The photos of the contacts, when stored in the database rather than on the filesystem
+CREATE TABLE IF NOT EXISTS contact_photos (
+    contact_id INTEGER PRIMARY KEY REFERENCES contacts(id) ON DELETE CASCADE,
+    -- The media type of both the original and the thumbnail, e.g. image/png
+    content_type VARCHAR (32) NOT NULL,
+    original BYTEA NOT NULL,
+    thumbnail BYTEA NOT NULL
+);
@@ -0,0 +1,69 @@
+ALTER TABLE contacts ADD COLUMN birthday DATE;
+
+-- The labeled dates of a contact recurring every year, e.g. a wedding anniversary
+CREATE TABLE IF NOT EXISTS contact_dates (
+    id SERIAL PRIMARY KEY,
+    contact_id INTEGER NOT NULL REFERENCES contacts(id) ON DELETE CASCADE,
+    label VARCHAR (64) NOT NULL,
+    date DATE NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS contact_dates_contact_id_idx ON contact_dates(contact_id);
+
+-- The anniversary of a date in the given year, Feb 29 falling on Feb 28 in the common years
+CREATE OR REPLACE FUNCTION anniversary(d DATE, year INTEGER) RETURNS DATE AS $$
+    SELECT CASE
+        WHEN extract(MONTH FROM d) = 2 AND extract(DAY FROM d) = 29
+            AND NOT ((year % 4 = 0 AND year % 100 <> 0) OR year % 400 = 0)
+            THEN make_date(year, 2, 28)
+        ELSE make_date(year, extract(MONTH FROM d)::INTEGER, extract(DAY FROM d)::INTEGER)
+    END;
+$$ LANGUAGE sql IMMUTABLE STRICT;
+
+-- The first anniversary of a date on or after the given day, wrapping around to the next year if needed
+CREATE OR REPLACE FUNCTION next_occurrence(d DATE, from_date DATE) RETURNS DATE AS $$
+    SELECT CASE
+        WHEN anniversary(d, extract(YEAR FROM from_date)::INTEGER) >= from_date
+            THEN anniversary(d, extract(YEAR FROM from_date)::INTEGER)
+        ELSE anniversary(d, extract(YEAR FROM from_date)::INTEGER + 1)
+    END;
+$$ LANGUAGE sql IMMUTABLE STRICT;
+
+DROP VIEW IF EXISTS contacts_view;
+
+-- A contact along with its phones, emails and addresses, as JSON arrays with the primary entry first, its tags, sorted,
+-- the organization it works at, if any, the values of its custom fields, its birthday and its labeled dates.
+CREATE OR REPLACE VIEW contacts_view AS
+    SELECT
+        c.id,
+        c.name,
+        COALESCE(
+            (SELECT json_agg(json_build_object('type', p.type, 'number', p.number, 'primary', p.is_primary) ORDER BY p.is_primary DESC, p.id)
+                FROM contact_phones p WHERE p.contact_id = c.id),
+            '[]'::json
+        ) AS phones,
+        COALESCE(
+            (SELECT json_agg(json_build_object('type', e.type, 'address', e.address, 'primary', e.is_primary) ORDER BY e.is_primary DESC, e.id)
+                FROM contact_emails e WHERE e.contact_id = c.id),
+            '[]'::json
+        ) AS emails,
+        COALESCE(
+            (SELECT json_agg(json_build_object('label', a.label, 'street', a.street, 'locality', a.locality, 'region', a.region, 'postal_code', a.postal_code, 'country', a.country, 'primary', a.is_primary) ORDER BY a.is_primary DESC, a.id)
+                FROM addresses a WHERE a.contact_id = c.id),
+            '[]'::json
+        ) AS addresses,
+        COALESCE(
+            (SELECT json_agg(t.name ORDER BY t.name)
+                FROM contact_tags ct JOIN tags t ON t.id = ct.tag_id WHERE ct.contact_id = c.id),
+            '[]'::json
+        ) AS tags,
+        (SELECT json_build_object('id', o.id, 'name', o.name, 'job_title', c.job_title)
+            FROM organizations o WHERE o.id = c.organization_id) AS organization,
+        c.custom_fields,
+        c.birthday,
+        COALESCE(
+            (SELECT json_agg(json_build_object('label', d.label, 'date', d.date) ORDER BY d.id)
+                FROM contact_dates d WHERE d.contact_id = c.id),
+            '[]'::json
+        ) AS dates
+    FROM contacts c;
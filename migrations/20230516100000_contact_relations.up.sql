@@ -0,0 +1,16 @@
+-- The typed edges between contacts, e.g. 1 reports_to 2, a bidirectional one holding the other way around too
+CREATE TABLE IF NOT EXISTS contact_relations (
+    id SERIAL PRIMARY KEY,
+    contact_id INTEGER NOT NULL REFERENCES contacts(id) ON DELETE CASCADE,
+    related_id INTEGER NOT NULL REFERENCES contacts(id) ON DELETE CASCADE,
+    type VARCHAR (32) NOT NULL CHECK (type IN ('assistant_of', 'spouse_of', 'reports_to')),
+    bidirectional BOOLEAN NOT NULL DEFAULT FALSE,
+    CHECK (contact_id <> related_id),
+    UNIQUE (contact_id, related_id, type)
+);
+
+CREATE INDEX IF NOT EXISTS contact_relations_related_id_idx ON contact_relations(related_id);
+
+-- A bidirectional relation already covers the one the other way around
+CREATE UNIQUE INDEX IF NOT EXISTS contact_relations_bidirectional_idx
+    ON contact_relations (LEAST(contact_id, related_id), GREATEST(contact_id, related_id), type) WHERE bidirectional;
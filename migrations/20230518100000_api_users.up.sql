@@ -0,0 +1,10 @@
+-- The API users, seeded from API_USERS_FILE on startup and managed by the admins afterwards
+CREATE TABLE IF NOT EXISTS api_users (
+    username VARCHAR (64) PRIMARY KEY,
+    -- The argon2 hash of the password, in the PHC string format
+    password_hash VARCHAR (255) NOT NULL,
+    role VARCHAR (16) NOT NULL CHECK (role IN ('reader', 'editor', 'admin')),
+    disabled BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
@@ -0,0 +1,14 @@
+-- The API keys of the services, minted by the admins
+CREATE TABLE IF NOT EXISTS api_keys (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR (64) NOT NULL,
+    -- The hex encoded SHA-256 hash of the key, the key itself being shown only once, when minted
+    key_hash CHAR (64) NOT NULL UNIQUE,
+    -- e.g. {contacts:read,contacts:write}
+    scopes VARCHAR (32)[] NOT NULL,
+    -- The username of the admin who minted the key
+    created_by VARCHAR (64) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    expires_at TIMESTAMPTZ,
+    last_used_at TIMESTAMPTZ
+);
@@ -0,0 +1,86 @@
+-- The address books, each one owned by an API user and holding its own contacts and organizations
+CREATE TABLE IF NOT EXISTS address_books (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR (255) NOT NULL,
+    -- The username of the owner
+    owner_id VARCHAR (64) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS address_books_owner_id_idx ON address_books(owner_id);
+
+-- The contacts and the organizations added so far go to an address book of the admin
+INSERT INTO address_books(name, owner_id)
+    SELECT 'Contacts', 'admin' WHERE EXISTS (SELECT 1 FROM contacts) OR EXISTS (SELECT 1 FROM organizations);
+
+ALTER TABLE contacts ADD COLUMN address_book_id INTEGER REFERENCES address_books(id) ON DELETE CASCADE;
+UPDATE contacts SET address_book_id = (SELECT MIN(id) FROM address_books);
+ALTER TABLE contacts ALTER COLUMN address_book_id SET NOT NULL;
+
+CREATE INDEX IF NOT EXISTS contacts_address_book_id_idx ON contacts(address_book_id);
+
+ALTER TABLE organizations ADD COLUMN address_book_id INTEGER REFERENCES address_books(id) ON DELETE CASCADE;
+UPDATE organizations SET address_book_id = (SELECT MIN(id) FROM address_books);
+ALTER TABLE organizations ALTER COLUMN address_book_id SET NOT NULL;
+
+-- The domains are unique within an address book only
+ALTER TABLE organizations DROP CONSTRAINT IF EXISTS organizations_domain_key;
+ALTER TABLE organizations ADD CONSTRAINT organizations_address_book_id_domain_key UNIQUE (address_book_id, domain);
+
+-- The changes are told apart by address book, for each one to have its own sync-tokens
+ALTER TABLE contact_changes ADD COLUMN address_book_id INTEGER;
+UPDATE contact_changes SET address_book_id = (SELECT MIN(id) FROM address_books);
+
+CREATE INDEX IF NOT EXISTS contact_changes_address_book_id_idx ON contact_changes(address_book_id);
+
+CREATE OR REPLACE FUNCTION log_contact_change() RETURNS TRIGGER AS $$
+BEGIN
+    IF (TG_OP = 'DELETE') THEN
+        INSERT INTO contact_changes(contact_id, deleted, address_book_id) VALUES (OLD.id, TRUE, OLD.address_book_id);
+    ELSE
+        INSERT INTO contact_changes(contact_id, deleted, address_book_id) VALUES (NEW.id, FALSE, NEW.address_book_id);
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP VIEW IF EXISTS contacts_view;
+
+-- A contact along with its phones, emails and addresses, as JSON arrays with the primary entry first, its tags, sorted,
+-- the organization it works at, if any, the values of its custom fields, its birthday, its labeled dates
+-- and the address book it belongs to.
+CREATE OR REPLACE VIEW contacts_view AS
+    SELECT
+        c.id,
+        c.name,
+        COALESCE(
+            (SELECT json_agg(json_build_object('type', p.type, 'number', p.number, 'primary', p.is_primary) ORDER BY p.is_primary DESC, p.id)
+                FROM contact_phones p WHERE p.contact_id = c.id),
+            '[]'::json
+        ) AS phones,
+        COALESCE(
+            (SELECT json_agg(json_build_object('type', e.type, 'address', e.address, 'primary', e.is_primary) ORDER BY e.is_primary DESC, e.id)
+                FROM contact_emails e WHERE e.contact_id = c.id),
+            '[]'::json
+        ) AS emails,
+        COALESCE(
+            (SELECT json_agg(json_build_object('label', a.label, 'street', a.street, 'locality', a.locality, 'region', a.region, 'postal_code', a.postal_code, 'country', a.country, 'primary', a.is_primary) ORDER BY a.is_primary DESC, a.id)
+                FROM addresses a WHERE a.contact_id = c.id),
+            '[]'::json
+        ) AS addresses,
+        COALESCE(
+            (SELECT json_agg(t.name ORDER BY t.name)
+                FROM contact_tags ct JOIN tags t ON t.id = ct.tag_id WHERE ct.contact_id = c.id),
+            '[]'::json
+        ) AS tags,
+        (SELECT json_build_object('id', o.id, 'name', o.name, 'job_title', c.job_title)
+            FROM organizations o WHERE o.id = c.organization_id) AS organization,
+        c.custom_fields,
+        c.birthday,
+        COALESCE(
+            (SELECT json_agg(json_build_object('label', d.label, 'date', d.date) ORDER BY d.id)
+                FROM contact_dates d WHERE d.contact_id = c.id),
+            '[]'::json
+        ) AS dates,
+        c.address_book_id
+    FROM contacts c;
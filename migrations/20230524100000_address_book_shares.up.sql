@@ -0,0 +1,13 @@
+-- The grants of access to an address book for another user or for a group, i.e. the users having a role
+CREATE TABLE IF NOT EXISTS address_book_shares (
+    id SERIAL PRIMARY KEY,
+    address_book_id INTEGER NOT NULL REFERENCES address_books(id) ON DELETE CASCADE,
+    grantee_kind VARCHAR (8) NOT NULL CHECK (grantee_kind IN ('user', 'group')),
+    -- The username of the user, or the role of the group
+    grantee VARCHAR (64) NOT NULL,
+    permission VARCHAR (8) NOT NULL CHECK (permission IN ('read', 'write')),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    UNIQUE (address_book_id, grantee_kind, grantee)
+);
+
+CREATE INDEX IF NOT EXISTS address_book_shares_grantee_idx ON address_book_shares(grantee_kind, grantee);
@@ -0,0 +1,147 @@
+use futures::TryStreamExt;
+use warp::hyper::StatusCode;
+use warp::Rejection;
+use warp::Reply;
+
+use crate::api::auth_filters::Principal;
+use crate::models::address_book::AddressBook;
+use crate::models::address_book::AddressBookId;
+use crate::models::address_book::NewAddressBook;
+use crate::models::contact::Contact;
+use crate::models::errors::Error;
+use crate::models::role::Role;
+use crate::models::share::Grantee;
+use crate::models::share::NewShare;
+use crate::models::share::Share;
+use crate::models::share::ShareId;
+use crate::repositories::address_books_repository::AddressBooksRepository;
+use crate::repositories::contacts_repository::ContactsRepository;
+use crate::repositories::photo_storage::SharedPhotoStorage;
+
+/// Lists the user's own address books, then the ones shared with the user.
+pub async fn get_address_books(
+    principal: Principal,
+    role: Option<Role>,
+    repository: impl AddressBooksRepository,
+) -> Result<impl Reply, Rejection> {
+    let mut address_books: Vec<AddressBook> = repository
+        .get_address_books(principal.username())
+        .await
+        .map_err(warp::reject::custom)?;
+    address_books.extend(
+        repository
+            .get_shared_address_books(principal.username(), role)
+            .await
+            .map_err(warp::reject::custom)?,
+    );
+    Ok(warp::reply::json(&address_books))
+}
+
+pub async fn add_address_book(
+    principal: Principal,
+    new_address_book: NewAddressBook,
+    mut repository: impl AddressBooksRepository,
+) -> Result<impl Reply, Rejection> {
+    let new_address_book: NewAddressBook =
+        new_address_book.validated().map_err(warp::reject::custom)?;
+    repository
+        .add_address_book(principal.username(), new_address_book)
+        .await
+        .map(|address_book: AddressBook| {
+            warp::reply::with_status(warp::reply::json(&address_book), StatusCode::CREATED)
+        })
+        .map_err(warp::reject::custom)
+}
+
+pub async fn get_address_book(
+    id: AddressBookId,
+    repository: impl AddressBooksRepository,
+) -> Result<impl Reply, Rejection> {
+    repository
+        .get_address_book(id)
+        .await
+        .map_err(warp::reject::custom)?
+        .map(|address_book: AddressBook| warp::reply::json(&address_book))
+        .ok_or(warp::reject::custom(Error::UnknownAddressBook(id.0)))
+}
+
+pub async fn update_address_book(
+    id: AddressBookId,
+    address_book: NewAddressBook,
+    mut repository: impl AddressBooksRepository,
+) -> Result<impl Reply, Rejection> {
+    let address_book: NewAddressBook = address_book.validated().map_err(warp::reject::custom)?;
+    repository
+        .update_address_book(id, address_book)
+        .await
+        .map(|_| StatusCode::NO_CONTENT)
+        .map_err(warp::reject::custom)
+}
+
+/// Deletes an address book along with its contacts, the photos of which are deleted first.
+pub async fn delete_address_book(
+    id: AddressBookId,
+    mut repository: impl ContactsRepository + AddressBooksRepository,
+    photo_storage: SharedPhotoStorage,
+) -> Result<impl Reply, Rejection> {
+    let contacts: Vec<Contact> = repository
+        .get_all_as_stream(id)
+        .try_collect()
+        .await
+        .map_err(warp::reject::custom)?;
+    for contact in contacts {
+        photo_storage
+            .delete_photo(contact.id)
+            .await
+            .map_err(warp::reject::custom)?;
+    }
+    repository
+        .delete_address_book(id)
+        .await
+        .map(|_| StatusCode::NO_CONTENT)
+        .map_err(warp::reject::custom)
+}
+
+pub async fn get_shares(
+    id: AddressBookId,
+    repository: impl AddressBooksRepository,
+) -> Result<impl Reply, Rejection> {
+    repository
+        .get_shares(id)
+        .await
+        .map(|shares: Vec<Share>| warp::reply::json(&shares))
+        .map_err(warp::reject::custom)
+}
+
+pub async fn add_share(
+    id: AddressBookId,
+    principal: Principal,
+    new_share: NewShare,
+    mut repository: impl AddressBooksRepository,
+) -> Result<impl Reply, Rejection> {
+    let new_share: NewShare = new_share.validated().map_err(warp::reject::custom)?;
+    if new_share.grantee == Grantee::User(principal.username().to_string()) {
+        return Err(warp::reject::custom(Error::InvalidBody(
+            "An address book cannot be shared with its owner".to_string(),
+        )));
+    }
+    repository
+        .add_share(id, new_share)
+        .await
+        .map(|share: Share| {
+            warp::reply::with_status(warp::reply::json(&share), StatusCode::CREATED)
+        })
+        .map_err(warp::reject::custom)
+}
+
+pub async fn delete_share(
+    id: AddressBookId,
+    share_id: i32,
+    mut repository: impl AddressBooksRepository,
+) -> Result<impl Reply, Rejection> {
+    repository
+        .delete_share(id, ShareId(share_id))
+        .await
+        .map(|_| StatusCode::NO_CONTENT)
+        .map_err(warp::reject::custom)
+}
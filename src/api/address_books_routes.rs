@@ -0,0 +1,414 @@
+use std::convert::Infallible;
+
+use warp::filters::path::FullPath;
+use warp::Filter;
+use warp::Rejection;
+use warp::Reply;
+
+use crate::api::address_books_handlers;
+use crate::api::auth_filters::with_authorized_principal;
+use crate::api::auth_filters::Principal;
+use crate::api::auth_filters::SharedAuthMiddleware;
+use crate::api::photos_routes::with_photo_storage;
+use crate::models::address_book::AddressBook;
+use crate::models::address_book::AddressBookId;
+use crate::models::errors::Error;
+use crate::models::role::Role;
+use crate::models::share::Share;
+use crate::models::share::SharePermission;
+use crate::repositories::address_books_repository::AddressBooksRepository;
+use crate::repositories::contacts_repository::ContactsRepository;
+use crate::repositories::photo_storage::SharedPhotoStorage;
+
+const MAX_JSON_PAYLOAD_SIZE: u64 = 1024 * 16;
+
+/// The address books of the authenticated user, each one holding its own contacts.
+pub fn get_address_books_routes<R>(
+    repository: R,
+    photo_storage: SharedPhotoStorage,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: ContactsRepository + AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    get_address_books_route(repository.clone(), auth_middleware.clone())
+        .or(add_address_book_route(
+            repository.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(get_address_book_route(
+            repository.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(update_address_book_route(
+            repository.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(delete_address_book_route(
+            repository.clone(),
+            photo_storage,
+            auth_middleware.clone(),
+        ))
+        .or(get_shares_route(
+            repository.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(add_share_route(repository.clone(), auth_middleware.clone()))
+        .or(delete_share_route(repository, auth_middleware))
+}
+
+fn get_address_books_route<R>(
+    repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("address-books")
+        .and(warp::get())
+        .and(with_grantee(auth_middleware, Role::Reader))
+        .and(with_repository(repository))
+        .and_then(address_books_handlers::get_address_books)
+}
+
+fn add_address_book_route<R>(
+    repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("address-books")
+        .and(warp::post())
+        .and(with_authorized_principal(auth_middleware, Role::Editor))
+        .and(warp::body::content_length_limit(MAX_JSON_PAYLOAD_SIZE))
+        .and(warp::body::json())
+        .and(with_repository(repository))
+        .and_then(address_books_handlers::add_address_book)
+}
+
+fn get_address_book_route<R>(
+    repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("address-books" / AddressBookId)
+        .and(warp::get())
+        .and(require_address_book(
+            repository.clone(),
+            auth_middleware,
+            Role::Reader,
+        ))
+        .and(with_repository(repository))
+        .and_then(address_books_handlers::get_address_book)
+}
+
+fn update_address_book_route<R>(
+    repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("address-books" / AddressBookId)
+        .and(warp::put())
+        .and(require_own_address_book(
+            repository.clone(),
+            auth_middleware,
+            Role::Editor,
+        ))
+        .and(warp::body::content_length_limit(MAX_JSON_PAYLOAD_SIZE))
+        .and(warp::body::json())
+        .and(with_repository(repository))
+        .and_then(address_books_handlers::update_address_book)
+}
+
+fn delete_address_book_route<R>(
+    repository: R,
+    photo_storage: SharedPhotoStorage,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: ContactsRepository + AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("address-books" / AddressBookId)
+        .and(warp::delete())
+        .and(require_own_address_book(
+            repository.clone(),
+            auth_middleware,
+            Role::Admin,
+        ))
+        .and(with_repository(repository))
+        .and(with_photo_storage(photo_storage))
+        .and_then(address_books_handlers::delete_address_book)
+}
+
+fn get_shares_route<R>(
+    repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("address-books" / AddressBookId / "shares")
+        .and(warp::get())
+        .and(require_own_address_book(
+            repository.clone(),
+            auth_middleware,
+            Role::Reader,
+        ))
+        .and(with_repository(repository))
+        .and_then(address_books_handlers::get_shares)
+}
+
+fn add_share_route<R>(
+    repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("address-books" / AddressBookId / "shares")
+        .and(warp::post())
+        .and(with_own_address_book(
+            repository.clone(),
+            auth_middleware,
+            Role::Editor,
+        ))
+        .and(warp::body::content_length_limit(MAX_JSON_PAYLOAD_SIZE))
+        .and(warp::body::json())
+        .and(with_repository(repository))
+        .and_then(address_books_handlers::add_share)
+}
+
+fn delete_share_route<R>(
+    repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("address-books" / AddressBookId / "shares" / i32)
+        .and(warp::delete())
+        .and(require_own_address_book(
+            repository.clone(),
+            auth_middleware,
+            Role::Editor,
+        ))
+        .and(with_repository(repository))
+        .and_then(address_books_handlers::delete_share)
+}
+
+/// Same as `with_authorized_principal`, but also extracts the role of the user on whose behalf the request is made,
+/// if any, i.e. the group it belongs to, as the address books are shared with groups too.
+/// The users of the identity provider belong to no group, not being API users.
+pub fn with_grantee(
+    auth_middleware: SharedAuthMiddleware,
+    required: Role,
+) -> impl Filter<Extract = (Principal, Option<Role>), Error = Rejection> + Clone {
+    with_authorized_principal(auth_middleware.clone(), required)
+        .and_then(move |principal: Principal| {
+            let auth_middleware: SharedAuthMiddleware = auth_middleware.clone();
+            async move {
+                if let Principal::AccessToken(_) = principal {
+                    return Ok((principal, None));
+                }
+                auth_middleware
+                    .role(principal.username())
+                    .await
+                    .map(|role: Option<Role>| (principal, role))
+                    .map_err(warp::reject::custom)
+            }
+        })
+        .untuple_one()
+}
+
+/// Same as `with_authorized_principal`, but also requires the address book in the path, e.g. `/address-books/1/contacts`
+/// or `/carddav/1/`, to be one of the user's, or of the admin who minted the API key, or to be shared with the user
+/// by a grant allowing the given role. Rejects with `Error::UnknownAddressBook` if it's neither, the same as if it
+/// didn't exist, or with `Error::Forbidden` if the grants of the user don't allow the role.
+/// Placed after the path of a route, which has already extracted the id of the address book, it reads it again.
+pub fn with_address_book<R>(
+    repository: R,
+    auth_middleware: SharedAuthMiddleware,
+    required: Role,
+) -> impl Filter<Extract = (Principal,), Error = Rejection> + Clone
+where
+    R: AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    with_address_book_access(repository, auth_middleware, required, true)
+}
+
+/// Same as `with_address_book`, for the routes which don't need the principal.
+pub fn require_address_book<R>(
+    repository: R,
+    auth_middleware: SharedAuthMiddleware,
+    required: Role,
+) -> impl Filter<Extract = (), Error = Rejection> + Clone
+where
+    R: AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    with_address_book(repository, auth_middleware, required)
+        .map(|_principal: Principal| ())
+        .untuple_one()
+}
+
+/// Same as `with_address_book`, but ignores the grants, e.g. for sharing the address book, left to its owner.
+fn with_own_address_book<R>(
+    repository: R,
+    auth_middleware: SharedAuthMiddleware,
+    required: Role,
+) -> impl Filter<Extract = (Principal,), Error = Rejection> + Clone
+where
+    R: AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    with_address_book_access(repository, auth_middleware, required, false)
+}
+
+/// Same as `with_own_address_book`, for the routes which don't need the principal.
+fn require_own_address_book<R>(
+    repository: R,
+    auth_middleware: SharedAuthMiddleware,
+    required: Role,
+) -> impl Filter<Extract = (), Error = Rejection> + Clone
+where
+    R: AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    with_own_address_book(repository, auth_middleware, required)
+        .map(|_principal: Principal| ())
+        .untuple_one()
+}
+
+fn with_address_book_access<R>(
+    repository: R,
+    auth_middleware: SharedAuthMiddleware,
+    required: Role,
+    shared: bool,
+) -> impl Filter<Extract = (Principal,), Error = Rejection> + Clone
+where
+    R: AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    with_grantee(auth_middleware, required)
+        .and(warp::path::full())
+        .and_then(
+            move |principal: Principal, role: Option<Role>, path: FullPath| {
+                let repository: R = repository.clone();
+                async move {
+                    let id: AddressBookId = address_book_id_of_path(path.as_str())
+                        .ok_or_else(warp::reject::not_found)?;
+                    check_access(&repository, &principal, role, id, required, shared)
+                        .await
+                        .map(|_| principal)
+                        .map_err(warp::reject::custom)
+                }
+            },
+        )
+}
+
+/// Lets the owner in, as well as the grantees if `shared`, provided their grants allow the required role.
+async fn check_access(
+    repository: &impl AddressBooksRepository,
+    principal: &Principal,
+    role: Option<Role>,
+    id: AddressBookId,
+    required: Role,
+    shared: bool,
+) -> Result<(), Error> {
+    let address_book: AddressBook = repository
+        .get_address_book(id)
+        .await?
+        .ok_or(Error::UnknownAddressBook(id.0))?;
+    if address_book.owner_id == principal.username() {
+        return Ok(());
+    }
+    let permission: Option<SharePermission> = repository
+        .get_shares(id)
+        .await?
+        .into_iter()
+        .filter(|share: &Share| share.grantee.includes(principal.username(), role))
+        .map(|share: Share| share.permission)
+        .max();
+    match permission {
+        Some(permission) if shared && permission.allows(required) => Ok(()),
+        Some(permission) if shared => Err(Error::Forbidden(format!(
+            "{} has the {} permission on the address book {}, while the {required} role is required",
+            principal.name(),
+            permission.as_str(),
+            id.0
+        ))),
+        Some(_) => Err(Error::Forbidden(format!(
+            "Only the owner of the address book {} may do so",
+            id.0
+        ))),
+        None => Err(Error::UnknownAddressBook(id.0)),
+    }
+}
+
+/// The id of the address book in a path, as its second segment, e.g. `1` in `/address-books/1/contacts/2`.
+fn address_book_id_of_path(path: &str) -> Option<AddressBookId> {
+    path.split('/')
+        .filter(|segment: &&str| !segment.is_empty())
+        .nth(1)
+        .and_then(|segment: &str| segment.parse::<AddressBookId>().ok())
+}
+
+fn with_repository<R>(repository: R) -> impl Filter<Extract = (R,), Error = Infallible> + Clone
+where
+    R: AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::any().map(move || repository.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::address_book::NewAddressBook;
+    use crate::models::api_key::ApiKeyScope;
+    use crate::models::token::AccessToken;
+    use crate::repositories::contacts_in_memory_repository::ContactsInMemoryRepository;
+
+    #[tokio::test]
+    async fn test_access_token_is_not_an_api_user() {
+        let mut repository: ContactsInMemoryRepository = ContactsInMemoryRepository::new();
+        let address_book: AddressBook = repository
+            .add_address_book(
+                "admin",
+                NewAddressBook {
+                    name: "Admin".to_string(),
+                },
+            )
+            .await
+            .unwrap();
+
+        // The user `admin` of the identity provider is not the API user `admin`
+        let principal: Principal = Principal::AccessToken(AccessToken::new(
+            "admin",
+            vec![ApiKeyScope::ContactsRead, ApiKeyScope::ContactsWrite],
+        ));
+        assert_eq!("oauth2:admin", principal.username());
+        assert!(matches!(
+            check_access(
+                &repository,
+                &principal,
+                None,
+                address_book.id,
+                Role::Reader,
+                true
+            )
+            .await,
+            Err(Error::UnknownAddressBook(_))
+        ));
+        assert!(check_access(
+            &repository,
+            &Principal::User("admin".to_string()),
+            None,
+            address_book.id,
+            Role::Reader,
+            true
+        )
+        .await
+        .is_ok());
+    }
+}
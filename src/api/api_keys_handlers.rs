@@ -0,0 +1,59 @@
+use warp::http::header::CACHE_CONTROL;
+use warp::hyper::StatusCode;
+use warp::Rejection;
+use warp::Reply;
+
+use crate::models::api_key::generate_key;
+use crate::models::api_key::hash_key;
+use crate::models::api_key::ApiKey;
+use crate::models::api_key::ApiKeyId;
+use crate::models::api_key::MintedApiKey;
+use crate::models::api_key::NewApiKey;
+use crate::repositories::api_keys_repository::SharedApiKeysRepository;
+
+/// The minted key is not to be cached by the clients or the proxies, as it's a secret.
+const NO_STORE: &str = "no-store";
+
+pub async fn get_api_keys(
+    _username: String,
+    api_keys_repository: SharedApiKeysRepository,
+) -> Result<impl Reply, Rejection> {
+    api_keys_repository
+        .get_api_keys()
+        .await
+        .map(|api_keys: Vec<ApiKey>| warp::reply::json(&api_keys))
+        .map_err(warp::reject::custom)
+}
+
+pub async fn add_api_key(
+    username: String,
+    new_api_key: NewApiKey,
+    api_keys_repository: SharedApiKeysRepository,
+) -> Result<impl Reply, Rejection> {
+    let new_api_key: NewApiKey = new_api_key.validated().map_err(warp::reject::custom)?;
+    let key: String = generate_key();
+    let api_key: ApiKey = api_keys_repository
+        .add_api_key(new_api_key, hash_key(&key), &username)
+        .await
+        .map_err(warp::reject::custom)?;
+    Ok(warp::reply::with_status(
+        warp::reply::with_header(
+            warp::reply::json(&MintedApiKey { api_key, key }),
+            CACHE_CONTROL,
+            NO_STORE,
+        ),
+        StatusCode::CREATED,
+    ))
+}
+
+pub async fn delete_api_key(
+    id: i32,
+    _username: String,
+    api_keys_repository: SharedApiKeysRepository,
+) -> Result<impl Reply, Rejection> {
+    api_keys_repository
+        .delete_api_key(ApiKeyId(id))
+        .await
+        .map(|_| StatusCode::NO_CONTENT)
+        .map_err(warp::reject::custom)
+}
@@ -0,0 +1,67 @@
+use std::convert::Infallible;
+
+use warp::Filter;
+use warp::Rejection;
+use warp::Reply;
+
+use crate::api::api_keys_handlers;
+use crate::api::auth_filters::with_role;
+use crate::api::auth_filters::SharedAuthMiddleware;
+use crate::models::role::Role;
+use crate::repositories::api_keys_repository::SharedApiKeysRepository;
+
+const MAX_JSON_PAYLOAD_SIZE: u64 = 1024 * 16;
+
+/// The management of the API keys of the services, by the admins only.
+pub fn get_api_keys_routes(
+    api_keys_repository: SharedApiKeysRepository,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    get_api_keys_route(api_keys_repository.clone(), auth_middleware.clone())
+        .or(add_api_key_route(
+            api_keys_repository.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(delete_api_key_route(api_keys_repository, auth_middleware))
+}
+
+fn get_api_keys_route(
+    api_keys_repository: SharedApiKeysRepository,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    warp::path!("api-keys")
+        .and(warp::get())
+        .and(with_role(auth_middleware, Role::Admin))
+        .and(with_repository(api_keys_repository))
+        .and_then(api_keys_handlers::get_api_keys)
+}
+
+fn add_api_key_route(
+    api_keys_repository: SharedApiKeysRepository,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    warp::path!("api-keys")
+        .and(warp::post())
+        .and(with_role(auth_middleware, Role::Admin))
+        .and(warp::body::content_length_limit(MAX_JSON_PAYLOAD_SIZE))
+        .and(warp::body::json())
+        .and(with_repository(api_keys_repository))
+        .and_then(api_keys_handlers::add_api_key)
+}
+
+fn delete_api_key_route(
+    api_keys_repository: SharedApiKeysRepository,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    warp::path!("api-keys" / i32)
+        .and(warp::delete())
+        .and(with_role(auth_middleware, Role::Admin))
+        .and(with_repository(api_keys_repository))
+        .and_then(api_keys_handlers::delete_api_key)
+}
+
+fn with_repository(
+    api_keys_repository: SharedApiKeysRepository,
+) -> impl Filter<Extract = (SharedApiKeysRepository,), Error = Infallible> + Clone {
+    warp::any().map(move || api_keys_repository.clone())
+}
@@ -0,0 +1,98 @@
+use warp::hyper::StatusCode;
+use warp::Rejection;
+use warp::Reply;
+
+use crate::models::api_user::ApiUser;
+use crate::models::api_user::NewApiUser;
+use crate::models::api_user::NewPassword;
+use crate::models::errors::Error;
+use crate::repositories::api_users_repository::SharedApiUsersRepository;
+
+pub async fn get_api_users(
+    _username: String,
+    api_users_repository: SharedApiUsersRepository,
+) -> Result<impl Reply, Rejection> {
+    api_users_repository
+        .get_api_users()
+        .await
+        .map(|api_users: Vec<ApiUser>| warp::reply::json(&api_users))
+        .map_err(warp::reject::custom)
+}
+
+pub async fn add_api_user(
+    _username: String,
+    new_api_user: NewApiUser,
+    api_users_repository: SharedApiUsersRepository,
+) -> Result<impl Reply, Rejection> {
+    let new_api_user: NewApiUser = new_api_user.validated().map_err(warp::reject::custom)?;
+    api_users_repository
+        .add_api_user(new_api_user)
+        .await
+        .map(|api_user: ApiUser| {
+            warp::reply::with_status(warp::reply::json(&api_user), StatusCode::CREATED)
+        })
+        .map_err(warp::reject::custom)
+}
+
+pub async fn disable_api_user(
+    target: String,
+    username: String,
+    api_users_repository: SharedApiUsersRepository,
+) -> Result<impl Reply, Rejection> {
+    not_self(&target, &username)?;
+    api_users_repository
+        .set_api_user_disabled(&target, true)
+        .await
+        .map(|_| StatusCode::NO_CONTENT)
+        .map_err(warp::reject::custom)
+}
+
+pub async fn enable_api_user(
+    target: String,
+    _username: String,
+    api_users_repository: SharedApiUsersRepository,
+) -> Result<impl Reply, Rejection> {
+    api_users_repository
+        .set_api_user_disabled(&target, false)
+        .await
+        .map(|_| StatusCode::NO_CONTENT)
+        .map_err(warp::reject::custom)
+}
+
+pub async fn update_api_user_password(
+    target: String,
+    _username: String,
+    new_password: NewPassword,
+    api_users_repository: SharedApiUsersRepository,
+) -> Result<impl Reply, Rejection> {
+    let new_password: NewPassword = new_password.validated().map_err(warp::reject::custom)?;
+    api_users_repository
+        .set_api_user_password(&target, &new_password.password)
+        .await
+        .map(|_| StatusCode::NO_CONTENT)
+        .map_err(warp::reject::custom)
+}
+
+pub async fn delete_api_user(
+    target: String,
+    username: String,
+    api_users_repository: SharedApiUsersRepository,
+) -> Result<impl Reply, Rejection> {
+    not_self(&target, &username)?;
+    api_users_repository
+        .delete_api_user(&target)
+        .await
+        .map(|_| StatusCode::NO_CONTENT)
+        .map_err(warp::reject::custom)
+}
+
+/// Keeps the admins from locking themselves out, by disabling or deleting their own user.
+fn not_self(target: &str, username: &str) -> Result<(), Rejection> {
+    if target == username {
+        Err(warp::reject::custom(Error::Conflict(format!(
+            "{username} cannot disable or delete its own user"
+        ))))
+    } else {
+        Ok(())
+    }
+}
@@ -0,0 +1,114 @@
+use std::convert::Infallible;
+
+use warp::Filter;
+use warp::Rejection;
+use warp::Reply;
+
+use crate::api::api_users_handlers;
+use crate::api::auth_filters::with_role;
+use crate::api::auth_filters::SharedAuthMiddleware;
+use crate::models::role::Role;
+use crate::repositories::api_users_repository::SharedApiUsersRepository;
+
+const MAX_JSON_PAYLOAD_SIZE: u64 = 1024 * 16;
+
+/// The management of the API users, by the admins only.
+pub fn get_api_users_routes(
+    api_users_repository: SharedApiUsersRepository,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    get_api_users_route(api_users_repository.clone(), auth_middleware.clone())
+        .or(add_api_user_route(
+            api_users_repository.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(disable_api_user_route(
+            api_users_repository.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(enable_api_user_route(
+            api_users_repository.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(update_api_user_password_route(
+            api_users_repository.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(delete_api_user_route(api_users_repository, auth_middleware))
+}
+
+fn get_api_users_route(
+    api_users_repository: SharedApiUsersRepository,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    warp::path!("api-users")
+        .and(warp::get())
+        .and(with_role(auth_middleware, Role::Admin))
+        .and(with_repository(api_users_repository))
+        .and_then(api_users_handlers::get_api_users)
+}
+
+fn add_api_user_route(
+    api_users_repository: SharedApiUsersRepository,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    warp::path!("api-users")
+        .and(warp::post())
+        .and(with_role(auth_middleware, Role::Admin))
+        .and(warp::body::content_length_limit(MAX_JSON_PAYLOAD_SIZE))
+        .and(warp::body::json())
+        .and(with_repository(api_users_repository))
+        .and_then(api_users_handlers::add_api_user)
+}
+
+fn disable_api_user_route(
+    api_users_repository: SharedApiUsersRepository,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    warp::path!("api-users" / String / "disable")
+        .and(warp::post())
+        .and(with_role(auth_middleware, Role::Admin))
+        .and(with_repository(api_users_repository))
+        .and_then(api_users_handlers::disable_api_user)
+}
+
+fn enable_api_user_route(
+    api_users_repository: SharedApiUsersRepository,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    warp::path!("api-users" / String / "enable")
+        .and(warp::post())
+        .and(with_role(auth_middleware, Role::Admin))
+        .and(with_repository(api_users_repository))
+        .and_then(api_users_handlers::enable_api_user)
+}
+
+fn update_api_user_password_route(
+    api_users_repository: SharedApiUsersRepository,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    warp::path!("api-users" / String / "password")
+        .and(warp::put())
+        .and(with_role(auth_middleware, Role::Admin))
+        .and(warp::body::content_length_limit(MAX_JSON_PAYLOAD_SIZE))
+        .and(warp::body::json())
+        .and(with_repository(api_users_repository))
+        .and_then(api_users_handlers::update_api_user_password)
+}
+
+fn delete_api_user_route(
+    api_users_repository: SharedApiUsersRepository,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    warp::path!("api-users" / String)
+        .and(warp::delete())
+        .and(with_role(auth_middleware, Role::Admin))
+        .and(with_repository(api_users_repository))
+        .and_then(api_users_handlers::delete_api_user)
+}
+
+fn with_repository(
+    api_users_repository: SharedApiUsersRepository,
+) -> impl Filter<Extract = (SharedApiUsersRepository,), Error = Infallible> + Clone {
+    warp::any().map(move || api_users_repository.clone())
+}
@@ -0,0 +1,202 @@
+use std::convert::Infallible;
+use std::net::IpAddr;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use warp::http::header::AUTHORIZATION;
+use warp::Filter;
+use warp::Rejection;
+
+use crate::middleware::auth::AuthMiddleware;
+use crate::middleware::jwt::TokenService;
+use crate::models::api_key::ApiKey;
+use crate::models::api_key::ApiKeyScope;
+use crate::models::errors::Error;
+use crate::models::role::Role;
+use crate::models::token::AccessToken;
+
+/// The auth middleware shared by all the routes requiring authentication.
+pub type SharedAuthMiddleware = Arc<dyn AuthMiddleware + Send + Sync>;
+
+/// The token service shared by the routes issuing the JWTs.
+pub type SharedTokenService = Arc<TokenService>;
+
+/// The header carrying the API key of a service.
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Who a request is authenticated as: a user, by its credentials or a token issued to it, a service, by its API key,
+/// or a user of the identity provider, by an access token the provider issued to it.
+#[derive(Debug, Clone)]
+pub enum Principal {
+    User(String),
+    ApiKey(ApiKey),
+    AccessToken(AccessToken),
+}
+
+impl Principal {
+    /// The username of the user, or the name of the API key, e.g. `api-key:billing-sync`,
+    /// or the username of the user of the identity provider, e.g. `oauth2:alice`.
+    pub fn name(&self) -> String {
+        match self {
+            Principal::User(username) => username.clone(),
+            Principal::ApiKey(api_key) => api_key.principal_name(),
+            Principal::AccessToken(access_token) => access_token.username.clone(),
+        }
+    }
+
+    /// The user on whose behalf the request is made, e.g. owning the address books it may access:
+    /// the user itself, the admin who minted the API key, or the user of the identity provider, e.g. `oauth2:alice`,
+    /// never taken for an API user.
+    pub fn username(&self) -> &str {
+        match self {
+            Principal::User(username) => username,
+            Principal::ApiKey(api_key) => &api_key.created_by,
+            Principal::AccessToken(access_token) => &access_token.username,
+        }
+    }
+}
+
+/// Extracts who the request is authenticated as, by the API key in the `X-Api-Key` header, if any,
+/// otherwise by the HTTP Authorization header, by HTTP Basic Auth or by a bearer token, issued by the API or,
+/// in the OAuth 2.0 resource-server mode, by the identity provider.
+/// Rejects with `Error::InvalidToken` if the bearer token is not valid, with `Error::TooManyAttempts` if the user or
+/// the client is locked out, or with `Error::Unauthorized` if the headers are missing or the credentials are not valid.
+pub fn with_authenticated_principal(
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (Principal,), Error = Rejection> + Clone {
+    warp::header::optional::<String>(API_KEY_HEADER)
+        .and(warp::header::optional::<String>(AUTHORIZATION.as_str()))
+        .and(with_client_ip())
+        .and_then(
+            move |api_key: Option<String>,
+                  auth_header: Option<String>,
+                  client_ip: Option<IpAddr>| {
+                let auth_middleware: SharedAuthMiddleware = auth_middleware.clone();
+                async move {
+                    if let Some(api_key) = api_key {
+                        return auth_middleware
+                            .authenticate_api_key(&api_key)
+                            .await
+                            .map(Principal::ApiKey)
+                            .map_err(|_| warp::reject::custom(Error::Unauthorized));
+                    }
+                    let auth_header: String =
+                        auth_header.ok_or(warp::reject::custom(Error::Unauthorized))?;
+                    if let Some(access_token) = auth_middleware
+                        .authenticate_access_token(&auth_header)
+                        .await
+                        .map_err(auth_rejection)?
+                    {
+                        return Ok(Principal::AccessToken(access_token));
+                    }
+                    auth_middleware
+                        .authenticate(auth_header, client_ip)
+                        .await
+                        .map(Principal::User)
+                        .map_err(auth_rejection)
+                }
+            },
+        )
+}
+
+/// Same as `with_authenticated_principal`, but by HTTP Basic Auth only, extracting the username,
+/// e.g. to trade the credentials for a token.
+pub fn with_basic_authenticated_user(
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (String,), Error = Rejection> + Clone {
+    warp::header::optional::<String>(AUTHORIZATION.as_str())
+        .and(with_client_ip())
+        .and_then(
+            move |auth_header: Option<String>, client_ip: Option<IpAddr>| {
+                let auth_middleware: SharedAuthMiddleware = auth_middleware.clone();
+                async move {
+                    let auth_header: String =
+                        auth_header.ok_or(warp::reject::custom(Error::Unauthorized))?;
+                    auth_middleware
+                        .authenticate_basic(auth_header, client_ip)
+                        .await
+                        .map_err(auth_rejection)
+                }
+            },
+        )
+}
+
+/// The IP of the client, if known.
+pub fn with_client_ip() -> impl Filter<Extract = (Option<IpAddr>,), Error = Infallible> + Clone {
+    warp::addr::remote().map(|addr: Option<SocketAddr>| addr.map(|addr: SocketAddr| addr.ip()))
+}
+
+/// Rejects a failed authentication as such, but for a bearer token which is not valid or a locked out login,
+/// which are told apart.
+fn auth_rejection(err: Error) -> Rejection {
+    match err {
+        Error::InvalidToken(_) | Error::TooManyAttempts { .. } => warp::reject::custom(err),
+        _ => warp::reject::custom(Error::Unauthorized),
+    }
+}
+
+/// Same as `with_authenticated_principal`, but also requires the user to have the given role, or one allowed to do
+/// more, or the API key or the access token to have the scope standing for the role.
+/// Rejects with `Error::Forbidden` if it doesn't.
+pub fn with_authorized_principal(
+    auth_middleware: SharedAuthMiddleware,
+    required: Role,
+) -> impl Filter<Extract = (Principal,), Error = Rejection> + Clone {
+    with_authenticated_principal(auth_middleware.clone()).and_then(move |principal: Principal| {
+        let auth_middleware: SharedAuthMiddleware = auth_middleware.clone();
+        async move {
+            match principal {
+                Principal::User(username) => match auth_middleware
+                    .role(&username)
+                    .await
+                    .map_err(warp::reject::custom)?
+                {
+                    Some(role) if role.allows(required) => Ok(Principal::User(username)),
+                    Some(role) => Err(warp::reject::custom(Error::Forbidden(format!(
+                        "{username} has the {role} role, while the {required} role is required"
+                    )))),
+                    None => Err(warp::reject::custom(Error::Unauthorized)),
+                },
+                Principal::ApiKey(api_key) if api_key.allows(required) => {
+                    Ok(Principal::ApiKey(api_key))
+                }
+                Principal::ApiKey(api_key) => Err(warp::reject::custom(Error::Forbidden(
+                    match ApiKeyScope::for_role(required) {
+                        Some(scope) => format!(
+                            "The API key {} doesn't have the {} scope",
+                            api_key.name,
+                            scope.as_str()
+                        ),
+                        None => {
+                            format!("The {required} role is required, which no API key stands for")
+                        }
+                    },
+                ))),
+                Principal::AccessToken(access_token) if access_token.allows(required) => {
+                    Ok(Principal::AccessToken(access_token))
+                }
+                Principal::AccessToken(access_token) => Err(warp::reject::custom(
+                    Error::Forbidden(match ApiKeyScope::for_role(required) {
+                        Some(scope) => format!(
+                            "The access token of {} doesn't have the {} scope",
+                            access_token.subject,
+                            scope.as_str()
+                        ),
+                        None => format!(
+                            "The {required} role is required, which no access token stands for"
+                        ),
+                    }),
+                )),
+            }
+        }
+    })
+}
+
+/// Same as `with_authorized_principal`, but extracts the username of the user, or the name of the API key.
+pub fn with_role(
+    auth_middleware: SharedAuthMiddleware,
+    required: Role,
+) -> impl Filter<Extract = (String,), Error = Rejection> + Clone {
+    with_authorized_principal(auth_middleware, required)
+        .map(|principal: Principal| principal.name())
+}
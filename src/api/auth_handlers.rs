@@ -0,0 +1,50 @@
+use warp::http::header::CACHE_CONTROL;
+use warp::Rejection;
+use warp::Reply;
+
+use crate::api::auth_filters::SharedAuthMiddleware;
+use crate::api::auth_filters::SharedTokenService;
+use crate::middleware::jwt::TokenUse;
+use crate::models::errors::Error;
+use crate::models::token::RefreshTokenRequest;
+use crate::models::token::TokenResponse;
+
+/// The token responses are not to be cached by the clients or the proxies (RFC 6749, section 5.1).
+const NO_STORE: &str = "no-store";
+
+pub async fn issue_token(
+    username: String,
+    token_service: SharedTokenService,
+) -> Result<impl Reply, Rejection> {
+    token_service
+        .issue(&username)
+        .map(token_reply)
+        .map_err(warp::reject::custom)
+}
+
+pub async fn refresh_token(
+    request: RefreshTokenRequest,
+    auth_middleware: SharedAuthMiddleware,
+    token_service: SharedTokenService,
+) -> Result<impl Reply, Rejection> {
+    let username: String = token_service
+        .verify(&request.refresh_token, TokenUse::Refresh)
+        .map_err(warp::reject::custom)?;
+    if !auth_middleware
+        .has_user(&username)
+        .await
+        .map_err(warp::reject::custom)?
+    {
+        return Err(warp::reject::custom(Error::InvalidToken(format!(
+            "Unknown user {username}"
+        ))));
+    }
+    token_service
+        .issue(&username)
+        .map(token_reply)
+        .map_err(warp::reject::custom)
+}
+
+fn token_reply(tokens: TokenResponse) -> impl Reply {
+    warp::reply::with_header(warp::reply::json(&tokens), CACHE_CONTROL, NO_STORE)
+}
@@ -0,0 +1,58 @@
+use std::convert::Infallible;
+
+use warp::Filter;
+use warp::Rejection;
+use warp::Reply;
+
+use crate::api::auth_filters::with_basic_authenticated_user;
+use crate::api::auth_filters::SharedAuthMiddleware;
+use crate::api::auth_filters::SharedTokenService;
+use crate::api::auth_handlers;
+
+const MAX_JSON_PAYLOAD_SIZE: u64 = 1024 * 16;
+
+/// The JWT issuing routes, trading the HTTP Basic Auth credentials of a user for a pair of tokens,
+/// and a refresh token for a new pair.
+pub fn get_auth_routes(
+    auth_middleware: SharedAuthMiddleware,
+    token_service: SharedTokenService,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    issue_token_route(auth_middleware.clone(), token_service.clone())
+        .or(refresh_token_route(auth_middleware, token_service))
+}
+
+fn issue_token_route(
+    auth_middleware: SharedAuthMiddleware,
+    token_service: SharedTokenService,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    warp::path!("auth" / "token")
+        .and(warp::post())
+        .and(with_basic_authenticated_user(auth_middleware))
+        .and(with_token_service(token_service))
+        .and_then(auth_handlers::issue_token)
+}
+
+fn refresh_token_route(
+    auth_middleware: SharedAuthMiddleware,
+    token_service: SharedTokenService,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    warp::path!("auth" / "refresh")
+        .and(warp::post())
+        .and(warp::body::content_length_limit(MAX_JSON_PAYLOAD_SIZE))
+        .and(warp::body::json())
+        .and(with_auth_middleware(auth_middleware))
+        .and(with_token_service(token_service))
+        .and_then(auth_handlers::refresh_token)
+}
+
+fn with_auth_middleware(
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (SharedAuthMiddleware,), Error = Infallible> + Clone {
+    warp::any().map(move || auth_middleware.clone())
+}
+
+fn with_token_service(
+    token_service: SharedTokenService,
+) -> impl Filter<Extract = (SharedTokenService,), Error = Infallible> + Clone {
+    warp::any().map(move || token_service.clone())
+}
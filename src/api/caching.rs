@@ -0,0 +1,82 @@
+use chrono::DateTime;
+use chrono::Utc;
+use warp::http::header::CACHE_CONTROL;
+use warp::http::header::LAST_MODIFIED;
+use warp::http::header::VARY;
+use warp::http::HeaderValue;
+use warp::hyper::Body;
+use warp::hyper::Response;
+use warp::hyper::StatusCode;
+
+/// Caches (browsers and reverse proxies alike) may store the reads, but have to revalidate them before reuse.
+const CACHE_CONTROL_VALUE: &str = "no-cache";
+/// The reads are negotiated by the `Accept` header; `Accept-Encoding` is added by the compression.
+const VARY_VALUE: &str = "Accept";
+const HTTP_DATE_FORMAT: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+/// Formats a timestamp as an HTTP date (RFC 7231), e.g. `Tue, 25 Apr 2023 10:00:00 GMT`.
+pub fn http_date(date_time: &DateTime<Utc>) -> String {
+    date_time.format(HTTP_DATE_FORMAT).to_string()
+}
+
+/// Whether the client's copy, as of its `If-Modified-Since` header, is still fresh.
+/// HTTP dates have a precision of one second, so the sub-second part of the last modification is ignored.
+pub fn is_not_modified(
+    if_modified_since: Option<&str>,
+    last_modified: Option<&DateTime<Utc>>,
+) -> bool {
+    let (Some(if_modified_since), Some(last_modified)) = (if_modified_since, last_modified) else {
+        return false;
+    };
+    DateTime::parse_from_rfc2822(if_modified_since)
+        .map(|if_modified_since| last_modified.timestamp() <= if_modified_since.timestamp())
+        .unwrap_or(false)
+}
+
+/// Adds the `Cache-Control`, `Vary` and, if known, the `Last-Modified` headers to a read.
+pub fn cacheable(
+    mut response: Response<Body>,
+    last_modified: Option<&DateTime<Utc>>,
+) -> Response<Body> {
+    let headers = response.headers_mut();
+    headers.insert(CACHE_CONTROL, HeaderValue::from_static(CACHE_CONTROL_VALUE));
+    headers.append(VARY, HeaderValue::from_static(VARY_VALUE));
+    if let Some(last_modified) = last_modified {
+        if let Ok(value) = HeaderValue::from_str(&http_date(last_modified)) {
+            headers.insert(LAST_MODIFIED, value);
+        }
+    }
+    response
+}
+
+/// The `304 Not Modified` answer to a conditional read, with the same caching headers as the full one.
+pub fn not_modified(last_modified: Option<&DateTime<Utc>>) -> Response<Body> {
+    let mut response: Response<Body> = Response::new(Body::empty());
+    *response.status_mut() = StatusCode::NOT_MODIFIED;
+    cacheable(response, last_modified)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_is_not_modified() {
+        let last_modified: DateTime<Utc> = Utc.with_ymd_and_hms(2023, 4, 25, 10, 0, 0).unwrap();
+        let date: String = http_date(&last_modified);
+
+        assert_eq!("Tue, 25 Apr 2023 10:00:00 GMT", date);
+        assert!(is_not_modified(Some(&date), Some(&last_modified)));
+        assert!(is_not_modified(
+            Some("Tue, 25 Apr 2023 10:00:01 GMT"),
+            Some(&last_modified)
+        ));
+        assert!(!is_not_modified(
+            Some("Tue, 25 Apr 2023 09:59:59 GMT"),
+            Some(&last_modified)
+        ));
+        assert!(!is_not_modified(Some("yesterday"), Some(&last_modified)));
+        assert!(!is_not_modified(Some(&date), None));
+    }
+}
@@ -0,0 +1,628 @@
+use std::sync::Arc;
+
+use futures::StreamExt;
+use futures::TryStreamExt;
+use sha2::Digest;
+use sha2::Sha256;
+use warp::http::header::CONTENT_TYPE;
+use warp::http::header::ETAG;
+use warp::http::header::LOCATION;
+use warp::http::HeaderValue;
+use warp::hyper::body::Bytes;
+use warp::hyper::Body;
+use warp::hyper::Response;
+use warp::hyper::StatusCode;
+use warp::Rejection;
+use warp::Reply;
+
+use crate::api::auth_filters::Principal;
+use crate::api::contacts_handlers;
+use crate::formats::contacts_vcard;
+use crate::formats::contacts_vcard::VCardFileName;
+use crate::formats::contacts_vcard::VCardVersion;
+use crate::formats::webdav;
+use crate::formats::webdav::Multistatus;
+use crate::formats::webdav::PropName;
+use crate::formats::webdav::PropfindRequest;
+use crate::formats::webdav::ReportRequest;
+use crate::formats::webdav::CALENDARSERVER_NAMESPACE;
+use crate::formats::webdav::CARDDAV_NAMESPACE;
+use crate::formats::webdav::DAV_NAMESPACE;
+use crate::middleware::validation::ValidationMiddleware;
+use crate::models::address_book::AddressBook;
+use crate::models::address_book::AddressBookId;
+use crate::models::contact::Address;
+use crate::models::contact::Contact;
+use crate::models::contact::ContactChanges;
+use crate::models::contact::ContactId;
+use crate::models::contact::Email;
+use crate::models::contact::NewContact;
+use crate::models::contact::Phone;
+use crate::models::errors::Error;
+use crate::models::import_report::ImportRow;
+use crate::models::role::Role;
+use crate::models::tag::Tag;
+use crate::repositories::address_books_repository::AddressBooksRepository;
+use crate::repositories::contacts_repository::ContactsRepository;
+use crate::repositories::photo_storage::SharedPhotoStorage;
+
+pub const ROOT_HREF: &str = "/carddav/";
+
+const DAV_COMPLIANCE: &str = "1, 3, addressbook";
+const DAV_ALLOWED_METHODS: &str = "OPTIONS, GET, PUT, DELETE, PROPFIND, REPORT";
+const XML_CONTENT_TYPE: &str = "application/xml; charset=utf-8";
+const VCARD_CONTENT_TYPE: &str = "text/vcard; charset=utf-8";
+const SYNC_TOKEN_PREFIX: &str = "urn:x-contacts-api:sync:";
+const DEPTH_ZERO: &str = "0";
+
+/// The resources exposed over CardDAV: the root (both principal and address book home),
+/// the address books of the user and the contacts in them.
+enum DavResource<'a> {
+    Root,
+    AddressBook { name: &'a str, sync_token: i64 },
+    Contact(&'a Contact),
+}
+
+pub async fn options() -> Result<impl Reply, Rejection> {
+    let mut response: Response<Body> = Response::new(Body::empty());
+    let headers = response.headers_mut();
+    headers.insert("DAV", HeaderValue::from_static(DAV_COMPLIANCE));
+    headers.insert("Allow", HeaderValue::from_static(DAV_ALLOWED_METHODS));
+    Ok(response)
+}
+
+pub async fn well_known() -> Result<impl Reply, Rejection> {
+    Ok(warp::redirect::permanent(warp::http::Uri::from_static(
+        ROOT_HREF,
+    )))
+}
+
+/// The root lists the address books of the user, or of the admin who minted the API key,
+/// then the ones shared with the user.
+pub async fn propfind_root(
+    principal: Principal,
+    role: Option<Role>,
+    depth: Option<String>,
+    body: Bytes,
+    repository: impl ContactsRepository + AddressBooksRepository,
+) -> Result<impl Reply, Rejection> {
+    let request: PropfindRequest = webdav::parse_propfind(&body).map_err(warp::reject::custom)?;
+    let mut multistatus: Multistatus = Multistatus::new();
+    add_resource(&mut multistatus, ROOT_HREF, &DavResource::Root, &request);
+    if depth.as_deref() != Some(DEPTH_ZERO) {
+        let mut address_books: Vec<AddressBook> = repository
+            .get_address_books(principal.username())
+            .await
+            .map_err(warp::reject::custom)?;
+        address_books.extend(
+            repository
+                .get_shared_address_books(principal.username(), role)
+                .await
+                .map_err(warp::reject::custom)?,
+        );
+        for address_book in address_books.iter() {
+            add_address_book(&mut multistatus, address_book, &repository, &request).await?;
+        }
+    }
+    Ok(multistatus_reply(&multistatus))
+}
+
+pub async fn propfind_address_book(
+    address_book_id: AddressBookId,
+    depth: Option<String>,
+    body: Bytes,
+    repository: impl ContactsRepository + AddressBooksRepository,
+) -> Result<impl Reply, Rejection> {
+    let request: PropfindRequest = webdav::parse_propfind(&body).map_err(warp::reject::custom)?;
+    let address_book: AddressBook = repository
+        .get_address_book(address_book_id)
+        .await
+        .map_err(warp::reject::custom)?
+        .ok_or(warp::reject::custom(Error::UnknownAddressBook(
+            address_book_id.0,
+        )))?;
+    let mut multistatus: Multistatus = Multistatus::new();
+    add_address_book(&mut multistatus, &address_book, &repository, &request).await?;
+    if depth.as_deref() != Some(DEPTH_ZERO) {
+        let contacts: Vec<Contact> = repository
+            .get_all_as_stream(address_book_id)
+            .try_collect()
+            .await
+            .map_err(warp::reject::custom)?;
+        for contact in contacts.iter() {
+            add_contact(&mut multistatus, address_book_id, contact, &request);
+        }
+    }
+    Ok(multistatus_reply(&multistatus))
+}
+
+pub async fn propfind_contact(
+    address_book_id: AddressBookId,
+    file_name: String,
+    body: Bytes,
+    contacts_repository: impl ContactsRepository,
+) -> Result<impl Reply, Rejection> {
+    let request: PropfindRequest = webdav::parse_propfind(&body).map_err(warp::reject::custom)?;
+    let contact: Contact =
+        get_existing_contact(address_book_id, &file_name, &contacts_repository).await?;
+    let mut multistatus: Multistatus = Multistatus::new();
+    add_contact(&mut multistatus, address_book_id, &contact, &request);
+    Ok(multistatus_reply(&multistatus))
+}
+
+pub async fn report(
+    address_book_id: AddressBookId,
+    body: Bytes,
+    contacts_repository: impl ContactsRepository,
+) -> Result<impl Reply, Rejection> {
+    let request: ReportRequest = webdav::parse_report(&body).map_err(warp::reject::custom)?;
+    let mut multistatus: Multistatus = Multistatus::new();
+
+    match request {
+        ReportRequest::AddressbookMultiget { props, hrefs } => {
+            for href in hrefs {
+                let contact: Option<Contact> = match contact_id_of_href(address_book_id, &href) {
+                    Some(id) => contacts_repository
+                        .get(address_book_id, id)
+                        .await
+                        .map_err(warp::reject::custom)?,
+                    None => None,
+                };
+                match contact {
+                    Some(contact) => {
+                        add_contact(&mut multistatus, address_book_id, &contact, &props)
+                    }
+                    None => multistatus.add_status(&href, StatusCode::NOT_FOUND),
+                }
+            }
+        }
+        ReportRequest::AddressbookQuery {
+            props,
+            filter,
+            limit,
+        } => {
+            let contacts: Vec<Contact> = contacts_repository
+                .get_all_as_stream(address_book_id)
+                .try_filter(|contact: &Contact| {
+                    let matches: bool = filter.matches(|name: &str| vcard_values(contact, name));
+                    async move { matches }
+                })
+                .take(limit.unwrap_or(usize::MAX))
+                .try_collect()
+                .await
+                .map_err(warp::reject::custom)?;
+            for contact in contacts.iter() {
+                add_contact(&mut multistatus, address_book_id, contact, &props);
+            }
+        }
+        ReportRequest::SyncCollection { props, sync_token } => {
+            let sync_token: Option<i64> = sync_token
+                .map(|token: String| parse_sync_token(&token))
+                .transpose()
+                .map_err(warp::reject::custom)?;
+            let changes: ContactChanges = contacts_repository
+                .get_changes(address_book_id, sync_token)
+                .await
+                .map_err(warp::reject::custom)?;
+            if sync_token.is_none() {
+                let contacts: Vec<Contact> = contacts_repository
+                    .get_all_as_stream(address_book_id)
+                    .try_collect()
+                    .await
+                    .map_err(warp::reject::custom)?;
+                for contact in contacts.iter() {
+                    add_contact(&mut multistatus, address_book_id, contact, &props);
+                }
+            }
+            for id in changes.updated {
+                match contacts_repository
+                    .get(address_book_id, id.clone())
+                    .await
+                    .map_err(warp::reject::custom)?
+                {
+                    Some(contact) => {
+                        add_contact(&mut multistatus, address_book_id, &contact, &props)
+                    }
+                    None => multistatus
+                        .add_status(&contact_href(address_book_id, &id), StatusCode::NOT_FOUND),
+                }
+            }
+            for id in changes.deleted {
+                multistatus.add_status(&contact_href(address_book_id, &id), StatusCode::NOT_FOUND);
+            }
+            multistatus.set_sync_token(sync_token_uri(changes.sync_token));
+        }
+    }
+    Ok(multistatus_reply(&multistatus))
+}
+
+pub async fn get_contact(
+    address_book_id: AddressBookId,
+    file_name: String,
+    if_none_match: Option<String>,
+    contacts_repository: impl ContactsRepository,
+) -> Result<impl Reply, Rejection> {
+    let contact: Contact =
+        get_existing_contact(address_book_id, &file_name, &contacts_repository).await?;
+    let vcard: String = contacts_vcard::to_vcard(&contact, VCardVersion::V3);
+    let etag: String = etag(&vcard);
+
+    let mut response: Response<Body> = if if_none_match.as_deref() == Some(etag.as_str()) {
+        let mut response: Response<Body> = Response::new(Body::empty());
+        *response.status_mut() = StatusCode::NOT_MODIFIED;
+        response
+    } else {
+        Response::new(Body::from(vcard))
+    };
+    let headers = response.headers_mut();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static(VCARD_CONTENT_TYPE));
+    if let Ok(etag) = HeaderValue::from_str(&etag) {
+        headers.insert(ETAG, etag);
+    }
+    Ok(response)
+}
+
+/// Creates or updates a contact out of a single vCard.
+/// New resources are created under an id chosen by the server, returned in the `Location` header.
+pub async fn put_contact(
+    address_book_id: AddressBookId,
+    file_name: String,
+    if_match: Option<String>,
+    if_none_match: Option<String>,
+    body: Bytes,
+    mut contacts_repository: impl ContactsRepository,
+    validation: Arc<ValidationMiddleware>,
+) -> Result<impl Reply, Rejection> {
+    let new_contact: NewContact = parse_single_vcard(&body).map_err(warp::reject::custom)?;
+    contacts_handlers::validate(&new_contact, &validation).await?;
+    let existing_contact: Option<Contact> = match contact_id_of_file_name(&file_name) {
+        Some(id) => contacts_repository
+            .get(address_book_id, id)
+            .await
+            .map_err(warp::reject::custom)?,
+        None => None,
+    };
+
+    match existing_contact {
+        Some(contact) => {
+            if if_none_match.as_deref() == Some("*") {
+                return Err(warp::reject::custom(Error::PreconditionFailed(
+                    "The resource already exists".to_string(),
+                )));
+            }
+            check_if_match(if_match, &contact)?;
+            contacts_repository
+                .update(
+                    address_book_id,
+                    Contact {
+                        id: contact.id.clone(),
+                        name: new_contact.name,
+                        phones: new_contact.phones,
+                        emails: new_contact.emails,
+                        addresses: new_contact.addresses,
+                        tags: new_contact.tags,
+                        birthday: new_contact.birthday,
+                        // vCards don't link to organizations by id nor carry custom fields or labeled dates, so keep the existing ones
+                        organization: contact.organization.clone(),
+                        custom_fields: contact.custom_fields.clone(),
+                        dates: contact.dates.clone(),
+                    },
+                    contact.id,
+                )
+                .await
+                .map_err(warp::reject::custom)?;
+            Ok(StatusCode::NO_CONTENT.into_response())
+        }
+        None => {
+            if if_match.is_some() {
+                return Err(warp::reject::custom(Error::PreconditionFailed(
+                    "The resource doesn't exist".to_string(),
+                )));
+            }
+            let contact: Contact = contacts_repository
+                .add(address_book_id, new_contact)
+                .await
+                .map_err(warp::reject::custom)?;
+            let mut response: Response<Body> = Response::new(Body::empty());
+            *response.status_mut() = StatusCode::CREATED;
+            if let Ok(location) = HeaderValue::from_str(&contact_href(address_book_id, &contact.id))
+            {
+                response.headers_mut().insert(LOCATION, location);
+            }
+            Ok(response)
+        }
+    }
+}
+
+pub async fn delete_contact(
+    address_book_id: AddressBookId,
+    file_name: String,
+    if_match: Option<String>,
+    mut contacts_repository: impl ContactsRepository,
+    photo_storage: SharedPhotoStorage,
+) -> Result<impl Reply, Rejection> {
+    let contact: Contact =
+        get_existing_contact(address_book_id, &file_name, &contacts_repository).await?;
+    check_if_match(if_match, &contact)?;
+    contacts_repository
+        .delete(address_book_id, contact.id.clone())
+        .await
+        .map_err(warp::reject::custom)?;
+    photo_storage
+        .delete_photo(contact.id)
+        .await
+        .map(|_| StatusCode::NO_CONTENT)
+        .map_err(warp::reject::custom)
+}
+
+async fn get_existing_contact(
+    address_book_id: AddressBookId,
+    file_name: &str,
+    contacts_repository: &impl ContactsRepository,
+) -> Result<Contact, Rejection> {
+    let id: ContactId = contact_id_of_file_name(file_name).ok_or(warp::reject::not_found())?;
+    contacts_repository
+        .get(address_book_id, id.clone())
+        .await
+        .map_err(warp::reject::custom)?
+        .ok_or(warp::reject::custom(Error::NotFound { id: id.0 }))
+}
+
+fn check_if_match(if_match: Option<String>, contact: &Contact) -> Result<(), Rejection> {
+    match if_match {
+        Some(if_match)
+            if if_match != "*"
+                && if_match != etag(&contacts_vcard::to_vcard(contact, VCardVersion::V3)) =>
+        {
+            Err(warp::reject::custom(Error::PreconditionFailed(
+                "The resource has been changed".to_string(),
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn parse_single_vcard(body: &[u8]) -> Result<NewContact, Error> {
+    let input: &str =
+        std::str::from_utf8(body).map_err(|err| Error::InvalidVCard(err.to_string()))?;
+    let mut rows: Vec<ImportRow> = contacts_vcard::parse(input)?;
+    if rows.len() != 1 {
+        return Err(Error::InvalidVCard(format!(
+            "Expected a single vCard, found {}",
+            rows.len()
+        )));
+    }
+    rows.remove(0)
+        .new_contact
+        .map_err(|messages: Vec<String>| Error::InvalidVCard(messages.join("; ")))
+}
+
+/// Adds an address book, along with its current sync-token.
+async fn add_address_book(
+    multistatus: &mut Multistatus,
+    address_book: &AddressBook,
+    contacts_repository: &impl ContactsRepository,
+    request: &PropfindRequest,
+) -> Result<(), Rejection> {
+    let changes: ContactChanges = contacts_repository
+        .get_changes(address_book.id, None)
+        .await
+        .map_err(warp::reject::custom)?;
+    let resource: DavResource = DavResource::AddressBook {
+        name: &address_book.name,
+        sync_token: changes.sync_token,
+    };
+    add_resource(
+        multistatus,
+        &address_book_href(address_book.id),
+        &resource,
+        request,
+    );
+    Ok(())
+}
+
+fn add_contact(
+    multistatus: &mut Multistatus,
+    address_book_id: AddressBookId,
+    contact: &Contact,
+    request: &PropfindRequest,
+) {
+    add_resource(
+        multistatus,
+        &contact_href(address_book_id, &contact.id),
+        &DavResource::Contact(contact),
+        request,
+    );
+}
+
+fn add_resource(
+    multistatus: &mut Multistatus,
+    href: &str,
+    resource: &DavResource,
+    request: &PropfindRequest,
+) {
+    let props: Vec<PropName> = match request {
+        PropfindRequest::AllProp => all_props(resource),
+        PropfindRequest::Prop(props) => props.clone(),
+    };
+    let mut found: Vec<(PropName, String)> = vec![];
+    let mut not_found: Vec<PropName> = vec![];
+    for prop in props {
+        match prop_value(resource, &prop) {
+            Some(value) => found.push((prop, value)),
+            None => not_found.push(prop),
+        }
+    }
+    multistatus.add_response(href, found, not_found);
+}
+
+fn all_props(resource: &DavResource) -> Vec<PropName> {
+    let names: &[(&str, &str)] = match resource {
+        DavResource::Root => &[
+            (DAV_NAMESPACE, "resourcetype"),
+            (DAV_NAMESPACE, "displayname"),
+            (DAV_NAMESPACE, "current-user-principal"),
+            (CARDDAV_NAMESPACE, "addressbook-home-set"),
+        ],
+        DavResource::AddressBook { .. } => &[
+            (DAV_NAMESPACE, "resourcetype"),
+            (DAV_NAMESPACE, "displayname"),
+            (DAV_NAMESPACE, "sync-token"),
+            (CALENDARSERVER_NAMESPACE, "getctag"),
+        ],
+        DavResource::Contact(_) => &[
+            (DAV_NAMESPACE, "resourcetype"),
+            (DAV_NAMESPACE, "getetag"),
+            (DAV_NAMESPACE, "getcontenttype"),
+        ],
+    };
+    names
+        .iter()
+        .map(|(namespace, name)| PropName::new(namespace, name))
+        .collect()
+}
+
+/// Returns the inner XML of a property of a resource, or None if the resource doesn't have it.
+fn prop_value(resource: &DavResource, prop: &PropName) -> Option<String> {
+    let root_href: String = format!("<d:href>{}</d:href>", ROOT_HREF);
+    match (prop.namespace.as_str(), prop.name.as_str(), resource) {
+        (DAV_NAMESPACE, "resourcetype", DavResource::Root) => Some("<d:collection/>".to_string()),
+        (DAV_NAMESPACE, "resourcetype", DavResource::AddressBook { .. }) => {
+            Some("<d:collection/><card:addressbook/>".to_string())
+        }
+        (DAV_NAMESPACE, "resourcetype", DavResource::Contact(_)) => Some(String::new()),
+        (DAV_NAMESPACE, "displayname", DavResource::Root) => Some("Contacts API".to_string()),
+        (DAV_NAMESPACE, "displayname", DavResource::AddressBook { name, .. }) => {
+            Some(webdav::escape(name))
+        }
+        (DAV_NAMESPACE, "current-user-principal", _)
+        | (DAV_NAMESPACE, "principal-URL", DavResource::Root)
+        | (CARDDAV_NAMESPACE, "addressbook-home-set", DavResource::Root) => Some(root_href),
+        (DAV_NAMESPACE, "current-user-privilege-set", _) => Some(
+            "<d:privilege><d:read/></d:privilege><d:privilege><d:write/></d:privilege>".to_string(),
+        ),
+        (DAV_NAMESPACE, "sync-token", DavResource::AddressBook { sync_token, .. }) => {
+            Some(webdav::escape(&sync_token_uri(*sync_token)))
+        }
+        (CALENDARSERVER_NAMESPACE, "getctag", DavResource::AddressBook { sync_token, .. }) => {
+            Some(sync_token.to_string())
+        }
+        (DAV_NAMESPACE, "supported-report-set", DavResource::AddressBook { .. }) => Some(
+            [
+                "<card:addressbook-query/>",
+                "<card:addressbook-multiget/>",
+                "<d:sync-collection/>",
+            ]
+            .iter()
+            .map(|report| {
+                format!(
+                    "<d:supported-report><d:report>{}</d:report></d:supported-report>",
+                    report
+                )
+            })
+            .collect(),
+        ),
+        (CARDDAV_NAMESPACE, "supported-address-data", DavResource::AddressBook { .. }) => Some(
+            "<card:address-data-type content-type=\"text/vcard\" version=\"3.0\"/>".to_string(),
+        ),
+        (DAV_NAMESPACE, "getetag", DavResource::Contact(contact)) => Some(webdav::escape(&etag(
+            &contacts_vcard::to_vcard(contact, VCardVersion::V3),
+        ))),
+        (DAV_NAMESPACE, "getcontenttype", DavResource::Contact(_)) => {
+            Some(VCARD_CONTENT_TYPE.to_string())
+        }
+        (CARDDAV_NAMESPACE, "address-data", DavResource::Contact(contact)) => Some(webdav::escape(
+            &contacts_vcard::to_vcard(contact, VCardVersion::V3),
+        )),
+        _ => None,
+    }
+}
+
+/// The values of a contact, by vCard property name, as used by `addressbook-query` filters.
+fn vcard_values(contact: &Contact, name: &str) -> Vec<String> {
+    match name {
+        "FN" | "N" => vec![contact.name.clone()],
+        "EMAIL" => contact
+            .emails
+            .iter()
+            .map(|email: &Email| email.address.clone())
+            .collect(),
+        "TEL" => contact
+            .phones
+            .iter()
+            .map(|phone: &Phone| phone.number.to_string())
+            .collect(),
+        "ADR" => contact
+            .addresses
+            .iter()
+            .map(|address: &Address| {
+                [
+                    address.street.as_str(),
+                    address.locality.as_str(),
+                    address.region.as_str(),
+                    address.postal_code.as_str(),
+                    address.country.alpha2(),
+                ]
+                .join(" ")
+            })
+            .collect(),
+        "CATEGORIES" => contact
+            .tags
+            .iter()
+            .map(|tag: &Tag| tag.to_string())
+            .collect(),
+        "UID" => vec![format!("contact-{}", contact.id.0)],
+        _ => vec![],
+    }
+}
+
+fn multistatus_reply(multistatus: &Multistatus) -> Response<Body> {
+    let mut response: Response<Body> = Response::new(Body::from(multistatus.to_xml()));
+    *response.status_mut() = StatusCode::MULTI_STATUS;
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static(XML_CONTENT_TYPE));
+    response
+}
+
+fn etag(vcard: &str) -> String {
+    let digest = Sha256::digest(vcard.as_bytes());
+    format!("\"{}\"", hex::encode(&digest[..16]))
+}
+
+fn address_book_href(address_book_id: AddressBookId) -> String {
+    format!("{}{}/", ROOT_HREF, address_book_id.0)
+}
+
+fn contact_href(address_book_id: AddressBookId, id: &ContactId) -> String {
+    format!("{}{}.vcf", address_book_href(address_book_id), id.0)
+}
+
+/// The id of a contact out of its href, which has to be in the given address book.
+fn contact_id_of_href(address_book_id: AddressBookId, href: &str) -> Option<ContactId> {
+    href.strip_prefix(address_book_href(address_book_id).as_str())
+        .and_then(contact_id_of_file_name)
+}
+
+fn contact_id_of_file_name(file_name: &str) -> Option<ContactId> {
+    file_name
+        .parse::<VCardFileName>()
+        .ok()
+        .map(|VCardFileName(id)| ContactId(id))
+}
+
+fn sync_token_uri(sync_token: i64) -> String {
+    format!("{}{}", SYNC_TOKEN_PREFIX, sync_token)
+}
+
+fn parse_sync_token(sync_token: &str) -> Result<i64, Error> {
+    sync_token
+        .strip_prefix(SYNC_TOKEN_PREFIX)
+        .and_then(|x: &str| x.parse::<i64>().ok())
+        .ok_or_else(|| Error::InvalidSyncToken(sync_token.to_string()))
+}
+
+/// The body of a `403 Forbidden` response to a `sync-collection` report with an invalid sync-token.
+pub fn invalid_sync_token_xml() -> String {
+    webdav::error_xml(&PropName::new(DAV_NAMESPACE, "valid-sync-token"))
+}
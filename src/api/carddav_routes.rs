@@ -0,0 +1,258 @@
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use warp::hyper::body::Bytes;
+use warp::hyper::Method;
+use warp::Filter;
+use warp::Rejection;
+use warp::Reply;
+
+use crate::api::address_books_routes::require_address_book;
+use crate::api::address_books_routes::with_grantee;
+use crate::api::auth_filters::SharedAuthMiddleware;
+use crate::api::carddav_handlers;
+use crate::api::contacts_routes::with_validation;
+use crate::api::photos_routes::with_photo_storage;
+use crate::middleware::validation::ValidationMiddleware;
+use crate::models::address_book::AddressBookId;
+use crate::models::errors::Error;
+use crate::models::role::Role;
+use crate::repositories::address_books_repository::AddressBooksRepository;
+use crate::repositories::contacts_repository::ContactsRepository;
+use crate::repositories::photo_storage::SharedPhotoStorage;
+
+const MAX_DAV_PAYLOAD_SIZE: u64 = 1024 * 64;
+const PROPFIND: &str = "PROPFIND";
+const REPORT: &str = "REPORT";
+
+/// The CardDAV (RFC 6352) subset: the address books of the user, each one with its contacts, behind HTTP Basic Auth.
+pub fn get_carddav_routes<R>(
+    contacts_repository: R,
+    validation: Arc<ValidationMiddleware>,
+    auth_middleware: SharedAuthMiddleware,
+    photo_storage: SharedPhotoStorage,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: ContactsRepository + AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    well_known_route()
+        .or(options_route())
+        .or(propfind_root_route(
+            contacts_repository.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(propfind_address_book_route(
+            contacts_repository.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(propfind_contact_route(
+            contacts_repository.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(report_route(
+            contacts_repository.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(get_contact_route(
+            contacts_repository.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(put_contact_route(
+            contacts_repository.clone(),
+            validation,
+            auth_middleware.clone(),
+        ))
+        .or(delete_contact_route(
+            contacts_repository,
+            auth_middleware,
+            photo_storage,
+        ))
+}
+
+fn well_known_route() -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    warp::path!(".well-known" / "carddav").and_then(carddav_handlers::well_known)
+}
+
+fn options_route() -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    warp::path("carddav")
+        .and(warp::options())
+        .and_then(carddav_handlers::options)
+}
+
+fn propfind_root_route<R>(
+    contacts_repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: ContactsRepository + AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::path("carddav")
+        .and(warp::path::end())
+        .and(dav_method(PROPFIND))
+        .and(with_grantee(auth_middleware, Role::Reader))
+        .and(warp::header::optional::<String>("depth"))
+        .and(dav_body())
+        .and(with_repository(contacts_repository))
+        .and_then(carddav_handlers::propfind_root)
+}
+
+fn propfind_address_book_route<R>(
+    contacts_repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: ContactsRepository + AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("carddav" / AddressBookId)
+        .and(dav_method(PROPFIND))
+        .and(require_address_book(
+            contacts_repository.clone(),
+            auth_middleware,
+            Role::Reader,
+        ))
+        .and(warp::header::optional::<String>("depth"))
+        .and(dav_body())
+        .and(with_repository(contacts_repository))
+        .and_then(carddav_handlers::propfind_address_book)
+}
+
+fn propfind_contact_route<R>(
+    contacts_repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: ContactsRepository + AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("carddav" / AddressBookId / String)
+        .and(dav_method(PROPFIND))
+        .and(require_address_book(
+            contacts_repository.clone(),
+            auth_middleware,
+            Role::Reader,
+        ))
+        .and(dav_body())
+        .and(with_repository(contacts_repository))
+        .and_then(carddav_handlers::propfind_contact)
+}
+
+fn report_route<R>(
+    contacts_repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: ContactsRepository + AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("carddav" / AddressBookId)
+        .and(dav_method(REPORT))
+        .and(require_address_book(
+            contacts_repository.clone(),
+            auth_middleware,
+            Role::Reader,
+        ))
+        .and(dav_body())
+        .and(with_repository(contacts_repository))
+        .and_then(carddav_handlers::report)
+}
+
+fn get_contact_route<R>(
+    contacts_repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: ContactsRepository + AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("carddav" / AddressBookId / String)
+        .and(warp::get())
+        .and(require_address_book(
+            contacts_repository.clone(),
+            auth_middleware,
+            Role::Reader,
+        ))
+        .and(warp::header::optional::<String>("if-none-match"))
+        .and(with_repository(contacts_repository))
+        .and_then(carddav_handlers::get_contact)
+}
+
+fn put_contact_route<R>(
+    contacts_repository: R,
+    validation: Arc<ValidationMiddleware>,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: ContactsRepository + AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("carddav" / AddressBookId / String)
+        .and(warp::put())
+        .and(require_address_book(
+            contacts_repository.clone(),
+            auth_middleware,
+            Role::Editor,
+        ))
+        .and(warp::header::optional::<String>("if-match"))
+        .and(warp::header::optional::<String>("if-none-match"))
+        .and(dav_body())
+        .and(with_repository(contacts_repository))
+        .and(with_validation(validation))
+        .and_then(carddav_handlers::put_contact)
+}
+
+fn delete_contact_route<R>(
+    contacts_repository: R,
+    auth_middleware: SharedAuthMiddleware,
+    photo_storage: SharedPhotoStorage,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: ContactsRepository + AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("carddav" / AddressBookId / String)
+        .and(warp::delete())
+        .and(require_address_book(
+            contacts_repository.clone(),
+            auth_middleware,
+            Role::Admin,
+        ))
+        .and(warp::header::optional::<String>("if-match"))
+        .and(with_repository(contacts_repository))
+        .and(with_photo_storage(photo_storage))
+        .and_then(carddav_handlers::delete_contact)
+}
+
+/// Matches the WebDAV extension methods, which `warp` has no filters for.
+fn dav_method(name: &'static str) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::method()
+        .and_then(move |method: Method| async move {
+            if method.as_str() == name {
+                Ok(())
+            } else {
+                Err(warp::reject::not_found())
+            }
+        })
+        .untuple_one()
+}
+
+/// The XML or vCard body of a request. It may be empty, e.g. a `PROPFIND` without a body means `allprop`.
+fn dav_body() -> impl Filter<Extract = (Bytes,), Error = Rejection> + Clone {
+    warp::header::optional::<u64>("content-length")
+        .and_then(|length: Option<u64>| async move {
+            match length {
+                Some(length) if length > MAX_DAV_PAYLOAD_SIZE => {
+                    Err(warp::reject::custom(Error::InvalidXml(format!(
+                        "The payload of {} bytes is over the limit of {} bytes",
+                        length, MAX_DAV_PAYLOAD_SIZE
+                    ))))
+                }
+                _ => Ok(()),
+            }
+        })
+        .untuple_one()
+        .and(warp::body::bytes())
+}
+
+fn with_repository<R>(
+    contacts_repository: R,
+) -> impl Filter<Extract = (R,), Error = Infallible> + Clone
+where
+    R: ContactsRepository + AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::any().map(move || contacts_repository.clone())
+}
@@ -0,0 +1,154 @@
+use async_compression::tokio::bufread::BrotliEncoder;
+use async_compression::tokio::bufread::DeflateEncoder;
+use async_compression::tokio::bufread::GzipEncoder;
+use futures::TryStreamExt;
+use tokio_util::io::ReaderStream;
+use tokio_util::io::StreamReader;
+use warp::http::header::CONTENT_ENCODING;
+use warp::http::header::CONTENT_LENGTH;
+use warp::http::header::CONTENT_TYPE;
+use warp::http::header::VARY;
+use warp::http::HeaderValue;
+use warp::hyper::body::HttpBody;
+use warp::hyper::Body;
+use warp::hyper::Response;
+use warp::hyper::StatusCode;
+use warp::Reply;
+
+/// Bodies smaller than this are not worth the compression overhead.
+const MIN_COMPRESSIBLE_SIZE: u64 = 1024;
+
+/// The content codings supported, in the order preferred by the server when the client has no preference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentEncoding {
+    Brotli,
+    Gzip,
+    Deflate,
+    Identity,
+}
+
+impl ContentEncoding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContentEncoding::Brotli => "br",
+            ContentEncoding::Gzip => "gzip",
+            ContentEncoding::Deflate => "deflate",
+            ContentEncoding::Identity => "identity",
+        }
+    }
+
+    /// Picks the content coding the client prefers, by `q` value, out of an `Accept-Encoding` header.
+    /// Ties are broken by the server's preference; without a supported coding the body is left as is.
+    pub fn from_accept_encoding(accept_encoding: Option<&str>) -> Self {
+        let Some(accept_encoding) = accept_encoding else {
+            return ContentEncoding::Identity;
+        };
+        let mut candidates: Vec<(f32, ContentEncoding)> = accept_encoding
+            .split(',')
+            .filter_map(|coding: &str| {
+                let mut parts = coding.split(';');
+                let content_encoding: ContentEncoding =
+                    match parts.next()?.trim().to_ascii_lowercase().as_str() {
+                        "br" | "*" => ContentEncoding::Brotli,
+                        "gzip" | "x-gzip" => ContentEncoding::Gzip,
+                        "deflate" => ContentEncoding::Deflate,
+                        _ => return None,
+                    };
+                let quality: f32 = parts
+                    .filter_map(|param: &str| param.trim().strip_prefix("q="))
+                    .find_map(|q: &str| q.parse::<f32>().ok())
+                    .unwrap_or(1.0);
+                Some((quality, content_encoding))
+            })
+            .filter(|(quality, _)| *quality > 0.0)
+            .collect();
+        candidates.sort_by(|(a, a_encoding), (b, b_encoding)| {
+            b.total_cmp(a)
+                .then_with(|| (*a_encoding as u8).cmp(&(*b_encoding as u8)))
+        });
+        candidates
+            .first()
+            .map(|(_, content_encoding)| *content_encoding)
+            .unwrap_or(ContentEncoding::Identity)
+    }
+}
+
+/// Compresses the body of a response with the content coding negotiated by the `Accept-Encoding` header.
+/// Empty, small, already encoded or already compressed (images) bodies are left as they are.
+pub fn compress<T: Reply>(accept_encoding: Option<String>, reply: T) -> Response<Body> {
+    let response: Response<Body> = reply.into_response();
+    if !is_compressible(&response) {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    parts
+        .headers
+        .append(VARY, HeaderValue::from_static("Accept-Encoding"));
+    let content_encoding: ContentEncoding =
+        ContentEncoding::from_accept_encoding(accept_encoding.as_deref());
+    if content_encoding == ContentEncoding::Identity {
+        return Response::from_parts(parts, body);
+    }
+
+    let reader = StreamReader::new(TryStreamExt::map_err(body, |err: warp::hyper::Error| {
+        std::io::Error::other(err)
+    }));
+    let body: Body = match content_encoding {
+        ContentEncoding::Brotli => Body::wrap_stream(ReaderStream::new(BrotliEncoder::new(reader))),
+        ContentEncoding::Gzip => Body::wrap_stream(ReaderStream::new(GzipEncoder::new(reader))),
+        ContentEncoding::Deflate => {
+            Body::wrap_stream(ReaderStream::new(DeflateEncoder::new(reader)))
+        }
+        ContentEncoding::Identity => unreachable!(),
+    };
+    parts.headers.remove(CONTENT_LENGTH);
+    parts.headers.insert(
+        CONTENT_ENCODING,
+        HeaderValue::from_static(content_encoding.as_str()),
+    );
+    Response::from_parts(parts, body)
+}
+
+fn is_compressible(response: &Response<Body>) -> bool {
+    let is_image: bool = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value: &HeaderValue| value.to_str().ok())
+        .is_some_and(|content_type: &str| content_type.starts_with("image/"));
+    let is_small: bool = response
+        .body()
+        .size_hint()
+        .exact()
+        .is_some_and(|size: u64| size < MIN_COMPRESSIBLE_SIZE);
+    response.status() != StatusCode::NO_CONTENT
+        && response.status() != StatusCode::NOT_MODIFIED
+        && !response.headers().contains_key(CONTENT_ENCODING)
+        && !is_image
+        && !is_small
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_accept_encoding() {
+        assert_eq!(
+            ContentEncoding::Identity,
+            ContentEncoding::from_accept_encoding(None)
+        );
+        assert_eq!(
+            ContentEncoding::Brotli,
+            ContentEncoding::from_accept_encoding(Some("gzip, deflate, br"))
+        );
+        assert_eq!(
+            ContentEncoding::Gzip,
+            ContentEncoding::from_accept_encoding(Some("br;q=0.5, gzip, deflate;q=0.8"))
+        );
+        assert_eq!(
+            ContentEncoding::Identity,
+            ContentEncoding::from_accept_encoding(Some("compress, br;q=0"))
+        );
+    }
+}
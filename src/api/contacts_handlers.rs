@@ -1,110 +1,447 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 
+use chrono::DateTime;
+use chrono::Utc;
+use futures::stream;
+use futures::StreamExt;
+use isocountry::CountryCode;
+use percent_encoding::percent_decode_str;
+use warp::http::header::CONTENT_DISPOSITION;
+use warp::http::header::CONTENT_TYPE;
+use warp::http::header::RETRY_AFTER;
+use warp::http::header::WWW_AUTHENTICATE;
+use warp::http::HeaderValue;
+use warp::hyper::body::Bytes;
+use warp::hyper::Body;
+use warp::hyper::Response;
 use warp::hyper::StatusCode;
+use warp::reject::PayloadTooLarge;
 use warp::reject::Reject;
 use warp::Rejection;
 use warp::Reply;
 
+use crate::api::caching;
+use crate::api::carddav_handlers;
+use crate::api::content_negotiation;
+use crate::api::content_negotiation::MediaType;
+use crate::api::rate_limiting::RATELIMIT_LIMIT;
+use crate::api::rate_limiting::RATELIMIT_REMAINING;
+use crate::api::rate_limiting::RATELIMIT_RESET;
+use crate::formats::contacts_csv;
+use crate::formats::contacts_csv::HeaderMapping;
+use crate::formats::contacts_vcard;
+use crate::formats::contacts_vcard::VCardFileName;
+use crate::formats::contacts_vcard::VCardVersion;
+use crate::middleware::validation::Validation;
+use crate::middleware::validation::ValidationMiddleware;
+use crate::models::address_book::AddressBookId;
+use crate::models::contact::parse_country;
 use crate::models::contact::Contact;
+use crate::models::contact::ContactFilter;
 use crate::models::contact::ContactId;
 use crate::models::contact::NewContact;
 use crate::models::contact::UpdateContactEmail;
 use crate::models::contact::UpdateContactPhoneNo;
+use crate::models::custom_field::CustomField;
+use crate::models::custom_field::CustomFieldValues;
 use crate::models::errors::Error;
+use crate::models::import_report::ImportReport;
+use crate::models::import_report::ImportRow;
+use crate::models::important_date::UpcomingDate;
+use crate::models::tag::unique_sorted;
+use crate::models::tag::Tag;
+use crate::models::tag::TagCount;
+use crate::models::tag::TagMode;
 use crate::repositories::contacts_repository::ContactsRepository;
+use crate::repositories::custom_fields_repository::CustomFieldsRepository;
+use crate::repositories::photo_storage::SharedPhotoStorage;
 
 const PAGE_NO_KEY: &str = "page_no";
 const PAGE_SIZE: &str = "page_size";
+const COUNTRY_KEY: &str = "country";
+const CITY_KEY: &str = "city";
+const TAG_KEY: &str = "tag";
+const TAG_MODE_KEY: &str = "tag_mode";
+/// Separates the tags of the `?tag=` query parameter, e.g. `?tag=family,friends`.
+const TAG_SEPARATOR: char = ',';
+/// Prefixes the query parameters filtering by a custom field, e.g. `?custom.level=gold`.
+const CUSTOM_FIELD_PREFIX: &str = "custom.";
+const DAYS_KEY: &str = "days";
+const DEFAULT_UPCOMING_DAYS: u32 = 30;
+/// The window of the upcoming dates is at most a year, as every date recurs within it.
+const MAX_UPCOMING_DAYS: u32 = 366;
+const CSV_CONTENT_TYPE: &str = "text/csv; charset=utf-8";
+const VCARD_CONTENT_TYPE: &str = "text/vcard; charset=utf-8";
+const XML_CONTENT_TYPE: &str = "application/xml; charset=utf-8";
+/// The media type of the RFC 7807 problem details.
+const PROBLEM_CONTENT_TYPE: &str = "application/problem+json";
+const BASIC_AUTH_CHALLENGE: &str = "Basic realm=\"contacts-api\", charset=\"UTF-8\"";
+const BEARER_AUTH_CHALLENGE: &str = "Bearer realm=\"contacts-api\"";
+const INVALID_TOKEN_CHALLENGE: &str = "Bearer realm=\"contacts-api\", error=\"invalid_token\"";
 
 pub async fn get_all_contacts(
+    address_book_id: AddressBookId,
     query_parameters: HashMap<String, String>,
-    contacts_repository: impl ContactsRepository,
+    accept: Option<String>,
+    if_modified_since: Option<String>,
+    contacts_repository: impl ContactsRepository + CustomFieldsRepository,
 ) -> Result<impl Reply, Rejection> {
+    let media_type: MediaType = MediaType::from_accept(accept.as_deref())?;
+    let custom_fields: Vec<CustomField> = contacts_repository
+        .get_custom_fields()
+        .await
+        .map_err(warp::reject::custom)?;
+    let filter: ContactFilter = get_filter(&query_parameters, &custom_fields)?;
     let pagination: Pagination = get_pagination(query_parameters)?;
-    contacts_repository
-        .get_all(pagination.page_no, pagination.page_size)
+    let last_modified: Option<DateTime<Utc>> = contacts_repository
+        .get_last_modified(address_book_id, None)
+        .await
+        .map_err(warp::reject::custom)?;
+    if caching::is_not_modified(if_modified_since.as_deref(), last_modified.as_ref()) {
+        return Ok(caching::not_modified(last_modified.as_ref()));
+    }
+    let contacts: Vec<Contact> = contacts_repository
+        .get_all(
+            address_book_id,
+            &filter,
+            pagination.page_no,
+            pagination.page_size,
+        )
         .await
-        .map(|contacts: Vec<Contact>| warp::reply::json(&contacts))
+        .map_err(warp::reject::custom)?;
+    content_negotiation::encode_many(&contacts, media_type)
+        .map(|body: Vec<u8>| {
+            caching::cacheable(
+                content_negotiation::reply(body, media_type),
+                last_modified.as_ref(),
+            )
+        })
         .map_err(warp::reject::custom)
 }
 
 pub async fn get_contact(
+    address_book_id: AddressBookId,
     id: i32,
+    accept: Option<String>,
+    if_modified_since: Option<String>,
     contacts_repository: impl ContactsRepository,
 ) -> Result<impl Reply, Rejection> {
-    let possible_contact: Option<Contact> = match contacts_repository.get(ContactId(id)).await {
+    let media_type: MediaType = MediaType::from_accept(accept.as_deref())?;
+    let possible_contact: Option<Contact> = match contacts_repository
+        .get(address_book_id, ContactId(id))
+        .await
+    {
         Ok(x) => x,
         Err(err) => return Err(warp::reject::custom(err)),
     };
-    possible_contact
-        .ok_or(warp::reject::custom(Error::NotFound { id }))
-        .map(|contact: Contact| warp::reply::json(&contact))
+    let contact: Contact = possible_contact.ok_or(warp::reject::custom(Error::NotFound { id }))?;
+    let last_modified: Option<DateTime<Utc>> = contacts_repository
+        .get_last_modified(address_book_id, Some(ContactId(id)))
+        .await
+        .map_err(warp::reject::custom)?;
+    if caching::is_not_modified(if_modified_since.as_deref(), last_modified.as_ref()) {
+        return Ok(caching::not_modified(last_modified.as_ref()));
+    }
+    content_negotiation::encode_one(&contact, media_type)
+        .map(|body: Vec<u8>| {
+            caching::cacheable(
+                content_negotiation::reply(body, media_type),
+                last_modified.as_ref(),
+            )
+        })
+        .map_err(warp::reject::custom)
 }
 
 pub async fn add_conact(
+    address_book_id: AddressBookId,
     new_contact: NewContact,
     mut contacts_repository: impl ContactsRepository,
+    validation: Arc<ValidationMiddleware>,
 ) -> Result<impl Reply, Rejection> {
+    validate(&new_contact, &validation).await?;
     contacts_repository
-        .add(new_contact)
+        .add(address_book_id, new_contact)
         .await
         .map(|contact: Contact| warp::reply::json(&contact))
         .map_err(warp::reject::custom)
 }
 
 pub async fn update_contact(
+    address_book_id: AddressBookId,
     id: i32,
     contact: Contact,
     mut contacts_repository: impl ContactsRepository,
+    validation: Arc<ValidationMiddleware>,
 ) -> Result<impl Reply, Rejection> {
+    validate(&NewContact::from(contact.clone()), &validation).await?;
     contacts_repository
-        .update(contact, ContactId(id))
+        .update(address_book_id, contact, ContactId(id))
         .await
         .map(|_| StatusCode::NO_CONTENT)
         .map_err(warp::reject::custom)
 }
 
 pub async fn update_contact_email(
+    address_book_id: AddressBookId,
     id: i32,
     payload: UpdateContactEmail,
     mut contacts_repository: impl ContactsRepository,
+    validation: Arc<ValidationMiddleware>,
 ) -> Result<impl Reply, Rejection> {
+    if !validation.is_email_valid(payload.email.clone()) {
+        return Err(warp::reject::custom(Error::InvalidBody(format!(
+            "Invalid email: {}",
+            payload.email
+        ))));
+    }
     contacts_repository
-        .update_email(payload.email, ContactId(id))
+        .update_email(address_book_id, payload.email, ContactId(id))
         .await
         .map(|_| StatusCode::NO_CONTENT)
         .map_err(warp::reject::custom)
 }
 
 pub async fn update_contact_phone_no(
+    address_book_id: AddressBookId,
     id: i32,
     payload: UpdateContactPhoneNo,
     mut contacts_repository: impl ContactsRepository,
+    validation: Arc<ValidationMiddleware>,
 ) -> Result<impl Reply, Rejection> {
+    match validation.get_valid_phone_no(&payload.phone_no).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return Err(warp::reject::custom(Error::InvalidBody(format!(
+                "Invalid phone_no: {}",
+                payload.phone_no
+            ))))
+        }
+        Err(err) => return Err(warp::reject::custom(Error::InvalidBody(err.to_string()))),
+    }
     contacts_repository
-        .update_phone_no(payload.phone_no, ContactId(id))
+        .update_phone_no(address_book_id, payload.phone_no, ContactId(id))
         .await
         .map(|_| StatusCode::NO_CONTENT)
         .map_err(warp::reject::custom)
 }
 
 pub async fn delete_contact(
+    address_book_id: AddressBookId,
+    id: i32,
+    mut contacts_repository: impl ContactsRepository,
+    photo_storage: SharedPhotoStorage,
+) -> Result<impl Reply, Rejection> {
+    contacts_repository
+        .delete(address_book_id, ContactId(id))
+        .await
+        .map_err(warp::reject::custom)?;
+    photo_storage
+        .delete_photo(ContactId(id))
+        .await
+        .map(|_| StatusCode::NO_CONTENT)
+        .map_err(warp::reject::custom)
+}
+
+pub async fn add_contact_tag(
+    address_book_id: AddressBookId,
     id: i32,
+    tag: String,
     mut contacts_repository: impl ContactsRepository,
 ) -> Result<impl Reply, Rejection> {
+    let tag: Tag = path_tag(&tag)?;
     contacts_repository
-        .delete(ContactId(id))
+        .add_tag(address_book_id, ContactId(id), tag)
         .await
         .map(|_| StatusCode::NO_CONTENT)
         .map_err(warp::reject::custom)
 }
 
-struct Pagination {
-    page_no: Option<u32>,
-    page_size: Option<u32>,
+pub async fn remove_contact_tag(
+    address_book_id: AddressBookId,
+    id: i32,
+    tag: String,
+    mut contacts_repository: impl ContactsRepository,
+) -> Result<impl Reply, Rejection> {
+    let tag: Tag = path_tag(&tag)?;
+    contacts_repository
+        .remove_tag(address_book_id, ContactId(id), tag)
+        .await
+        .map(|_| StatusCode::NO_CONTENT)
+        .map_err(warp::reject::custom)
+}
+
+pub async fn get_tags(
+    address_book_id: AddressBookId,
+    contacts_repository: impl ContactsRepository,
+) -> Result<impl Reply, Rejection> {
+    contacts_repository
+        .get_tags(address_book_id)
+        .await
+        .map(|tags: Vec<TagCount>| warp::reply::json(&tags))
+        .map_err(warp::reject::custom)
+}
+
+pub async fn get_upcoming_dates(
+    address_book_id: AddressBookId,
+    query_parameters: HashMap<String, String>,
+    contacts_repository: impl ContactsRepository,
+) -> Result<impl Reply, Rejection> {
+    let days: u32 = match query_parameters.get(DAYS_KEY) {
+        Some(days) => days
+            .trim()
+            .parse::<u32>()
+            .ok()
+            .filter(|days: &u32| *days <= MAX_UPCOMING_DAYS)
+            .ok_or_else(|| Error::InvalidDays(days.to_string()))?,
+        None => DEFAULT_UPCOMING_DAYS,
+    };
+    contacts_repository
+        .get_upcoming_dates(address_book_id, Utc::now().date_naive(), days)
+        .await
+        .map(|upcoming_dates: Vec<UpcomingDate>| warp::reply::json(&upcoming_dates))
+        .map_err(warp::reject::custom)
+}
+
+pub async fn export_contacts_csv(
+    address_book_id: AddressBookId,
+    contacts_repository: impl ContactsRepository,
+) -> Result<impl Reply, Rejection> {
+    let header: Result<Bytes, Error> = contacts_csv::header_line();
+    let lines = contacts_repository
+        .get_all_as_stream(address_book_id)
+        .map(|contact: Result<Contact, Error>| contact.and_then(|x| contacts_csv::to_line(&x)));
+    let body: Body = Body::wrap_stream(stream::once(async { header }).chain(lines));
+    Ok(attachment(body, CSV_CONTENT_TYPE, "contacts.csv"))
+}
+
+pub async fn import_contacts_csv(
+    address_book_id: AddressBookId,
+    query_parameters: HashMap<String, String>,
+    body: Bytes,
+    contacts_repository: impl ContactsRepository,
+    validation: Arc<ValidationMiddleware>,
+) -> Result<impl Reply, Rejection> {
+    let mapping: HeaderMapping = HeaderMapping::from_query(&query_parameters);
+    let rows: Vec<ImportRow> =
+        contacts_csv::parse(&body, &mapping).map_err(warp::reject::custom)?;
+    let report: ImportReport =
+        import_rows(address_book_id, rows, contacts_repository, validation).await;
+    Ok(warp::reply::json(&report))
+}
+
+pub async fn get_contact_vcard(
+    address_book_id: AddressBookId,
+    file_name: VCardFileName,
+    query_parameters: HashMap<String, String>,
+    contacts_repository: impl ContactsRepository,
+) -> Result<impl Reply, Rejection> {
+    let VCardFileName(id) = file_name;
+    let version: VCardVersion =
+        VCardVersion::from_query(&query_parameters).map_err(warp::reject::custom)?;
+    let contact: Contact = contacts_repository
+        .get(address_book_id, ContactId(id))
+        .await
+        .map_err(warp::reject::custom)?
+        .ok_or(warp::reject::custom(Error::NotFound { id }))?;
+    let body: Body = Body::from(contacts_vcard::to_vcard(&contact, version));
+    Ok(attachment(body, VCARD_CONTENT_TYPE, &format!("{}.vcf", id)))
+}
+
+pub async fn export_contacts_vcard(
+    address_book_id: AddressBookId,
+    query_parameters: HashMap<String, String>,
+    contacts_repository: impl ContactsRepository,
+) -> Result<impl Reply, Rejection> {
+    let version: VCardVersion =
+        VCardVersion::from_query(&query_parameters).map_err(warp::reject::custom)?;
+    let cards = contacts_repository.get_all_as_stream(address_book_id).map(
+        move |contact: Result<Contact, Error>| {
+            contact.map(|x: Contact| contacts_vcard::to_vcard(&x, version))
+        },
+    );
+    Ok(attachment(
+        Body::wrap_stream(cards),
+        VCARD_CONTENT_TYPE,
+        "contacts.vcf",
+    ))
+}
+
+pub async fn import_contacts_vcard(
+    address_book_id: AddressBookId,
+    body: Bytes,
+    contacts_repository: impl ContactsRepository,
+    validation: Arc<ValidationMiddleware>,
+) -> Result<impl Reply, Rejection> {
+    let input: &str = std::str::from_utf8(&body)
+        .map_err(|err| warp::reject::custom(Error::InvalidVCard(err.to_string())))?;
+    let rows: Vec<ImportRow> = contacts_vcard::parse(input).map_err(warp::reject::custom)?;
+    let report: ImportReport =
+        import_rows(address_book_id, rows, contacts_repository, validation).await;
+    Ok(warp::reply::json(&report))
+}
+
+/// Wraps a body into a response to be downloaded as a file.
+fn attachment(body: Body, content_type: &'static str, file_name: &str) -> Response<Body> {
+    let mut response: Response<Body> = Response::new(body);
+    let headers = response.headers_mut();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+    if let Ok(content_disposition) =
+        HeaderValue::from_str(&format!("attachment; filename=\"{}\"", file_name))
+    {
+        headers.insert(CONTENT_DISPOSITION, content_disposition);
+    }
+    response
+}
+
+/// Rejects a contact with all the failed checks, if any, as the imported ones are.
+pub async fn validate(
+    new_contact: &NewContact,
+    validation: &ValidationMiddleware,
+) -> Result<(), Error> {
+    let messages: Vec<String> = validation.validate(new_contact).await;
+    if messages.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::InvalidBody(messages.join("; ")))
+    }
+}
+
+/// Validates and adds every parsed row, collecting the outcome in a per-row report.
+async fn import_rows(
+    address_book_id: AddressBookId,
+    rows: Vec<ImportRow>,
+    mut contacts_repository: impl ContactsRepository,
+    validation: Arc<ValidationMiddleware>,
+) -> ImportReport {
+    let mut report: ImportReport = ImportReport::default();
+    for ImportRow { row, new_contact } in rows {
+        let new_contact: NewContact = match new_contact {
+            Ok(new_contact) => new_contact,
+            Err(messages) => {
+                report.add_failure(row, messages);
+                continue;
+            }
+        };
+        let messages: Vec<String> = validation.validate(&new_contact).await;
+        if !messages.is_empty() {
+            report.add_failure(row, messages);
+            continue;
+        }
+        match contacts_repository.add(address_book_id, new_contact).await {
+            Ok(_) => report.add_success(),
+            Err(err) => report.add_failure(row, vec![err.to_string()]),
+        }
+    }
+    report
+}
+
+pub struct Pagination {
+    pub page_no: Option<u32>,
+    pub page_size: Option<u32>,
 }
 
-fn get_pagination(query_parameters: HashMap<String, String>) -> Result<Pagination, Error> {
+pub fn get_pagination(query_parameters: HashMap<String, String>) -> Result<Pagination, Error> {
     let mut page_no: Option<u32> = None;
     if query_parameters.contains_key(PAGE_NO_KEY) {
         page_no = match query_parameters.get(PAGE_NO_KEY).unwrap().parse::<u32>() {
@@ -124,28 +461,500 @@ fn get_pagination(query_parameters: HashMap<String, String>) -> Result<Paginatio
     Ok(Pagination { page_no, page_size })
 }
 
-pub async fn handle_rejection(r: Rejection) -> Result<impl Reply, Rejection> {
+fn get_filter(
+    query_parameters: &HashMap<String, String>,
+    custom_fields: &[CustomField],
+) -> Result<ContactFilter, Error> {
+    let country: Option<CountryCode> = query_parameters
+        .get(COUNTRY_KEY)
+        .map(|country: &String| parse_country(country))
+        .transpose()?;
+    let city: Option<String> = query_parameters
+        .get(CITY_KEY)
+        .map(|city: &String| city.trim().to_string())
+        .filter(|city: &String| !city.is_empty());
+    let tags: Vec<Tag> = query_parameters
+        .get(TAG_KEY)
+        .map(|tags: &String| {
+            tags.split(TAG_SEPARATOR)
+                .filter(|tag: &&str| !tag.trim().is_empty())
+                .map(Tag::parse)
+                .collect::<Result<Vec<Tag>, Error>>()
+        })
+        .transpose()?
+        .unwrap_or_default();
+    let tag_mode: TagMode = query_parameters
+        .get(TAG_MODE_KEY)
+        .map(|tag_mode: &String| tag_mode.parse::<TagMode>())
+        .transpose()?
+        .unwrap_or_default();
+    let mut custom_field_values: CustomFieldValues = CustomFieldValues::new();
+    for (key, value) in query_parameters {
+        let Some(name) = key.strip_prefix(CUSTOM_FIELD_PREFIX) else {
+            continue;
+        };
+        let custom_field: &CustomField = custom_fields
+            .iter()
+            .find(|custom_field: &&CustomField| custom_field.name == name)
+            .ok_or_else(|| Error::InvalidCustomField(format!("Unknown field {name}")))?;
+        custom_field_values.insert(name.to_string(), custom_field.parse_value(value)?);
+    }
+    Ok(ContactFilter {
+        country,
+        city,
+        tags: unique_sorted(tags),
+        tag_mode,
+        custom_fields: custom_field_values,
+    })
+}
+
+/// Reads a tag out of a percent-encoded path segment, e.g. `best%20friends`.
+fn path_tag(tag: &str) -> Result<Tag, Error> {
+    Tag::parse(&percent_decode_str(tag).decode_utf8_lossy())
+}
+
+pub async fn handle_rejection(r: Rejection) -> Result<warp::reply::Response, Rejection> {
     if let Some(Error::StringToU32(message)) = r.find::<Error>() {
+        Ok(warp::reply::with_status(message.to_owned(), StatusCode::BAD_REQUEST).into_response())
+    } else if let Some(Error::Db(message)) = r.find::<Error>() {
+        Ok(
+            warp::reply::with_status(message.to_owned(), StatusCode::INTERNAL_SERVER_ERROR)
+                .into_response(),
+        )
+    } else if let Some(Error::InvalidCsv(message)) = r.find::<Error>() {
+        Ok(warp::reply::with_status(message.to_owned(), StatusCode::BAD_REQUEST).into_response())
+    } else if let Some(Error::InvalidVCard(message)) = r.find::<Error>() {
+        Ok(warp::reply::with_status(message.to_owned(), StatusCode::BAD_REQUEST).into_response())
+    } else if let Some(Error::InvalidXml(message)) = r.find::<Error>() {
+        Ok(warp::reply::with_status(message.to_owned(), StatusCode::BAD_REQUEST).into_response())
+    } else if let Some(Error::InvalidSyncToken(_)) = r.find::<Error>() {
+        Ok(warp::reply::with_status(
+            warp::reply::with_header(
+                carddav_handlers::invalid_sync_token_xml(),
+                CONTENT_TYPE,
+                XML_CONTENT_TYPE,
+            ),
+            StatusCode::FORBIDDEN,
+        )
+        .into_response())
+    } else if let Some(Error::PreconditionFailed(message)) = r.find::<Error>() {
+        Ok(
+            warp::reply::with_status(message.to_owned(), StatusCode::PRECONDITION_FAILED)
+                .into_response(),
+        )
+    } else if let Some(Error::NotAcceptable(_)) = r.find::<Error>() {
+        Ok(warp::reply::with_status(
+            format!(
+                "Supported media types: {}",
+                MediaType::all()
+                    .iter()
+                    .map(|media_type: &MediaType| media_type.as_str())
+                    .collect::<Vec<&str>>()
+                    .join(", ")
+            ),
+            StatusCode::NOT_ACCEPTABLE,
+        )
+        .into_response())
+    } else if let Some(Error::UnsupportedMediaType(content_type)) = r.find::<Error>() {
+        Ok(warp::reply::with_status(
+            Error::UnsupportedMediaType(content_type.to_owned()).to_string(),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        )
+        .into_response())
+    } else if let Some(Error::InvalidCountry(country)) = r.find::<Error>() {
+        Ok(warp::reply::with_status(
+            Error::InvalidCountry(country.to_owned()).to_string(),
+            StatusCode::BAD_REQUEST,
+        )
+        .into_response())
+    } else if let Some(Error::InvalidTag(tag)) = r.find::<Error>() {
         Ok(warp::reply::with_status(
-            message.to_owned(),
+            Error::InvalidTag(tag.to_owned()).to_string(),
             StatusCode::BAD_REQUEST,
+        )
+        .into_response())
+    } else if let Some(Error::InvalidTagMode(tag_mode)) = r.find::<Error>() {
+        Ok(warp::reply::with_status(
+            Error::InvalidTagMode(tag_mode.to_owned()).to_string(),
+            StatusCode::BAD_REQUEST,
+        )
+        .into_response())
+    } else if let Some(Error::InvalidDays(days)) = r.find::<Error>() {
+        Ok(warp::reply::with_status(
+            Error::InvalidDays(days.to_owned()).to_string(),
+            StatusCode::BAD_REQUEST,
+        )
+        .into_response())
+    } else if let Some(Error::InvalidDepth(depth)) = r.find::<Error>() {
+        Ok(warp::reply::with_status(
+            Error::InvalidDepth(depth.to_owned()).to_string(),
+            StatusCode::BAD_REQUEST,
+        )
+        .into_response())
+    } else if let Some(Error::InvalidCustomField(message)) = r.find::<Error>() {
+        Ok(warp::reply::with_status(
+            Error::InvalidCustomField(message.to_owned()).to_string(),
+            StatusCode::BAD_REQUEST,
+        )
+        .into_response())
+    } else if let Some(Error::UnknownOrganization(id)) = r.find::<Error>() {
+        Ok(warp::reply::with_status(
+            Error::UnknownOrganization(*id).to_string(),
+            StatusCode::BAD_REQUEST,
+        )
+        .into_response())
+    } else if let Some(Error::UnknownAddressBook(id)) = r.find::<Error>() {
+        Ok(problem(
+            StatusCode::NOT_FOUND,
+            &Error::UnknownAddressBook(*id).to_string(),
         ))
-    } else if let Some(Error::Db(message)) = r.find::<Error>() {
+    } else if let Some(Error::Conflict(message)) = r.find::<Error>() {
+        Ok(warp::reply::with_status(
+            Error::Conflict(message.to_owned()).to_string(),
+            StatusCode::CONFLICT,
+        )
+        .into_response())
+    } else if let Some(Error::InvalidPhoto(message)) = r.find::<Error>() {
+        Ok(warp::reply::with_status(
+            Error::InvalidPhoto(message.to_owned()).to_string(),
+            StatusCode::BAD_REQUEST,
+        )
+        .into_response())
+    } else if let Some(Error::Storage(message)) = r.find::<Error>() {
+        Ok(
+            warp::reply::with_status(message.to_owned(), StatusCode::INTERNAL_SERVER_ERROR)
+                .into_response(),
+        )
+    } else if r.find::<PayloadTooLarge>().is_some() {
         Ok(warp::reply::with_status(
-            message.to_owned(),
-            StatusCode::INTERNAL_SERVER_ERROR,
+            "Payload too large".to_string(),
+            StatusCode::PAYLOAD_TOO_LARGE,
+        )
+        .into_response())
+    } else if let Some(Error::InvalidBody(message)) = r.find::<Error>() {
+        Ok(warp::reply::with_status(message.to_owned(), StatusCode::BAD_REQUEST).into_response())
+    } else if let Some(Error::Encoding(message)) = r.find::<Error>() {
+        Ok(
+            warp::reply::with_status(message.to_owned(), StatusCode::INTERNAL_SERVER_ERROR)
+                .into_response(),
+        )
+    } else if let Some(Error::Unauthorized) = r.find::<Error>() {
+        Ok(unauthorized(
+            Error::Unauthorized.to_string(),
+            BEARER_AUTH_CHALLENGE,
         ))
-    } else if let Some(Error::NotFound { id }) = r.find::<Error>() {
+    } else if let Some(Error::TooManyAttempts { retry_after }) = r.find::<Error>() {
         Ok(warp::reply::with_status(
-            id.to_string(),
-            StatusCode::NOT_FOUND,
+            warp::reply::with_header(
+                Error::TooManyAttempts {
+                    retry_after: *retry_after,
+                }
+                .to_string(),
+                RETRY_AFTER,
+                retry_after.to_string(),
+            ),
+            StatusCode::TOO_MANY_REQUESTS,
+        )
+        .into_response())
+    } else if let Some(Error::RateLimited {
+        limit,
+        reset,
+        retry_after,
+    }) = r.find::<Error>()
+    {
+        let mut response: warp::reply::Response = problem(
+            StatusCode::TOO_MANY_REQUESTS,
+            &Error::RateLimited {
+                limit: *limit,
+                reset: *reset,
+                retry_after: *retry_after,
+            }
+            .to_string(),
+        );
+        for (name, value) in [
+            (RETRY_AFTER.as_str(), *retry_after),
+            (RATELIMIT_LIMIT, *limit),
+            (RATELIMIT_REMAINING, 0),
+            (RATELIMIT_RESET, *reset),
+        ] {
+            response
+                .headers_mut()
+                .insert(name, HeaderValue::from(value));
+        }
+        Ok(response)
+    } else if let Some(Error::InvalidToken(message)) = r.find::<Error>() {
+        Ok(unauthorized(
+            Error::InvalidToken(message.to_owned()).to_string(),
+            INVALID_TOKEN_CHALLENGE,
         ))
+    } else if let Some(Error::InvalidApiUser(message)) = r.find::<Error>() {
+        Ok(warp::reply::with_status(
+            Error::InvalidApiUser(message.to_owned()).to_string(),
+            StatusCode::BAD_REQUEST,
+        )
+        .into_response())
+    } else if let Some(Error::InvalidApiKey(message)) = r.find::<Error>() {
+        Ok(warp::reply::with_status(
+            Error::InvalidApiKey(message.to_owned()).to_string(),
+            StatusCode::BAD_REQUEST,
+        )
+        .into_response())
+    } else if let Some(Error::UnknownUser(username)) = r.find::<Error>() {
+        Ok(warp::reply::with_status(
+            Error::UnknownUser(username.to_owned()).to_string(),
+            StatusCode::NOT_FOUND,
+        )
+        .into_response())
+    } else if let Some(Error::Forbidden(message)) = r.find::<Error>() {
+        Ok(problem(StatusCode::FORBIDDEN, message))
+    } else if let Some(Error::InvalidJwtKey(message)) = r.find::<Error>() {
+        Ok(
+            warp::reply::with_status(message.to_owned(), StatusCode::INTERNAL_SERVER_ERROR)
+                .into_response(),
+        )
+    } else if let Some(Error::NotFound { id }) = r.find::<Error>() {
+        Ok(warp::reply::with_status(id.to_string(), StatusCode::NOT_FOUND).into_response())
     } else {
         Ok(warp::reply::with_status(
             "Bad request of route not found".to_string(),
             StatusCode::BAD_REQUEST,
-        ))
+        )
+        .into_response())
     }
 }
 
+/// A 401 response challenging the client to authenticate by either HTTP Basic Auth or a bearer token.
+fn unauthorized(message: String, bearer_challenge: &'static str) -> warp::reply::Response {
+    let mut response: warp::reply::Response =
+        warp::reply::with_status(message, StatusCode::UNAUTHORIZED).into_response();
+    response.headers_mut().append(
+        WWW_AUTHENTICATE,
+        HeaderValue::from_static(BASIC_AUTH_CHALLENGE),
+    );
+    response
+        .headers_mut()
+        .append(WWW_AUTHENTICATE, HeaderValue::from_static(bearer_challenge));
+    response
+}
+
+/// A RFC 7807 problem details response, e.g. `{"type": "about:blank", "title": "Forbidden", "status": 403, "detail": "..."}`.
+fn problem(status: StatusCode, detail: &str) -> warp::reply::Response {
+    let body: serde_json::Value = serde_json::json!({
+        "type": "about:blank",
+        "title": status.canonical_reason().unwrap_or_default(),
+        "status": status.as_u16(),
+        "detail": detail,
+    });
+    warp::reply::with_status(
+        warp::reply::with_header(warp::reply::json(&body), CONTENT_TYPE, PROBLEM_CONTENT_TYPE),
+        status,
+    )
+    .into_response()
+}
+
 impl Reject for Error {}
+
+#[cfg(test)]
+mod tests {
+    use warp::Filter;
+
+    use super::*;
+    use crate::models::contact::Email;
+    use crate::models::contact::EmailType;
+    use crate::models::phone_number::PhoneNumber;
+    use crate::repositories::contacts_in_memory_repository::ContactsInMemoryRepository;
+
+    const BOOK: AddressBookId = AddressBookId(1);
+
+    /// Validates against a stub of the number verification API, telling the German numbers valid.
+    async fn validation() -> Arc<ValidationMiddleware> {
+        let number_verification = warp::path!("validate")
+            .and(warp::query::<HashMap<String, String>>())
+            .map(|query: HashMap<String, String>| {
+                let number: String = query.get("number").cloned().unwrap_or_default();
+                warp::reply::json(&serde_json::json!({
+                    "valid": number.starts_with("49"),
+                    "number": number,
+                    "local_format": "",
+                    "international_format": "",
+                    "country_prefix": "",
+                    "country_code": "DE",
+                    "country_name": "",
+                    "location": "",
+                    "carrier": "",
+                    "line_type": "",
+                }))
+            });
+        let (addr, server) = warp::serve(number_verification).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+        Arc::new(ValidationMiddleware::new_with_api_layer(
+            "test".to_string(),
+            format!("http://{addr}/validate?number="),
+        ))
+    }
+
+    fn email(address: &str, primary: bool) -> Email {
+        Email {
+            email_type: EmailType::default(),
+            address: address.to_string(),
+            primary,
+        }
+    }
+
+    fn new_contact(emails: Vec<Email>) -> NewContact {
+        NewContact {
+            name: "John".to_string(),
+            phones: vec![],
+            emails,
+            addresses: vec![],
+            tags: vec![],
+            organization: None,
+            custom_fields: CustomFieldValues::new(),
+            birthday: None,
+            dates: vec![],
+        }
+    }
+
+    async fn is_bad_request(rejection: Rejection) -> bool {
+        handle_rejection(rejection).await.unwrap().status() == StatusCode::BAD_REQUEST
+    }
+
+    #[tokio::test]
+    async fn test_add_and_update_validate_the_contact() {
+        let repository: ContactsInMemoryRepository = ContactsInMemoryRepository::new();
+        let validation: Arc<ValidationMiddleware> = validation().await;
+
+        let two_primaries: NewContact = new_contact(vec![
+            email("john@example.com", true),
+            email("john@example.org", true),
+        ]);
+        let rejection: Rejection =
+            add_conact(BOOK, two_primaries, repository.clone(), validation.clone())
+                .await
+                .err()
+                .unwrap();
+        assert!(is_bad_request(rejection).await);
+        let rejection: Rejection = add_conact(
+            BOOK,
+            new_contact(vec![email("john", false)]),
+            repository.clone(),
+            validation.clone(),
+        )
+        .await
+        .err()
+        .unwrap();
+        assert!(is_bad_request(rejection).await);
+        assert!(add_conact(
+            BOOK,
+            new_contact(vec![email("john@example.com", true)]),
+            repository.clone(),
+            validation.clone(),
+        )
+        .await
+        .is_ok());
+
+        let mut contact: Contact = repository
+            .get_all(BOOK, &ContactFilter::default(), None, None)
+            .await
+            .unwrap()
+            .remove(0);
+        contact.emails.push(email("john@example.org", true));
+        let rejection: Rejection = update_contact(
+            BOOK,
+            contact.id.0,
+            contact.clone(),
+            repository.clone(),
+            validation.clone(),
+        )
+        .await
+        .err()
+        .unwrap();
+        assert!(is_bad_request(rejection).await);
+        contact.emails[1].primary = false;
+        assert!(update_contact(
+            BOOK,
+            contact.id.0,
+            contact.clone(),
+            repository.clone(),
+            validation,
+        )
+        .await
+        .is_ok());
+        assert_eq!(
+            2,
+            repository
+                .get(BOOK, contact.id)
+                .await
+                .unwrap()
+                .unwrap()
+                .emails
+                .len()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_update_email_and_phone_no_validate_them() {
+        let mut repository: ContactsInMemoryRepository = ContactsInMemoryRepository::new();
+        let validation: Arc<ValidationMiddleware> = validation().await;
+        let contact: Contact = repository
+            .add(BOOK, new_contact(vec![email("john@example.com", true)]))
+            .await
+            .unwrap();
+
+        let rejection: Rejection = update_contact_email(
+            BOOK,
+            contact.id.0,
+            UpdateContactEmail {
+                email: "john".to_string(),
+            },
+            repository.clone(),
+            validation.clone(),
+        )
+        .await
+        .err()
+        .unwrap();
+        assert!(is_bad_request(rejection).await);
+        let rejection: Rejection = update_contact_phone_no(
+            BOOK,
+            contact.id.0,
+            UpdateContactPhoneNo {
+                phone_no: PhoneNumber::parse("+14155552671").unwrap(),
+            },
+            repository.clone(),
+            validation.clone(),
+        )
+        .await
+        .err()
+        .unwrap();
+        assert!(is_bad_request(rejection).await);
+        let unchanged: Contact = repository
+            .get(BOOK, contact.id.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!("john@example.com", unchanged.emails[0].address);
+        assert!(unchanged.phones.is_empty());
+
+        assert!(update_contact_phone_no(
+            BOOK,
+            contact.id.0,
+            UpdateContactPhoneNo {
+                phone_no: PhoneNumber::parse("+4915112345678").unwrap(),
+            },
+            repository.clone(),
+            validation,
+        )
+        .await
+        .is_ok());
+        assert_eq!(
+            1,
+            repository
+                .get(BOOK, contact.id)
+                .await
+                .unwrap()
+                .unwrap()
+                .phones
+                .len()
+        );
+    }
+}
@@ -1,24 +1,97 @@
 use std::convert::Infallible;
+use std::sync::Arc;
 
 use serde::de::DeserializeOwned;
 use warp::cors::Builder;
+use warp::http::header::ACCEPT;
+use warp::http::header::ACCEPT_ENCODING;
+use warp::http::header::CONTENT_TYPE;
+use warp::http::header::IF_MODIFIED_SINCE;
+use warp::hyper::body::Bytes;
 use warp::hyper::Method;
 use warp::log::Info;
 use warp::Filter;
 use warp::Rejection;
 use warp::Reply;
 
+use crate::api::address_books_routes::get_address_books_routes;
+use crate::api::address_books_routes::require_address_book;
+use crate::api::api_keys_routes::get_api_keys_routes;
+use crate::api::api_users_routes::get_api_users_routes;
+use crate::api::auth_filters::SharedAuthMiddleware;
+use crate::api::auth_filters::SharedTokenService;
+use crate::api::auth_routes::get_auth_routes;
+use crate::api::carddav_routes::get_carddav_routes;
+use crate::api::compression;
 use crate::api::contacts_handlers;
-use crate::repositories::contacts_db_repository::ContactsDbRepository;
+use crate::api::content_negotiation;
+use crate::api::content_negotiation::MediaType;
+use crate::api::custom_fields_routes::get_custom_fields_routes;
+use crate::api::notes_routes::get_notes_routes;
+use crate::api::organizations_routes::get_organizations_routes;
+use crate::api::photos_routes::get_photos_routes;
+use crate::api::photos_routes::with_photo_storage;
+use crate::api::rate_limiting;
+use crate::api::rate_limiting::RateLimitedAuthMiddleware;
+use crate::api::rate_limiting::SharedRateLimiter;
+use crate::api::relations_routes::get_relations_routes;
+use crate::formats::contacts_csv::CsvRecord;
+use crate::formats::contacts_vcard::VCardFileName;
+use crate::middleware::validation::ValidationMiddleware;
+use crate::models::address_book::AddressBookId;
+use crate::models::role::Role;
+use crate::repositories::address_books_repository::AddressBooksRepository;
+use crate::repositories::api_keys_repository::SharedApiKeysRepository;
+use crate::repositories::api_users_repository::SharedApiUsersRepository;
+use crate::repositories::contacts_repository::ContactsRepository;
+use crate::repositories::custom_fields_repository::CustomFieldsRepository;
+use crate::repositories::notes_repository::NotesRepository;
+use crate::repositories::organizations_repository::OrganizationsRepository;
+use crate::repositories::photo_storage::SharedPhotoStorage;
+use crate::repositories::relations_repository::RelationsRepository;
 
 const MAX_JSON_PAYLOAD_SIZE: u64 = 1024 * 16;
+const MAX_IMPORT_PAYLOAD_SIZE: u64 = 1024 * 1024;
 
-pub fn get_all_routes(
-    db_repository: ContactsDbRepository,
-) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+#[allow(clippy::too_many_arguments)]
+pub fn get_all_routes<R>(
+    contacts_repository: R,
+    validation: Arc<ValidationMiddleware>,
+    auth_middleware: SharedAuthMiddleware,
+    token_service: SharedTokenService,
+    photo_storage: SharedPhotoStorage,
+    api_users_repository: SharedApiUsersRepository,
+    api_keys_repository: SharedApiKeysRepository,
+    rate_limiter: SharedRateLimiter,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: ContactsRepository
+        + AddressBooksRepository
+        + OrganizationsRepository
+        + NotesRepository
+        + CustomFieldsRepository
+        + RelationsRepository
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+{
     let cors: Builder = warp::cors()
         .allow_any_origin()
-        .allow_header("content-type")
+        .allow_headers([
+            "accept",
+            "accept-encoding",
+            "authorization",
+            "content-type",
+            "if-modified-since",
+            "x-api-key",
+        ])
+        .expose_headers([
+            rate_limiting::RATELIMIT_LIMIT,
+            rate_limiting::RATELIMIT_REMAINING,
+            rate_limiting::RATELIMIT_RESET,
+            "retry-after",
+        ])
         .allow_methods([
             Method::GET.as_str(),
             Method::POST.as_str(),
@@ -31,93 +104,493 @@ pub fn get_all_routes(
         eprintln!("{} {} {}", info.method(), info.path(), info.status());
     });
 
-    get_all_contacts_route(db_repository.clone())
-        .or(get_contact_route(db_repository.clone()))
-        .or(add_contact_route(db_repository.clone()))
-        .or(update_contact_route(db_repository.clone()))
-        .or(update_contact_email_route(db_repository.clone()))
-        .or(update_contact_phone_no_route(db_repository.clone()))
-        .or(delete_contact_route(db_repository))
+    // The routes authenticating a client tell the rate limiter who it is, for its next requests
+    let auth_middleware: SharedAuthMiddleware = Arc::new(RateLimitedAuthMiddleware::new(
+        auth_middleware,
+        rate_limiter.clone(),
+    ));
+    let routes = get_auth_routes(auth_middleware.clone(), token_service.clone())
+        .or(get_api_users_routes(
+            api_users_repository,
+            auth_middleware.clone(),
+        ))
+        .or(get_api_keys_routes(
+            api_keys_repository,
+            auth_middleware.clone(),
+        ))
+        .or(get_address_books_routes(
+            contacts_repository.clone(),
+            photo_storage.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(get_all_contacts_route(
+            contacts_repository.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(export_contacts_csv_route(
+            contacts_repository.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(import_contacts_csv_route(
+            contacts_repository.clone(),
+            validation.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(get_contact_route(
+            contacts_repository.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(get_contact_vcard_route(
+            contacts_repository.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(export_contacts_vcard_route(
+            contacts_repository.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(import_contacts_vcard_route(
+            contacts_repository.clone(),
+            validation.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(add_contact_route(
+            contacts_repository.clone(),
+            validation.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(update_contact_route(
+            contacts_repository.clone(),
+            validation.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(update_contact_email_route(
+            contacts_repository.clone(),
+            validation.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(update_contact_phone_no_route(
+            contacts_repository.clone(),
+            validation.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(delete_contact_route(
+            contacts_repository.clone(),
+            photo_storage.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(add_contact_tag_route(
+            contacts_repository.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(remove_contact_tag_route(
+            contacts_repository.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(get_tags_route(
+            contacts_repository.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(get_upcoming_dates_route(
+            contacts_repository.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(get_organizations_routes(
+            contacts_repository.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(get_custom_fields_routes(
+            contacts_repository.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(get_notes_routes(
+            contacts_repository.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(get_relations_routes(
+            contacts_repository.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(get_photos_routes(
+            contacts_repository.clone(),
+            photo_storage.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(get_carddav_routes(
+            contacts_repository,
+            validation,
+            auth_middleware,
+            photo_storage,
+        ))
+        .recover(contacts_handlers::handle_rejection)
+        .map(Reply::into_response)
+        // Keeps the type of the routes within the compiler's recursion limit once wrapped
+        .boxed();
+
+    // Rate limited before anything else, so that the limited requests don't reach the database,
+    // while the responses to the other ones, errors included, tell what is left of the limit
+    let routes = rate_limiting::with_rate_limit(rate_limiter, token_service)
+        .and(routes)
+        .map(rate_limiting::with_rate_limit_headers)
         .with(cors)
         .with(logging)
-        .recover(contacts_handlers::handle_rejection)
+        .recover(contacts_handlers::handle_rejection);
+
+    warp::header::optional::<String>(ACCEPT_ENCODING.as_str())
+        .and(routes)
+        .map(compression::compress)
 }
 
-fn get_all_contacts_route(
-    db_repository: ContactsDbRepository,
-) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
-    warp::path!("contacts")
+fn get_all_contacts_route<R>(
+    contacts_repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: ContactsRepository
+        + CustomFieldsRepository
+        + AddressBooksRepository
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+{
+    warp::path!("address-books" / AddressBookId / "contacts")
         .and(warp::get())
+        .and(require_address_book(
+            contacts_repository.clone(),
+            auth_middleware,
+            Role::Reader,
+        ))
         .and(warp::query())
-        .and(with_repository(db_repository))
+        .and(warp::header::optional::<String>(ACCEPT.as_str()))
+        .and(warp::header::optional::<String>(IF_MODIFIED_SINCE.as_str()))
+        .and(with_repository(contacts_repository))
         .and_then(contacts_handlers::get_all_contacts)
 }
 
-fn get_contact_route(
-    db_repository: ContactsDbRepository,
-) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
-    warp::path!("contacts" / i32)
+fn export_contacts_csv_route<R>(
+    contacts_repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: ContactsRepository + AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("address-books" / AddressBookId / "contacts" / "export.csv")
+        .and(warp::get())
+        .and(require_address_book(
+            contacts_repository.clone(),
+            auth_middleware,
+            Role::Reader,
+        ))
+        .and(with_repository(contacts_repository))
+        .and_then(contacts_handlers::export_contacts_csv)
+}
+
+fn import_contacts_csv_route<R>(
+    contacts_repository: R,
+    validation: Arc<ValidationMiddleware>,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: ContactsRepository + AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("address-books" / AddressBookId / "contacts" / "import")
+        .and(warp::post())
+        .and(require_address_book(
+            contacts_repository.clone(),
+            auth_middleware,
+            Role::Editor,
+        ))
+        .and(warp::query())
+        .and(import_body())
+        .and(with_repository(contacts_repository))
+        .and(with_validation(validation))
+        .and_then(contacts_handlers::import_contacts_csv)
+}
+
+fn get_contact_vcard_route<R>(
+    contacts_repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: ContactsRepository + AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("address-books" / AddressBookId / "contacts" / VCardFileName)
+        .and(warp::get())
+        .and(require_address_book(
+            contacts_repository.clone(),
+            auth_middleware,
+            Role::Reader,
+        ))
+        .and(warp::query())
+        .and(with_repository(contacts_repository))
+        .and_then(contacts_handlers::get_contact_vcard)
+}
+
+fn export_contacts_vcard_route<R>(
+    contacts_repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: ContactsRepository + AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("address-books" / AddressBookId / "contacts" / "export.vcf")
+        .and(warp::get())
+        .and(require_address_book(
+            contacts_repository.clone(),
+            auth_middleware,
+            Role::Reader,
+        ))
+        .and(warp::query())
+        .and(with_repository(contacts_repository))
+        .and_then(contacts_handlers::export_contacts_vcard)
+}
+
+fn import_contacts_vcard_route<R>(
+    contacts_repository: R,
+    validation: Arc<ValidationMiddleware>,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: ContactsRepository + AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("address-books" / AddressBookId / "contacts" / "import" / "vcard")
+        .and(warp::post())
+        .and(require_address_book(
+            contacts_repository.clone(),
+            auth_middleware,
+            Role::Editor,
+        ))
+        .and(import_body())
+        .and(with_repository(contacts_repository))
+        .and(with_validation(validation))
+        .and_then(contacts_handlers::import_contacts_vcard)
+}
+
+fn get_contact_route<R>(
+    contacts_repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: ContactsRepository + AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("address-books" / AddressBookId / "contacts" / i32)
         .and(warp::get())
-        .and(with_repository(db_repository))
+        .and(require_address_book(
+            contacts_repository.clone(),
+            auth_middleware,
+            Role::Reader,
+        ))
+        .and(warp::header::optional::<String>(ACCEPT.as_str()))
+        .and(warp::header::optional::<String>(IF_MODIFIED_SINCE.as_str()))
+        .and(with_repository(contacts_repository))
         .and_then(contacts_handlers::get_contact)
 }
 
-fn add_contact_route(
-    db_repository: ContactsDbRepository,
-) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
-    warp::path!("contacts")
+fn add_contact_route<R>(
+    contacts_repository: R,
+    validation: Arc<ValidationMiddleware>,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: ContactsRepository + AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("address-books" / AddressBookId / "contacts")
         .and(warp::post())
+        .and(require_address_book(
+            contacts_repository.clone(),
+            auth_middleware,
+            Role::Editor,
+        ))
         .and(json_body())
-        .and(with_repository(db_repository))
+        .and(with_repository(contacts_repository))
+        .and(with_validation(validation))
         .and_then(contacts_handlers::add_conact)
 }
 
-fn update_contact_route(
-    db_repository: ContactsDbRepository,
-) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
-    warp::path!("contacts" / i32)
+fn update_contact_route<R>(
+    contacts_repository: R,
+    validation: Arc<ValidationMiddleware>,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: ContactsRepository + AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("address-books" / AddressBookId / "contacts" / i32)
         .and(warp::put())
+        .and(require_address_book(
+            contacts_repository.clone(),
+            auth_middleware,
+            Role::Editor,
+        ))
         .and(json_body())
-        .and(with_repository(db_repository))
+        .and(with_repository(contacts_repository))
+        .and(with_validation(validation))
         .and_then(contacts_handlers::update_contact)
 }
 
-fn update_contact_email_route(
-    db_repository: ContactsDbRepository,
-) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
-    warp::path!("contacts-update-email" / i32)
+fn update_contact_email_route<R>(
+    contacts_repository: R,
+    validation: Arc<ValidationMiddleware>,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: ContactsRepository + AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("address-books" / AddressBookId / "contacts-update-email" / i32)
         .and(warp::post())
+        .and(require_address_book(
+            contacts_repository.clone(),
+            auth_middleware,
+            Role::Editor,
+        ))
         .and(json_body())
-        .and(with_repository(db_repository))
+        .and(with_repository(contacts_repository))
+        .and(with_validation(validation))
         .and_then(contacts_handlers::update_contact_email)
 }
 
-fn update_contact_phone_no_route(
-    db_repository: ContactsDbRepository,
-) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
-    warp::path!("contacts-update-phone-no" / i32)
+fn update_contact_phone_no_route<R>(
+    contacts_repository: R,
+    validation: Arc<ValidationMiddleware>,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: ContactsRepository + AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("address-books" / AddressBookId / "contacts-update-phone-no" / i32)
         .and(warp::post())
+        .and(require_address_book(
+            contacts_repository.clone(),
+            auth_middleware,
+            Role::Editor,
+        ))
         .and(json_body())
-        .and(with_repository(db_repository))
+        .and(with_repository(contacts_repository))
+        .and(with_validation(validation))
         .and_then(contacts_handlers::update_contact_phone_no)
 }
 
-fn delete_contact_route(
-    db_repository: ContactsDbRepository,
-) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
-    warp::path!("contacts" / i32)
+fn delete_contact_route<R>(
+    contacts_repository: R,
+    photo_storage: SharedPhotoStorage,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: ContactsRepository + AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("address-books" / AddressBookId / "contacts" / i32)
         .and(warp::delete())
-        .and(with_repository(db_repository))
+        .and(require_address_book(
+            contacts_repository.clone(),
+            auth_middleware,
+            Role::Admin,
+        ))
+        .and(with_repository(contacts_repository))
+        .and(with_photo_storage(photo_storage))
         .and_then(contacts_handlers::delete_contact)
 }
 
-fn with_repository(
-    db_repository: ContactsDbRepository,
-) -> impl Filter<Extract = (ContactsDbRepository,), Error = Infallible> + Clone {
-    warp::any().map(move || db_repository.clone())
+fn add_contact_tag_route<R>(
+    contacts_repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: ContactsRepository + AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("address-books" / AddressBookId / "contacts" / i32 / "tags" / String)
+        .and(warp::put())
+        .and(require_address_book(
+            contacts_repository.clone(),
+            auth_middleware,
+            Role::Editor,
+        ))
+        .and(with_repository(contacts_repository))
+        .and_then(contacts_handlers::add_contact_tag)
+}
+
+fn remove_contact_tag_route<R>(
+    contacts_repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: ContactsRepository + AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("address-books" / AddressBookId / "contacts" / i32 / "tags" / String)
+        .and(warp::delete())
+        .and(require_address_book(
+            contacts_repository.clone(),
+            auth_middleware,
+            Role::Editor,
+        ))
+        .and(with_repository(contacts_repository))
+        .and_then(contacts_handlers::remove_contact_tag)
+}
+
+fn get_tags_route<R>(
+    contacts_repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: ContactsRepository + AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("address-books" / AddressBookId / "tags")
+        .and(warp::get())
+        .and(require_address_book(
+            contacts_repository.clone(),
+            auth_middleware,
+            Role::Reader,
+        ))
+        .and(with_repository(contacts_repository))
+        .and_then(contacts_handlers::get_tags)
+}
+
+fn get_upcoming_dates_route<R>(
+    contacts_repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: ContactsRepository + AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("address-books" / AddressBookId / "contacts" / "upcoming-dates")
+        .and(warp::get())
+        .and(require_address_book(
+            contacts_repository.clone(),
+            auth_middleware,
+            Role::Reader,
+        ))
+        .and(warp::query())
+        .and(with_repository(contacts_repository))
+        .and_then(contacts_handlers::get_upcoming_dates)
 }
 
-fn json_body<T: DeserializeOwned + Send>() -> impl Filter<Extract = (T,), Error = Rejection> + Clone
+fn with_repository<R>(
+    contacts_repository: R,
+) -> impl Filter<Extract = (R,), Error = Infallible> + Clone
+where
+    R: ContactsRepository + Clone + Send + Sync + 'static,
 {
-    warp::body::content_length_limit(MAX_JSON_PAYLOAD_SIZE).and(warp::body::json())
+    warp::any().map(move || contacts_repository.clone())
+}
+
+/// The payload of a request, decoded according to its `Content-Type` (JSON, MessagePack, CBOR or CSV).
+fn json_body<T: DeserializeOwned + CsvRecord + Send>(
+) -> impl Filter<Extract = (T,), Error = Rejection> + Clone {
+    warp::body::content_length_limit(MAX_JSON_PAYLOAD_SIZE)
+        .and(warp::header::optional::<String>(CONTENT_TYPE.as_str()))
+        .and(warp::body::bytes())
+        .and_then(|content_type: Option<String>, body: Bytes| async move {
+            MediaType::from_content_type(content_type.as_deref())
+                .and_then(|media_type: MediaType| content_negotiation::decode(&body, media_type))
+                .map_err(warp::reject::custom)
+        })
+}
+
+pub fn with_validation(
+    validation: Arc<ValidationMiddleware>,
+) -> impl Filter<Extract = (Arc<ValidationMiddleware>,), Error = Infallible> + Clone {
+    warp::any().map(move || validation.clone())
+}
+
+fn import_body() -> impl Filter<Extract = (Bytes,), Error = Rejection> + Clone {
+    warp::body::content_length_limit(MAX_IMPORT_PAYLOAD_SIZE).and(warp::body::bytes())
 }
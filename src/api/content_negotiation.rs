@@ -0,0 +1,273 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use warp::http::header::CONTENT_TYPE;
+use warp::http::HeaderValue;
+use warp::hyper::Body;
+use warp::hyper::Response;
+
+use crate::formats::contacts_csv::CsvRecord;
+use crate::models::errors::Error;
+
+/// The media types the API can encode responses to and decode requests from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaType {
+    Json,
+    MessagePack,
+    Cbor,
+    Csv,
+}
+
+impl MediaType {
+    pub fn all() -> [MediaType; 4] {
+        [
+            MediaType::Json,
+            MediaType::MessagePack,
+            MediaType::Cbor,
+            MediaType::Csv,
+        ]
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MediaType::Json => "application/json",
+            MediaType::MessagePack => "application/msgpack",
+            MediaType::Cbor => "application/cbor",
+            MediaType::Csv => "text/csv; charset=utf-8",
+        }
+    }
+
+    fn from_mime(mime: &str) -> Option<Self> {
+        match mime.trim().to_ascii_lowercase().as_str() {
+            "application/json" | "application/*" | "*/*" => Some(MediaType::Json),
+            "application/msgpack" | "application/x-msgpack" | "application/vnd.msgpack" => {
+                Some(MediaType::MessagePack)
+            }
+            "application/cbor" => Some(MediaType::Cbor),
+            "text/csv" | "text/*" => Some(MediaType::Csv),
+            _ => None,
+        }
+    }
+
+    /// Picks the supported media type the client prefers, by `q` value, out of an `Accept` header.
+    /// Without an `Accept` header JSON is used; if none of the accepted media types is supported, fails with `Error::NotAcceptable`.
+    pub fn from_accept(accept: Option<&str>) -> Result<Self, Error> {
+        let Some(accept) = accept.filter(|x: &&str| !x.trim().is_empty()) else {
+            return Ok(MediaType::Json);
+        };
+        let mut candidates: Vec<(f32, MediaType)> = accept
+            .split(',')
+            .filter_map(|range: &str| {
+                let mut parts = range.split(';');
+                let media_type: MediaType = MediaType::from_mime(parts.next()?)?;
+                let quality: f32 = parts
+                    .filter_map(|param: &str| param.trim().strip_prefix("q="))
+                    .find_map(|q: &str| q.parse::<f32>().ok())
+                    .unwrap_or(1.0);
+                Some((quality, media_type))
+            })
+            .filter(|(quality, _)| *quality > 0.0)
+            .collect();
+        candidates.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+        candidates
+            .first()
+            .map(|(_, media_type)| *media_type)
+            .ok_or_else(|| Error::NotAcceptable(accept.to_string()))
+    }
+
+    /// Reads the media type of a request body out of its `Content-Type` header, JSON if missing.
+    /// Fails with `Error::UnsupportedMediaType` for any other media type than the supported ones.
+    pub fn from_content_type(content_type: Option<&str>) -> Result<Self, Error> {
+        let Some(content_type) = content_type else {
+            return Ok(MediaType::Json);
+        };
+        let mime: &str = content_type.split(';').next().unwrap_or_default();
+        match MediaType::from_mime(mime) {
+            Some(media_type) if !mime.contains('*') => Ok(media_type),
+            _ => Err(Error::UnsupportedMediaType(content_type.to_string())),
+        }
+    }
+}
+
+/// Encodes a single value. As CSV, that is a header line followed by a single record.
+pub fn encode_one<T: Serialize + CsvRecord>(
+    value: &T,
+    media_type: MediaType,
+) -> Result<Vec<u8>, Error> {
+    match media_type {
+        MediaType::Csv => encode_csv(std::slice::from_ref(value)),
+        _ => encode(value, media_type),
+    }
+}
+
+/// Encodes a list of values. As CSV, that is a header line followed by a record per value.
+pub fn encode_many<T: Serialize + CsvRecord>(
+    values: &[T],
+    media_type: MediaType,
+) -> Result<Vec<u8>, Error> {
+    match media_type {
+        MediaType::Csv => encode_csv(values),
+        _ => encode(&values, media_type),
+    }
+}
+
+/// Decodes a request body. As CSV, the body is expected to have a header line and a single record.
+/// A CSV payload the record cannot be converted from fails with `Error::InvalidCsv`.
+pub fn decode<T: DeserializeOwned + CsvRecord>(
+    body: &[u8],
+    media_type: MediaType,
+) -> Result<T, Error> {
+    match media_type {
+        MediaType::Json => {
+            serde_json::from_slice(body).map_err(|err| Error::InvalidBody(err.to_string()))
+        }
+        MediaType::MessagePack => {
+            rmp_serde::from_slice(body).map_err(|err| Error::InvalidBody(err.to_string()))
+        }
+        MediaType::Cbor => {
+            ciborium::de::from_reader(body).map_err(|err| Error::InvalidBody(err.to_string()))
+        }
+        MediaType::Csv => csv::Reader::from_reader(body)
+            .deserialize::<T::Record>()
+            .next()
+            .unwrap_or_else(|| {
+                Err(csv::Error::from(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "no record found",
+                )))
+            })
+            .map_err(|err| Error::InvalidBody(err.to_string()))
+            .and_then(T::from_record),
+    }
+}
+
+/// Wraps an encoded value into a response with the matching `Content-Type`.
+pub fn reply(body: Vec<u8>, media_type: MediaType) -> Response<Body> {
+    let mut response: Response<Body> = Response::new(Body::from(body));
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static(media_type.as_str()));
+    response
+}
+
+fn encode<T: Serialize + ?Sized>(value: &T, media_type: MediaType) -> Result<Vec<u8>, Error> {
+    match media_type {
+        MediaType::Json => {
+            serde_json::to_vec(value).map_err(|err| Error::Encoding(err.to_string()))
+        }
+        MediaType::MessagePack => {
+            rmp_serde::to_vec_named(value).map_err(|err| Error::Encoding(err.to_string()))
+        }
+        MediaType::Cbor => {
+            let mut bytes: Vec<u8> = vec![];
+            ciborium::ser::into_writer(value, &mut bytes)
+                .map(|_| bytes)
+                .map_err(|err| Error::Encoding(err.to_string()))
+        }
+        MediaType::Csv => Err(Error::Encoding("CSV needs a list of records".to_string())),
+    }
+}
+
+fn encode_csv<T: CsvRecord>(values: &[T]) -> Result<Vec<u8>, Error> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    for value in values {
+        writer.serialize(value.to_record())?;
+    }
+    writer
+        .into_inner()
+        .map_err(|err| Error::Encoding(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::contact::Address;
+    use crate::models::contact::Contact;
+    use crate::models::contact::ContactId;
+    use crate::models::contact::Email;
+    use crate::models::contact::EmailType;
+    use crate::models::contact::Phone;
+    use crate::models::contact::PhoneType;
+    use crate::models::custom_field::CustomFieldValues;
+    use crate::models::important_date::ImportantDate;
+    use crate::models::phone_number::PhoneNumber;
+    use chrono::NaiveDate;
+    use isocountry::CountryCode;
+
+    #[test]
+    fn test_from_accept() {
+        assert_eq!(
+            Ok(MediaType::Json),
+            MediaType::from_accept(None).map_err(|e| e.to_string())
+        );
+        assert_eq!(
+            Ok(MediaType::Cbor),
+            MediaType::from_accept(Some("application/json;q=0.5, application/cbor"))
+                .map_err(|e| e.to_string())
+        );
+        assert_eq!(
+            Ok(MediaType::Csv),
+            MediaType::from_accept(Some("text/html, text/*;q=0.8, */*;q=0.1"))
+                .map_err(|e| e.to_string())
+        );
+        assert!(matches!(
+            MediaType::from_accept(Some("application/xml, application/json;q=0")),
+            Err(Error::NotAcceptable(_))
+        ));
+        assert!(matches!(
+            MediaType::from_content_type(Some("application/xml")),
+            Err(Error::UnsupportedMediaType(_))
+        ));
+    }
+
+    #[test]
+    fn test_encode_and_decode() {
+        let contact: Contact = Contact {
+            id: ContactId(1),
+            name: "John Doe".to_string(),
+            phones: vec![Phone {
+                phone_type: PhoneType::Work,
+                number: PhoneNumber::parse("+4915112345678").unwrap(),
+                primary: true,
+            }],
+            emails: vec![Email {
+                email_type: EmailType::Home,
+                address: "john@doe.com".to_string(),
+                primary: true,
+            }],
+            addresses: vec![Address {
+                label: Some("home".to_string()),
+                street: "Main St. 1".to_string(),
+                locality: "Berlin".to_string(),
+                region: String::new(),
+                postal_code: "10115".to_string(),
+                country: CountryCode::DEU,
+                primary: true,
+            }],
+            tags: vec![],
+            organization: None,
+            custom_fields: CustomFieldValues::from([(
+                "vip".to_string(),
+                serde_json::Value::Bool(true),
+            )]),
+            birthday: NaiveDate::from_ymd_opt(2000, 2, 29),
+            dates: vec![ImportantDate {
+                label: "anniversary".to_string(),
+                date: NaiveDate::from_ymd_opt(2015, 6, 20).unwrap(),
+            }],
+        };
+
+        for media_type in MediaType::all() {
+            let encoded: Vec<u8> = encode_one(&contact, media_type).unwrap();
+            let decoded: Contact = decode(&encoded, media_type).unwrap();
+            assert_eq!(contact.id, decoded.id);
+            assert_eq!(contact.phones, decoded.phones);
+            assert_eq!(contact.emails, decoded.emails);
+            assert_eq!(contact.addresses, decoded.addresses);
+            assert_eq!(contact.custom_fields, decoded.custom_fields);
+            assert_eq!(contact.birthday, decoded.birthday);
+            assert_eq!(contact.dates, decoded.dates);
+        }
+        let csv: Vec<u8> = encode_many(&[contact.clone(), contact], MediaType::Csv).unwrap();
+        assert_eq!(3, String::from_utf8(csv).unwrap().lines().count());
+    }
+}
@@ -0,0 +1,45 @@
+use warp::hyper::StatusCode;
+use warp::Rejection;
+use warp::Reply;
+
+use crate::models::custom_field::CustomField;
+use crate::models::custom_field::NewCustomField;
+use crate::repositories::custom_fields_repository::CustomFieldsRepository;
+
+pub async fn get_custom_fields(
+    _username: String,
+    custom_fields_repository: impl CustomFieldsRepository,
+) -> Result<impl Reply, Rejection> {
+    custom_fields_repository
+        .get_custom_fields()
+        .await
+        .map(|custom_fields: Vec<CustomField>| warp::reply::json(&custom_fields))
+        .map_err(warp::reject::custom)
+}
+
+pub async fn put_custom_field(
+    name: String,
+    _username: String,
+    new_custom_field: NewCustomField,
+    mut custom_fields_repository: impl CustomFieldsRepository,
+) -> Result<impl Reply, Rejection> {
+    let custom_field: CustomField =
+        CustomField::new(&name, new_custom_field).map_err(warp::reject::custom)?;
+    custom_fields_repository
+        .put_custom_field(custom_field)
+        .await
+        .map(|_| StatusCode::NO_CONTENT)
+        .map_err(warp::reject::custom)
+}
+
+pub async fn delete_custom_field(
+    name: String,
+    _username: String,
+    mut custom_fields_repository: impl CustomFieldsRepository,
+) -> Result<impl Reply, Rejection> {
+    custom_fields_repository
+        .delete_custom_field(name)
+        .await
+        .map(|_| StatusCode::NO_CONTENT)
+        .map_err(warp::reject::custom)
+}
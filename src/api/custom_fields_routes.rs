@@ -0,0 +1,85 @@
+use std::convert::Infallible;
+
+use warp::Filter;
+use warp::Rejection;
+use warp::Reply;
+
+use crate::api::auth_filters::with_role;
+use crate::api::auth_filters::SharedAuthMiddleware;
+use crate::api::custom_fields_handlers;
+use crate::models::role::Role;
+use crate::repositories::custom_fields_repository::CustomFieldsRepository;
+
+const MAX_JSON_PAYLOAD_SIZE: u64 = 1024 * 16;
+
+/// The admin-managed definitions of the custom fields of the contacts, behind auth, only the admins changing them.
+pub fn get_custom_fields_routes<R>(
+    custom_fields_repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: CustomFieldsRepository + Clone + Send + Sync + 'static,
+{
+    get_custom_fields_route(custom_fields_repository.clone(), auth_middleware.clone())
+        .or(put_custom_field_route(
+            custom_fields_repository.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(delete_custom_field_route(
+            custom_fields_repository,
+            auth_middleware,
+        ))
+}
+
+fn get_custom_fields_route<R>(
+    custom_fields_repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: CustomFieldsRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("custom-fields")
+        .and(warp::get())
+        .and(with_role(auth_middleware, Role::Reader))
+        .and(with_repository(custom_fields_repository))
+        .and_then(custom_fields_handlers::get_custom_fields)
+}
+
+fn put_custom_field_route<R>(
+    custom_fields_repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: CustomFieldsRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("custom-fields" / String)
+        .and(warp::put())
+        .and(with_role(auth_middleware, Role::Admin))
+        .and(warp::body::content_length_limit(MAX_JSON_PAYLOAD_SIZE))
+        .and(warp::body::json())
+        .and(with_repository(custom_fields_repository))
+        .and_then(custom_fields_handlers::put_custom_field)
+}
+
+fn delete_custom_field_route<R>(
+    custom_fields_repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: CustomFieldsRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("custom-fields" / String)
+        .and(warp::delete())
+        .and(with_role(auth_middleware, Role::Admin))
+        .and(with_repository(custom_fields_repository))
+        .and_then(custom_fields_handlers::delete_custom_field)
+}
+
+fn with_repository<R>(
+    custom_fields_repository: R,
+) -> impl Filter<Extract = (R,), Error = Infallible> + Clone
+where
+    R: CustomFieldsRepository + Clone + Send + Sync + 'static,
+{
+    warp::any().map(move || custom_fields_repository.clone())
+}
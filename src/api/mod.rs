@@ -1,2 +1,27 @@
+pub mod address_books_handlers;
+pub mod address_books_routes;
+pub mod api_keys_handlers;
+pub mod api_keys_routes;
+pub mod api_users_handlers;
+pub mod api_users_routes;
+pub mod auth_filters;
+pub mod auth_handlers;
+pub mod auth_routes;
+pub mod caching;
+pub mod carddav_handlers;
+pub mod carddav_routes;
+pub mod compression;
 pub mod contacts_handlers;
 pub mod contacts_routes;
+pub mod content_negotiation;
+pub mod custom_fields_handlers;
+pub mod custom_fields_routes;
+pub mod notes_handlers;
+pub mod notes_routes;
+pub mod organizations_handlers;
+pub mod organizations_routes;
+pub mod photos_handlers;
+pub mod photos_routes;
+pub mod rate_limiting;
+pub mod relations_handlers;
+pub mod relations_routes;
@@ -0,0 +1,235 @@
+use warp::hyper::StatusCode;
+use warp::Rejection;
+use warp::Reply;
+
+use crate::middleware::validation::Validation;
+use crate::middleware::validation::ValidationMiddleware;
+use crate::models::address_book::AddressBookId;
+use crate::models::contact::Contact;
+use crate::models::contact::ContactId;
+use crate::models::errors::Error;
+use crate::models::note::NewNote;
+use crate::models::note::Note;
+use crate::models::note::NoteId;
+use crate::repositories::contacts_repository::ContactsRepository;
+use crate::repositories::notes_repository::NotesRepository;
+
+pub async fn get_notes(
+    address_book_id: AddressBookId,
+    contact_id: i32,
+    _username: String,
+    repository: impl ContactsRepository + NotesRepository,
+) -> Result<impl Reply, Rejection> {
+    existing_contact(address_book_id, contact_id, &repository).await?;
+    repository
+        .get_notes(ContactId(contact_id))
+        .await
+        .map(|notes: Vec<Note>| warp::reply::json(&notes))
+        .map_err(warp::reject::custom)
+}
+
+pub async fn add_note(
+    address_book_id: AddressBookId,
+    contact_id: i32,
+    username: String,
+    new_note: NewNote,
+    mut repository: impl ContactsRepository + NotesRepository,
+) -> Result<impl Reply, Rejection> {
+    let new_note: NewNote = validated(new_note)?;
+    existing_contact(address_book_id, contact_id, &repository).await?;
+    repository
+        .add_note(ContactId(contact_id), username, new_note)
+        .await
+        .map(|note: Note| warp::reply::with_status(warp::reply::json(&note), StatusCode::CREATED))
+        .map_err(warp::reject::custom)
+}
+
+pub async fn update_note(
+    address_book_id: AddressBookId,
+    contact_id: i32,
+    id: i32,
+    username: String,
+    is_admin: bool,
+    new_note: NewNote,
+    mut repository: impl ContactsRepository + NotesRepository,
+) -> Result<impl Reply, Rejection> {
+    let new_note: NewNote = validated(new_note)?;
+    existing_contact(address_book_id, contact_id, &repository).await?;
+    require_author(contact_id, id, &username, is_admin, &repository).await?;
+    repository
+        .update_note(ContactId(contact_id), NoteId(id), new_note)
+        .await
+        .map(|_| StatusCode::NO_CONTENT)
+        .map_err(warp::reject::custom)
+}
+
+pub async fn delete_note(
+    address_book_id: AddressBookId,
+    contact_id: i32,
+    id: i32,
+    username: String,
+    is_admin: bool,
+    mut repository: impl ContactsRepository + NotesRepository,
+) -> Result<impl Reply, Rejection> {
+    existing_contact(address_book_id, contact_id, &repository).await?;
+    require_author(contact_id, id, &username, is_admin, &repository).await?;
+    repository
+        .delete_note(ContactId(contact_id), NoteId(id))
+        .await
+        .map(|_| StatusCode::NO_CONTENT)
+        .map_err(warp::reject::custom)
+}
+
+/// Rejects with `Error::NotFound` unless the contact exists in the address book.
+async fn existing_contact(
+    address_book_id: AddressBookId,
+    contact_id: i32,
+    repository: &impl ContactsRepository,
+) -> Result<Contact, Rejection> {
+    repository
+        .get(address_book_id, ContactId(contact_id))
+        .await
+        .map_err(warp::reject::custom)?
+        .ok_or(warp::reject::custom(Error::NotFound { id: contact_id }))
+}
+
+/// Rejects with `Error::Forbidden` unless the note was written by the user, or the user is an admin.
+/// A note which doesn't exist is left to the repository, as a no-op.
+async fn require_author(
+    contact_id: i32,
+    id: i32,
+    username: &str,
+    is_admin: bool,
+    repository: &impl NotesRepository,
+) -> Result<(), Rejection> {
+    let note: Option<Note> = repository
+        .get_notes(ContactId(contact_id))
+        .await
+        .map_err(warp::reject::custom)?
+        .into_iter()
+        .find(|note: &Note| note.id == NoteId(id));
+    match note {
+        Some(note) if note.author != username && !is_admin => {
+            Err(warp::reject::custom(Error::Forbidden(format!(
+                "Only the author of the note {id}, {}, or an admin may change it",
+                note.author
+            ))))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn validated(new_note: NewNote) -> Result<NewNote, Error> {
+    if ValidationMiddleware::is_note_valid(&new_note.body) {
+        Ok(new_note)
+    } else {
+        Err(Error::InvalidBody(
+            "The body of a note must have 1 to 10000 characters".to_string(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::contact::NewContact;
+    use crate::models::custom_field::CustomFieldValues;
+    use crate::repositories::contacts_in_memory_repository::ContactsInMemoryRepository;
+
+    const BOOK: AddressBookId = AddressBookId(1);
+
+    #[tokio::test]
+    async fn test_only_the_author_or_an_admin_changes_a_note() {
+        let mut repository: ContactsInMemoryRepository = ContactsInMemoryRepository::new();
+        let contact: Contact = repository
+            .add(
+                BOOK,
+                NewContact {
+                    name: "John".to_string(),
+                    phones: vec![],
+                    emails: vec![],
+                    addresses: vec![],
+                    tags: vec![],
+                    organization: None,
+                    custom_fields: CustomFieldValues::new(),
+                    birthday: None,
+                    dates: vec![],
+                },
+            )
+            .await
+            .unwrap();
+        let note: Note = repository
+            .add_note(
+                contact.id.clone(),
+                "reader".to_string(),
+                NewNote {
+                    body: "Called".to_string(),
+                },
+            )
+            .await
+            .unwrap();
+        let edit = || NewNote {
+            body: "Met".to_string(),
+        };
+
+        let rejection: Rejection = update_note(
+            BOOK,
+            contact.id.0,
+            note.id.0,
+            "other".to_string(),
+            false,
+            edit(),
+            repository.clone(),
+        )
+        .await
+        .err()
+        .unwrap();
+        assert!(matches!(rejection.find(), Some(Error::Forbidden(_))));
+        let rejection: Rejection = delete_note(
+            BOOK,
+            contact.id.0,
+            note.id.0,
+            "other".to_string(),
+            false,
+            repository.clone(),
+        )
+        .await
+        .err()
+        .unwrap();
+        assert!(matches!(rejection.find(), Some(Error::Forbidden(_))));
+        assert_eq!(
+            vec!["Called"],
+            repository
+                .get_notes(contact.id.clone())
+                .await
+                .unwrap()
+                .iter()
+                .map(|note: &Note| note.body.as_str())
+                .collect::<Vec<&str>>()
+        );
+
+        // The author and an admin may
+        assert!(update_note(
+            BOOK,
+            contact.id.0,
+            note.id.0,
+            "reader".to_string(),
+            false,
+            edit(),
+            repository.clone(),
+        )
+        .await
+        .is_ok());
+        assert!(delete_note(
+            BOOK,
+            contact.id.0,
+            note.id.0,
+            "admin".to_string(),
+            true,
+            repository.clone(),
+        )
+        .await
+        .is_ok());
+        assert!(repository.get_notes(contact.id).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,302 @@
+use std::convert::Infallible;
+
+use warp::Filter;
+use warp::Rejection;
+use warp::Reply;
+
+use crate::api::address_books_routes::with_address_book;
+use crate::api::auth_filters::Principal;
+use crate::api::auth_filters::SharedAuthMiddleware;
+use crate::api::notes_handlers;
+use crate::models::address_book::AddressBookId;
+use crate::models::role::Role;
+use crate::repositories::address_books_repository::AddressBooksRepository;
+use crate::repositories::contacts_repository::ContactsRepository;
+use crate::repositories::notes_repository::NotesRepository;
+
+const MAX_JSON_PAYLOAD_SIZE: u64 = 1024 * 16;
+
+/// The notes on the contacts of an address book, as the author of a note is the authenticated user.
+pub fn get_notes_routes<R>(
+    repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: ContactsRepository
+        + NotesRepository
+        + AddressBooksRepository
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+{
+    get_notes_route(repository.clone(), auth_middleware.clone())
+        .or(add_note_route(repository.clone(), auth_middleware.clone()))
+        .or(update_note_route(
+            repository.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(delete_note_route(repository, auth_middleware))
+}
+
+fn get_notes_route<R>(
+    repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: ContactsRepository
+        + NotesRepository
+        + AddressBooksRepository
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+{
+    warp::path!("address-books" / AddressBookId / "contacts" / i32 / "notes")
+        .and(warp::get())
+        .and(with_author(
+            repository.clone(),
+            auth_middleware,
+            Role::Reader,
+        ))
+        .and(with_repository(repository))
+        .and_then(notes_handlers::get_notes)
+}
+
+fn add_note_route<R>(
+    repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: ContactsRepository
+        + NotesRepository
+        + AddressBooksRepository
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+{
+    warp::path!("address-books" / AddressBookId / "contacts" / i32 / "notes")
+        .and(warp::post())
+        .and(with_author(
+            repository.clone(),
+            auth_middleware,
+            Role::Editor,
+        ))
+        .and(warp::body::content_length_limit(MAX_JSON_PAYLOAD_SIZE))
+        .and(warp::body::json())
+        .and(with_repository(repository))
+        .and_then(notes_handlers::add_note)
+}
+
+fn update_note_route<R>(
+    repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: ContactsRepository
+        + NotesRepository
+        + AddressBooksRepository
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+{
+    warp::path!("address-books" / AddressBookId / "contacts" / i32 / "notes" / i32)
+        .and(warp::put())
+        .and(with_author_or_admin(repository.clone(), auth_middleware))
+        .and(warp::body::content_length_limit(MAX_JSON_PAYLOAD_SIZE))
+        .and(warp::body::json())
+        .and(with_repository(repository))
+        .and_then(notes_handlers::update_note)
+}
+
+fn delete_note_route<R>(
+    repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: ContactsRepository
+        + NotesRepository
+        + AddressBooksRepository
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+{
+    warp::path!("address-books" / AddressBookId / "contacts" / i32 / "notes" / i32)
+        .and(warp::delete())
+        .and(with_author_or_admin(repository.clone(), auth_middleware))
+        .and(with_repository(repository))
+        .and_then(notes_handlers::delete_note)
+}
+
+/// The username of the author of a note, or the name of the API key, who has to be allowed in the address book
+/// with the given role: a `reader` to read the notes, an `editor` to write them.
+fn with_author<R>(
+    repository: R,
+    auth_middleware: SharedAuthMiddleware,
+    required: Role,
+) -> impl Filter<Extract = (String,), Error = Rejection> + Clone
+where
+    R: AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    with_address_book(repository, auth_middleware, required)
+        .map(|principal: Principal| principal.name())
+}
+
+/// Same as `with_author`, requiring an `editor`, but also extracts whether the user is an admin, who may change
+/// the notes of the others. The API keys and the users of the identity provider never are.
+fn with_author_or_admin<R>(
+    repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (String, bool), Error = Rejection> + Clone
+where
+    R: AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    with_address_book(repository, auth_middleware.clone(), Role::Editor)
+        .and_then(move |principal: Principal| {
+            let auth_middleware: SharedAuthMiddleware = auth_middleware.clone();
+            async move {
+                let is_admin: bool = match &principal {
+                    Principal::User(username) => {
+                        auth_middleware
+                            .role(username)
+                            .await
+                            .map_err(warp::reject::custom)?
+                            == Some(Role::Admin)
+                    }
+                    _ => false,
+                };
+                Ok::<(String, bool), Rejection>((principal.name(), is_admin))
+            }
+        })
+        .untuple_one()
+}
+
+fn with_repository<R>(repository: R) -> impl Filter<Extract = (R,), Error = Infallible> + Clone
+where
+    R: ContactsRepository
+        + NotesRepository
+        + AddressBooksRepository
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+{
+    warp::any().map(move || repository.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+    use base64::engine::general_purpose as base64Engine;
+    use base64::Engine;
+    use warp::http::StatusCode;
+
+    use super::*;
+    use crate::api::contacts_handlers::handle_rejection;
+    use crate::middleware::auth::parse_auth_header;
+    use crate::middleware::auth::AuthMiddleware;
+    use crate::models::address_book::AddressBook;
+    use crate::models::address_book::NewAddressBook;
+    use crate::models::api_key::ApiKey;
+    use crate::models::contact::Contact;
+    use crate::models::contact::NewContact;
+    use crate::models::custom_field::CustomFieldValues;
+    use crate::models::errors::Error;
+    use crate::repositories::contacts_in_memory_repository::ContactsInMemoryRepository;
+
+    /// Lets in the users whose password is `password`, as readers but for `editor`.
+    struct Users;
+
+    #[async_trait]
+    impl AuthMiddleware for Users {
+        async fn http_basic_auth(&self, auth_header: String) -> Result<bool, Error> {
+            Ok(parse_auth_header(auth_header)?.1 == "password")
+        }
+
+        async fn has_user(&self, _username: &str) -> Result<bool, Error> {
+            Ok(true)
+        }
+
+        async fn role(&self, username: &str) -> Result<Option<Role>, Error> {
+            Ok(Some(match username {
+                "editor" => Role::Editor,
+                _ => Role::Reader,
+            }))
+        }
+
+        async fn authenticate_api_key(&self, _api_key: &str) -> Result<ApiKey, Error> {
+            Err(Error::Unauthorized)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_readers_only_read_the_notes() {
+        let mut repository: ContactsInMemoryRepository = ContactsInMemoryRepository::new();
+        let mut paths: Vec<String> = vec![];
+        for owner in ["reader", "editor"] {
+            let address_book: AddressBook = repository
+                .add_address_book(
+                    owner,
+                    NewAddressBook {
+                        name: owner.to_string(),
+                    },
+                )
+                .await
+                .unwrap();
+            let contact: Contact = repository
+                .add(
+                    address_book.id,
+                    NewContact {
+                        name: "John".to_string(),
+                        phones: vec![],
+                        emails: vec![],
+                        addresses: vec![],
+                        tags: vec![],
+                        organization: None,
+                        custom_fields: CustomFieldValues::new(),
+                        birthday: None,
+                        dates: vec![],
+                    },
+                )
+                .await
+                .unwrap();
+            paths.push(format!(
+                "/address-books/{}/contacts/{}/notes",
+                address_book.id.0, contact.id.0
+            ));
+        }
+        let routes = get_notes_routes(repository, Arc::new(Users)).recover(handle_rejection);
+        let status = |username: &str, method: &str, path: &str| {
+            warp::test::request()
+                .method(method)
+                .path(path)
+                .header(
+                    "authorization",
+                    format!(
+                        "Basic {}",
+                        base64Engine::STANDARD.encode(format!("{username}:password"))
+                    ),
+                )
+                .json(&serde_json::json!({"body": "Called"}))
+                .reply(&routes)
+        };
+
+        assert_eq!(
+            StatusCode::FORBIDDEN,
+            status("reader", "POST", &paths[0]).await.status()
+        );
+        assert_eq!(
+            StatusCode::OK,
+            status("reader", "GET", &paths[0]).await.status()
+        );
+        assert_eq!(
+            StatusCode::CREATED,
+            status("editor", "POST", &paths[1]).await.status()
+        );
+    }
+}
@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+
+use warp::hyper::StatusCode;
+use warp::Rejection;
+use warp::Reply;
+
+use crate::api::contacts_handlers::get_pagination;
+use crate::api::contacts_handlers::Pagination;
+use crate::middleware::validation::Validation;
+use crate::middleware::validation::ValidationMiddleware;
+use crate::models::address_book::AddressBookId;
+use crate::models::contact::Contact;
+use crate::models::errors::Error;
+use crate::models::organization::NewOrganization;
+use crate::models::organization::Organization;
+use crate::models::organization::OrganizationId;
+use crate::repositories::organizations_repository::OrganizationsRepository;
+
+pub async fn get_all_organizations(
+    address_book_id: AddressBookId,
+    query_parameters: HashMap<String, String>,
+    organizations_repository: impl OrganizationsRepository,
+) -> Result<impl Reply, Rejection> {
+    let pagination: Pagination = get_pagination(query_parameters)?;
+    organizations_repository
+        .get_all_organizations(address_book_id, pagination.page_no, pagination.page_size)
+        .await
+        .map(|organizations: Vec<Organization>| warp::reply::json(&organizations))
+        .map_err(warp::reject::custom)
+}
+
+pub async fn get_organization(
+    address_book_id: AddressBookId,
+    id: i32,
+    organizations_repository: impl OrganizationsRepository,
+) -> Result<impl Reply, Rejection> {
+    organizations_repository
+        .get_organization(address_book_id, OrganizationId(id))
+        .await
+        .map_err(warp::reject::custom)?
+        .map(|organization: Organization| warp::reply::json(&organization))
+        .ok_or(warp::reject::custom(Error::NotFound { id }))
+}
+
+pub async fn add_organization(
+    address_book_id: AddressBookId,
+    new_organization: NewOrganization,
+    mut organizations_repository: impl OrganizationsRepository,
+) -> Result<impl Reply, Rejection> {
+    let new_organization: NewOrganization = validated(new_organization)?;
+    organizations_repository
+        .add_organization(address_book_id, new_organization)
+        .await
+        .map(|organization: Organization| {
+            warp::reply::with_status(warp::reply::json(&organization), StatusCode::CREATED)
+        })
+        .map_err(warp::reject::custom)
+}
+
+pub async fn update_organization(
+    address_book_id: AddressBookId,
+    id: i32,
+    organization: NewOrganization,
+    mut organizations_repository: impl OrganizationsRepository,
+) -> Result<impl Reply, Rejection> {
+    let organization: NewOrganization = validated(organization)?;
+    organizations_repository
+        .update_organization(address_book_id, organization, OrganizationId(id))
+        .await
+        .map(|_| StatusCode::NO_CONTENT)
+        .map_err(warp::reject::custom)
+}
+
+pub async fn delete_organization(
+    address_book_id: AddressBookId,
+    id: i32,
+    mut organizations_repository: impl OrganizationsRepository,
+) -> Result<impl Reply, Rejection> {
+    organizations_repository
+        .delete_organization(address_book_id, OrganizationId(id))
+        .await
+        .map(|_| StatusCode::NO_CONTENT)
+        .map_err(warp::reject::custom)
+}
+
+pub async fn get_organization_contacts(
+    address_book_id: AddressBookId,
+    id: i32,
+    organizations_repository: impl OrganizationsRepository,
+) -> Result<impl Reply, Rejection> {
+    organizations_repository
+        .get_organization_contacts(address_book_id, OrganizationId(id))
+        .await
+        .map(|contacts: Vec<Contact>| warp::reply::json(&contacts))
+        .map_err(warp::reject::custom)
+}
+
+pub async fn get_suggested_contacts(
+    address_book_id: AddressBookId,
+    id: i32,
+    organizations_repository: impl OrganizationsRepository,
+) -> Result<impl Reply, Rejection> {
+    organizations_repository
+        .get_suggested_contacts(address_book_id, OrganizationId(id))
+        .await
+        .map(|contacts: Vec<Contact>| warp::reply::json(&contacts))
+        .map_err(warp::reject::custom)
+}
+
+/// Normalizes the domain of an organization, then rejects it with all the failed checks, if any.
+fn validated(new_organization: NewOrganization) -> Result<NewOrganization, Error> {
+    let new_organization: NewOrganization = new_organization.normalized();
+    let messages: Vec<String> = ValidationMiddleware::validate_organization(&new_organization);
+    if messages.is_empty() {
+        Ok(new_organization)
+    } else {
+        Err(Error::InvalidBody(messages.join("; ")))
+    }
+}
@@ -0,0 +1,189 @@
+use std::convert::Infallible;
+
+use warp::Filter;
+use warp::Rejection;
+use warp::Reply;
+
+use crate::api::address_books_routes::require_address_book;
+use crate::api::auth_filters::SharedAuthMiddleware;
+use crate::api::organizations_handlers;
+use crate::models::address_book::AddressBookId;
+use crate::models::role::Role;
+use crate::repositories::address_books_repository::AddressBooksRepository;
+use crate::repositories::organizations_repository::OrganizationsRepository;
+
+const MAX_JSON_PAYLOAD_SIZE: u64 = 1024 * 16;
+
+pub fn get_organizations_routes<R>(
+    organizations_repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: OrganizationsRepository + AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    get_all_organizations_route(organizations_repository.clone(), auth_middleware.clone())
+        .or(get_organization_route(
+            organizations_repository.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(add_organization_route(
+            organizations_repository.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(update_organization_route(
+            organizations_repository.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(delete_organization_route(
+            organizations_repository.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(get_organization_contacts_route(
+            organizations_repository.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(get_suggested_contacts_route(
+            organizations_repository,
+            auth_middleware,
+        ))
+}
+
+fn get_all_organizations_route<R>(
+    organizations_repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: OrganizationsRepository + AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("address-books" / AddressBookId / "organizations")
+        .and(warp::get())
+        .and(require_address_book(
+            organizations_repository.clone(),
+            auth_middleware,
+            Role::Reader,
+        ))
+        .and(warp::query())
+        .and(with_repository(organizations_repository))
+        .and_then(organizations_handlers::get_all_organizations)
+}
+
+fn get_organization_route<R>(
+    organizations_repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: OrganizationsRepository + AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("address-books" / AddressBookId / "organizations" / i32)
+        .and(warp::get())
+        .and(require_address_book(
+            organizations_repository.clone(),
+            auth_middleware,
+            Role::Reader,
+        ))
+        .and(with_repository(organizations_repository))
+        .and_then(organizations_handlers::get_organization)
+}
+
+fn add_organization_route<R>(
+    organizations_repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: OrganizationsRepository + AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("address-books" / AddressBookId / "organizations")
+        .and(warp::post())
+        .and(require_address_book(
+            organizations_repository.clone(),
+            auth_middleware,
+            Role::Editor,
+        ))
+        .and(warp::body::content_length_limit(MAX_JSON_PAYLOAD_SIZE))
+        .and(warp::body::json())
+        .and(with_repository(organizations_repository))
+        .and_then(organizations_handlers::add_organization)
+}
+
+fn update_organization_route<R>(
+    organizations_repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: OrganizationsRepository + AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("address-books" / AddressBookId / "organizations" / i32)
+        .and(warp::put())
+        .and(require_address_book(
+            organizations_repository.clone(),
+            auth_middleware,
+            Role::Editor,
+        ))
+        .and(warp::body::content_length_limit(MAX_JSON_PAYLOAD_SIZE))
+        .and(warp::body::json())
+        .and(with_repository(organizations_repository))
+        .and_then(organizations_handlers::update_organization)
+}
+
+fn delete_organization_route<R>(
+    organizations_repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: OrganizationsRepository + AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("address-books" / AddressBookId / "organizations" / i32)
+        .and(warp::delete())
+        .and(require_address_book(
+            organizations_repository.clone(),
+            auth_middleware,
+            Role::Admin,
+        ))
+        .and(with_repository(organizations_repository))
+        .and_then(organizations_handlers::delete_organization)
+}
+
+fn get_organization_contacts_route<R>(
+    organizations_repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: OrganizationsRepository + AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("address-books" / AddressBookId / "organizations" / i32 / "contacts")
+        .and(warp::get())
+        .and(require_address_book(
+            organizations_repository.clone(),
+            auth_middleware,
+            Role::Reader,
+        ))
+        .and(with_repository(organizations_repository))
+        .and_then(organizations_handlers::get_organization_contacts)
+}
+
+fn get_suggested_contacts_route<R>(
+    organizations_repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: OrganizationsRepository + AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("address-books" / AddressBookId / "organizations" / i32 / "suggested-contacts")
+        .and(warp::get())
+        .and(require_address_book(
+            organizations_repository.clone(),
+            auth_middleware,
+            Role::Reader,
+        ))
+        .and(with_repository(organizations_repository))
+        .and_then(organizations_handlers::get_suggested_contacts)
+}
+
+fn with_repository<R>(
+    organizations_repository: R,
+) -> impl Filter<Extract = (R,), Error = Infallible> + Clone
+where
+    R: OrganizationsRepository + AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::any().map(move || organizations_repository.clone())
+}
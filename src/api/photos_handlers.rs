@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+
+use warp::http::header::CACHE_CONTROL;
+use warp::http::header::CONTENT_TYPE;
+use warp::http::header::ETAG;
+use warp::http::HeaderValue;
+use warp::hyper::body::Bytes;
+use warp::hyper::Body;
+use warp::hyper::Response;
+use warp::hyper::StatusCode;
+use warp::Rejection;
+use warp::Reply;
+
+use crate::models::address_book::AddressBookId;
+use crate::models::contact::ContactId;
+use crate::models::errors::Error;
+use crate::models::photo::ContactPhoto;
+use crate::models::photo::Photo;
+use crate::models::photo::PhotoSize;
+use crate::repositories::contacts_repository::ContactsRepository;
+use crate::repositories::photo_storage::SharedPhotoStorage;
+
+const SIZE_KEY: &str = "size";
+/// Caches may store the photos, but have to revalidate them, by ETag, before reuse.
+const CACHE_CONTROL_VALUE: &str = "no-cache";
+
+pub async fn get_photo(
+    address_book_id: AddressBookId,
+    id: i32,
+    query_parameters: HashMap<String, String>,
+    if_none_match: Option<String>,
+    contacts_repository: impl ContactsRepository,
+    photo_storage: SharedPhotoStorage,
+) -> Result<impl Reply, Rejection> {
+    check_contact(address_book_id, id, &contacts_repository).await?;
+    let size: PhotoSize = query_parameters
+        .get(SIZE_KEY)
+        .map(|size: &String| size.parse::<PhotoSize>())
+        .transpose()?
+        .unwrap_or_default();
+    let photo: Photo = photo_storage
+        .get_photo(ContactId(id), size)
+        .await
+        .map_err(warp::reject::custom)?
+        .ok_or(warp::reject::custom(Error::NotFound { id }))?;
+    let etag: String = photo.etag();
+
+    let mut response: Response<Body> = if is_none_match(if_none_match.as_deref(), &etag) {
+        let mut response: Response<Body> = Response::new(Body::empty());
+        *response.status_mut() = StatusCode::NOT_MODIFIED;
+        response
+    } else {
+        Response::new(Body::from(photo.bytes))
+    };
+    let headers = response.headers_mut();
+    headers.insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static(photo.image_type.content_type()),
+    );
+    headers.insert(CACHE_CONTROL, HeaderValue::from_static(CACHE_CONTROL_VALUE));
+    if let Ok(etag) = HeaderValue::from_str(&etag) {
+        headers.insert(ETAG, etag);
+    }
+    Ok(response)
+}
+
+pub async fn put_photo(
+    address_book_id: AddressBookId,
+    id: i32,
+    body: Bytes,
+    contacts_repository: impl ContactsRepository,
+    photo_storage: SharedPhotoStorage,
+) -> Result<impl Reply, Rejection> {
+    check_contact(address_book_id, id, &contacts_repository).await?;
+    let photo: ContactPhoto = ContactPhoto::from_upload(body.to_vec())
+        .await
+        .map_err(warp::reject::custom)?;
+    let etag: String = photo.original.etag();
+    photo_storage
+        .put_photo(ContactId(id), photo)
+        .await
+        .map_err(warp::reject::custom)?;
+    Ok(warp::reply::with_status(
+        warp::reply::with_header(warp::reply(), ETAG, etag),
+        StatusCode::NO_CONTENT,
+    ))
+}
+
+pub async fn delete_photo(
+    address_book_id: AddressBookId,
+    id: i32,
+    contacts_repository: impl ContactsRepository,
+    photo_storage: SharedPhotoStorage,
+) -> Result<impl Reply, Rejection> {
+    check_contact(address_book_id, id, &contacts_repository).await?;
+    photo_storage
+        .delete_photo(ContactId(id))
+        .await
+        .map(|_| StatusCode::NO_CONTENT)
+        .map_err(warp::reject::custom)
+}
+
+/// Rejects with `Error::NotFound` unless the contact exists in the address book.
+async fn check_contact(
+    address_book_id: AddressBookId,
+    id: i32,
+    contacts_repository: &impl ContactsRepository,
+) -> Result<(), Rejection> {
+    contacts_repository
+        .get(address_book_id, ContactId(id))
+        .await
+        .map_err(warp::reject::custom)?
+        .map(|_| ())
+        .ok_or(warp::reject::custom(Error::NotFound { id }))
+}
+
+/// Whether the client's copy, as of its `If-None-Match` header, is the current one.
+fn is_none_match(if_none_match: Option<&str>, etag: &str) -> bool {
+    if_none_match.is_some_and(|if_none_match: &str| {
+        if_none_match
+            .split(',')
+            .map(|x: &str| x.trim().trim_start_matches("W/"))
+            .any(|x: &str| x == "*" || x == etag)
+    })
+}
@@ -0,0 +1,133 @@
+use std::convert::Infallible;
+use std::env;
+
+use warp::http::header::IF_NONE_MATCH;
+use warp::Filter;
+use warp::Rejection;
+use warp::Reply;
+
+use crate::api::address_books_routes::require_address_book;
+use crate::api::auth_filters::SharedAuthMiddleware;
+use crate::api::photos_handlers;
+use crate::models::address_book::AddressBookId;
+use crate::models::role::Role;
+use crate::repositories::address_books_repository::AddressBooksRepository;
+use crate::repositories::contacts_repository::ContactsRepository;
+use crate::repositories::photo_storage::SharedPhotoStorage;
+
+const MAX_PHOTO_SIZE_KEY: &str = "MAX_PHOTO_SIZE";
+const DEFAULT_MAX_PHOTO_SIZE: u64 = 1024 * 1024 * 5;
+
+/// The photos of the contacts, their size limited by `MAX_PHOTO_SIZE` (in bytes) rather than by the JSON payload one.
+pub fn get_photos_routes<R>(
+    contacts_repository: R,
+    photo_storage: SharedPhotoStorage,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: ContactsRepository + AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    let max_photo_size: u64 = env::var(MAX_PHOTO_SIZE_KEY)
+        .map(|x: String| {
+            x.parse::<u64>()
+                .unwrap_or_else(|_| panic!("Cannot parse {MAX_PHOTO_SIZE_KEY}: {x}"))
+        })
+        .unwrap_or(DEFAULT_MAX_PHOTO_SIZE);
+
+    get_photo_route(
+        contacts_repository.clone(),
+        photo_storage.clone(),
+        auth_middleware.clone(),
+    )
+    .or(put_photo_route(
+        contacts_repository.clone(),
+        photo_storage.clone(),
+        max_photo_size,
+        auth_middleware.clone(),
+    ))
+    .or(delete_photo_route(
+        contacts_repository,
+        photo_storage,
+        auth_middleware,
+    ))
+}
+
+fn get_photo_route<R>(
+    contacts_repository: R,
+    photo_storage: SharedPhotoStorage,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: ContactsRepository + AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("address-books" / AddressBookId / "contacts" / i32 / "photo")
+        .and(warp::get())
+        .and(require_address_book(
+            contacts_repository.clone(),
+            auth_middleware,
+            Role::Reader,
+        ))
+        .and(warp::query())
+        .and(warp::header::optional::<String>(IF_NONE_MATCH.as_str()))
+        .and(with_repository(contacts_repository))
+        .and(with_photo_storage(photo_storage))
+        .and_then(photos_handlers::get_photo)
+}
+
+fn put_photo_route<R>(
+    contacts_repository: R,
+    photo_storage: SharedPhotoStorage,
+    max_photo_size: u64,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: ContactsRepository + AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("address-books" / AddressBookId / "contacts" / i32 / "photo")
+        .and(warp::put())
+        .and(require_address_book(
+            contacts_repository.clone(),
+            auth_middleware,
+            Role::Editor,
+        ))
+        .and(warp::body::content_length_limit(max_photo_size))
+        .and(warp::body::bytes())
+        .and(with_repository(contacts_repository))
+        .and(with_photo_storage(photo_storage))
+        .and_then(photos_handlers::put_photo)
+}
+
+fn delete_photo_route<R>(
+    contacts_repository: R,
+    photo_storage: SharedPhotoStorage,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: ContactsRepository + AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::path!("address-books" / AddressBookId / "contacts" / i32 / "photo")
+        .and(warp::delete())
+        .and(require_address_book(
+            contacts_repository.clone(),
+            auth_middleware,
+            Role::Editor,
+        ))
+        .and(with_repository(contacts_repository))
+        .and(with_photo_storage(photo_storage))
+        .and_then(photos_handlers::delete_photo)
+}
+
+fn with_repository<R>(
+    contacts_repository: R,
+) -> impl Filter<Extract = (R,), Error = Infallible> + Clone
+where
+    R: ContactsRepository + AddressBooksRepository + Clone + Send + Sync + 'static,
+{
+    warp::any().map(move || contacts_repository.clone())
+}
+
+pub fn with_photo_storage(
+    photo_storage: SharedPhotoStorage,
+) -> impl Filter<Extract = (SharedPhotoStorage,), Error = Infallible> + Clone {
+    warp::any().map(move || photo_storage.clone())
+}
@@ -0,0 +1,320 @@
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use log::warn;
+use warp::http::header::AUTHORIZATION;
+use warp::http::HeaderValue;
+use warp::hyper::Body;
+use warp::hyper::Method;
+use warp::hyper::Response;
+use warp::path::FullPath;
+use warp::Filter;
+use warp::Rejection;
+use warp::Reply;
+
+use crate::api::auth_filters::with_client_ip;
+use crate::api::auth_filters::SharedAuthMiddleware;
+use crate::api::auth_filters::SharedTokenService;
+use crate::api::auth_filters::API_KEY_HEADER;
+use crate::middleware::auth::AuthMiddleware;
+use crate::middleware::jwt::parse_bearer_token;
+use crate::middleware::jwt::TokenUse;
+use crate::middleware::rate_limiter::RateLimitStatus;
+use crate::middleware::rate_limiter::RateLimiter;
+use crate::models::api_key::hash_key;
+use crate::models::api_key::ApiKey;
+use crate::models::errors::Error;
+use crate::models::role::Role;
+use crate::models::token::AccessToken;
+
+/// The rate limiter shared by all the routes.
+pub type SharedRateLimiter = Arc<RateLimiter>;
+
+pub const RATELIMIT_LIMIT: &str = "ratelimit-limit";
+pub const RATELIMIT_REMAINING: &str = "ratelimit-remaining";
+pub const RATELIMIT_RESET: &str = "ratelimit-reset";
+
+/// Takes the request out of the rate limit of the client, extracting what is left of it.
+/// Rejects with `Error::RateLimited` once the client used up its requests of the route.
+pub fn with_rate_limit(
+    rate_limiter: SharedRateLimiter,
+    token_service: SharedTokenService,
+) -> impl Filter<Extract = (RateLimitStatus,), Error = Rejection> + Clone {
+    warp::method()
+        .and(warp::path::full())
+        .and(warp::header::optional::<String>(API_KEY_HEADER))
+        .and(warp::header::optional::<String>(AUTHORIZATION.as_str()))
+        .and(with_client_ip())
+        .and_then(
+            move |method: Method,
+                  path: FullPath,
+                  api_key: Option<String>,
+                  auth_header: Option<String>,
+                  client_ip: Option<IpAddr>| {
+                let rate_limiter: SharedRateLimiter = rate_limiter.clone();
+                let token_service: SharedTokenService = token_service.clone();
+                async move {
+                    let credentials: Option<Credentials> = match (api_key, auth_header) {
+                        (Some(api_key), _) => Some(Credentials::ApiKey(api_key)),
+                        (None, Some(auth_header)) => Some(Credentials::Authorization(auth_header)),
+                        (None, None) => None,
+                    };
+                    let client: String = credentials
+                        .as_ref()
+                        .and_then(|credentials: &Credentials| {
+                            verified_client(credentials, &token_service, &rate_limiter)
+                        })
+                        .unwrap_or(match client_ip {
+                            Some(client_ip) => format!("ip:{client_ip}"),
+                            None => "ip:unknown".to_string(),
+                        });
+                    rate_limiter
+                        .acquire(&client, method.as_str(), path.as_str())
+                        .map_err(|err| {
+                            warn!("Rate limited {client} on {method} {}: {err}", path.as_str());
+                            warp::reject::custom(err)
+                        })
+                }
+            },
+        )
+}
+
+/// Tells the client what is left of its rate limit, by the `RateLimit-*` headers.
+pub fn with_rate_limit_headers<T: Reply>(status: RateLimitStatus, reply: T) -> Response<Body> {
+    let mut response: Response<Body> = reply.into_response();
+    for (name, value) in [
+        (RATELIMIT_LIMIT, status.limit),
+        (RATELIMIT_REMAINING, status.remaining),
+        (RATELIMIT_RESET, status.reset),
+    ] {
+        response.headers_mut().insert(name, HeaderValue::from(value));
+    }
+    response
+}
+
+/// The credentials of a request: an API key, or an HTTP Authorization header.
+enum Credentials {
+    ApiKey(String),
+    Authorization(String),
+}
+
+impl Credentials {
+    /// The hash the verified credentials are remembered by.
+    fn hash(&self) -> String {
+        match self {
+            Credentials::ApiKey(api_key) => hash_key(&format!("api-key:{api_key}")),
+            Credentials::Authorization(auth_header) => hash_key(auth_header),
+        }
+    }
+}
+
+/// Who the rate limit of a request is counted against, if its credentials are verified: the API key, or the user
+/// of the bearer token or of the HTTP Basic Auth credentials. The access tokens issued by the API are verified
+/// right away, as it takes no lookup, while the other credentials are only if the routes authenticated them lately.
+/// The requests the credentials of which are not verified, e.g. made up ones, count against the client IP.
+fn verified_client(
+    credentials: &Credentials,
+    token_service: &SharedTokenService,
+    rate_limiter: &RateLimiter,
+) -> Option<String> {
+    if let Credentials::Authorization(auth_header) = credentials {
+        if let Some(token) = parse_bearer_token(auth_header) {
+            if token_service.is_own_token(token) {
+                return token_service
+                    .verify(token, TokenUse::Access)
+                    .ok()
+                    .map(|username: String| format!("user:{username}"));
+            }
+        }
+    }
+    rate_limiter.verified_client(&credentials.hash())
+}
+
+/// Remembers who the credentials are once the routes authenticated them, e.g. throttling the failed logins,
+/// for the rate limit of the next requests made with them to count against the API key or the user.
+/// Delegates the rest to the wrapped middleware.
+pub struct RateLimitedAuthMiddleware {
+    inner: SharedAuthMiddleware,
+    rate_limiter: SharedRateLimiter,
+}
+
+impl RateLimitedAuthMiddleware {
+    pub fn new(inner: SharedAuthMiddleware, rate_limiter: SharedRateLimiter) -> Self {
+        RateLimitedAuthMiddleware {
+            inner,
+            rate_limiter,
+        }
+    }
+
+    fn remember(&self, credentials: Credentials, client: String) {
+        self.rate_limiter
+            .remember_client(&credentials.hash(), &client);
+    }
+}
+
+#[async_trait]
+impl AuthMiddleware for RateLimitedAuthMiddleware {
+    async fn http_basic_auth(&self, auth_header: String) -> Result<bool, Error> {
+        self.inner.http_basic_auth(auth_header).await
+    }
+
+    async fn has_user(&self, username: &str) -> Result<bool, Error> {
+        self.inner.has_user(username).await
+    }
+
+    async fn role(&self, username: &str) -> Result<Option<Role>, Error> {
+        self.inner.role(username).await
+    }
+
+    async fn authenticate_api_key(&self, api_key: &str) -> Result<ApiKey, Error> {
+        let authenticated: ApiKey = self.inner.authenticate_api_key(api_key).await?;
+        self.remember(
+            Credentials::ApiKey(api_key.to_string()),
+            format!("api-key:{}", authenticated.id.0),
+        );
+        Ok(authenticated)
+    }
+
+    async fn authenticate_access_token(
+        &self,
+        auth_header: &str,
+    ) -> Result<Option<AccessToken>, Error> {
+        let access_token: Option<AccessToken> =
+            self.inner.authenticate_access_token(auth_header).await?;
+        if let Some(access_token) = &access_token {
+            self.remember(
+                Credentials::Authorization(auth_header.to_string()),
+                format!("user:{}", access_token.username),
+            );
+        }
+        Ok(access_token)
+    }
+
+    async fn authenticate_basic(
+        &self,
+        auth_header: String,
+        client_ip: Option<IpAddr>,
+    ) -> Result<String, Error> {
+        let username: String = self
+            .inner
+            .authenticate_basic(auth_header.clone(), client_ip)
+            .await?;
+        self.remember(
+            Credentials::Authorization(auth_header),
+            format!("user:{username}"),
+        );
+        Ok(username)
+    }
+
+    async fn authenticate(
+        &self,
+        auth_header: String,
+        client_ip: Option<IpAddr>,
+    ) -> Result<String, Error> {
+        let username: String = self
+            .inner
+            .authenticate(auth_header.clone(), client_ip)
+            .await?;
+        self.remember(
+            Credentials::Authorization(auth_header),
+            format!("user:{username}"),
+        );
+        Ok(username)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::engine::general_purpose as base64Engine;
+    use base64::Engine;
+
+    use super::*;
+    use crate::middleware::auth::parse_auth_header;
+    use crate::middleware::login_throttle::LoginThrottle;
+    use crate::middleware::login_throttle::ThrottleConfig;
+    use crate::middleware::login_throttle::ThrottledAuthMiddleware;
+    use crate::middleware::rate_limiter::RateLimit;
+    use crate::middleware::rate_limiter::RateLimitsConfig;
+
+    /// Lets in the users whose password is `password`.
+    struct Users;
+
+    #[async_trait]
+    impl AuthMiddleware for Users {
+        async fn http_basic_auth(&self, auth_header: String) -> Result<bool, Error> {
+            Ok(parse_auth_header(auth_header)?.1 == "password")
+        }
+
+        async fn has_user(&self, _username: &str) -> Result<bool, Error> {
+            Ok(true)
+        }
+
+        async fn role(&self, _username: &str) -> Result<Option<Role>, Error> {
+            Ok(Some(Role::Reader))
+        }
+
+        async fn authenticate_api_key(&self, _api_key: &str) -> Result<ApiKey, Error> {
+            Err(Error::Unauthorized)
+        }
+    }
+
+    fn basic_auth_header(credentials: &str) -> String {
+        format!("Basic {}", base64Engine::STANDARD.encode(credentials))
+    }
+
+    #[tokio::test]
+    async fn test_only_the_authenticated_clients_are_remembered() {
+        let rate_limiter: SharedRateLimiter = Arc::new(
+            RateLimiter::new_with_config(RateLimitsConfig {
+                default: RateLimit {
+                    per_second: 1.0,
+                    burst: 2,
+                    daily_quota: None,
+                },
+                routes: vec![],
+            })
+            .unwrap(),
+        );
+        let auth_middleware: RateLimitedAuthMiddleware = RateLimitedAuthMiddleware::new(
+            Arc::new(ThrottledAuthMiddleware::new(
+                Arc::new(Users),
+                LoginThrottle::new(ThrottleConfig {
+                    max_failures_per_user: 1,
+                    max_failures_per_ip: 10,
+                    lockout: 30,
+                    max_lockout: 100,
+                }),
+            )),
+            rate_limiter.clone(),
+        );
+        let verified_client = |auth_header: &str| -> Option<String> {
+            rate_limiter
+                .verified_client(&Credentials::Authorization(auth_header.to_string()).hash())
+        };
+
+        // A wrong password counts towards the lockout, and its client stays unknown, even once it's right
+        let wrong: String = basic_auth_header("reader:wrong");
+        let right: String = basic_auth_header("reader:password");
+        assert!(matches!(
+            auth_middleware.authenticate(wrong.clone(), None).await,
+            Err(Error::Unauthorized)
+        ));
+        assert!(matches!(
+            auth_middleware.authenticate(right.clone(), None).await,
+            Err(Error::TooManyAttempts { .. })
+        ));
+        assert_eq!(None, verified_client(&wrong));
+        assert_eq!(None, verified_client(&right));
+
+        let other: String = basic_auth_header("other:password");
+        assert_eq!(
+            "other",
+            auth_middleware
+                .authenticate(other.clone(), None)
+                .await
+                .unwrap()
+        );
+        assert_eq!(Some("user:other".to_string()), verified_client(&other));
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+
+use warp::hyper::StatusCode;
+use warp::Rejection;
+use warp::Reply;
+
+use crate::models::address_book::AddressBookId;
+use crate::models::contact::Contact;
+use crate::models::contact::ContactId;
+use crate::models::errors::Error;
+use crate::models::relation::next_hop;
+use crate::models::relation::NewRelation;
+use crate::models::relation::Relation;
+use crate::models::relation::RelationGraph;
+use crate::models::relation::RelationId;
+use crate::models::relation::RelationNode;
+use crate::repositories::contacts_repository::ContactsRepository;
+use crate::repositories::relations_repository::RelationsRepository;
+
+const DEPTH_KEY: &str = "depth";
+const DEFAULT_DEPTH: u32 = 1;
+/// The graph grows quickly with the depth, so it's capped to the relations of the relations of the relations.
+const MAX_DEPTH: u32 = 3;
+
+pub async fn get_relations(
+    address_book_id: AddressBookId,
+    contact_id: i32,
+    query_parameters: HashMap<String, String>,
+    repository: impl ContactsRepository + RelationsRepository,
+) -> Result<impl Reply, Rejection> {
+    let depth: u32 = match query_parameters.get(DEPTH_KEY) {
+        Some(depth) => depth
+            .trim()
+            .parse::<u32>()
+            .ok()
+            .filter(|depth: &u32| (1..=MAX_DEPTH).contains(depth))
+            .ok_or_else(|| Error::InvalidDepth(depth.to_string()))?,
+        None => DEFAULT_DEPTH,
+    };
+    existing_contact(address_book_id, contact_id, &repository).await?;
+    relation_graph(address_book_id, ContactId(contact_id), depth, &repository)
+        .await
+        .map(|graph: RelationGraph| warp::reply::json(&graph))
+        .map_err(warp::reject::custom)
+}
+
+pub async fn add_relation(
+    address_book_id: AddressBookId,
+    contact_id: i32,
+    new_relation: NewRelation,
+    mut repository: impl ContactsRepository + RelationsRepository,
+) -> Result<impl Reply, Rejection> {
+    let new_relation: NewRelation = new_relation.normalized();
+    if new_relation.related_id.0 == contact_id {
+        return Err(warp::reject::custom(Error::InvalidBody(
+            "A contact cannot be related to itself".to_string(),
+        )));
+    }
+    existing_contact(address_book_id, contact_id, &repository).await?;
+    if repository
+        .get(address_book_id, new_relation.related_id.clone())
+        .await
+        .map_err(warp::reject::custom)?
+        .is_none()
+    {
+        return Err(warp::reject::custom(Error::InvalidBody(format!(
+            "Unknown related contact {}",
+            new_relation.related_id.0
+        ))));
+    }
+    repository
+        .add_relation(ContactId(contact_id), new_relation)
+        .await
+        .map(|relation: Relation| {
+            warp::reply::with_status(warp::reply::json(&relation), StatusCode::CREATED)
+        })
+        .map_err(warp::reject::custom)
+}
+
+pub async fn delete_relation(
+    address_book_id: AddressBookId,
+    contact_id: i32,
+    id: i32,
+    mut repository: impl ContactsRepository + RelationsRepository,
+) -> Result<impl Reply, Rejection> {
+    existing_contact(address_book_id, contact_id, &repository).await?;
+    repository
+        .delete_relation(ContactId(contact_id), RelationId(id))
+        .await
+        .map(|_| StatusCode::NO_CONTENT)
+        .map_err(warp::reject::custom)
+}
+
+/// Walks the relations, in either direction, breadth first, up to the given number of hops from the contact.
+async fn relation_graph(
+    address_book_id: AddressBookId,
+    contact_id: ContactId,
+    depth: u32,
+    repository: &(impl ContactsRepository + RelationsRepository),
+) -> Result<RelationGraph, Error> {
+    let mut visited: BTreeSet<i32> = BTreeSet::from([contact_id.0]);
+    let mut frontier: Vec<ContactId> = vec![contact_id];
+    let mut edges: Vec<Relation> = vec![];
+    for _ in 0..depth {
+        if frontier.is_empty() {
+            break;
+        }
+        let relations: Vec<Relation> = repository.get_relations(&frontier).await?;
+        frontier = next_hop(&relations, &visited);
+        visited.extend(frontier.iter().map(|id: &ContactId| id.0));
+        for relation in relations {
+            if !edges.contains(&relation) {
+                edges.push(relation);
+            }
+        }
+    }
+    edges.sort_by_key(|relation: &Relation| relation.id.0);
+
+    let mut nodes: Vec<RelationNode> = vec![];
+    for id in visited {
+        if let Some(contact) = repository.get(address_book_id, ContactId(id)).await? {
+            nodes.push(RelationNode {
+                id: contact.id,
+                name: contact.name,
+            });
+        }
+    }
+    Ok(RelationGraph { nodes, edges })
+}
+
+/// Rejects with `Error::NotFound` unless the contact exists in the address book.
+async fn existing_contact(
+    address_book_id: AddressBookId,
+    contact_id: i32,
+    repository: &impl ContactsRepository,
+) -> Result<Contact, Rejection> {
+    repository
+        .get(address_book_id, ContactId(contact_id))
+        .await
+        .map_err(warp::reject::custom)?
+        .ok_or(warp::reject::custom(Error::NotFound { id: contact_id }))
+}
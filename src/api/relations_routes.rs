@@ -0,0 +1,125 @@
+use std::convert::Infallible;
+
+use warp::Filter;
+use warp::Rejection;
+use warp::Reply;
+
+use crate::api::address_books_routes::require_address_book;
+use crate::api::auth_filters::SharedAuthMiddleware;
+use crate::api::relations_handlers;
+use crate::models::address_book::AddressBookId;
+use crate::models::role::Role;
+use crate::repositories::address_books_repository::AddressBooksRepository;
+use crate::repositories::contacts_repository::ContactsRepository;
+use crate::repositories::relations_repository::RelationsRepository;
+
+const MAX_JSON_PAYLOAD_SIZE: u64 = 1024 * 16;
+
+pub fn get_relations_routes<R>(
+    repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: ContactsRepository
+        + RelationsRepository
+        + AddressBooksRepository
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+{
+    get_relations_route(repository.clone(), auth_middleware.clone())
+        .or(add_relation_route(
+            repository.clone(),
+            auth_middleware.clone(),
+        ))
+        .or(delete_relation_route(repository, auth_middleware))
+}
+
+fn get_relations_route<R>(
+    repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: ContactsRepository
+        + RelationsRepository
+        + AddressBooksRepository
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+{
+    warp::path!("address-books" / AddressBookId / "contacts" / i32 / "relations")
+        .and(warp::get())
+        .and(require_address_book(
+            repository.clone(),
+            auth_middleware,
+            Role::Reader,
+        ))
+        .and(warp::query())
+        .and(with_repository(repository))
+        .and_then(relations_handlers::get_relations)
+}
+
+fn add_relation_route<R>(
+    repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: ContactsRepository
+        + RelationsRepository
+        + AddressBooksRepository
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+{
+    warp::path!("address-books" / AddressBookId / "contacts" / i32 / "relations")
+        .and(warp::post())
+        .and(require_address_book(
+            repository.clone(),
+            auth_middleware,
+            Role::Editor,
+        ))
+        .and(warp::body::content_length_limit(MAX_JSON_PAYLOAD_SIZE))
+        .and(warp::body::json())
+        .and(with_repository(repository))
+        .and_then(relations_handlers::add_relation)
+}
+
+fn delete_relation_route<R>(
+    repository: R,
+    auth_middleware: SharedAuthMiddleware,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    R: ContactsRepository
+        + RelationsRepository
+        + AddressBooksRepository
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+{
+    warp::path!("address-books" / AddressBookId / "contacts" / i32 / "relations" / i32)
+        .and(warp::delete())
+        .and(require_address_book(
+            repository.clone(),
+            auth_middleware,
+            Role::Editor,
+        ))
+        .and(with_repository(repository))
+        .and_then(relations_handlers::delete_relation)
+}
+
+fn with_repository<R>(repository: R) -> impl Filter<Extract = (R,), Error = Infallible> + Clone
+where
+    R: ContactsRepository
+        + RelationsRepository
+        + AddressBooksRepository
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+{
+    warp::any().map(move || repository.clone())
+}
@@ -0,0 +1,618 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+use csv::ReaderBuilder;
+use csv::StringRecord;
+use csv::WriterBuilder;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde::Serialize;
+use warp::hyper::body::Bytes;
+
+use crate::models::contact::parse_country;
+use crate::models::contact::with_single_primary;
+use crate::models::contact::Address;
+use crate::models::contact::Contact;
+use crate::models::contact::ContactId;
+use crate::models::contact::Email;
+use crate::models::contact::EmailType;
+use crate::models::contact::NewContact;
+use crate::models::contact::Phone;
+use crate::models::contact::PhoneType;
+use crate::models::contact::UpdateContactEmail;
+use crate::models::contact::UpdateContactPhoneNo;
+use crate::models::custom_field::CustomFieldValues;
+use crate::models::errors::Error;
+use crate::models::import_report::ImportRow;
+use crate::models::important_date::ImportantDate;
+use crate::models::organization::Affiliation;
+use crate::models::organization::OrganizationId;
+use crate::models::phone_number::PhoneNumber;
+use crate::models::tag::Tag;
+
+pub const ID_COLUMN: &str = "id";
+pub const NAME_COLUMN: &str = "name";
+pub const PHONES_COLUMN: &str = "phones";
+pub const EMAILS_COLUMN: &str = "emails";
+pub const ADDRESSES_COLUMN: &str = "addresses";
+pub const TAGS_COLUMN: &str = "tags";
+pub const ORGANIZATION_ID_COLUMN: &str = "organization_id";
+pub const JOB_TITLE_COLUMN: &str = "job_title";
+pub const CUSTOM_FIELDS_COLUMN: &str = "custom_fields";
+pub const BIRTHDAY_COLUMN: &str = "birthday";
+pub const DATES_COLUMN: &str = "dates";
+
+/// Separates the entries of the phones and emails columns, e.g. `mobile:+4915112345678; work:+49301234567`.
+const LIST_SEPARATOR: char = ';';
+/// Separates the type from the value of an entry of the phones and emails columns.
+const TYPE_SEPARATOR: char = ':';
+/// Separates the fields of an entry of the addresses column, e.g. `home:Main St. 1|Berlin||10115|DE`.
+const ADDRESS_FIELD_SEPARATOR: char = '|';
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Maps the fields of a contact to the headers of a CSV file.
+#[derive(Debug, Clone)]
+pub struct HeaderMapping {
+    name: String,
+    phones: String,
+    emails: String,
+    addresses: String,
+    tags: String,
+    custom_fields: String,
+    birthday: String,
+    dates: String,
+}
+
+impl HeaderMapping {
+    /// Builds the mapping out of query parameters such as `?name=Full Name&emails=E-mail`.
+    /// Fields not present in the query parameters are expected under their own name.
+    pub fn from_query(query_parameters: &HashMap<String, String>) -> Self {
+        let header_for = |field: &str| -> String {
+            query_parameters
+                .get(field)
+                .cloned()
+                .unwrap_or(field.to_string())
+        };
+        HeaderMapping {
+            name: header_for(NAME_COLUMN),
+            phones: header_for(PHONES_COLUMN),
+            emails: header_for(EMAILS_COLUMN),
+            addresses: header_for(ADDRESSES_COLUMN),
+            tags: header_for(TAGS_COLUMN),
+            custom_fields: header_for(CUSTOM_FIELDS_COLUMN),
+            birthday: header_for(BIRTHDAY_COLUMN),
+            dates: header_for(DATES_COLUMN),
+        }
+    }
+}
+
+impl Default for HeaderMapping {
+    fn default() -> Self {
+        Self::from_query(&HashMap::new())
+    }
+}
+
+/// The flat, one record per value, CSV representation of a payload.
+pub trait CsvRecord: Sized {
+    type Record: Serialize + DeserializeOwned;
+
+    fn to_record(&self) -> Self::Record;
+
+    fn from_record(record: Self::Record) -> Result<Self, Error>;
+}
+
+/// A contact as a flat CSV record, its phones, emails and addresses being lists of `type:value` entries, the primary one first,
+/// its tags a list of names, the organization it works at given by id, its custom fields a JSON object
+/// and its important dates a list of `label:date` entries.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ContactRecord {
+    #[serde(default)]
+    pub id: Option<i32>,
+    pub name: String,
+    #[serde(default)]
+    pub phones: String,
+    #[serde(default)]
+    pub emails: String,
+    #[serde(default)]
+    pub addresses: String,
+    #[serde(default)]
+    pub tags: String,
+    #[serde(default)]
+    pub organization_id: Option<i32>,
+    #[serde(default)]
+    pub job_title: String,
+    #[serde(default)]
+    pub custom_fields: String,
+    #[serde(default)]
+    pub birthday: String,
+    #[serde(default)]
+    pub dates: String,
+}
+
+impl CsvRecord for Contact {
+    type Record = ContactRecord;
+
+    fn to_record(&self) -> Self::Record {
+        ContactRecord {
+            id: Some(self.id.0),
+            name: self.name.clone(),
+            phones: format_phones(&self.phones),
+            emails: format_emails(&self.emails),
+            addresses: format_addresses(&self.addresses),
+            tags: format_tags(&self.tags),
+            organization_id: organization_id(self.organization.as_ref()),
+            job_title: job_title(self.organization.as_ref()),
+            custom_fields: format_custom_fields(&self.custom_fields),
+            birthday: format_birthday(self.birthday),
+            dates: format_dates(&self.dates),
+        }
+    }
+
+    fn from_record(record: Self::Record) -> Result<Self, Error> {
+        let id: i32 = record
+            .id
+            .ok_or_else(|| Error::InvalidCsv(format!("Missing {}", ID_COLUMN)))?;
+        Ok(Contact {
+            id: ContactId(id),
+            name: record.name,
+            phones: parse_phones(&record.phones).map_err(Error::InvalidCsv)?,
+            emails: parse_emails(&record.emails).map_err(Error::InvalidCsv)?,
+            addresses: parse_addresses(&record.addresses).map_err(Error::InvalidCsv)?,
+            tags: parse_tags(&record.tags).map_err(Error::InvalidCsv)?,
+            organization: affiliation(record.organization_id, record.job_title),
+            custom_fields: parse_custom_fields(&record.custom_fields).map_err(Error::InvalidCsv)?,
+            birthday: parse_birthday(&record.birthday).map_err(Error::InvalidCsv)?,
+            dates: parse_dates(&record.dates).map_err(Error::InvalidCsv)?,
+        })
+    }
+}
+
+impl CsvRecord for NewContact {
+    type Record = ContactRecord;
+
+    fn to_record(&self) -> Self::Record {
+        ContactRecord {
+            id: None,
+            name: self.name.clone(),
+            phones: format_phones(&self.phones),
+            emails: format_emails(&self.emails),
+            addresses: format_addresses(&self.addresses),
+            tags: format_tags(&self.tags),
+            organization_id: organization_id(self.organization.as_ref()),
+            job_title: job_title(self.organization.as_ref()),
+            custom_fields: format_custom_fields(&self.custom_fields),
+            birthday: format_birthday(self.birthday),
+            dates: format_dates(&self.dates),
+        }
+    }
+
+    fn from_record(record: Self::Record) -> Result<Self, Error> {
+        Ok(NewContact {
+            name: record.name,
+            phones: parse_phones(&record.phones).map_err(Error::InvalidCsv)?,
+            emails: parse_emails(&record.emails).map_err(Error::InvalidCsv)?,
+            addresses: parse_addresses(&record.addresses).map_err(Error::InvalidCsv)?,
+            tags: parse_tags(&record.tags).map_err(Error::InvalidCsv)?,
+            organization: affiliation(record.organization_id, record.job_title),
+            custom_fields: parse_custom_fields(&record.custom_fields).map_err(Error::InvalidCsv)?,
+            birthday: parse_birthday(&record.birthday).map_err(Error::InvalidCsv)?,
+            dates: parse_dates(&record.dates).map_err(Error::InvalidCsv)?,
+        })
+    }
+}
+
+impl CsvRecord for UpdateContactEmail {
+    type Record = Self;
+
+    fn to_record(&self) -> Self::Record {
+        self.clone()
+    }
+
+    fn from_record(record: Self::Record) -> Result<Self, Error> {
+        Ok(record)
+    }
+}
+
+impl CsvRecord for UpdateContactPhoneNo {
+    type Record = Self;
+
+    fn to_record(&self) -> Self::Record {
+        self.clone()
+    }
+
+    fn from_record(record: Self::Record) -> Result<Self, Error> {
+        Ok(record)
+    }
+}
+
+/// Returns the header line used when exporting contacts.
+pub fn header_line() -> Result<Bytes, Error> {
+    write_line(&[
+        ID_COLUMN,
+        NAME_COLUMN,
+        PHONES_COLUMN,
+        EMAILS_COLUMN,
+        ADDRESSES_COLUMN,
+        TAGS_COLUMN,
+        ORGANIZATION_ID_COLUMN,
+        JOB_TITLE_COLUMN,
+        CUSTOM_FIELDS_COLUMN,
+        BIRTHDAY_COLUMN,
+        DATES_COLUMN,
+    ])
+}
+
+/// Returns a single contact as a CSV line, in the same order as the `header_line`.
+pub fn to_line(contact: &Contact) -> Result<Bytes, Error> {
+    write_line(&[
+        contact.id.0.to_string(),
+        contact.name.clone(),
+        format_phones(&contact.phones),
+        format_emails(&contact.emails),
+        format_addresses(&contact.addresses),
+        format_tags(&contact.tags),
+        organization_id(contact.organization.as_ref())
+            .map(|id: i32| id.to_string())
+            .unwrap_or_default(),
+        job_title(contact.organization.as_ref()),
+        format_custom_fields(&contact.custom_fields),
+        format_birthday(contact.birthday),
+        format_dates(&contact.dates),
+    ])
+}
+
+/// Parses a CSV payload with a header line into import rows. The addresses, tags, custom fields, birthday and dates columns are optional.
+/// The organization columns are ignored, as the ids of the organizations don't carry over between address books.
+/// Fails as a whole only if the payload is not a CSV or another mapped header is missing.
+pub fn parse(input: &[u8], mapping: &HeaderMapping) -> Result<Vec<ImportRow>, Error> {
+    let mut reader = ReaderBuilder::new().trim(csv::Trim::All).from_reader(input);
+    let headers: StringRecord = reader.headers()?.clone();
+    let column_of = |header: &String| -> Result<usize, Error> {
+        headers
+            .iter()
+            .position(|x: &str| x.eq_ignore_ascii_case(header))
+            .ok_or_else(|| Error::InvalidCsv(format!("Missing column {}", header)))
+    };
+    let name_column: usize = column_of(&mapping.name)?;
+    let phones_column: usize = column_of(&mapping.phones)?;
+    let emails_column: usize = column_of(&mapping.emails)?;
+    let addresses_column: Option<usize> = column_of(&mapping.addresses).ok();
+    let tags_column: Option<usize> = column_of(&mapping.tags).ok();
+    let custom_fields_column: Option<usize> = column_of(&mapping.custom_fields).ok();
+    let birthday_column: Option<usize> = column_of(&mapping.birthday).ok();
+    let dates_column: Option<usize> = column_of(&mapping.dates).ok();
+
+    let mut rows: Vec<ImportRow> = vec![];
+    for record in reader.records() {
+        let record: StringRecord = record?;
+        let row: u64 = record.position().map_or(0, |position| position.line());
+        let field = |column: usize| record.get(column).unwrap_or_default().to_string();
+        let addresses: Result<Vec<Address>, String> =
+            addresses_column.map_or(Ok(vec![]), |column: usize| parse_addresses(&field(column)));
+        let tags: Result<Vec<Tag>, String> =
+            tags_column.map_or(Ok(vec![]), |column: usize| parse_tags(&field(column)));
+        let custom_fields: Result<CustomFieldValues, String> = custom_fields_column
+            .map_or(Ok(CustomFieldValues::new()), |column: usize| {
+                parse_custom_fields(&field(column))
+            });
+        let birthday: Result<Option<NaiveDate>, String> =
+            birthday_column.map_or(Ok(None), |column: usize| parse_birthday(&field(column)));
+        let dates: Result<Vec<ImportantDate>, String> =
+            dates_column.map_or(Ok(vec![]), |column: usize| parse_dates(&field(column)));
+        let new_contact: Result<NewContact, Vec<String>> = match (
+            parse_phones(&field(phones_column)),
+            parse_emails(&field(emails_column)),
+            addresses,
+            tags,
+            custom_fields,
+            birthday,
+            dates,
+        ) {
+            (
+                Ok(phones),
+                Ok(emails),
+                Ok(addresses),
+                Ok(tags),
+                Ok(custom_fields),
+                Ok(birthday),
+                Ok(dates),
+            ) => Ok(NewContact {
+                name: field(name_column),
+                phones,
+                emails,
+                addresses,
+                tags,
+                organization: None,
+                custom_fields,
+                birthday,
+                dates,
+            }),
+            (phones, emails, addresses, tags, custom_fields, birthday, dates) => Err([
+                phones.err(),
+                emails.err(),
+                addresses.err(),
+                tags.err(),
+                custom_fields.err(),
+                birthday.err(),
+                dates.err(),
+            ]
+            .into_iter()
+            .flatten()
+            .collect()),
+        };
+        rows.push(ImportRow { row, new_contact });
+    }
+    Ok(rows)
+}
+
+/// Formats the custom fields as a JSON object, e.g. `{"level":"gold"}`, or nothing if there are none.
+pub fn format_custom_fields(custom_fields: &CustomFieldValues) -> String {
+    if custom_fields.is_empty() {
+        return String::new();
+    }
+    serde_json::to_string(custom_fields).unwrap_or_default()
+}
+
+/// Parses the custom fields out of a JSON object. An empty value means no custom fields.
+pub fn parse_custom_fields(value: &str) -> Result<CustomFieldValues, String> {
+    if value.trim().is_empty() {
+        return Ok(CustomFieldValues::new());
+    }
+    serde_json::from_str(value).map_err(|err: serde_json::Error| err.to_string())
+}
+
+/// Formats the birthday as an ISO 8601 date, e.g. `1990-06-20`, or nothing if unknown.
+pub fn format_birthday(birthday: Option<NaiveDate>) -> String {
+    birthday
+        .map(|birthday: NaiveDate| birthday.format(DATE_FORMAT).to_string())
+        .unwrap_or_default()
+}
+
+/// Parses the birthday out of an ISO 8601 date. An empty value means an unknown birthday.
+pub fn parse_birthday(value: &str) -> Result<Option<NaiveDate>, String> {
+    if value.trim().is_empty() {
+        return Ok(None);
+    }
+    parse_date(value.trim()).map(Some)
+}
+
+/// Formats important dates as `label:date` entries, e.g. `anniversary:2015-06-20`.
+pub fn format_dates(dates: &[ImportantDate]) -> String {
+    format_list(
+        dates
+            .iter()
+            .map(|x: &ImportantDate| (x.label.as_str(), x.date.format(DATE_FORMAT).to_string())),
+    )
+}
+
+/// Parses `label:date` entries, the label being a single word.
+pub fn parse_dates(value: &str) -> Result<Vec<ImportantDate>, String> {
+    let mut dates: Vec<ImportantDate> = vec![];
+    for (label, date) in parse_list(value) {
+        let label: &str = label.ok_or_else(|| format!("Missing label of the date {}", date))?;
+        dates.push(ImportantDate {
+            label: label.to_string(),
+            date: parse_date(date)?,
+        });
+    }
+    Ok(dates)
+}
+
+fn parse_date(value: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value, DATE_FORMAT)
+        .map_err(|_| format!("Invalid date {}, expected YYYY-MM-DD", value))
+}
+
+fn organization_id(affiliation: Option<&Affiliation>) -> Option<i32> {
+    affiliation.map(|affiliation: &Affiliation| affiliation.id.0)
+}
+
+fn job_title(affiliation: Option<&Affiliation>) -> String {
+    affiliation
+        .and_then(|affiliation: &Affiliation| affiliation.job_title.clone())
+        .unwrap_or_default()
+}
+
+/// Links a contact to the organization of the given id, if any. An empty job title means none.
+fn affiliation(organization_id: Option<i32>, job_title: String) -> Option<Affiliation> {
+    organization_id.map(|id: i32| Affiliation {
+        id: OrganizationId(id),
+        name: String::new(),
+        job_title: Some(job_title).filter(|x: &String| !x.is_empty()),
+    })
+}
+
+/// Formats phones as `type:number` entries, the primary one first.
+pub fn format_phones(phones: &[Phone]) -> String {
+    format_list(
+        with_single_primary(phones.to_vec())
+            .iter()
+            .map(|phone: &Phone| (phone.phone_type.as_str(), phone.number.to_string())),
+    )
+}
+
+/// Formats emails as `type:address` entries, the primary one first.
+pub fn format_emails(emails: &[Email]) -> String {
+    format_list(
+        with_single_primary(emails.to_vec())
+            .iter()
+            .map(|email: &Email| (email.email_type.as_str(), email.address.clone())),
+    )
+}
+
+/// Parses `type:number` entries, the first one being the primary one. Entries without a type are mobile phones.
+pub fn parse_phones(value: &str) -> Result<Vec<Phone>, String> {
+    let mut phones: Vec<Phone> = vec![];
+    for (index, (phone_type, number)) in parse_list(value).into_iter().enumerate() {
+        let phone_type: PhoneType = phone_type
+            .map(|x: &str| x.parse::<PhoneType>())
+            .transpose()?
+            .unwrap_or_default();
+        let number: PhoneNumber = number.parse::<PhoneNumber>().map_err(|x| x.to_string())?;
+        phones.push(Phone {
+            phone_type,
+            number,
+            primary: index == 0,
+        });
+    }
+    Ok(phones)
+}
+
+/// Parses `type:address` entries, the first one being the primary one. Entries without a type are of type other.
+pub fn parse_emails(value: &str) -> Result<Vec<Email>, String> {
+    let mut emails: Vec<Email> = vec![];
+    for (index, (email_type, address)) in parse_list(value).into_iter().enumerate() {
+        let email_type: EmailType = email_type
+            .map(|x: &str| x.parse::<EmailType>())
+            .transpose()?
+            .unwrap_or_default();
+        emails.push(Email {
+            email_type,
+            address: address.to_string(),
+            primary: index == 0,
+        });
+    }
+    Ok(emails)
+}
+
+/// Formats addresses as `label:street|locality|region|postal code|country` entries, the primary one first.
+pub fn format_addresses(addresses: &[Address]) -> String {
+    format_list(
+        with_single_primary(addresses.to_vec())
+            .iter()
+            .map(|address: &Address| {
+                let fields: String = [
+                    address.street.as_str(),
+                    address.locality.as_str(),
+                    address.region.as_str(),
+                    address.postal_code.as_str(),
+                    address.country.alpha2(),
+                ]
+                .join(&ADDRESS_FIELD_SEPARATOR.to_string());
+                (address.label.as_deref().unwrap_or_default(), fields)
+            }),
+    )
+}
+
+/// Parses `label:street|locality|region|postal code|country` entries, the first one being the primary one.
+/// The label is optional and, if given, a single word.
+pub fn parse_addresses(value: &str) -> Result<Vec<Address>, String> {
+    let mut addresses: Vec<Address> = vec![];
+    for (index, (label, fields)) in parse_list(value).into_iter().enumerate() {
+        let fields: Vec<&str> = fields
+            .split(ADDRESS_FIELD_SEPARATOR)
+            .map(str::trim)
+            .collect();
+        let [street, locality, region, postal_code, country] = fields[..] else {
+            return Err(format!(
+                "Invalid address {}, expected street|locality|region|postal code|country",
+                fields.join(&ADDRESS_FIELD_SEPARATOR.to_string())
+            ));
+        };
+        addresses.push(Address {
+            label: label.map(str::to_string),
+            street: street.to_string(),
+            locality: locality.to_string(),
+            region: region.to_string(),
+            postal_code: postal_code.to_string(),
+            country: parse_country(country).map_err(|x| x.to_string())?,
+            primary: index == 0,
+        });
+    }
+    Ok(addresses)
+}
+
+/// Formats tags as a list of names, e.g. `family; friends`.
+pub fn format_tags(tags: &[Tag]) -> String {
+    tags.iter()
+        .map(|tag: &Tag| tag.to_string())
+        .collect::<Vec<String>>()
+        .join(&format!("{} ", LIST_SEPARATOR))
+}
+
+/// Parses a list of tag names, skipping the empty ones.
+pub fn parse_tags(value: &str) -> Result<Vec<Tag>, String> {
+    value
+        .split(LIST_SEPARATOR)
+        .filter(|tag: &&str| !tag.trim().is_empty())
+        .map(|tag: &str| Tag::parse(tag).map_err(|err| err.to_string()))
+        .collect()
+}
+
+fn format_list<'a>(entries: impl Iterator<Item = (&'a str, String)>) -> String {
+    entries
+        .map(|(entry_type, value)| match entry_type {
+            "" => value,
+            _ => format!("{}{}{}", entry_type, TYPE_SEPARATOR, value),
+        })
+        .collect::<Vec<String>>()
+        .join(&format!("{} ", LIST_SEPARATOR))
+}
+
+/// Splits a list cell into its (optional type, value) entries, skipping the empty ones.
+/// A value may itself contain the type separator (e.g. `tel:`), so only a leading word is taken as the type.
+fn parse_list(value: &str) -> Vec<(Option<&str>, &str)> {
+    value
+        .split(LIST_SEPARATOR)
+        .map(str::trim)
+        .filter(|entry: &&str| !entry.is_empty())
+        .map(|entry: &str| match entry.split_once(TYPE_SEPARATOR) {
+            Some((entry_type, value))
+                if entry_type.chars().all(|c: char| c.is_ascii_alphabetic()) =>
+            {
+                (Some(entry_type.trim()), value.trim())
+            }
+            _ => (None, entry),
+        })
+        .collect()
+}
+
+fn write_line<T: AsRef<[u8]>>(fields: &[T]) -> Result<Bytes, Error> {
+    let mut writer = WriterBuilder::new().from_writer(vec![]);
+    writer.write_record(fields)?;
+    writer
+        .into_inner()
+        .map(Bytes::from)
+        .map_err(|err| Error::InvalidCsv(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_with_header_mapping() {
+        let input: &str = "E-mail,Full Name,Mobile,addresses\njohn@doe.com,\"Doe, John\",work:+4930123456; 0151 12345678,home:Main St. 1|Berlin||10115|de\njane@doe.com,Jane,n/a,Main St. 2|Paris||75001|XX\n";
+        let mut query_parameters: HashMap<String, String> = HashMap::new();
+        query_parameters.insert(NAME_COLUMN.to_string(), "full name".to_string());
+        query_parameters.insert(PHONES_COLUMN.to_string(), "Mobile".to_string());
+        query_parameters.insert(EMAILS_COLUMN.to_string(), "E-mail".to_string());
+
+        let rows: Vec<ImportRow> = parse(
+            input.as_bytes(),
+            &HeaderMapping::from_query(&query_parameters),
+        )
+        .unwrap();
+
+        assert_eq!(2, rows.len());
+        let first: &NewContact = rows[0].new_contact.as_ref().unwrap();
+        assert_eq!("Doe, John", first.name);
+        assert_eq!(2, first.phones.len());
+        assert_eq!(PhoneType::Work, first.phones[0].phone_type);
+        assert!(first.phones[0].primary);
+        assert_eq!("+4915112345678", first.phones[1].number.e164());
+        assert_eq!("john@doe.com", first.emails[0].address);
+        assert_eq!(Some("home".to_string()), first.addresses[0].label);
+        assert_eq!("DE", first.addresses[0].country.alpha2());
+        assert_eq!(3, rows[1].row);
+        assert_eq!(2, rows[1].new_contact.as_ref().unwrap_err().len());
+    }
+
+    #[test]
+    fn test_parse_with_missing_column() {
+        let input: &str = "name,emails\nJohn,john@doe.com\n";
+        let result: Result<Vec<ImportRow>, Error> =
+            parse(input.as_bytes(), &HeaderMapping::default());
+        assert!(matches!(result, Err(Error::InvalidCsv(_))));
+    }
+}
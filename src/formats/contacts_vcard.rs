@@ -0,0 +1,766 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use chrono::NaiveDate;
+
+use crate::models::contact::parse_country;
+use crate::models::contact::with_single_primary;
+use crate::models::contact::Address;
+use crate::models::contact::Contact;
+use crate::models::contact::Email;
+use crate::models::contact::EmailType;
+use crate::models::contact::NewContact;
+use crate::models::contact::Phone;
+use crate::models::contact::PhoneType;
+use crate::models::custom_field::CustomFieldValues;
+use crate::models::errors::Error;
+use crate::models::import_report::ImportRow;
+use crate::models::phone_number::PhoneNumber;
+use crate::models::tag::unique_sorted;
+use crate::models::tag::Tag;
+
+const VERSION_KEY: &str = "version";
+const VCF_EXTENSION: &str = ".vcf";
+const MAX_LINE_LENGTH: usize = 75;
+/// The `ADR` types which describe the kind of delivery rather than label the address.
+const DELIVERY_TYPES: [&str; 5] = ["PREF", "DOM", "INTL", "POSTAL", "PARCEL"];
+/// The `BDAY` formats, extended (3.0) and basic (4.0), either being accepted on import.
+const BDAY_FORMATS: [&str; 2] = ["%Y-%m-%d", "%Y%m%d"];
+
+/// The vCard versions supported, 3.0 (RFC 2426) and 4.0 (RFC 6350).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VCardVersion {
+    #[default]
+    V3,
+    V4,
+}
+
+impl VCardVersion {
+    /// Reads the version out of the `?version=` query parameter, defaulting to 3.0.
+    pub fn from_query(query_parameters: &HashMap<String, String>) -> Result<Self, Error> {
+        match query_parameters.get(VERSION_KEY) {
+            Some(version) => version.parse(),
+            None => Ok(VCardVersion::default()),
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            VCardVersion::V3 => "3.0",
+            VCardVersion::V4 => "4.0",
+        }
+    }
+
+    fn bday_format(&self) -> &'static str {
+        match self {
+            VCardVersion::V3 => BDAY_FORMATS[0],
+            VCardVersion::V4 => BDAY_FORMATS[1],
+        }
+    }
+}
+
+impl FromStr for VCardVersion {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "3.0" => Ok(VCardVersion::V3),
+            "4.0" => Ok(VCardVersion::V4),
+            other => Err(Error::InvalidVCard(format!(
+                "Unsupported VERSION {}, expected 3.0 or 4.0",
+                other
+            ))),
+        }
+    }
+}
+
+/// A `{id}.vcf` path segment.
+#[derive(Debug, Clone, Copy)]
+pub struct VCardFileName(pub i32);
+
+impl FromStr for VCardFileName {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.strip_suffix(VCF_EXTENSION)
+            .and_then(|id: &str| id.parse::<i32>().ok())
+            .map(VCardFileName)
+            .ok_or_else(|| Error::InvalidVCard(format!("Invalid vCard file name {}", s)))
+    }
+}
+
+/// A single content line of a vCard, `NAME;PARAM=VALUE:value`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Property {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl Property {
+    fn new(name: &str, value: String) -> Self {
+        Property {
+            name: name.to_string(),
+            params: vec![],
+            value,
+        }
+    }
+
+    fn with_param(mut self, name: &str, value: &str) -> Self {
+        self.params.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Returns a contact as a vCard of the given version, with CRLF line endings and folded lines.
+pub fn to_vcard(contact: &Contact, version: VCardVersion) -> String {
+    let mut vcard: String = String::new();
+    for property in properties(contact, version) {
+        let mut line: String = property.name;
+        for (name, value) in property.params {
+            line.push_str(&format!(";{}={}", name, value));
+        }
+        line.push(':');
+        line.push_str(&property.value);
+        vcard.push_str(&fold(&line));
+    }
+    vcard
+}
+
+fn properties(contact: &Contact, version: VCardVersion) -> Vec<Property> {
+    let (given_name, family_name): (&str, &str) = match contact.name.trim().rsplit_once(' ') {
+        Some((given_name, family_name)) => (given_name, family_name),
+        None => ("", contact.name.trim()),
+    };
+
+    let mut properties: Vec<Property> = vec![
+        Property::new("BEGIN", "VCARD".to_string()),
+        Property::new("VERSION", version.as_str().to_string()),
+        Property::new("UID", format!("contact-{}", contact.id.0)),
+        Property::new("FN", escape(&contact.name)),
+        Property::new(
+            "N",
+            format!("{};{};;;", escape(family_name), escape(given_name)),
+        ),
+    ];
+    for phone in with_single_primary(contact.phones.clone()) {
+        properties.push(tel_property(&phone, version));
+    }
+    for email in with_single_primary(contact.emails.clone()) {
+        properties.push(email_property(&email, version));
+    }
+    for address in with_single_primary(contact.addresses.clone()) {
+        properties.push(adr_property(&address, version));
+    }
+    if !contact.tags.is_empty() {
+        let categories: Vec<String> = contact
+            .tags
+            .iter()
+            .map(|tag: &Tag| escape(tag.as_str()))
+            .collect();
+        properties.push(Property::new("CATEGORIES", categories.join(",")));
+    }
+    if let Some(affiliation) = &contact.organization {
+        properties.push(Property::new("ORG", escape(&affiliation.name)));
+        if let Some(job_title) = &affiliation.job_title {
+            properties.push(Property::new("TITLE", escape(job_title)));
+        }
+    }
+    if let Some(birthday) = contact.birthday {
+        properties.push(Property::new(
+            "BDAY",
+            birthday.format(version.bday_format()).to_string(),
+        ));
+    }
+    properties.push(Property::new("END", "VCARD".to_string()));
+    properties
+}
+
+fn tel_property(phone: &Phone, version: VCardVersion) -> Property {
+    let phone_no: String = phone.number.to_string();
+    let phone_type: &str = match phone.phone_type {
+        PhoneType::Mobile => "CELL",
+        PhoneType::Home => "HOME",
+        PhoneType::Work => "WORK",
+        PhoneType::Fax => "FAX",
+        PhoneType::Other => "VOICE",
+    };
+    match version {
+        VCardVersion::V3 if phone.primary => {
+            Property::new("TEL", phone_no).with_param("TYPE", &format!("{},PREF", phone_type))
+        }
+        VCardVersion::V3 => Property::new("TEL", phone_no).with_param("TYPE", phone_type),
+        VCardVersion::V4 => {
+            let property: Property = Property::new("TEL", format!("tel:{}", phone_no))
+                .with_param("VALUE", "uri")
+                .with_param("TYPE", &phone_type.to_ascii_lowercase());
+            if phone.primary {
+                property.with_param("PREF", "1")
+            } else {
+                property
+            }
+        }
+    }
+}
+
+fn email_property(email: &Email, version: VCardVersion) -> Property {
+    let property: Property = Property::new("EMAIL", escape(&email.address));
+    let email_type: Option<&str> = match email.email_type {
+        EmailType::Home => Some("HOME"),
+        EmailType::Work => Some("WORK"),
+        EmailType::Other => None,
+    };
+    match version {
+        VCardVersion::V3 => {
+            let types: Vec<&str> = [
+                Some("INTERNET"),
+                email_type,
+                email.primary.then_some("PREF"),
+            ]
+            .into_iter()
+            .flatten()
+            .collect();
+            property.with_param("TYPE", &types.join(","))
+        }
+        VCardVersion::V4 => {
+            let property: Property = match email_type {
+                Some(email_type) => property.with_param("TYPE", &email_type.to_ascii_lowercase()),
+                None => property,
+            };
+            if email.primary {
+                property.with_param("PREF", "1")
+            } else {
+                property
+            }
+        }
+    }
+}
+
+/// An `ADR` property, with an empty post office box and extended address.
+/// The label is written as the `TYPE`, if it is a single word.
+fn adr_property(address: &Address, version: VCardVersion) -> Property {
+    let property: Property = Property::new(
+        "ADR",
+        format!(
+            ";;{};{};{};{};{}",
+            escape(&address.street),
+            escape(&address.locality),
+            escape(&address.region),
+            escape(&address.postal_code),
+            address.country.alpha2()
+        ),
+    );
+    let label: Option<&str> = address
+        .label
+        .as_deref()
+        .filter(|x: &&str| !x.is_empty() && x.chars().all(|c: char| c.is_ascii_alphanumeric()));
+    match version {
+        VCardVersion::V3 => {
+            let label: Option<String> = label.map(|x: &str| x.to_ascii_uppercase());
+            let types: Vec<&str> = [label.as_deref(), address.primary.then_some("PREF")]
+                .into_iter()
+                .flatten()
+                .collect();
+            if types.is_empty() {
+                property
+            } else {
+                property.with_param("TYPE", &types.join(","))
+            }
+        }
+        VCardVersion::V4 => {
+            let property: Property = match label {
+                Some(label) => property.with_param("TYPE", &label.to_ascii_lowercase()),
+                None => property,
+            };
+            if address.primary {
+                property.with_param("PREF", "1")
+            } else {
+                property
+            }
+        }
+    }
+}
+
+/// Parses a payload of one or more vCards into import rows, one per card.
+/// Fails as a whole only if the payload doesn't contain any card or has content outside of a card.
+pub fn parse(input: &str) -> Result<Vec<ImportRow>, Error> {
+    let mut rows: Vec<ImportRow> = vec![];
+    let mut current: Option<CardBuilder> = None;
+
+    for (line_no, line) in unfold(input) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let property: Property = match parse_property(&line) {
+            Ok(property) => property,
+            Err(message) => match current.as_mut() {
+                Some(card) => {
+                    card.errors.push(format!("Line {}: {}", line_no, message));
+                    continue;
+                }
+                None => {
+                    return Err(Error::InvalidVCard(format!(
+                        "Line {}: {}",
+                        line_no, message
+                    )))
+                }
+            },
+        };
+
+        match (property.name.as_str(), current.take()) {
+            ("BEGIN", previous) if property.value.eq_ignore_ascii_case("VCARD") => {
+                if let Some(mut card) = previous {
+                    card.errors.push("Missing END:VCARD".to_string());
+                    rows.push(card.build());
+                }
+                current = Some(CardBuilder::new(rows.len() as u64 + 1));
+            }
+            ("END", Some(card)) if property.value.eq_ignore_ascii_case("VCARD") => {
+                rows.push(card.build());
+            }
+            (_, Some(mut card)) => {
+                card.apply(&property, line_no);
+                current = Some(card);
+            }
+            (name, None) => {
+                return Err(Error::InvalidVCard(format!(
+                    "Line {}: {} found outside of BEGIN:VCARD and END:VCARD",
+                    line_no, name
+                )))
+            }
+        }
+    }
+
+    if let Some(mut card) = current {
+        card.errors.push("Missing END:VCARD".to_string());
+        rows.push(card.build());
+    }
+    if rows.is_empty() {
+        return Err(Error::InvalidVCard("No BEGIN:VCARD found".to_string()));
+    }
+    Ok(rows)
+}
+
+/// Collects the properties of a single card while it is parsed.
+struct CardBuilder {
+    row: u64,
+    version: Option<VCardVersion>,
+    formatted_name: Option<String>,
+    name: Option<String>,
+    phones: Vec<Phone>,
+    emails: Vec<Email>,
+    addresses: Vec<Address>,
+    tags: Vec<Tag>,
+    birthday: Option<NaiveDate>,
+    errors: Vec<String>,
+}
+
+impl CardBuilder {
+    fn new(row: u64) -> Self {
+        CardBuilder {
+            row,
+            version: None,
+            formatted_name: None,
+            name: None,
+            phones: vec![],
+            emails: vec![],
+            addresses: vec![],
+            tags: vec![],
+            birthday: None,
+            errors: vec![],
+        }
+    }
+
+    fn apply(&mut self, property: &Property, line_no: usize) {
+        match property.name.as_str() {
+            "VERSION" => match property.value.parse::<VCardVersion>() {
+                Ok(version) => self.version = Some(version),
+                Err(_) => self.errors.push(format!(
+                    "Line {}: Unsupported VERSION {}, expected 3.0 or 4.0",
+                    line_no, property.value
+                )),
+            },
+            "FN" => self.formatted_name = Some(unescape(&property.value)),
+            "N" => {
+                let components: Vec<String> = split_unescaped(&property.value, ';')
+                    .iter()
+                    .map(|x| unescape(x))
+                    .collect();
+                let given_and_family: Vec<&str> = [components.get(1), components.first()]
+                    .into_iter()
+                    .flatten()
+                    .map(|x: &String| x.trim())
+                    .filter(|x: &&str| !x.is_empty())
+                    .collect();
+                self.name = Some(given_and_family.join(" "));
+            }
+            "TEL" => match parse_phone_no(&property.value) {
+                Some(number) => self.phones.push(Phone {
+                    phone_type: phone_type(property),
+                    number,
+                    primary: is_preferred(property),
+                }),
+                None => self.errors.push(format!(
+                    "Line {}: Cannot parse {} as phone_no",
+                    line_no, property.value
+                )),
+            },
+            "EMAIL" => self.emails.push(Email {
+                email_type: email_type(property),
+                address: unescape(&property.value).trim().to_string(),
+                primary: is_preferred(property),
+            }),
+            "ADR" => match parse_address(property) {
+                Ok(address) => self.addresses.push(address),
+                Err(message) => self.errors.push(format!("Line {}: {}", line_no, message)),
+            },
+            "CATEGORIES" => {
+                for category in split_unescaped(&property.value, ',') {
+                    match Tag::parse(&unescape(&category)) {
+                        Ok(tag) => self.tags.push(tag),
+                        Err(err) => self.errors.push(format!("Line {}: {}", line_no, err)),
+                    }
+                }
+            }
+            // A birthday without a year (`--0620`) or given as text cannot be stored, so it's skipped
+            "BDAY"
+                if !property.value.starts_with("--")
+                    && !property
+                        .param("VALUE")
+                        .is_some_and(|x: &str| x.eq_ignore_ascii_case("text")) =>
+            {
+                match parse_bday(&property.value) {
+                    Some(birthday) => self.birthday = Some(birthday),
+                    None => self.errors.push(format!(
+                        "Line {}: Cannot parse {} as birthday",
+                        line_no, property.value
+                    )),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn build(mut self) -> ImportRow {
+        if self.version.is_none() && !self.errors.iter().any(|x| x.contains("VERSION")) {
+            self.errors.push("Missing VERSION".to_string());
+        }
+        let name: Option<String> = self
+            .formatted_name
+            .or(self.name)
+            .filter(|x: &String| !x.trim().is_empty());
+        if name.is_none() {
+            self.errors.push("Missing FN".to_string());
+        }
+
+        let new_contact: Result<NewContact, Vec<String>> = match name {
+            Some(name) if self.errors.is_empty() => Ok(NewContact {
+                name,
+                phones: with_single_primary(self.phones),
+                emails: with_single_primary(self.emails),
+                addresses: with_single_primary(self.addresses),
+                tags: unique_sorted(self.tags),
+                // ORG is a name, so it cannot be linked to an organization by id
+                organization: None,
+                custom_fields: CustomFieldValues::new(),
+                birthday: self.birthday,
+                dates: vec![],
+            }),
+            _ => Err(self.errors),
+        };
+        ImportRow {
+            row: self.row,
+            new_contact,
+        }
+    }
+}
+
+fn is_preferred(property: &Property) -> bool {
+    property.param("PREF").is_some()
+        || property
+            .param("TYPE")
+            .is_some_and(|x: &str| x.split(',').any(|y| y.eq_ignore_ascii_case("pref")))
+}
+
+/// The phone type out of the `TYPE` values, e.g. `TYPE=WORK,CELL` is a mobile phone.
+fn phone_type(property: &Property) -> PhoneType {
+    let types: Vec<String> = type_values(property);
+    [
+        ("CELL", PhoneType::Mobile),
+        ("FAX", PhoneType::Fax),
+        ("WORK", PhoneType::Work),
+        ("HOME", PhoneType::Home),
+    ]
+    .into_iter()
+    .find(|(name, _)| types.iter().any(|x: &String| x == name))
+    .map_or(PhoneType::Other, |(_, phone_type)| phone_type)
+}
+
+fn email_type(property: &Property) -> EmailType {
+    let types: Vec<String> = type_values(property);
+    if types.iter().any(|x: &String| x == "WORK") {
+        EmailType::Work
+    } else if types.iter().any(|x: &String| x == "HOME") {
+        EmailType::Home
+    } else {
+        EmailType::Other
+    }
+}
+
+/// All the `TYPE` values of a property, upper case, whether given as one list or as repeated parameters.
+fn type_values(property: &Property) -> Vec<String> {
+    property
+        .params
+        .iter()
+        .filter(|(name, _)| name == "TYPE")
+        .flat_map(|(_, value)| value.split(','))
+        .map(|x: &str| x.trim().to_ascii_uppercase())
+        .collect()
+}
+
+/// Reads the structured `ADR` value, `box;extended;street;locality;region;code;country`.
+/// The extended address, if any, is kept along with the street; the post office box is dropped.
+fn parse_address(property: &Property) -> Result<Address, String> {
+    let components: Vec<String> = split_unescaped(&property.value, ';')
+        .iter()
+        .map(|x| unescape(x).trim().to_string())
+        .collect();
+    let component = |index: usize| -> String { components.get(index).cloned().unwrap_or_default() };
+    let country: String = component(6);
+    if country.is_empty() {
+        return Err(format!("Missing country in ADR {}", property.value));
+    }
+    let street: String = [component(1), component(2)]
+        .into_iter()
+        .filter(|x: &String| !x.is_empty())
+        .collect::<Vec<String>>()
+        .join(", ");
+    Ok(Address {
+        label: type_values(property)
+            .into_iter()
+            .find(|x: &String| !DELIVERY_TYPES.contains(&x.as_str()))
+            .map(|x: String| x.to_ascii_lowercase()),
+        street,
+        locality: component(3),
+        region: component(4),
+        postal_code: component(5),
+        country: parse_country(&country).map_err(|err| err.to_string())?,
+        primary: is_preferred(property),
+    })
+}
+
+/// Parses the date of a `BDAY`, in either format, dropping the time, if any.
+fn parse_bday(value: &str) -> Option<NaiveDate> {
+    let date: &str = value.split('T').next().unwrap_or_default().trim();
+    BDAY_FORMATS
+        .iter()
+        .find_map(|format: &&str| NaiveDate::parse_from_str(date, format).ok())
+}
+
+fn parse_phone_no(value: &str) -> Option<PhoneNumber> {
+    PhoneNumber::parse(&unescape(value)).ok()
+}
+
+/// Splits a content line into its name, parameters and value. Group prefixes (`item1.TEL`) are dropped.
+fn parse_property(line: &str) -> Result<Property, String> {
+    let mut in_quotes: bool = false;
+    let colon: usize = line
+        .char_indices()
+        .find(|(_, c)| {
+            if *c == '"' {
+                in_quotes = !in_quotes;
+            }
+            *c == ':' && !in_quotes
+        })
+        .map(|(index, _)| index)
+        .ok_or_else(|| format!("Property without a value: {}", line))?;
+
+    let (head, value): (&str, &str) = (&line[..colon], &line[colon + 1..]);
+    let mut parts = head.split(';');
+    let name: &str = parts.next().unwrap_or_default();
+    let name: &str = name.rsplit_once('.').map_or(name, |(_, x)| x);
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(format!("Invalid property name: {}", head));
+    }
+
+    let params: Vec<(String, String)> = parts
+        .map(|param: &str| match param.split_once('=') {
+            Some((key, value)) => (
+                key.to_ascii_uppercase(),
+                value.trim_matches('"').to_string(),
+            ),
+            // vCard 2.1 style bare parameters, e.g. `TEL;CELL:...`
+            None => ("TYPE".to_string(), param.to_string()),
+        })
+        .collect();
+
+    Ok(Property {
+        name: name.to_ascii_uppercase(),
+        params,
+        value: value.to_string(),
+    })
+}
+
+/// Joins folded lines back, keeping the number of the line each property started at.
+fn unfold(input: &str) -> Vec<(usize, String)> {
+    let mut lines: Vec<(usize, String)> = vec![];
+    for (index, line) in input.lines().enumerate() {
+        let line: &str = line.strip_suffix('\r').unwrap_or(line);
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some((_, previous))) => previous.push_str(continuation),
+            _ => lines.push((index + 1, line.to_string())),
+        }
+    }
+    lines
+}
+
+/// Folds a content line at 75 octets, without splitting UTF-8 characters, and terminates it with CRLF.
+fn fold(line: &str) -> String {
+    let mut folded: String = String::new();
+    let mut length: usize = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > MAX_LINE_LENGTH {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(',', "\\,")
+        .replace(';', "\\;")
+        .replace('\n', "\\n")
+}
+
+fn unescape(value: &str) -> String {
+    let mut unescaped: String = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => unescaped.push('\n'),
+            Some(other) => unescaped.push(other),
+            None => {}
+        }
+    }
+    unescaped
+}
+
+/// Splits a structured value on a separator not preceded by a backslash.
+fn split_unescaped(value: &str, separator: char) -> Vec<String> {
+    let mut parts: Vec<String> = vec![String::new()];
+    let mut escaped: bool = false;
+    for c in value.chars() {
+        if c == separator && !escaped {
+            parts.push(String::new());
+        } else if let Some(part) = parts.last_mut() {
+            part.push(c);
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::contact::ContactId;
+    use isocountry::CountryCode;
+
+    #[test]
+    fn test_to_vcard_and_parse() {
+        let contact: Contact = Contact {
+            id: ContactId(7),
+            name: "John Doe, Jr.".to_string(),
+            phones: vec![
+                Phone {
+                    phone_type: PhoneType::Work,
+                    number: PhoneNumber::parse("+4930123456;ext=12").unwrap(),
+                    primary: false,
+                },
+                Phone {
+                    phone_type: PhoneType::Mobile,
+                    number: PhoneNumber::parse("+4915112345678").unwrap(),
+                    primary: true,
+                },
+            ],
+            emails: vec![
+                Email {
+                    email_type: EmailType::Home,
+                    address: "john@doe.com".to_string(),
+                    primary: true,
+                },
+                Email {
+                    email_type: EmailType::Other,
+                    address: "jd@example.com".to_string(),
+                    primary: false,
+                },
+            ],
+            addresses: vec![Address {
+                label: Some("work".to_string()),
+                street: "Unter den Linden 1; 2nd floor".to_string(),
+                locality: "Berlin".to_string(),
+                region: "Berlin".to_string(),
+                postal_code: "10117".to_string(),
+                country: CountryCode::DEU,
+                primary: true,
+            }],
+            tags: vec![Tag::parse("family").unwrap(), Tag::parse("work").unwrap()],
+            organization: None,
+            custom_fields: CustomFieldValues::new(),
+            birthday: NaiveDate::from_ymd_opt(1990, 2, 28),
+            dates: vec![],
+        };
+
+        for version in [VCardVersion::V3, VCardVersion::V4] {
+            let vcard: String = to_vcard(&contact, version);
+            assert!(vcard.ends_with("END:VCARD\r\n"));
+
+            let rows: Vec<ImportRow> = parse(&vcard).unwrap();
+            assert_eq!(1, rows.len());
+            let new_contact: &NewContact = rows[0].new_contact.as_ref().unwrap();
+            assert_eq!(contact.name, new_contact.name);
+            assert_eq!(
+                with_single_primary(contact.phones.clone()),
+                new_contact.phones
+            );
+            assert_eq!(contact.emails, new_contact.emails);
+            assert_eq!(contact.addresses, new_contact.addresses);
+            assert_eq!(contact.tags, new_contact.tags);
+            assert_eq!(contact.birthday, new_contact.birthday);
+        }
+    }
+
+    #[test]
+    fn test_parse_malformed_cards() {
+        let input: &str = "BEGIN:VCARD\r\nVERSION:2.1\r\nFN:Jane\r\nTEL:abc\r\nEMAIL:jane@doe.com\r\nEND:VCARD\r\nBEGIN:VCARD\r\nVERSION:4.0\r\nN:Doe;Jo\r\n hn;;;\r\nTEL;TYPE=work:+49 151 1234567\r\nEMAIL:john@doe.com\r\n";
+
+        let rows: Vec<ImportRow> = parse(input).unwrap();
+
+        assert_eq!(2, rows.len());
+        let errors: &Vec<String> = rows[0].new_contact.as_ref().unwrap_err();
+        assert!(errors[0].starts_with("Line 2: Unsupported VERSION 2.1"));
+        assert!(errors[1].starts_with("Line 4: Cannot parse abc"));
+        assert_eq!(
+            &vec!["Missing END:VCARD".to_string()],
+            rows[1].new_contact.as_ref().unwrap_err()
+        );
+        assert!(parse("FN:John\r\n").is_err());
+    }
+}
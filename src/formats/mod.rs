@@ -0,0 +1,3 @@
+pub mod contacts_csv;
+pub mod contacts_vcard;
+pub mod webdav;
@@ -0,0 +1,471 @@
+use roxmltree::Document;
+use roxmltree::Node;
+use warp::hyper::StatusCode;
+
+use crate::models::errors::Error;
+
+pub const DAV_NAMESPACE: &str = "DAV:";
+pub const CARDDAV_NAMESPACE: &str = "urn:ietf:params:xml:ns:carddav";
+pub const CALENDARSERVER_NAMESPACE: &str = "http://calendarserver.org/ns/";
+
+/// The prefixes used when writing the namespaces above. They are declared on the `multistatus` element.
+const NAMESPACE_PREFIXES: [(&str, &str); 3] = [
+    (DAV_NAMESPACE, "d"),
+    (CARDDAV_NAMESPACE, "card"),
+    (CALENDARSERVER_NAMESPACE, "cs"),
+];
+
+/// The qualified name of a WebDAV property, e.g. `{DAV:}getetag`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PropName {
+    pub namespace: String,
+    pub name: String,
+}
+
+impl PropName {
+    pub fn new(namespace: &str, name: &str) -> Self {
+        PropName {
+            namespace: namespace.to_string(),
+            name: name.to_string(),
+        }
+    }
+
+    /// Writes the property as an element with the given inner XML, or as an empty element.
+    fn to_xml(&self, inner_xml: Option<&str>) -> String {
+        let (tag, declaration): (String, String) = match NAMESPACE_PREFIXES
+            .iter()
+            .find(|(namespace, _)| *namespace == self.namespace)
+        {
+            Some((_, prefix)) => (format!("{}:{}", prefix, self.name), String::new()),
+            None => (
+                format!("x:{}", self.name),
+                format!(" xmlns:x=\"{}\"", escape(&self.namespace)),
+            ),
+        };
+        match inner_xml {
+            Some(inner_xml) if !inner_xml.is_empty() => {
+                format!("<{tag}{declaration}>{inner_xml}</{tag}>")
+            }
+            _ => format!("<{tag}{declaration}/>"),
+        }
+    }
+}
+
+/// The body of a `PROPFIND` request. An empty body means `allprop`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PropfindRequest {
+    AllProp,
+    Prop(Vec<PropName>),
+}
+
+/// The body of a `REPORT` request, limited to the reports supported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReportRequest {
+    /// RFC 6352, section 8.7
+    AddressbookMultiget {
+        props: PropfindRequest,
+        hrefs: Vec<String>,
+    },
+    /// RFC 6352, section 8.6
+    AddressbookQuery {
+        props: PropfindRequest,
+        filter: QueryFilter,
+        limit: Option<usize>,
+    },
+    /// RFC 6578, section 3.2
+    SyncCollection {
+        props: PropfindRequest,
+        sync_token: Option<String>,
+    },
+}
+
+/// The `filter` of an `addressbook-query` report.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct QueryFilter {
+    pub all_of: bool,
+    pub prop_filters: Vec<PropFilter>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PropFilter {
+    pub name: String,
+    pub all_of: bool,
+    pub is_not_defined: bool,
+    pub text_matches: Vec<TextMatch>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextMatch {
+    pub value: String,
+    pub match_type: MatchType,
+    pub negate: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchType {
+    Equals,
+    Contains,
+    StartsWith,
+    EndsWith,
+}
+
+impl QueryFilter {
+    /// Checks the filter against the values of a card, looked up by property name (e.g. `EMAIL`).
+    /// A filter without any prop-filter matches everything.
+    pub fn matches(&self, values_of: impl Fn(&str) -> Vec<String>) -> bool {
+        let mut results = self
+            .prop_filters
+            .iter()
+            .map(|prop_filter: &PropFilter| prop_filter.matches(&values_of(&prop_filter.name)));
+        if self.prop_filters.is_empty() {
+            true
+        } else if self.all_of {
+            results.all(|x| x)
+        } else {
+            results.any(|x| x)
+        }
+    }
+}
+
+impl PropFilter {
+    fn matches(&self, values: &[String]) -> bool {
+        if self.is_not_defined {
+            return values.is_empty();
+        }
+        if values.is_empty() {
+            return false;
+        }
+        let mut results = self.text_matches.iter().map(|text_match: &TextMatch| {
+            values
+                .iter()
+                .any(|value: &String| text_match.matches(value))
+        });
+        if self.text_matches.is_empty() {
+            true
+        } else if self.all_of {
+            results.all(|x| x)
+        } else {
+            results.any(|x| x)
+        }
+    }
+}
+
+impl TextMatch {
+    /// Matches using the default `i;unicode-casemap` collation, i.e. case-insensitive.
+    fn matches(&self, value: &str) -> bool {
+        let value: String = value.to_lowercase();
+        let expected: String = self.value.to_lowercase();
+        let result: bool = match self.match_type {
+            MatchType::Equals => value == expected,
+            MatchType::Contains => value.contains(&expected),
+            MatchType::StartsWith => value.starts_with(&expected),
+            MatchType::EndsWith => value.ends_with(&expected),
+        };
+        result != self.negate
+    }
+}
+
+pub fn parse_propfind(body: &[u8]) -> Result<PropfindRequest, Error> {
+    if body.iter().all(u8::is_ascii_whitespace) {
+        return Ok(PropfindRequest::AllProp);
+    }
+    let body: &str = as_str(body)?;
+    let document: Document = Document::parse(body)?;
+    let root: Node = document.root_element();
+    if !is(&root, DAV_NAMESPACE, "propfind") {
+        return Err(Error::InvalidXml(format!(
+            "Expected propfind, found {}",
+            root.tag_name().name()
+        )));
+    }
+    Ok(parse_props(&root))
+}
+
+pub fn parse_report(body: &[u8]) -> Result<ReportRequest, Error> {
+    let body: &str = as_str(body)?;
+    let document: Document = Document::parse(body)?;
+    let root: Node = document.root_element();
+    let props: PropfindRequest = parse_props(&root);
+
+    if is(&root, CARDDAV_NAMESPACE, "addressbook-multiget") {
+        let hrefs: Vec<String> = children(&root, DAV_NAMESPACE, "href")
+            .map(|href: Node| text(&href))
+            .collect();
+        Ok(ReportRequest::AddressbookMultiget { props, hrefs })
+    } else if is(&root, CARDDAV_NAMESPACE, "addressbook-query") {
+        let filter: QueryFilter = children(&root, CARDDAV_NAMESPACE, "filter")
+            .next()
+            .map(|filter: Node| parse_filter(&filter))
+            .unwrap_or_default();
+        let limit: Option<usize> = children(&root, CARDDAV_NAMESPACE, "limit")
+            .flat_map(|limit: Node| children(&limit, CARDDAV_NAMESPACE, "nresults"))
+            .next()
+            .and_then(|nresults: Node| text(&nresults).parse::<usize>().ok());
+        Ok(ReportRequest::AddressbookQuery {
+            props,
+            filter,
+            limit,
+        })
+    } else if is(&root, DAV_NAMESPACE, "sync-collection") {
+        let sync_token: Option<String> = children(&root, DAV_NAMESPACE, "sync-token")
+            .next()
+            .map(|sync_token: Node| text(&sync_token))
+            .filter(|sync_token: &String| !sync_token.is_empty());
+        Ok(ReportRequest::SyncCollection { props, sync_token })
+    } else {
+        Err(Error::InvalidXml(format!(
+            "Unsupported report {}",
+            root.tag_name().name()
+        )))
+    }
+}
+
+fn parse_props(parent: &Node) -> PropfindRequest {
+    match children(parent, DAV_NAMESPACE, "prop").next() {
+        Some(prop) => PropfindRequest::Prop(
+            prop.children()
+                .filter(Node::is_element)
+                .map(|x: Node| {
+                    PropName::new(
+                        x.tag_name().namespace().unwrap_or_default(),
+                        x.tag_name().name(),
+                    )
+                })
+                .collect(),
+        ),
+        None => PropfindRequest::AllProp,
+    }
+}
+
+fn parse_filter(filter: &Node) -> QueryFilter {
+    QueryFilter {
+        all_of: filter.attribute("test") == Some("allof"),
+        prop_filters: children(filter, CARDDAV_NAMESPACE, "prop-filter")
+            .map(|prop_filter: Node| PropFilter {
+                name: prop_filter
+                    .attribute("name")
+                    .unwrap_or_default()
+                    .to_uppercase(),
+                all_of: prop_filter.attribute("test") == Some("allof"),
+                is_not_defined: children(&prop_filter, CARDDAV_NAMESPACE, "is-not-defined")
+                    .next()
+                    .is_some(),
+                text_matches: children(&prop_filter, CARDDAV_NAMESPACE, "text-match")
+                    .map(|text_match: Node| TextMatch {
+                        value: text(&text_match),
+                        match_type: match text_match.attribute("match-type") {
+                            Some("equals") => MatchType::Equals,
+                            Some("starts-with") => MatchType::StartsWith,
+                            Some("ends-with") => MatchType::EndsWith,
+                            _ => MatchType::Contains,
+                        },
+                        negate: text_match.attribute("negate-condition") == Some("yes"),
+                    })
+                    .collect(),
+            })
+            .collect(),
+    }
+}
+
+/// Builds a `207 Multi-Status` response body.
+pub struct Multistatus {
+    responses: String,
+    sync_token: Option<String>,
+}
+
+impl Multistatus {
+    pub fn new() -> Self {
+        Multistatus {
+            responses: String::new(),
+            sync_token: None,
+        }
+    }
+
+    /// Adds a resource, with the properties found (name and inner XML) and those not found.
+    pub fn add_response(
+        &mut self,
+        href: &str,
+        found: Vec<(PropName, String)>,
+        not_found: Vec<PropName>,
+    ) {
+        self.responses
+            .push_str(&format!("<d:response><d:href>{}</d:href>", escape(href)));
+        if !found.is_empty() {
+            let props: String = found
+                .iter()
+                .map(|(prop, inner_xml)| prop.to_xml(Some(inner_xml)))
+                .collect();
+            self.responses.push_str(&propstat(&props, StatusCode::OK));
+        }
+        if !not_found.is_empty() {
+            let props: String = not_found.iter().map(|prop| prop.to_xml(None)).collect();
+            self.responses
+                .push_str(&propstat(&props, StatusCode::NOT_FOUND));
+        }
+        self.responses.push_str("</d:response>");
+    }
+
+    /// Adds a resource with only a status, e.g. a deleted or unknown one.
+    pub fn add_status(&mut self, href: &str, status: StatusCode) {
+        self.responses.push_str(&format!(
+            "<d:response><d:href>{}</d:href><d:status>{}</d:status></d:response>",
+            escape(href),
+            status_line(status)
+        ));
+    }
+
+    pub fn set_sync_token(&mut self, sync_token: String) {
+        self.sync_token = Some(sync_token);
+    }
+
+    pub fn to_xml(&self) -> String {
+        let namespaces: String = NAMESPACE_PREFIXES
+            .iter()
+            .map(|(namespace, prefix)| format!(" xmlns:{}=\"{}\"", prefix, namespace))
+            .collect();
+        let sync_token: String = self
+            .sync_token
+            .as_ref()
+            .map(|x| format!("<d:sync-token>{}</d:sync-token>", escape(x)))
+            .unwrap_or_default();
+        format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<d:multistatus{}>{}{}</d:multistatus>",
+            namespaces, self.responses, sync_token
+        )
+    }
+}
+
+impl Default for Multistatus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Builds a WebDAV `error` body with a single precondition element, e.g. `{DAV:}valid-sync-token`.
+pub fn error_xml(precondition: &PropName) -> String {
+    let namespaces: String = NAMESPACE_PREFIXES
+        .iter()
+        .map(|(namespace, prefix)| format!(" xmlns:{}=\"{}\"", prefix, namespace))
+        .collect();
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<d:error{}>{}</d:error>",
+        namespaces,
+        precondition.to_xml(None)
+    )
+}
+
+pub fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn propstat(props: &str, status: StatusCode) -> String {
+    format!(
+        "<d:propstat><d:prop>{}</d:prop><d:status>{}</d:status></d:propstat>",
+        props,
+        status_line(status)
+    )
+}
+
+fn status_line(status: StatusCode) -> String {
+    format!(
+        "HTTP/1.1 {} {}",
+        status.as_u16(),
+        status.canonical_reason().unwrap_or_default()
+    )
+}
+
+fn as_str(body: &[u8]) -> Result<&str, Error> {
+    std::str::from_utf8(body).map_err(|err| Error::InvalidXml(err.to_string()))
+}
+
+fn is(node: &Node, namespace: &str, name: &str) -> bool {
+    node.tag_name().namespace() == Some(namespace) && node.tag_name().name() == name
+}
+
+fn children<'a, 'input: 'a>(
+    parent: &Node<'a, 'input>,
+    namespace: &'a str,
+    name: &'a str,
+) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    parent
+        .children()
+        .filter(move |x: &Node| x.is_element() && is(x, namespace, name))
+}
+
+fn text(node: &Node) -> String {
+    node.text().unwrap_or_default().trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_addressbook_query() {
+        let body: &str = r#"<?xml version="1.0" encoding="utf-8" ?>
+            <C:addressbook-query xmlns:D="DAV:" xmlns:C="urn:ietf:params:xml:ns:carddav">
+                <D:prop><D:getetag/><C:address-data/></D:prop>
+                <C:filter test="allof">
+                    <C:prop-filter name="FN">
+                        <C:text-match match-type="starts-with">john</C:text-match>
+                    </C:prop-filter>
+                    <C:prop-filter name="EMAIL">
+                        <C:text-match negate-condition="yes">@example.com</C:text-match>
+                    </C:prop-filter>
+                </C:filter>
+                <C:limit><C:nresults>10</C:nresults></C:limit>
+            </C:addressbook-query>"#;
+
+        let ReportRequest::AddressbookQuery {
+            props,
+            filter,
+            limit,
+        } = parse_report(body.as_bytes()).unwrap()
+        else {
+            panic!("Expected an addressbook-query");
+        };
+
+        assert_eq!(
+            PropfindRequest::Prop(vec![
+                PropName::new(DAV_NAMESPACE, "getetag"),
+                PropName::new(CARDDAV_NAMESPACE, "address-data"),
+            ]),
+            props
+        );
+        assert_eq!(Some(10), limit);
+        let values_of = |email: &'static str| {
+            move |name: &str| match name {
+                "FN" => vec!["John Doe".to_string()],
+                "EMAIL" => vec![email.to_string()],
+                _ => vec![],
+            }
+        };
+        assert!(filter.matches(values_of("john@doe.com")));
+        assert!(!filter.matches(values_of("john@example.com")));
+    }
+
+    #[test]
+    fn test_multistatus_to_xml() {
+        let mut multistatus: Multistatus = Multistatus::new();
+        multistatus.add_response(
+            "/carddav/contacts/1.vcf",
+            vec![(
+                PropName::new(DAV_NAMESPACE, "getetag"),
+                "\"abc\"".to_string(),
+            )],
+            vec![PropName::new("http://example.com/ns/", "color")],
+        );
+        multistatus.set_sync_token("token".to_string());
+
+        let xml: String = multistatus.to_xml();
+
+        assert!(Document::parse(&xml).is_ok());
+        assert!(xml.contains("<d:getetag>\"abc\"</d:getetag>"));
+        assert!(xml.contains("<x:color xmlns:x=\"http://example.com/ns/\"/>"));
+        assert!(xml.contains("HTTP/1.1 404 Not Found"));
+    }
+}
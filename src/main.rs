@@ -1,17 +1,46 @@
 use dotenv::dotenv;
+use log::error;
+use std::collections::HashMap;
 use std::env;
 use std::net::SocketAddr;
+use std::process;
+use std::sync::Arc;
 
 mod api;
+mod formats;
 mod middleware;
 mod models;
 mod repositories;
 
+use crate::api::auth_filters::SharedAuthMiddleware;
+use crate::api::auth_filters::SharedTokenService;
 use crate::api::contacts_routes::get_all_routes;
+use crate::api::rate_limiting::SharedRateLimiter;
+use crate::middleware::auth::api_users_file_path;
+use crate::middleware::auth::read_api_users_file;
+use crate::middleware::auth::AuthInMemoryMiddleware;
+use crate::middleware::auth::SeedUser;
+use crate::middleware::auth_db::AuthDbMiddleware;
+use crate::middleware::jwt::BearerAuthMiddleware;
+use crate::middleware::jwt::TokenService;
+use crate::middleware::login_throttle::LoginThrottle;
+use crate::middleware::login_throttle::ThrottleConfig;
+use crate::middleware::login_throttle::ThrottledAuthMiddleware;
+use crate::middleware::oauth2::ResourceServer;
+use crate::middleware::rate_limiter::RateLimiter;
+use crate::middleware::validation::ValidationMiddleware;
+use crate::models::errors::Error;
 use crate::repositories::contacts_db_repository::ContactsDbRepository;
+use crate::repositories::contacts_in_memory_repository::ContactsInMemoryRepository;
+use crate::repositories::photo_fs_storage::PhotoFsStorage;
+use crate::repositories::photo_storage::SharedPhotoStorage;
 
 const API_PORT_KEY: &str = "API_PORT";
 const DEFAULT_API_PORT: &str = "8090";
+const CONTACTS_REPOSITORY_KEY: &str = "CONTACTS_REPOSITORY";
+const IN_MEMORY_CONTACTS_REPOSITORY: &str = "in_memory";
+const PHOTO_STORAGE_KEY: &str = "PHOTO_STORAGE";
+const DB_PHOTO_STORAGE: &str = "db";
 
 #[tokio::main]
 async fn main() {
@@ -19,10 +48,86 @@ async fn main() {
     env_logger::init();
 
     let addr: SocketAddr = get_addr();
-    let db_repository: ContactsDbRepository = ContactsDbRepository::new().await;
-    let routes = get_all_routes(db_repository);
+    let validation: Arc<ValidationMiddleware> = Arc::new(ValidationMiddleware::new());
+    let token_service: SharedTokenService = Arc::new(exit_on_error(
+        TokenService::new().await,
+        "Cannot load the JWT keys",
+    ));
+    let rate_limiter: SharedRateLimiter = Arc::new(RateLimiter::new().await);
+    let resource_server: Option<Arc<ResourceServer>> = ResourceServer::from_env().map(Arc::new);
 
-    warp::serve(routes).run(addr).await;
+    if env::var(CONTACTS_REPOSITORY_KEY).as_deref() == Ok(IN_MEMORY_CONTACTS_REPOSITORY) {
+        let in_memory_repository: ContactsInMemoryRepository = ContactsInMemoryRepository::new();
+        let photo_storage: SharedPhotoStorage = Arc::new(PhotoFsStorage::new().await);
+        let api_users: Arc<AuthInMemoryMiddleware> = Arc::new(exit_on_error(
+            AuthInMemoryMiddleware::new().await,
+            "Cannot load the API users, rejecting the users file",
+        ));
+        api_users.clone().watch_file();
+        let auth_middleware: SharedAuthMiddleware = Arc::new(BearerAuthMiddleware::new(
+            Arc::new(ThrottledAuthMiddleware::new(
+                api_users.clone(),
+                LoginThrottle::new(ThrottleConfig::from_env()),
+            )),
+            token_service.clone(),
+            resource_server,
+        ));
+        let routes = get_all_routes(
+            in_memory_repository,
+            validation,
+            auth_middleware,
+            token_service,
+            photo_storage,
+            api_users.clone(),
+            api_users,
+            rate_limiter,
+        );
+        warp::serve(routes).run(addr).await;
+    } else {
+        let db_repository: ContactsDbRepository = ContactsDbRepository::new().await;
+        let photo_storage: SharedPhotoStorage =
+            if env::var(PHOTO_STORAGE_KEY).as_deref() == Ok(DB_PHOTO_STORAGE) {
+                Arc::new(db_repository.photo_storage())
+            } else {
+                Arc::new(PhotoFsStorage::new().await)
+            };
+        let api_users: Arc<AuthDbMiddleware> = Arc::new(db_repository.auth_middleware());
+        let seed_users: HashMap<String, SeedUser> = exit_on_error(
+            read_api_users_file(&api_users_file_path()).await,
+            "Cannot seed the API users",
+        );
+        exit_on_error(
+            api_users.seed(seed_users).await,
+            "Cannot seed the API users",
+        );
+        let auth_middleware: SharedAuthMiddleware = Arc::new(BearerAuthMiddleware::new(
+            Arc::new(ThrottledAuthMiddleware::new(
+                api_users.clone(),
+                LoginThrottle::new(ThrottleConfig::from_env()),
+            )),
+            token_service.clone(),
+            resource_server,
+        ));
+        let routes = get_all_routes(
+            db_repository,
+            validation,
+            auth_middleware,
+            token_service,
+            photo_storage,
+            api_users.clone(),
+            api_users,
+            rate_limiter,
+        );
+        warp::serve(routes).run(addr).await;
+    }
+}
+
+/// Logs why the app cannot start, then exits.
+fn exit_on_error<T>(result: Result<T, Error>, message: &str) -> T {
+    result.unwrap_or_else(|err| {
+        error!("{message}: {err}");
+        process::exit(1)
+    })
 }
 
 fn get_addr() -> SocketAddr {
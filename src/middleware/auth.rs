@@ -1,51 +1,305 @@
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::env;
+use std::fs::Metadata;
+use std::io;
+use std::net::IpAddr;
 use std::sync::Arc;
+use std::time::Duration;
+use std::time::SystemTime;
 
 use async_trait::async_trait;
 use base64::engine::general_purpose as base64Engine;
 use base64::Engine;
+use chrono::DateTime;
+use chrono::Utc;
+use log::error;
+use log::info;
+use serde::Deserialize;
 use tokio::fs::File;
 use tokio::io::AsyncReadExt;
+use tokio::signal::unix::signal;
+use tokio::signal::unix::Signal;
+use tokio::signal::unix::SignalKind;
 use tokio::sync::RwLock;
+use tokio::time::Interval;
 
+use crate::models::api_key::hash_key;
+use crate::models::api_key::ApiKey;
+use crate::models::api_key::ApiKeyId;
+use crate::models::api_key::NewApiKey;
+use crate::models::api_user::ApiUser;
+use crate::models::api_user::NewApiUser;
 use crate::models::errors::Error;
+use crate::models::role::Role;
+use crate::models::token::AccessToken;
+use crate::repositories::api_keys_repository::ApiKeysRepository;
+use crate::repositories::api_users_repository::ApiUsersRepository;
 
 const API_USERS_FILE_KEY: &str = "API_USERS_FILE";
 const DEFAULT_FILE: &str = "api_users.json";
+const API_USERS_FILE_POLL_INTERVAL_KEY: &str = "API_USERS_FILE_POLL_INTERVAL";
+const DEFAULT_POLL_INTERVAL: u64 = 5;
 
 #[async_trait]
-trait AuthMiddleware {
+pub trait AuthMiddleware {
     /// Checks if the authorization header assuming HTTP Basic Auth schema agains a given data store of users.
     async fn http_basic_auth(&self, auth_header: String) -> Result<bool, Error>;
+
+    /// Whether the user exists, e.g. to check the owner of a token still does.
+    async fn has_user(&self, username: &str) -> Result<bool, Error>;
+
+    /// The role of the user, if the user exists.
+    async fn role(&self, username: &str) -> Result<Option<Role>, Error>;
+
+    /// Authenticates a service by its API key (`X-Api-Key: <key>`), recording the use of the key.
+    /// Returns `Error::Unauthorized` if the key is unknown or expired.
+    async fn authenticate_api_key(&self, api_key: &str) -> Result<ApiKey, Error>;
+
+    /// Authenticates a user by an access token of the identity provider (`Authorization: Bearer <token>`),
+    /// in the OAuth 2.0 resource-server mode. Returns None if the header carries no such token, e.g. one issued by
+    /// the API, for `authenticate` to handle. Not supported by default.
+    async fn authenticate_access_token(
+        &self,
+        _auth_header: &str,
+    ) -> Result<Option<AccessToken>, Error> {
+        Ok(None)
+    }
+
+    /// Same as `http_basic_auth`, but returns the username of the authenticated user.
+    /// The IP of the client, if known, is for the middlewares throttling the failed logins.
+    async fn authenticate_basic(
+        &self,
+        auth_header: String,
+        _client_ip: Option<IpAddr>,
+    ) -> Result<String, Error> {
+        let (username, _): (String, String) = parse_auth_header(auth_header.clone())?;
+        if self.http_basic_auth(auth_header).await? {
+            Ok(username)
+        } else {
+            Err(Error::Unauthorized)
+        }
+    }
+
+    /// Authenticates the user by any of the supported schemes, returning the username. Only HTTP Basic Auth by default.
+    async fn authenticate(
+        &self,
+        auth_header: String,
+        client_ip: Option<IpAddr>,
+    ) -> Result<String, Error> {
+        self.authenticate_basic(auth_header, client_ip).await
+    }
+}
+
+/// An entry of the users file, seeding the API users: either the password alone, for a `reader`,
+/// or the password along with the role, e.g. `{"password": "drowssap", "role": "editor"}`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(from = "SeedUserEntry")]
+pub struct SeedUser {
+    pub password: String,
+    pub role: Role,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SeedUserEntry {
+    Password(String),
+    User { password: String, role: Role },
+}
+
+impl From<SeedUserEntry> for SeedUser {
+    fn from(entry: SeedUserEntry) -> Self {
+        match entry {
+            SeedUserEntry::Password(password) => SeedUser {
+                password,
+                role: Role::Reader,
+            },
+            SeedUserEntry::User { password, role } => SeedUser { password, role },
+        }
+    }
+}
+
+/// The path of the users file, `API_USERS_FILE`.
+pub fn api_users_file_path() -> String {
+    env::var(API_USERS_FILE_KEY).unwrap_or(DEFAULT_FILE.to_string())
+}
+
+/// Reads the users of a users file, by username.
+/// No users file is shipped, so that no deployment lets in a user whose password is known to anyone having the sources.
+pub async fn read_api_users_file(path: &str) -> Result<HashMap<String, SeedUser>, Error> {
+    let mut file: File = File::open(path).await.map_err(|err| match err.kind() {
+        io::ErrorKind::NotFound => Error::InvalidUsersFile(format!(
+            "{path} is missing, e.g. copy api_users.example.json to it and set the passwords"
+        )),
+        _ => Error::InvalidUsersFile(format!("{path}: {err}")),
+    })?;
+    let mut file_contents: Vec<u8> = vec![];
+    file.read_to_end(&mut file_contents)
+        .await
+        .map_err(|err| Error::InvalidUsersFile(format!("{path}: {err}")))?;
+    serde_json::from_slice(&file_contents)
+        .map_err(|err| Error::InvalidUsersFile(format!("{path}: {err}")))
+}
+
+struct InMemoryUser {
+    password: String,
+    role: Role,
+    disabled: bool,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    /// Whether the user was added or changed via the routes, its state then taking precedence over the users file.
+    managed: bool,
+}
+
+/// The users of the users file, merged with the loaded ones: the ones added or changed via the routes are kept as they
+/// are, and the ones deleted via the routes stay deleted, while the others are the ones of the file, keeping the
+/// creation time of the ones loaded already.
+fn to_in_memory_users(
+    existing_data: HashMap<String, SeedUser>,
+    loaded: HashMap<String, InMemoryUser>,
+    deleted: &HashSet<String>,
+) -> HashMap<String, InMemoryUser> {
+    let now: DateTime<Utc> = Utc::now();
+    let (mut users, loaded): (HashMap<String, InMemoryUser>, HashMap<String, InMemoryUser>) =
+        loaded
+            .into_iter()
+            .partition(|(_, loaded_user)| loaded_user.managed);
+    for (username, user) in existing_data.into_iter() {
+        if users.contains_key(&username) || deleted.contains(&username) {
+            continue;
+        }
+        let created_at: DateTime<Utc> = loaded
+            .get(&username)
+            .map(|loaded_user: &InMemoryUser| loaded_user.created_at)
+            .unwrap_or(now);
+        let in_memory_user: InMemoryUser = InMemoryUser {
+            password: user.password,
+            role: user.role,
+            disabled: false,
+            created_at,
+            updated_at: now,
+            managed: false,
+        };
+        users.insert(username, in_memory_user);
+    }
+    users
+}
+
+/// The modification time of a file, if it can be read.
+async fn modified(path: &str) -> Option<SystemTime> {
+    tokio::fs::metadata(path)
+        .await
+        .and_then(|metadata: Metadata| metadata.modified())
+        .ok()
+}
+
+impl InMemoryUser {
+    fn to_api_user(&self, username: &str) -> ApiUser {
+        ApiUser {
+            username: username.to_string(),
+            role: self.role,
+            disabled: self.disabled,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        }
+    }
 }
 
-struct AuthInMemoryMiddleware {
-    data: Arc<RwLock<HashMap<String, String>>>,
+/// Keeps the API users in memory, as seeded by the users file, and the API keys, by their hashes,
+/// so the changes to them are lost on restart. The users of the file are replaced by its new ones whenever it's
+/// reloaded, the changes made via the routes taking precedence.
+pub struct AuthInMemoryMiddleware {
+    data: Arc<RwLock<HashMap<String, InMemoryUser>>>,
+    /// The usernames deleted via the routes, not to be brought back by a reload of the users file.
+    deleted: Arc<RwLock<HashSet<String>>>,
+    api_keys: Arc<RwLock<HashMap<String, ApiKey>>>,
+    file_path: Option<String>,
 }
 
 impl AuthInMemoryMiddleware {
-    async fn new() -> Self {
-        let api_users_file_path: String =
-            env::var(API_USERS_FILE_KEY).unwrap_or(DEFAULT_FILE.to_string());
-        Self::new_with_file(api_users_file_path).await
+    /// Loads the users of the users file. Fails with `Error::InvalidUsersFile` if it cannot be read,
+    /// e.g. as it's malformed.
+    pub async fn new() -> Result<Self, Error> {
+        Self::new_with_file(api_users_file_path()).await
     }
 
-    async fn new_with_file(path: String) -> Self {
-        let mut file: File = File::open(path).await.unwrap();
-        let mut file_contents: Vec<u8> = vec![];
-        file.read_to_end(&mut file_contents).await.unwrap();
-        let existing_data: HashMap<String, String> =
-            serde_json::from_slice(&file_contents).unwrap();
-        Self::new_with_data(existing_data).await
+    async fn new_with_file(path: String) -> Result<Self, Error> {
+        let existing_data: HashMap<String, SeedUser> = read_api_users_file(&path).await?;
+        let mut auth_middleware: AuthInMemoryMiddleware = Self::new_with_data(existing_data).await;
+        auth_middleware.file_path = Some(path);
+        Ok(auth_middleware)
     }
 
-    async fn new_with_data(existing_data: HashMap<String, String>) -> Self {
-        let new_data: Arc<RwLock<HashMap<String, String>>> = Arc::new(RwLock::new(HashMap::new()));
-        for (username, password) in existing_data.into_iter() {
-            new_data.write().await.insert(username, password);
+    async fn new_with_data(existing_data: HashMap<String, SeedUser>) -> Self {
+        AuthInMemoryMiddleware {
+            data: Arc::new(RwLock::new(to_in_memory_users(
+                existing_data,
+                HashMap::new(),
+                &HashSet::new(),
+            ))),
+            deleted: Arc::new(RwLock::new(HashSet::new())),
+            api_keys: Arc::new(RwLock::new(HashMap::new())),
+            file_path: None,
         }
-        AuthInMemoryMiddleware { data: new_data }
+    }
+
+    /// Replaces the users of the users file by its new ones, all at once, keeping the changes made via the routes.
+    /// If the file cannot be read, e.g. as it's malformed, it's rejected, the users being left as they are.
+    pub async fn reload(&self) -> Result<usize, Error> {
+        let path: &str = self
+            .file_path
+            .as_deref()
+            .ok_or(Error::InvalidUsersFile("no users file".to_string()))?;
+        let existing_data: HashMap<String, SeedUser> = read_api_users_file(path).await?;
+        // Locked in the same order as when adding a user
+        let mut data = self.data.write().await;
+        let deleted = self.deleted.read().await;
+        *data = to_in_memory_users(existing_data, std::mem::take(&mut *data), &deleted);
+        Ok(data.len())
+    }
+
+    /// Reloads the users file whenever it's modified, as polled every `API_USERS_FILE_POLL_INTERVAL` seconds,
+    /// or on SIGHUP. A failed reload is logged, the users being left as they are.
+    pub fn watch_file(self: Arc<Self>) {
+        let path: String = match self.file_path.clone() {
+            Some(path) => path,
+            None => return,
+        };
+        let poll_interval: u64 = env::var(API_USERS_FILE_POLL_INTERVAL_KEY)
+            .map(|x: String| {
+                x.parse::<u64>()
+                    .ok()
+                    .filter(|x: &u64| *x > 0)
+                    .unwrap_or_else(|| {
+                        panic!("Cannot parse {API_USERS_FILE_POLL_INTERVAL_KEY}: {x}")
+                    })
+            })
+            .unwrap_or(DEFAULT_POLL_INTERVAL);
+        let mut sighup: Signal = signal(SignalKind::hangup()).expect("Cannot listen for SIGHUP");
+        tokio::spawn(async move {
+            let mut interval: Interval = tokio::time::interval(Duration::from_secs(poll_interval));
+            let mut last_modified: Option<SystemTime> = modified(&path).await;
+            loop {
+                tokio::select! {
+                    _ = sighup.recv() => {
+                        info!("SIGHUP received, reloading the API users from {path}");
+                    }
+                    _ = interval.tick() => {
+                        let modified: Option<SystemTime> = modified(&path).await;
+                        if modified == last_modified {
+                            continue;
+                        }
+                        last_modified = modified;
+                    }
+                }
+                match self.reload().await {
+                    Ok(count) => info!("Reloaded {count} API users from {path}"),
+                    Err(err) => {
+                        error!("Cannot reload the API users, keeping the loaded ones: {err}")
+                    }
+                }
+            }
+        });
     }
 
     fn get_credentials(input: String) -> Result<(String, String), Error> {
@@ -62,32 +316,190 @@ impl AuthInMemoryMiddleware {
 #[async_trait]
 impl AuthMiddleware for AuthInMemoryMiddleware {
     async fn http_basic_auth(&self, auth_header: String) -> Result<bool, Error> {
-        if let Some((auth_type, encoded_credentials)) = auth_header.split_once(' ') {
-            if encoded_credentials.contains(' ') {
-                Err(Error::InvalidAuthHeader)
-            } else if auth_type.to_lowercase() != "basic" {
-                Err(Error::InvalidScheme(auth_type.to_string()))
-            } else {
-                let (username, password): (String, String) =
-                    AuthInMemoryMiddleware::get_credentials(encoded_credentials.to_string())?;
-                Ok(self.data.read().await.get(&username) == Some(&password))
-            }
+        let (username, password): (String, String) = parse_auth_header(auth_header)?;
+        Ok(self
+            .data
+            .read()
+            .await
+            .get(&username)
+            .map(|user: &InMemoryUser| !user.disabled && user.password == password)
+            .unwrap_or(false))
+    }
+
+    async fn has_user(&self, username: &str) -> Result<bool, Error> {
+        Ok(self.role(username).await?.is_some())
+    }
+
+    async fn role(&self, username: &str) -> Result<Option<Role>, Error> {
+        Ok(self
+            .data
+            .read()
+            .await
+            .get(username)
+            .filter(|user: &&InMemoryUser| !user.disabled)
+            .map(|user: &InMemoryUser| user.role))
+    }
+
+    async fn authenticate_api_key(&self, api_key: &str) -> Result<ApiKey, Error> {
+        let now: DateTime<Utc> = Utc::now();
+        let mut api_keys = self.api_keys.write().await;
+        let api_key: &mut ApiKey = api_keys
+            .get_mut(&hash_key(api_key))
+            .filter(|api_key: &&mut ApiKey| !api_key.is_expired(now))
+            .ok_or(Error::Unauthorized)?;
+        api_key.last_used_at = Some(now);
+        Ok(api_key.clone())
+    }
+}
+
+#[async_trait]
+impl ApiKeysRepository for AuthInMemoryMiddleware {
+    async fn get_api_keys(&self) -> Result<Vec<ApiKey>, Error> {
+        let mut api_keys: Vec<ApiKey> = self.api_keys.read().await.values().cloned().collect();
+        api_keys.sort_by_key(|api_key: &ApiKey| api_key.id.0);
+        Ok(api_keys)
+    }
+
+    async fn add_api_key(
+        &self,
+        new_api_key: NewApiKey,
+        key_hash: String,
+        created_by: &str,
+    ) -> Result<ApiKey, Error> {
+        let mut api_keys = self.api_keys.write().await;
+        let id: i32 = api_keys
+            .values()
+            .map(|api_key: &ApiKey| api_key.id.0)
+            .max()
+            .unwrap_or(0)
+            + 1;
+        let api_key: ApiKey = ApiKey {
+            id: ApiKeyId(id),
+            name: new_api_key.name,
+            scopes: new_api_key.scopes,
+            created_by: created_by.to_string(),
+            created_at: Utc::now(),
+            expires_at: new_api_key.expires_at,
+            last_used_at: None,
+        };
+        api_keys.insert(key_hash, api_key.clone());
+        Ok(api_key)
+    }
+
+    async fn delete_api_key(&self, id: ApiKeyId) -> Result<(), Error> {
+        let mut api_keys = self.api_keys.write().await;
+        let len: usize = api_keys.len();
+        api_keys.retain(|_, api_key: &mut ApiKey| api_key.id != id);
+        if api_keys.len() < len {
+            Ok(())
         } else {
+            Err(Error::NotFound { id: id.0 })
+        }
+    }
+}
+
+#[async_trait]
+impl ApiUsersRepository for AuthInMemoryMiddleware {
+    async fn get_api_users(&self) -> Result<Vec<ApiUser>, Error> {
+        let mut api_users: Vec<ApiUser> = self
+            .data
+            .read()
+            .await
+            .iter()
+            .map(|(username, user)| user.to_api_user(username))
+            .collect();
+        api_users.sort_by(|a: &ApiUser, b: &ApiUser| a.username.cmp(&b.username));
+        Ok(api_users)
+    }
+
+    async fn add_api_user(&self, new_api_user: NewApiUser) -> Result<ApiUser, Error> {
+        let mut data = self.data.write().await;
+        if data.contains_key(&new_api_user.username) {
+            return Err(Error::Conflict(format!(
+                "The username {} is taken",
+                new_api_user.username
+            )));
+        }
+        let now: DateTime<Utc> = Utc::now();
+        let user: InMemoryUser = InMemoryUser {
+            password: new_api_user.password,
+            role: new_api_user.role,
+            disabled: false,
+            created_at: now,
+            updated_at: now,
+            managed: true,
+        };
+        let api_user: ApiUser = user.to_api_user(&new_api_user.username);
+        self.deleted.write().await.remove(&new_api_user.username);
+        data.insert(new_api_user.username, user);
+        Ok(api_user)
+    }
+
+    async fn set_api_user_disabled(&self, username: &str, disabled: bool) -> Result<(), Error> {
+        let mut data = self.data.write().await;
+        let user: &mut InMemoryUser = data
+            .get_mut(username)
+            .ok_or(Error::UnknownUser(username.to_string()))?;
+        user.disabled = disabled;
+        user.updated_at = Utc::now();
+        user.managed = true;
+        Ok(())
+    }
+
+    async fn set_api_user_password(&self, username: &str, password: &str) -> Result<(), Error> {
+        let mut data = self.data.write().await;
+        let user: &mut InMemoryUser = data
+            .get_mut(username)
+            .ok_or(Error::UnknownUser(username.to_string()))?;
+        user.password = password.to_string();
+        user.updated_at = Utc::now();
+        user.managed = true;
+        Ok(())
+    }
+
+    async fn delete_api_user(&self, username: &str) -> Result<(), Error> {
+        self.data
+            .write()
+            .await
+            .remove(username)
+            .ok_or(Error::UnknownUser(username.to_string()))?;
+        self.deleted.write().await.insert(username.to_string());
+        Ok(())
+    }
+}
+
+/// Extracts the username and the password out of an HTTP Basic Auth header.
+pub fn parse_auth_header(auth_header: String) -> Result<(String, String), Error> {
+    if let Some((auth_type, encoded_credentials)) = auth_header.split_once(' ') {
+        if encoded_credentials.contains(' ') {
             Err(Error::InvalidAuthHeader)
+        } else if auth_type.to_lowercase() != "basic" {
+            Err(Error::InvalidScheme(auth_type.to_string()))
+        } else {
+            AuthInMemoryMiddleware::get_credentials(encoded_credentials.to_string())
         }
+    } else {
+        Err(Error::InvalidAuthHeader)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::api_key::ApiKeyScope;
 
     #[tokio::test]
     async fn test_http_basic_auth() {
         let key: String = "api_username".to_string();
         let value: String = "api_password".to_string();
-        let mut existing_data: HashMap<String, String> = HashMap::new();
-        existing_data.insert(key.clone(), value.clone());
+        let mut existing_data: HashMap<String, SeedUser> = HashMap::new();
+        existing_data.insert(
+            key.clone(),
+            SeedUser {
+                password: value.clone(),
+                role: Role::Reader,
+            },
+        );
         let auth_middleware: AuthInMemoryMiddleware =
             AuthInMemoryMiddleware::new_with_data(existing_data).await;
 
@@ -100,4 +512,187 @@ mod tests {
         assert!(actual_result.is_ok());
         assert_eq!(Some(true), actual_result.ok());
     }
+
+    #[tokio::test]
+    async fn test_roles_from_users_file() {
+        let existing_data: HashMap<String, SeedUser> = serde_json::from_str(
+            r#"{"reader": "password", "editor": {"password": "password", "role": "editor"}}"#,
+        )
+        .unwrap();
+        let auth_middleware: AuthInMemoryMiddleware =
+            AuthInMemoryMiddleware::new_with_data(existing_data).await;
+
+        assert_eq!(
+            Some(Role::Reader),
+            auth_middleware.role("reader").await.unwrap()
+        );
+        assert_eq!(
+            Some(Role::Editor),
+            auth_middleware.role("editor").await.unwrap()
+        );
+        assert_eq!(None, auth_middleware.role("admin").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_disabled_user() {
+        let mut existing_data: HashMap<String, SeedUser> = HashMap::new();
+        existing_data.insert(
+            "api_username".to_string(),
+            SeedUser {
+                password: "api_password".to_string(),
+                role: Role::Editor,
+            },
+        );
+        let auth_middleware: AuthInMemoryMiddleware =
+            AuthInMemoryMiddleware::new_with_data(existing_data).await;
+        let encoded_credentials: String =
+            base64Engine::STANDARD.encode("api_username:api_password");
+        let header_value: String = format!("Basic {encoded_credentials}");
+
+        auth_middleware
+            .set_api_user_disabled("api_username", true)
+            .await
+            .unwrap();
+        assert_eq!(
+            Some(false),
+            auth_middleware
+                .http_basic_auth(header_value.clone())
+                .await
+                .ok()
+        );
+        assert_eq!(None, auth_middleware.role("api_username").await.unwrap());
+
+        auth_middleware
+            .set_api_user_disabled("api_username", false)
+            .await
+            .unwrap();
+        assert_eq!(
+            Some(true),
+            auth_middleware.http_basic_auth(header_value).await.ok()
+        );
+        assert!(auth_middleware
+            .delete_api_user("other_username")
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_api_key() {
+        let auth_middleware: AuthInMemoryMiddleware =
+            AuthInMemoryMiddleware::new_with_data(HashMap::new()).await;
+        let key: String = "cak_key".to_string();
+        let new_api_key: NewApiKey = NewApiKey {
+            name: "billing-sync".to_string(),
+            scopes: vec![ApiKeyScope::ContactsRead],
+            expires_at: None,
+        };
+        let api_key: ApiKey = auth_middleware
+            .add_api_key(new_api_key, hash_key(&key), "admin")
+            .await
+            .unwrap();
+        assert_eq!(None, api_key.last_used_at);
+
+        let authenticated: ApiKey = auth_middleware.authenticate_api_key(&key).await.unwrap();
+        assert_eq!(api_key.id, authenticated.id);
+        assert!(authenticated.last_used_at.is_some());
+        assert!(auth_middleware
+            .authenticate_api_key("cak_other_key")
+            .await
+            .is_err());
+
+        auth_middleware.delete_api_key(api_key.id).await.unwrap();
+        assert!(auth_middleware.authenticate_api_key(&key).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_reload() {
+        let path: String = env::temp_dir()
+            .join(format!("api_users_{}.json", std::process::id()))
+            .to_string_lossy()
+            .to_string();
+        tokio::fs::write(&path, r#"{"reader": "password"}"#)
+            .await
+            .unwrap();
+        let auth_middleware: AuthInMemoryMiddleware =
+            AuthInMemoryMiddleware::new_with_file(path.clone())
+                .await
+                .unwrap();
+        assert_eq!(
+            Some(Role::Reader),
+            auth_middleware.role("reader").await.unwrap()
+        );
+
+        tokio::fs::write(
+            &path,
+            r#"{"editor": {"password": "password", "role": "editor"}}"#,
+        )
+        .await
+        .unwrap();
+        assert_eq!(1, auth_middleware.reload().await.unwrap());
+        assert_eq!(None, auth_middleware.role("reader").await.unwrap());
+        assert_eq!(
+            Some(Role::Editor),
+            auth_middleware.role("editor").await.unwrap()
+        );
+
+        // A malformed file leaves the loaded users in place
+        tokio::fs::write(&path, r#"{"editor": "#).await.unwrap();
+        assert!(auth_middleware.reload().await.is_err());
+        assert_eq!(
+            Some(Role::Editor),
+            auth_middleware.role("editor").await.unwrap()
+        );
+
+        tokio::fs::remove_file(&path).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reload_keeps_the_changes_made_via_the_routes() {
+        let path: String = env::temp_dir()
+            .join(format!("api_users_managed_{}.json", std::process::id()))
+            .to_string_lossy()
+            .to_string();
+        let users_file: &str = r#"{"reader": "password", "editor": {"password": "password", "role": "editor"}, "other": "password"}"#;
+        tokio::fs::write(&path, users_file).await.unwrap();
+        let auth_middleware: AuthInMemoryMiddleware =
+            AuthInMemoryMiddleware::new_with_file(path.clone())
+                .await
+                .unwrap();
+
+        auth_middleware
+            .set_api_user_disabled("reader", true)
+            .await
+            .unwrap();
+        auth_middleware.delete_api_user("other").await.unwrap();
+        auth_middleware
+            .add_api_user(NewApiUser {
+                username: "admin".to_string(),
+                password: "password".to_string(),
+                role: Role::Admin,
+            })
+            .await
+            .unwrap();
+
+        tokio::fs::write(&path, users_file).await.unwrap();
+        assert_eq!(3, auth_middleware.reload().await.unwrap());
+        assert_eq!(None, auth_middleware.role("reader").await.unwrap());
+        assert!(!auth_middleware.has_user("other").await.unwrap());
+        assert_eq!(
+            Some(Role::Admin),
+            auth_middleware.role("admin").await.unwrap()
+        );
+        assert_eq!(
+            Some(Role::Editor),
+            auth_middleware.role("editor").await.unwrap()
+        );
+
+        // A malformed file is rejected on startup too
+        tokio::fs::write(&path, r#"{"editor": "#).await.unwrap();
+        assert!(matches!(
+            AuthInMemoryMiddleware::new_with_file(path.clone()).await,
+            Err(Error::InvalidUsersFile(_))
+        ));
+
+        tokio::fs::remove_file(&path).await.unwrap();
+    }
 }
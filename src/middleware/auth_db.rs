@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+
+use argon2::password_hash::SaltString;
+use argon2::Argon2;
+use argon2::PasswordHash;
+use argon2::PasswordHasher;
+use argon2::PasswordVerifier;
+use async_trait::async_trait;
+use rand_core::OsRng;
+use sqlx::postgres::PgRow;
+use sqlx::Pool;
+use sqlx::Postgres;
+use sqlx::Row;
+
+use crate::middleware::auth::parse_auth_header;
+use crate::middleware::auth::AuthMiddleware;
+use crate::middleware::auth::SeedUser;
+use crate::models::api_key::hash_key;
+use crate::models::api_key::ApiKey;
+use crate::models::api_key::ApiKeyId;
+use crate::models::api_key::ApiKeyScope;
+use crate::models::api_key::NewApiKey;
+use crate::models::api_user::ApiUser;
+use crate::models::api_user::NewApiUser;
+use crate::models::errors::Error;
+use crate::models::role::Role;
+use crate::repositories::api_keys_repository::ApiKeysRepository;
+use crate::repositories::api_users_repository::ApiUsersRepository;
+
+const SQL_SELECT_ALL: &str =
+    "SELECT username, role, disabled, created_at, updated_at FROM api_users ORDER BY username;";
+const SQL_SELECT_PASSWORD_HASH: &str =
+    "SELECT password_hash FROM api_users WHERE username = $1 AND NOT disabled;";
+const SQL_SELECT_ROLE: &str = "SELECT role FROM api_users WHERE username = $1 AND NOT disabled;";
+const SQL_INSERT: &str = "INSERT INTO api_users(username, password_hash, role) VALUES ($1, $2, $3) RETURNING username, role, disabled, created_at, updated_at;";
+const SQL_INSERT_SEED: &str = "INSERT INTO api_users(username, password_hash, role) VALUES ($1, $2, $3) ON CONFLICT (username) DO NOTHING;";
+const SQL_UPDATE_DISABLED: &str =
+    "UPDATE api_users SET disabled = $1, updated_at = now() WHERE username = $2;";
+const SQL_UPDATE_PASSWORD_HASH: &str =
+    "UPDATE api_users SET password_hash = $1, updated_at = now() WHERE username = $2;";
+const SQL_DELETE: &str = "DELETE FROM api_users WHERE username = $1;";
+const SQL_SELECT_API_KEYS: &str = "SELECT id, name, scopes, created_by, created_at, expires_at, last_used_at FROM api_keys ORDER BY id;";
+const SQL_INSERT_API_KEY: &str = "INSERT INTO api_keys(name, key_hash, scopes, created_by, expires_at) VALUES ($1, $2, $3, $4, $5) RETURNING id, name, scopes, created_by, created_at, expires_at, last_used_at;";
+const SQL_USE_API_KEY: &str = "UPDATE api_keys SET last_used_at = now() WHERE key_hash = $1 AND (expires_at IS NULL OR expires_at > now()) RETURNING id, name, scopes, created_by, created_at, expires_at, last_used_at;";
+const SQL_DELETE_API_KEY: &str = "DELETE FROM api_keys WHERE id = $1;";
+
+/// Keeps the API users in the `api_users` table, with argon2 hashed passwords, and the API keys in the `api_keys` one.
+/// The users file only seeds the table: its users are added if missing, never overwritten.
+#[derive(Debug, Clone)]
+pub struct AuthDbMiddleware {
+    db_pool: Pool<Postgres>,
+}
+
+impl AuthDbMiddleware {
+    pub fn new(db_pool: Pool<Postgres>) -> Self {
+        AuthDbMiddleware { db_pool }
+    }
+
+    /// Adds the given users, unless users with the same usernames exist already.
+    pub async fn seed(&self, seed_users: HashMap<String, SeedUser>) -> Result<(), Error> {
+        for (username, user) in seed_users.into_iter() {
+            sqlx::query(SQL_INSERT_SEED)
+                .bind(username)
+                .bind(hash_password(user.password).await?)
+                .bind(user.role.as_str())
+                .execute(&self.db_pool)
+                .await?;
+        }
+        Ok(())
+    }
+
+    fn get_api_user_from_row(row: PgRow) -> Result<ApiUser, Error> {
+        Ok(ApiUser {
+            username: row.get("username"),
+            role: row.get::<String, &str>("role").parse()?,
+            disabled: row.get("disabled"),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+        })
+    }
+
+    fn get_api_key_from_row(row: PgRow) -> Result<ApiKey, Error> {
+        Ok(ApiKey {
+            id: ApiKeyId(row.get("id")),
+            name: row.get("name"),
+            scopes: row
+                .get::<Vec<String>, &str>("scopes")
+                .iter()
+                .map(|scope: &String| ApiKeyScope::parse(scope))
+                .collect::<Result<Vec<ApiKeyScope>, Error>>()?,
+            created_by: row.get("created_by"),
+            created_at: row.get("created_at"),
+            expires_at: row.get("expires_at"),
+            last_used_at: row.get("last_used_at"),
+        })
+    }
+
+    /// Tells an update of a single user which affected no rows, as there is no such user.
+    fn existing_user(username: &str, rows_affected: u64) -> Result<(), Error> {
+        if rows_affected == 0 {
+            Err(Error::UnknownUser(username.to_string()))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[async_trait]
+impl AuthMiddleware for AuthDbMiddleware {
+    async fn http_basic_auth(&self, auth_header: String) -> Result<bool, Error> {
+        let (username, password): (String, String) = parse_auth_header(auth_header)?;
+        let password_hash: Option<String> = sqlx::query(SQL_SELECT_PASSWORD_HASH)
+            .bind(username)
+            .map(|row: PgRow| row.get("password_hash"))
+            .fetch_optional(&self.db_pool)
+            .await?;
+        match password_hash {
+            Some(password_hash) => verify_password(password, password_hash).await,
+            None => Ok(false),
+        }
+    }
+
+    async fn has_user(&self, username: &str) -> Result<bool, Error> {
+        Ok(self.role(username).await?.is_some())
+    }
+
+    async fn role(&self, username: &str) -> Result<Option<Role>, Error> {
+        let role: Option<String> = sqlx::query(SQL_SELECT_ROLE)
+            .bind(username)
+            .map(|row: PgRow| row.get("role"))
+            .fetch_optional(&self.db_pool)
+            .await?;
+        role.map(|role: String| role.parse()).transpose()
+    }
+
+    async fn authenticate_api_key(&self, api_key: &str) -> Result<ApiKey, Error> {
+        sqlx::query(SQL_USE_API_KEY)
+            .bind(hash_key(api_key))
+            .fetch_optional(&self.db_pool)
+            .await?
+            .map(AuthDbMiddleware::get_api_key_from_row)
+            .unwrap_or(Err(Error::Unauthorized))
+    }
+}
+
+#[async_trait]
+impl ApiKeysRepository for AuthDbMiddleware {
+    async fn get_api_keys(&self) -> Result<Vec<ApiKey>, Error> {
+        sqlx::query(SQL_SELECT_API_KEYS)
+            .fetch_all(&self.db_pool)
+            .await?
+            .into_iter()
+            .map(AuthDbMiddleware::get_api_key_from_row)
+            .collect()
+    }
+
+    async fn add_api_key(
+        &self,
+        new_api_key: NewApiKey,
+        key_hash: String,
+        created_by: &str,
+    ) -> Result<ApiKey, Error> {
+        let scopes: Vec<&str> = new_api_key
+            .scopes
+            .iter()
+            .map(|scope: &ApiKeyScope| scope.as_str())
+            .collect();
+        let row: PgRow = sqlx::query(SQL_INSERT_API_KEY)
+            .bind(new_api_key.name)
+            .bind(key_hash)
+            .bind(scopes)
+            .bind(created_by)
+            .bind(new_api_key.expires_at)
+            .fetch_one(&self.db_pool)
+            .await?;
+        AuthDbMiddleware::get_api_key_from_row(row)
+    }
+
+    async fn delete_api_key(&self, id: ApiKeyId) -> Result<(), Error> {
+        let rows_affected: u64 = sqlx::query(SQL_DELETE_API_KEY)
+            .bind(id.0)
+            .execute(&self.db_pool)
+            .await?
+            .rows_affected();
+        if rows_affected == 0 {
+            Err(Error::NotFound { id: id.0 })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[async_trait]
+impl ApiUsersRepository for AuthDbMiddleware {
+    async fn get_api_users(&self) -> Result<Vec<ApiUser>, Error> {
+        sqlx::query(SQL_SELECT_ALL)
+            .fetch_all(&self.db_pool)
+            .await?
+            .into_iter()
+            .map(AuthDbMiddleware::get_api_user_from_row)
+            .collect()
+    }
+
+    async fn add_api_user(&self, new_api_user: NewApiUser) -> Result<ApiUser, Error> {
+        let password_hash: String = hash_password(new_api_user.password).await?;
+        let row: PgRow = sqlx::query(SQL_INSERT)
+            .bind(&new_api_user.username)
+            .bind(password_hash)
+            .bind(new_api_user.role.as_str())
+            .fetch_one(&self.db_pool)
+            .await
+            .map_err(|err: sqlx::Error| match Error::from(err) {
+                Error::Conflict(_) => {
+                    Error::Conflict(format!("The username {} is taken", new_api_user.username))
+                }
+                other => other,
+            })?;
+        AuthDbMiddleware::get_api_user_from_row(row)
+    }
+
+    async fn set_api_user_disabled(&self, username: &str, disabled: bool) -> Result<(), Error> {
+        let rows_affected: u64 = sqlx::query(SQL_UPDATE_DISABLED)
+            .bind(disabled)
+            .bind(username)
+            .execute(&self.db_pool)
+            .await?
+            .rows_affected();
+        AuthDbMiddleware::existing_user(username, rows_affected)
+    }
+
+    async fn set_api_user_password(&self, username: &str, password: &str) -> Result<(), Error> {
+        let rows_affected: u64 = sqlx::query(SQL_UPDATE_PASSWORD_HASH)
+            .bind(hash_password(password.to_string()).await?)
+            .bind(username)
+            .execute(&self.db_pool)
+            .await?
+            .rows_affected();
+        AuthDbMiddleware::existing_user(username, rows_affected)
+    }
+
+    async fn delete_api_user(&self, username: &str) -> Result<(), Error> {
+        let rows_affected: u64 = sqlx::query(SQL_DELETE)
+            .bind(username)
+            .execute(&self.db_pool)
+            .await?
+            .rows_affected();
+        AuthDbMiddleware::existing_user(username, rows_affected)
+    }
+}
+
+/// Hashes a password with argon2 and a random salt, off the async runtime as it is deliberately slow.
+async fn hash_password(password: String) -> Result<String, Error> {
+    tokio::task::spawn_blocking(move || {
+        let salt: SaltString = SaltString::generate(&mut OsRng);
+        Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash: PasswordHash| hash.to_string())
+            .map_err(|err| Error::Db(format!("Cannot hash the password: {err}")))
+    })
+    .await
+    .map_err(|err| Error::Db(err.to_string()))?
+}
+
+/// Checks a password against its argon2 hash, off the async runtime as it is deliberately slow.
+async fn verify_password(password: String, password_hash: String) -> Result<bool, Error> {
+    tokio::task::spawn_blocking(move || {
+        let password_hash: PasswordHash = PasswordHash::new(&password_hash)
+            .map_err(|err| Error::Db(format!("Invalid password hash: {err}")))?;
+        Ok(Argon2::default()
+            .verify_password(password.as_bytes(), &password_hash)
+            .is_ok())
+    })
+    .await
+    .map_err(|err| Error::Db(err.to_string()))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_hash_password() {
+        let password_hash: String = hash_password("api_password".to_string()).await.unwrap();
+        assert!(password_hash.starts_with("$argon2"));
+        assert!(
+            verify_password("api_password".to_string(), password_hash.clone())
+                .await
+                .unwrap()
+        );
+        assert!(
+            !verify_password("other_password".to_string(), password_hash)
+                .await
+                .unwrap()
+        );
+    }
+}
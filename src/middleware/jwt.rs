@@ -0,0 +1,440 @@
+use std::collections::HashMap;
+use std::env;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use jsonwebtoken::get_current_timestamp;
+use jsonwebtoken::Algorithm;
+use jsonwebtoken::DecodingKey;
+use jsonwebtoken::EncodingKey;
+use jsonwebtoken::Header;
+use jsonwebtoken::TokenData;
+use jsonwebtoken::Validation;
+use serde::{Deserialize, Serialize};
+
+use crate::middleware::auth::AuthMiddleware;
+use crate::middleware::oauth2::ResourceServer;
+use crate::models::api_key::ApiKey;
+use crate::models::errors::Error;
+use crate::models::role::Role;
+use crate::models::token::AccessToken;
+use crate::models::token::TokenResponse;
+use crate::models::token::BEARER_TOKEN_TYPE;
+
+const JWT_KEYS_FILE_KEY: &str = "JWT_KEYS_FILE";
+/// The shortest HS256 secret accepted, of 256 bits, as long as the hash.
+const MIN_HS256_SECRET_LENGTH: usize = 32;
+const ACCESS_TOKEN_TTL_KEY: &str = "ACCESS_TOKEN_TTL";
+const DEFAULT_ACCESS_TOKEN_TTL: u64 = 60 * 15;
+const REFRESH_TOKEN_TTL_KEY: &str = "REFRESH_TOKEN_TTL";
+const DEFAULT_REFRESH_TOKEN_TTL: u64 = 60 * 60 * 24 * 7;
+/// The `iss` claim of the issued tokens, only the ones issued by this API being accepted.
+const ISSUER: &str = "contacts-api";
+
+/// The keys file, e.g. `{"signing_kid": "2023-05", "keys": [{"kid": "2023-05", "algorithm": "HS256", "key_file": "secret.key"}]}`.
+/// Rotating the keys means adding a new one and signing with it, while keeping the old one until the tokens signed
+/// with it expire.
+#[derive(Deserialize, Debug)]
+struct KeysFile {
+    signing_kid: String,
+    keys: Vec<KeyEntry>,
+}
+
+#[derive(Deserialize, Debug)]
+struct KeyEntry {
+    kid: String,
+    algorithm: Algorithm,
+    /// The secret of a HS256 key, or the PEM encoded private key of a RS256 one.
+    /// Only the signing key needs the private key of a RS256 key pair.
+    key_file: Option<String>,
+    /// The PEM encoded public key of a RS256 key pair.
+    public_key_file: Option<String>,
+}
+
+/// A key verifying the tokens carrying its `kid` in the header, and signing the new ones if it's the signing key.
+pub struct JwtKey {
+    pub kid: String,
+    pub algorithm: Algorithm,
+    pub encoding_key: Option<EncodingKey>,
+    pub decoding_key: DecodingKey,
+}
+
+impl JwtKey {
+    /// A HS256 key, both signing and verifying the tokens with the same secret.
+    pub fn hs256(kid: &str, secret: &[u8]) -> Self {
+        JwtKey {
+            kid: kid.to_string(),
+            algorithm: Algorithm::HS256,
+            encoding_key: Some(EncodingKey::from_secret(secret)),
+            decoding_key: DecodingKey::from_secret(secret),
+        }
+    }
+
+    async fn from_entry(entry: KeyEntry) -> Result<Self, Error> {
+        match entry.algorithm {
+            Algorithm::HS256 => {
+                let secret: Vec<u8> = read_key_file(&entry.kid, entry.key_file).await?;
+                if secret.trim_ascii().len() < MIN_HS256_SECRET_LENGTH {
+                    return Err(Error::InvalidJwtKey(format!(
+                        "{}: the secret is shorter than {MIN_HS256_SECRET_LENGTH} bytes",
+                        entry.kid
+                    )));
+                }
+                Ok(JwtKey::hs256(&entry.kid, &secret))
+            }
+            Algorithm::RS256 => {
+                let encoding_key: Option<EncodingKey> = match entry.key_file {
+                    Some(path) => Some(
+                        EncodingKey::from_rsa_pem(&read_key_file(&entry.kid, Some(path)).await?)
+                            .map_err(|err| invalid_key(&entry.kid, err))?,
+                    ),
+                    None => None,
+                };
+                let public_key: Vec<u8> = read_key_file(&entry.kid, entry.public_key_file).await?;
+                let decoding_key: DecodingKey = DecodingKey::from_rsa_pem(&public_key)
+                    .map_err(|err| invalid_key(&entry.kid, err))?;
+                Ok(JwtKey {
+                    kid: entry.kid,
+                    algorithm: Algorithm::RS256,
+                    encoding_key,
+                    decoding_key,
+                })
+            }
+            other => Err(Error::InvalidJwtKey(format!(
+                "{}: unsupported algorithm {:?}, expected HS256 or RS256",
+                entry.kid, other
+            ))),
+        }
+    }
+}
+
+/// Tells the access tokens from the refresh tokens, so that neither can be used as the other.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenUse {
+    Access,
+    Refresh,
+}
+
+impl TokenUse {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenUse::Access => "access",
+            TokenUse::Refresh => "refresh",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+struct Claims {
+    sub: String,
+    iss: String,
+    iat: u64,
+    exp: u64,
+    token_use: TokenUse,
+}
+
+/// Issues and verifies the signed JWTs of the API users.
+pub struct TokenService {
+    signing_kid: String,
+    keys: HashMap<String, JwtKey>,
+    access_token_ttl: u64,
+    refresh_token_ttl: u64,
+}
+
+impl TokenService {
+    pub async fn new() -> Result<Self, Error> {
+        // No keys are shipped, so that no deployment signs with a secret known to anyone having the sources
+        let keys_file_path: String = env::var(JWT_KEYS_FILE_KEY).map_err(|_| {
+            Error::InvalidJwtKey(format!(
+                "{JWT_KEYS_FILE_KEY} is missing, e.g. copy jwt_keys.example.json to jwt_keys.json, \
+                 generate its key and set {JWT_KEYS_FILE_KEY}=jwt_keys.json"
+            ))
+        })?;
+        let access_token_ttl: u64 = ttl_from_env(ACCESS_TOKEN_TTL_KEY, DEFAULT_ACCESS_TOKEN_TTL);
+        let refresh_token_ttl: u64 = ttl_from_env(REFRESH_TOKEN_TTL_KEY, DEFAULT_REFRESH_TOKEN_TTL);
+        Self::new_with_file(&keys_file_path, access_token_ttl, refresh_token_ttl).await
+    }
+
+    async fn new_with_file(
+        path: &str,
+        access_token_ttl: u64,
+        refresh_token_ttl: u64,
+    ) -> Result<Self, Error> {
+        let file_contents: Vec<u8> = tokio::fs::read(path)
+            .await
+            .map_err(|err| Error::InvalidJwtKey(format!("{path}: {err}")))?;
+        let keys_file: KeysFile = serde_json::from_slice(&file_contents)
+            .map_err(|err| Error::InvalidJwtKey(format!("{path}: {err}")))?;
+        let mut keys: Vec<JwtKey> = vec![];
+        for entry in keys_file.keys.into_iter() {
+            keys.push(JwtKey::from_entry(entry).await?);
+        }
+        Self::new_with_keys(
+            keys,
+            &keys_file.signing_kid,
+            access_token_ttl,
+            refresh_token_ttl,
+        )
+    }
+
+    pub fn new_with_keys(
+        keys: Vec<JwtKey>,
+        signing_kid: &str,
+        access_token_ttl: u64,
+        refresh_token_ttl: u64,
+    ) -> Result<Self, Error> {
+        let keys: HashMap<String, JwtKey> = keys
+            .into_iter()
+            .map(|key: JwtKey| (key.kid.clone(), key))
+            .collect();
+        match keys.get(signing_kid) {
+            Some(key) if key.encoding_key.is_some() => Ok(TokenService {
+                signing_kid: signing_kid.to_string(),
+                keys,
+                access_token_ttl,
+                refresh_token_ttl,
+            }),
+            Some(_) => Err(Error::InvalidJwtKey(format!(
+                "{signing_kid}: the signing key has no private key"
+            ))),
+            None => Err(Error::InvalidJwtKey(format!(
+                "{signing_kid}: the signing key is not among the keys"
+            ))),
+        }
+    }
+
+    /// Issues a new access token and refresh token to the given user.
+    pub fn issue(&self, username: &str) -> Result<TokenResponse, Error> {
+        Ok(TokenResponse {
+            access_token: self.sign(username, TokenUse::Access, self.access_token_ttl)?,
+            token_type: BEARER_TOKEN_TYPE.to_string(),
+            expires_in: self.access_token_ttl,
+            refresh_token: self.sign(username, TokenUse::Refresh, self.refresh_token_ttl)?,
+        })
+    }
+
+    /// Returns the username of the user a token was issued to,
+    /// if it was signed by one of the keys, hasn't expired and is meant for the given use.
+    pub fn verify(&self, token: &str, token_use: TokenUse) -> Result<String, Error> {
+        let header: Header = jsonwebtoken::decode_header(token)?;
+        let kid: String = header
+            .kid
+            .ok_or(Error::InvalidToken("Missing kid header".to_string()))?;
+        let key: &JwtKey = self
+            .keys
+            .get(&kid)
+            .ok_or(Error::InvalidToken(format!("Unknown kid {kid}")))?;
+        let mut validation: Validation = Validation::new(key.algorithm);
+        validation.set_issuer(&[ISSUER]);
+        let token_data: TokenData<Claims> =
+            jsonwebtoken::decode(token, &key.decoding_key, &validation)?;
+        if token_data.claims.token_use == token_use {
+            Ok(token_data.claims.sub)
+        } else {
+            Err(Error::InvalidToken(format!(
+                "The token_use claim is not {}",
+                token_use.as_str()
+            )))
+        }
+    }
+
+    /// Whether a token was signed by one of the keys, as its `kid` tells, rather than by another issuer.
+    pub fn is_own_token(&self, token: &str) -> bool {
+        jsonwebtoken::decode_header(token)
+            .ok()
+            .and_then(|header: Header| header.kid)
+            .map(|kid: String| self.keys.contains_key(&kid))
+            .unwrap_or(false)
+    }
+
+    fn sign(&self, username: &str, token_use: TokenUse, ttl: u64) -> Result<String, Error> {
+        let key: &JwtKey = &self.keys[&self.signing_kid];
+        let mut header: Header = Header::new(key.algorithm);
+        header.kid = Some(key.kid.clone());
+        let now: u64 = get_current_timestamp();
+        let claims: Claims = Claims {
+            sub: username.to_string(),
+            iss: ISSUER.to_string(),
+            iat: now,
+            exp: now + ttl,
+            token_use,
+        };
+        let encoding_key: &EncodingKey = key
+            .encoding_key
+            .as_ref()
+            .ok_or(Error::InvalidJwtKey(format!("{}: no private key", key.kid)))?;
+        jsonwebtoken::encode(&header, &claims, encoding_key)
+            .map_err(|err| invalid_key(&key.kid, err))
+    }
+}
+
+/// Authenticates the users by the access tokens issued to them (`Authorization: Bearer <token>`),
+/// falling back to the wrapped middleware for the other schemes.
+/// With a resource server, the bearer tokens not issued by the API are validated by it.
+pub struct BearerAuthMiddleware {
+    inner: Arc<dyn AuthMiddleware + Send + Sync>,
+    token_service: Arc<TokenService>,
+    resource_server: Option<Arc<ResourceServer>>,
+}
+
+impl BearerAuthMiddleware {
+    pub fn new(
+        inner: Arc<dyn AuthMiddleware + Send + Sync>,
+        token_service: Arc<TokenService>,
+        resource_server: Option<Arc<ResourceServer>>,
+    ) -> Self {
+        BearerAuthMiddleware {
+            inner,
+            token_service,
+            resource_server,
+        }
+    }
+}
+
+#[async_trait]
+impl AuthMiddleware for BearerAuthMiddleware {
+    async fn http_basic_auth(&self, auth_header: String) -> Result<bool, Error> {
+        self.inner.http_basic_auth(auth_header).await
+    }
+
+    async fn has_user(&self, username: &str) -> Result<bool, Error> {
+        self.inner.has_user(username).await
+    }
+
+    async fn role(&self, username: &str) -> Result<Option<Role>, Error> {
+        self.inner.role(username).await
+    }
+
+    async fn authenticate_api_key(&self, api_key: &str) -> Result<ApiKey, Error> {
+        self.inner.authenticate_api_key(api_key).await
+    }
+
+    async fn authenticate_access_token(
+        &self,
+        auth_header: &str,
+    ) -> Result<Option<AccessToken>, Error> {
+        match (parse_bearer_token(auth_header), &self.resource_server) {
+            (Some(token), Some(resource_server)) if !self.token_service.is_own_token(token) => {
+                resource_server.validate(token).await.map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    async fn authenticate(
+        &self,
+        auth_header: String,
+        client_ip: Option<IpAddr>,
+    ) -> Result<String, Error> {
+        match parse_bearer_token(&auth_header) {
+            Some(token) => {
+                let username: String = self.token_service.verify(token, TokenUse::Access)?;
+                if self.inner.has_user(&username).await? {
+                    Ok(username)
+                } else {
+                    Err(Error::InvalidToken(format!("Unknown user {username}")))
+                }
+            }
+            None => self.inner.authenticate_basic(auth_header, client_ip).await,
+        }
+    }
+}
+
+/// Extracts the token out of an HTTP Bearer Auth header, if the header has the Bearer scheme.
+pub fn parse_bearer_token(auth_header: &str) -> Option<&str> {
+    auth_header
+        .split_once(' ')
+        .filter(|(auth_type, _)| auth_type.eq_ignore_ascii_case("bearer"))
+        .map(|(_, token)| token.trim())
+}
+
+async fn read_key_file(kid: &str, path: Option<String>) -> Result<Vec<u8>, Error> {
+    let path: String = path.ok_or(Error::InvalidJwtKey(format!("{kid}: missing key file")))?;
+    tokio::fs::read(&path)
+        .await
+        .map_err(|err| Error::InvalidJwtKey(format!("{kid}: {path}: {err}")))
+}
+
+fn invalid_key(kid: &str, err: jsonwebtoken::errors::Error) -> Error {
+    Error::InvalidJwtKey(format!("{kid}: {err}"))
+}
+
+pub(crate) fn ttl_from_env(key: &str, default: u64) -> u64 {
+    env::var(key)
+        .map(|x: String| {
+            x.parse::<u64>()
+                .unwrap_or_else(|_| panic!("Cannot parse {key}: {x}"))
+        })
+        .unwrap_or(default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_missing_or_weak_key() {
+        let entry = |key_file: &str| KeyEntry {
+            kid: "2023-05".to_string(),
+            algorithm: Algorithm::HS256,
+            key_file: Some(key_file.to_string()),
+            public_key_file: None,
+        };
+        let key_file: std::path::PathBuf = env::temp_dir().join("contacts-api-weak-jwt.key");
+        tokio::fs::write(&key_file, b"secret\n").await.unwrap();
+        assert!(matches!(
+            JwtKey::from_entry(entry(key_file.to_str().unwrap())).await,
+            Err(Error::InvalidJwtKey(_))
+        ));
+        tokio::fs::remove_file(&key_file).await.unwrap();
+        assert!(matches!(
+            JwtKey::from_entry(entry(key_file.to_str().unwrap())).await,
+            Err(Error::InvalidJwtKey(_))
+        ));
+    }
+
+    #[test]
+    fn test_token_rotation() {
+        let old_key: JwtKey = JwtKey::hs256("2023-04", b"old secret");
+        let old_service: TokenService =
+            TokenService::new_with_keys(vec![old_key], "2023-04", 60, 120).unwrap();
+        let old_tokens: TokenResponse = old_service.issue("api_username").unwrap();
+
+        // Signing with a new key, still accepting the tokens signed with the old one
+        let keys: Vec<JwtKey> = vec![
+            JwtKey::hs256("2023-04", b"old secret"),
+            JwtKey::hs256("2023-05", b"new secret"),
+        ];
+        let service: TokenService = TokenService::new_with_keys(keys, "2023-05", 60, 120).unwrap();
+        let new_tokens: TokenResponse = service.issue("api_username").unwrap();
+        assert_eq!(
+            "2023-05",
+            jsonwebtoken::decode_header(&new_tokens.access_token)
+                .unwrap()
+                .kid
+                .unwrap()
+        );
+        for token in [&old_tokens.access_token, &new_tokens.access_token] {
+            assert_eq!(
+                "api_username",
+                service.verify(token, TokenUse::Access).unwrap()
+            );
+        }
+        assert_eq!(
+            "api_username",
+            service
+                .verify(&old_tokens.refresh_token, TokenUse::Refresh)
+                .unwrap()
+        );
+
+        // A refresh token is not an access token, and the tokens of a dropped key are rejected
+        assert!(service
+            .verify(&new_tokens.refresh_token, TokenUse::Access)
+            .is_err());
+        assert!(old_service
+            .verify(&new_tokens.access_token, TokenUse::Access)
+            .is_err());
+    }
+}
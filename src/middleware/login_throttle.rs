@@ -0,0 +1,355 @@
+use std::collections::HashMap;
+use std::env;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use async_trait::async_trait;
+use log::warn;
+
+use crate::middleware::auth::parse_auth_header;
+use crate::middleware::auth::AuthMiddleware;
+use crate::models::api_key::ApiKey;
+use crate::models::errors::Error;
+use crate::models::role::Role;
+
+const MAX_FAILURES_PER_USER_KEY: &str = "LOGIN_MAX_FAILURES_PER_USER";
+const DEFAULT_MAX_FAILURES_PER_USER: u32 = 5;
+const MAX_FAILURES_PER_IP_KEY: &str = "LOGIN_MAX_FAILURES_PER_IP";
+const DEFAULT_MAX_FAILURES_PER_IP: u32 = 20;
+const LOCKOUT_KEY: &str = "LOGIN_LOCKOUT";
+const DEFAULT_LOCKOUT: u64 = 30;
+const MAX_LOCKOUT_KEY: &str = "LOGIN_MAX_LOCKOUT";
+const DEFAULT_MAX_LOCKOUT: u64 = 60 * 15;
+/// At most this many usernames and IPs are tracked. Past it, the ones not failing lately are forgotten,
+/// then the ones failing the longest ago, those locked out last.
+const MAX_TRACKED: usize = 10_000;
+
+/// How many failed logins are tolerated and how long the lockouts last, in seconds.
+#[derive(Debug, Clone, Copy)]
+pub struct ThrottleConfig {
+    pub max_failures_per_user: u32,
+    pub max_failures_per_ip: u32,
+    pub lockout: u64,
+    pub max_lockout: u64,
+}
+
+impl ThrottleConfig {
+    pub fn from_env() -> Self {
+        ThrottleConfig {
+            max_failures_per_user: from_env(
+                MAX_FAILURES_PER_USER_KEY,
+                DEFAULT_MAX_FAILURES_PER_USER,
+            ),
+            max_failures_per_ip: from_env(MAX_FAILURES_PER_IP_KEY, DEFAULT_MAX_FAILURES_PER_IP),
+            lockout: from_env(LOCKOUT_KEY, DEFAULT_LOCKOUT),
+            max_lockout: from_env(MAX_LOCKOUT_KEY, DEFAULT_MAX_LOCKOUT),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum ThrottleKey {
+    Username(String),
+    Ip(IpAddr),
+}
+
+#[derive(Debug)]
+struct Failures {
+    count: u32,
+    last_failure: Instant,
+    locked_until: Option<Instant>,
+}
+
+/// Tracks the failed logins per username and per client IP. Once either has failed too many times in a row,
+/// it's locked out, for twice as long after each further failure, up to `max_lockout`.
+/// A successful login clears the failures of the username, but not the ones of the IP.
+pub struct LoginThrottle {
+    config: ThrottleConfig,
+    failures: Mutex<HashMap<ThrottleKey, Failures>>,
+    max_tracked: usize,
+}
+
+impl LoginThrottle {
+    pub fn new(config: ThrottleConfig) -> Self {
+        LoginThrottle {
+            config,
+            failures: Mutex::new(HashMap::new()),
+            max_tracked: MAX_TRACKED,
+        }
+    }
+
+    /// Rejects with `Error::TooManyAttempts` if either the username or the IP is locked out.
+    pub fn check(&self, username: &str, client_ip: Option<IpAddr>) -> Result<(), Error> {
+        self.check_at(username, client_ip, Instant::now())
+    }
+
+    pub fn record_failure(&self, username: &str, client_ip: Option<IpAddr>) {
+        self.record_failure_at(username, client_ip, Instant::now())
+    }
+
+    pub fn record_success(&self, username: &str) {
+        self.failures
+            .lock()
+            .unwrap()
+            .remove(&ThrottleKey::Username(username.to_string()));
+    }
+
+    fn check_at(
+        &self,
+        username: &str,
+        client_ip: Option<IpAddr>,
+        now: Instant,
+    ) -> Result<(), Error> {
+        let failures = self.failures.lock().unwrap();
+        let retry_after: Option<Duration> = keys(username, client_ip)
+            .iter()
+            .filter_map(|key: &ThrottleKey| failures.get(key))
+            .filter_map(|failures: &Failures| failures.locked_until)
+            .filter(|locked_until: &Instant| *locked_until > now)
+            .map(|locked_until: Instant| locked_until - now)
+            .max();
+        match retry_after {
+            // Rounded up, so that retrying after as many seconds is never too early
+            Some(retry_after) => Err(Error::TooManyAttempts {
+                retry_after: retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn record_failure_at(&self, username: &str, client_ip: Option<IpAddr>, now: Instant) {
+        let mut failures = self.failures.lock().unwrap();
+        let forget_after: Duration = Duration::from_secs(self.config.max_lockout);
+        let keys: Vec<ThrottleKey> = keys(username, client_ip);
+        let untracked: usize = keys
+            .iter()
+            .filter(|key| !failures.contains_key(key))
+            .count();
+        if failures.len() + untracked > self.max_tracked {
+            failures
+                .retain(|_, failures: &mut Failures| now - failures.last_failure < forget_after);
+        }
+        if failures.len() + untracked > self.max_tracked {
+            let mut oldest: Vec<(bool, Instant, ThrottleKey)> = failures
+                .iter()
+                .filter(|(key, _)| !keys.contains(key))
+                .map(|(key, failures): (&ThrottleKey, &Failures)| {
+                    let locked_out: bool = failures
+                        .locked_until
+                        .is_some_and(|locked_until: Instant| locked_until > now);
+                    (locked_out, failures.last_failure, key.clone())
+                })
+                .collect();
+            oldest.sort_by_key(|(locked_out, last_failure, _)| (*locked_out, *last_failure));
+            let excess: usize = failures.len() + untracked - self.max_tracked;
+            for (_, _, key) in oldest.into_iter().take(excess) {
+                failures.remove(&key);
+            }
+        }
+        for key in keys.into_iter() {
+            let max_failures: u32 = match key {
+                ThrottleKey::Username(_) => self.config.max_failures_per_user,
+                ThrottleKey::Ip(_) => self.config.max_failures_per_ip,
+            };
+            let entry: &mut Failures = failures.entry(key.clone()).or_insert(Failures {
+                count: 0,
+                last_failure: now,
+                locked_until: None,
+            });
+            // The failures long gone don't count any more
+            if now - entry.last_failure >= forget_after {
+                entry.count = 0;
+            }
+            entry.count += 1;
+            entry.last_failure = now;
+            if entry.count >= max_failures {
+                let lockout: Duration = self.lockout(entry.count - max_failures);
+                entry.locked_until = Some(now + lockout);
+                warn!(
+                    "Locked out {:?} for {}s after {} failed logins",
+                    key,
+                    lockout.as_secs(),
+                    entry.count
+                );
+            }
+        }
+    }
+
+    /// The lockout after as many failures over the limit, doubling with each of them.
+    fn lockout(&self, over_limit: u32) -> Duration {
+        let lockout: u64 = self
+            .config
+            .lockout
+            .saturating_mul(2_u64.saturating_pow(over_limit));
+        Duration::from_secs(lockout.min(self.config.max_lockout))
+    }
+}
+
+fn keys(username: &str, client_ip: Option<IpAddr>) -> Vec<ThrottleKey> {
+    let mut keys: Vec<ThrottleKey> = vec![ThrottleKey::Username(username.to_string())];
+    if let Some(client_ip) = client_ip {
+        keys.push(ThrottleKey::Ip(client_ip));
+    }
+    keys
+}
+
+/// Throttles the HTTP Basic Auth logins by the `LoginThrottle`, delegating the rest to the wrapped middleware.
+pub struct ThrottledAuthMiddleware {
+    inner: Arc<dyn AuthMiddleware + Send + Sync>,
+    throttle: LoginThrottle,
+}
+
+impl ThrottledAuthMiddleware {
+    pub fn new(inner: Arc<dyn AuthMiddleware + Send + Sync>, throttle: LoginThrottle) -> Self {
+        ThrottledAuthMiddleware { inner, throttle }
+    }
+}
+
+#[async_trait]
+impl AuthMiddleware for ThrottledAuthMiddleware {
+    async fn http_basic_auth(&self, auth_header: String) -> Result<bool, Error> {
+        self.inner.http_basic_auth(auth_header).await
+    }
+
+    async fn has_user(&self, username: &str) -> Result<bool, Error> {
+        self.inner.has_user(username).await
+    }
+
+    async fn role(&self, username: &str) -> Result<Option<Role>, Error> {
+        self.inner.role(username).await
+    }
+
+    async fn authenticate_api_key(&self, api_key: &str) -> Result<ApiKey, Error> {
+        self.inner.authenticate_api_key(api_key).await
+    }
+
+    async fn authenticate_basic(
+        &self,
+        auth_header: String,
+        client_ip: Option<IpAddr>,
+    ) -> Result<String, Error> {
+        let (username, _): (String, String) = parse_auth_header(auth_header.clone())?;
+        self.throttle.check(&username, client_ip).inspect_err(|_| {
+            warn!("Rejected a login of {username} from {client_ip:?} while locked out")
+        })?;
+        match self.inner.authenticate_basic(auth_header, client_ip).await {
+            Ok(username) => {
+                self.throttle.record_success(&username);
+                Ok(username)
+            }
+            Err(Error::Unauthorized) => {
+                warn!("Failed login of {username} from {client_ip:?}");
+                self.throttle.record_failure(&username, client_ip);
+                Err(Error::Unauthorized)
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
+
+fn from_env<T: std::str::FromStr>(key: &str, default: T) -> T {
+    env::var(key)
+        .map(|x: String| {
+            x.parse::<T>()
+                .unwrap_or_else(|_| panic!("Cannot parse {key}: {x}"))
+        })
+        .unwrap_or(default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lockout() {
+        let throttle: LoginThrottle = LoginThrottle::new(ThrottleConfig {
+            max_failures_per_user: 3,
+            max_failures_per_ip: 10,
+            lockout: 30,
+            max_lockout: 100,
+        });
+        let client_ip: Option<IpAddr> = Some("127.0.0.1".parse().unwrap());
+        let now: Instant = Instant::now();
+
+        throttle.record_failure_at("admin", client_ip, now);
+        throttle.record_failure_at("admin", client_ip, now);
+        assert!(throttle.check_at("admin", client_ip, now).is_ok());
+
+        // Locked out after the 3rd failure, for twice as long after the 4th one, up to the max
+        throttle.record_failure_at("admin", client_ip, now);
+        assert!(matches!(
+            throttle.check_at("admin", None, now),
+            Err(Error::TooManyAttempts { retry_after: 30 })
+        ));
+        assert!(throttle
+            .check_at("admin", None, now + Duration::from_secs(30))
+            .is_ok());
+        throttle.record_failure_at("admin", client_ip, now);
+        assert!(matches!(
+            throttle.check_at("admin", None, now),
+            Err(Error::TooManyAttempts { retry_after: 60 })
+        ));
+        throttle.record_failure_at("admin", client_ip, now);
+        assert!(matches!(
+            throttle.check_at("admin", None, now),
+            Err(Error::TooManyAttempts { retry_after: 100 })
+        ));
+
+        // The IP is not locked out yet, and a successful login clears the failures of the username
+        assert!(throttle.check_at("other", client_ip, now).is_ok());
+        throttle.record_success("admin");
+        assert!(throttle.check_at("admin", client_ip, now).is_ok());
+    }
+
+    #[test]
+    fn test_max_tracked() {
+        let mut throttle: LoginThrottle = LoginThrottle::new(ThrottleConfig {
+            max_failures_per_user: 2,
+            max_failures_per_ip: 100,
+            lockout: 30,
+            max_lockout: 100,
+        });
+        throttle.max_tracked = 3;
+        let tracked = |throttle: &LoginThrottle, username: &str| -> bool {
+            throttle
+                .failures
+                .lock()
+                .unwrap()
+                .contains_key(&ThrottleKey::Username(username.to_string()))
+        };
+        let now: Instant = Instant::now();
+
+        // All failing lately, so the oldest are evicted, those locked out last
+        throttle.record_failure_at("locked", None, now);
+        throttle.record_failure_at("locked", None, now);
+        throttle.record_failure_at("first", None, now + Duration::from_secs(1));
+        throttle.record_failure_at("second", None, now + Duration::from_secs(2));
+        let later: Instant = now + Duration::from_secs(3);
+        throttle.record_failure_at("third", None, later);
+        assert!(tracked(&throttle, "locked"));
+        assert!(!tracked(&throttle, "first"));
+        assert!(tracked(&throttle, "second"));
+        assert!(tracked(&throttle, "third"));
+        assert!(throttle.check_at("locked", None, later).is_err());
+
+        // Spraying usernames from an IP never grows past the cap, nor forgets the IP
+        let client_ip: IpAddr = "127.0.0.1".parse().unwrap();
+        for i in 0..10 {
+            throttle.record_failure_at(&format!("user{i}"), Some(client_ip), later);
+            assert!(throttle.failures.lock().unwrap().len() <= 3);
+        }
+        assert_eq!(
+            throttle
+                .failures
+                .lock()
+                .unwrap()
+                .get(&ThrottleKey::Ip(client_ip))
+                .map(|failures: &Failures| failures.count),
+            Some(10)
+        );
+        assert!(tracked(&throttle, "locked"));
+    }
+}
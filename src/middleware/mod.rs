@@ -1,2 +1,7 @@
 pub mod auth;
+pub mod auth_db;
+pub mod jwt;
+pub mod login_throttle;
+pub mod oauth2;
+pub mod rate_limiter;
 pub mod validation;
@@ -0,0 +1,418 @@
+use std::collections::HashMap;
+use std::env;
+use std::sync::Mutex;
+
+use chrono::DateTime;
+use chrono::Days;
+use chrono::NaiveDate;
+use chrono::Utc;
+use serde::Deserialize;
+
+use crate::models::errors::Error;
+
+const RATE_LIMITS_FILE_KEY: &str = "RATE_LIMITS_FILE";
+const DEFAULT_RATE_LIMITS_FILE: &str = "rate_limits.json";
+/// Past this many tracked buckets, the full ones not counting towards a daily quota are forgotten.
+const MAX_TRACKED: usize = 10_000;
+/// How long the client of verified credentials is remembered, in seconds.
+const VERIFIED_CLIENT_TTL: i64 = 60 * 5;
+
+/// How fast a client may send requests: `burst` requests at once, then `per_second` requests a second,
+/// and at most `daily_quota` requests a day, if any, the days being the UTC ones.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub per_second: f64,
+    pub burst: u32,
+    pub daily_quota: Option<u64>,
+}
+
+/// The rate limit of the requests with the given method, if any, and a path starting with the segments of the given
+/// one, a `*` segment standing for any, e.g. `/address-books/*/contacts/import` for the imports into any address book.
+#[derive(Deserialize, Debug, Clone)]
+pub struct RouteRateLimit {
+    pub method: Option<String>,
+    pub path: String,
+    #[serde(flatten)]
+    pub limit: RateLimit,
+}
+
+/// The rate limits file, e.g. `{"default": {"per_second": 5, "burst": 20}, "routes": [{"method": "POST",
+/// "path": "/address-books/*/contacts/import", "per_second": 0.1, "burst": 2, "daily_quota": 100}]}`.
+/// A request is limited by the first of the routes matching it, otherwise by the default limit.
+#[derive(Deserialize, Debug, Clone)]
+pub struct RateLimitsConfig {
+    pub default: RateLimit,
+    #[serde(default)]
+    pub routes: Vec<RouteRateLimit>,
+}
+
+/// What is left of the limit of a client after a request: `remaining` out of `limit` requests, all of them being
+/// available again in `reset` seconds. It's the daily quota once fewer requests are left of it than of the burst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitStatus {
+    pub limit: u64,
+    pub remaining: u64,
+    pub reset: u64,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated_at: DateTime<Utc>,
+    day: NaiveDate,
+    used_today: u64,
+}
+
+/// Limits the requests of each client to each route by a token bucket, refilled at the rate of the route,
+/// and by the daily quota of the route, if any. The clients are told apart by the caller, e.g. by username.
+pub struct RateLimiter {
+    config: RateLimitsConfig,
+    buckets: Mutex<HashMap<(String, Option<usize>), Bucket>>,
+    /// The clients of the credentials verified lately, by the hash of the credentials, along with when they were.
+    verified_clients: Mutex<HashMap<String, (String, DateTime<Utc>)>>,
+}
+
+impl RateLimiter {
+    pub async fn new() -> Self {
+        let file_path: String =
+            env::var(RATE_LIMITS_FILE_KEY).unwrap_or(DEFAULT_RATE_LIMITS_FILE.to_string());
+        Self::new_with_file(&file_path)
+            .await
+            .unwrap_or_else(|err| panic!("Cannot load the rate limits from {file_path}: {err}"))
+    }
+
+    async fn new_with_file(path: &str) -> Result<Self, Error> {
+        let file_contents: Vec<u8> = tokio::fs::read(path)
+            .await
+            .map_err(|err| Error::InvalidRateLimits(err.to_string()))?;
+        let config: RateLimitsConfig = serde_json::from_slice(&file_contents)
+            .map_err(|err| Error::InvalidRateLimits(err.to_string()))?;
+        Self::new_with_config(config)
+    }
+
+    /// Checks every rate is positive and every burst allows at least one request.
+    pub fn new_with_config(config: RateLimitsConfig) -> Result<Self, Error> {
+        let limits = std::iter::once(("default", &config.default)).chain(
+            config
+                .routes
+                .iter()
+                .map(|route: &RouteRateLimit| (route.path.as_str(), &route.limit)),
+        );
+        for (name, limit) in limits {
+            if !limit.per_second.is_finite() || limit.per_second <= 0.0 || limit.burst == 0 {
+                return Err(Error::InvalidRateLimits(format!(
+                    "{name}: expected a positive per_second and burst"
+                )));
+            }
+        }
+        Ok(RateLimiter {
+            config,
+            buckets: Mutex::new(HashMap::new()),
+            verified_clients: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// The client the credentials of the given hash were verified to be lately, if they were.
+    pub fn verified_client(&self, credentials_hash: &str) -> Option<String> {
+        self.verified_client_at(credentials_hash, Utc::now())
+    }
+
+    /// Remembers the client the credentials of the given hash were verified to be, for the next requests to count
+    /// against it. While as many clients as tracked at most were verified lately, the new ones are not remembered,
+    /// so that their requests keep counting against the client IP.
+    pub fn remember_client(&self, credentials_hash: &str, client: &str) {
+        self.remember_client_at(credentials_hash, client, Utc::now())
+    }
+
+    fn verified_client_at(&self, credentials_hash: &str, now: DateTime<Utc>) -> Option<String> {
+        self.verified_clients
+            .lock()
+            .unwrap()
+            .get(credentials_hash)
+            .filter(|(_, verified_at)| is_verified_lately(*verified_at, now))
+            .map(|(client, _)| client.clone())
+    }
+
+    fn remember_client_at(&self, credentials_hash: &str, client: &str, now: DateTime<Utc>) {
+        let mut verified_clients = self.verified_clients.lock().unwrap();
+        if verified_clients.len() >= MAX_TRACKED {
+            verified_clients.retain(|_, (_, verified_at)| is_verified_lately(*verified_at, now));
+            if verified_clients.len() >= MAX_TRACKED {
+                return;
+            }
+        }
+        verified_clients.insert(credentials_hash.to_string(), (client.to_string(), now));
+    }
+
+    /// Takes a request of the given client out of its bucket of the route, rejecting with `Error::RateLimited`
+    /// if the bucket is empty or the daily quota is used up.
+    pub fn acquire(&self, client: &str, method: &str, path: &str) -> Result<RateLimitStatus, Error> {
+        self.acquire_at(client, method, path, Utc::now())
+    }
+
+    fn acquire_at(
+        &self,
+        client: &str,
+        method: &str,
+        path: &str,
+        now: DateTime<Utc>,
+    ) -> Result<RateLimitStatus, Error> {
+        let route: Option<usize> = self.route(method, path);
+        let limit: RateLimit = self.limit(route);
+        let burst: f64 = f64::from(limit.burst);
+        let today: NaiveDate = now.date_naive();
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_TRACKED {
+            buckets.retain(|(_, route), bucket: &mut Bucket| {
+                let limit: RateLimit = self.limit(*route);
+                refilled(bucket, &limit, now) < f64::from(limit.burst)
+                    || (limit.daily_quota.is_some() && bucket.day == today)
+            });
+        }
+        let bucket: &mut Bucket = buckets
+            .entry((client.to_string(), route))
+            .or_insert(Bucket {
+                tokens: burst,
+                updated_at: now,
+                day: today,
+                used_today: 0,
+            });
+        bucket.tokens = refilled(bucket, &limit, now);
+        bucket.updated_at = now;
+        if bucket.day != today {
+            bucket.day = today;
+            bucket.used_today = 0;
+        }
+
+        let until_tomorrow: u64 = until_tomorrow(now);
+        if let Some(daily_quota) = limit.daily_quota {
+            if bucket.used_today >= daily_quota {
+                return Err(Error::RateLimited {
+                    limit: daily_quota,
+                    reset: until_tomorrow,
+                    retry_after: until_tomorrow,
+                });
+            }
+        }
+        if bucket.tokens < 1.0 {
+            return Err(Error::RateLimited {
+                limit: u64::from(limit.burst),
+                reset: seconds((burst - bucket.tokens) / limit.per_second),
+                retry_after: seconds((1.0 - bucket.tokens) / limit.per_second),
+            });
+        }
+        bucket.tokens -= 1.0;
+        bucket.used_today += 1;
+
+        let status: RateLimitStatus = RateLimitStatus {
+            limit: u64::from(limit.burst),
+            remaining: bucket.tokens.floor() as u64,
+            reset: seconds((burst - bucket.tokens) / limit.per_second),
+        };
+        match limit.daily_quota {
+            Some(daily_quota) if daily_quota - bucket.used_today < status.remaining => {
+                Ok(RateLimitStatus {
+                    limit: daily_quota,
+                    remaining: daily_quota - bucket.used_today,
+                    reset: until_tomorrow,
+                })
+            }
+            _ => Ok(status),
+        }
+    }
+
+    /// The index of the first route matching the request, if any.
+    fn route(&self, method: &str, path: &str) -> Option<usize> {
+        self.config.routes.iter().position(|route: &RouteRateLimit| {
+            is_path_of_route(path, &route.path)
+                && route
+                    .method
+                    .as_deref()
+                    .map(|route_method: &str| route_method.eq_ignore_ascii_case(method))
+                    .unwrap_or(true)
+        })
+    }
+
+    fn limit(&self, route: Option<usize>) -> RateLimit {
+        route
+            .map(|index: usize| self.config.routes[index].limit)
+            .unwrap_or(self.config.default)
+    }
+}
+
+/// Whether the path starts with the segments of the path of the route, a `*` segment of which matches any segment.
+fn is_path_of_route(path: &str, route_path: &str) -> bool {
+    let mut segments = path.split('/').filter(|segment: &&str| !segment.is_empty());
+    route_path
+        .split('/')
+        .filter(|segment: &&str| !segment.is_empty())
+        .all(|route_segment: &str| {
+            segments
+                .next()
+                .map(|segment: &str| route_segment == "*" || route_segment == segment)
+                .unwrap_or(false)
+        })
+}
+
+fn is_verified_lately(verified_at: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+    now - verified_at < chrono::Duration::seconds(VERIFIED_CLIENT_TTL)
+}
+
+/// The tokens in the bucket by now, refilled since its last request, up to the burst.
+fn refilled(bucket: &Bucket, limit: &RateLimit, now: DateTime<Utc>) -> f64 {
+    let elapsed: f64 = (now - bucket.updated_at)
+        .to_std()
+        .map(|elapsed| elapsed.as_secs_f64())
+        .unwrap_or(0.0);
+    (bucket.tokens + elapsed * limit.per_second).min(f64::from(limit.burst))
+}
+
+/// The seconds until the next UTC midnight, when the daily quotas are refilled.
+fn until_tomorrow(now: DateTime<Utc>) -> u64 {
+    let tomorrow: DateTime<Utc> = (now.date_naive() + Days::new(1))
+        .and_hms_opt(0, 0, 0)
+        .unwrap()
+        .and_utc();
+    seconds((tomorrow - now).num_milliseconds() as f64 / 1000.0)
+}
+
+/// Rounded up, so that retrying after as many seconds is never too early.
+fn seconds(seconds: f64) -> u64 {
+    seconds.ceil().max(0.0) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_acquire() {
+        let rate_limiter: RateLimiter = RateLimiter::new_with_config(RateLimitsConfig {
+            default: RateLimit {
+                per_second: 1.0,
+                burst: 2,
+                daily_quota: None,
+            },
+            routes: vec![RouteRateLimit {
+                method: Some("POST".to_string()),
+                path: "/address-books/*/contacts/import".to_string(),
+                limit: RateLimit {
+                    per_second: 10.0,
+                    burst: 10,
+                    daily_quota: Some(3),
+                },
+            }],
+        })
+        .unwrap();
+        let now: DateTime<Utc> = Utc.with_ymd_and_hms(2023, 5, 22, 23, 0, 0).unwrap();
+
+        // The burst, then one more request a second
+        assert_eq!(
+            RateLimitStatus {
+                limit: 2,
+                remaining: 1,
+                reset: 1
+            },
+            rate_limiter
+                .acquire_at("admin", "GET", "/address-books/1/contacts", now)
+                .unwrap()
+        );
+        assert!(rate_limiter
+            .acquire_at("admin", "GET", "/address-books/1/contacts/1", now)
+            .is_ok());
+        assert!(matches!(
+            rate_limiter.acquire_at("admin", "GET", "/address-books/1/contacts", now),
+            Err(Error::RateLimited {
+                limit: 2,
+                reset: 2,
+                retry_after: 1
+            })
+        ));
+        assert!(rate_limiter
+            .acquire_at("other", "GET", "/address-books/1/contacts", now)
+            .is_ok());
+        assert!(rate_limiter
+            .acquire_at(
+                "admin",
+                "GET",
+                "/address-books/1/contacts",
+                now + Duration::seconds(1)
+            )
+            .is_ok());
+
+        // The daily quota of the route, refilled at midnight
+        for remaining in [2, 1, 0] {
+            assert_eq!(
+                remaining,
+                rate_limiter
+                    .acquire_at("admin", "POST", "/address-books/1/contacts/import", now)
+                    .unwrap()
+                    .remaining
+            );
+        }
+        assert!(matches!(
+            rate_limiter.acquire_at("admin", "POST", "/address-books/1/contacts/import", now),
+            Err(Error::RateLimited {
+                limit: 3,
+                reset: 3600,
+                retry_after: 3600
+            })
+        ));
+        assert!(rate_limiter
+            .acquire_at(
+                "admin",
+                "POST",
+                "/address-books/1/contacts/import",
+                now + Duration::hours(1)
+            )
+            .is_ok());
+    }
+
+    #[test]
+    fn test_verified_clients() {
+        let rate_limiter: RateLimiter = RateLimiter::new_with_config(RateLimitsConfig {
+            default: RateLimit {
+                per_second: 1.0,
+                burst: 2,
+                daily_quota: None,
+            },
+            routes: vec![],
+        })
+        .unwrap();
+        let now: DateTime<Utc> = Utc.with_ymd_and_hms(2023, 5, 22, 23, 0, 0).unwrap();
+
+        assert_eq!(None, rate_limiter.verified_client_at("hash", now));
+        rate_limiter.remember_client_at("hash", "user:admin", now);
+        assert_eq!(
+            Some("user:admin".to_string()),
+            rate_limiter.verified_client_at("hash", now + Duration::seconds(60))
+        );
+        assert_eq!(
+            None,
+            rate_limiter.verified_client_at("hash", now + Duration::seconds(VERIFIED_CLIENT_TTL))
+        );
+    }
+
+    #[test]
+    fn test_route_paths() {
+        assert!(is_path_of_route(
+            "/address-books/12/contacts/import",
+            "/address-books/*/contacts/import"
+        ));
+        assert!(is_path_of_route("/auth/token", "/auth"));
+        assert!(!is_path_of_route(
+            "/address-books/12/contacts/1",
+            "/address-books/*/contacts/import"
+        ));
+        assert!(!is_path_of_route(
+            "/address-books/12/contacts",
+            "/address-books/*/contacts/import"
+        ));
+        assert!(!is_path_of_route(
+            "/contacts/import",
+            "/address-books/*/contacts/import"
+        ));
+    }
+}
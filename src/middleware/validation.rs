@@ -4,42 +4,70 @@ use async_trait::async_trait;
 use lazy_regex::lazy_regex;
 use lazy_regex::Lazy;
 use lazy_regex::Regex;
+use reqwest::Client;
+use reqwest::Response;
 use reqwest_middleware::ClientBuilder;
 use reqwest_middleware::ClientWithMiddleware;
 use reqwest_retry::policies::ExponentialBackoff;
 use reqwest_retry::RetryTransientMiddleware;
-use reqwest::Client;
-use reqwest::Response;
 use serde::Deserialize;
 
+use crate::models::contact::Address;
+use crate::models::contact::Email;
+use crate::models::contact::NewContact;
+use crate::models::contact::Phone;
 use crate::models::errors::Error;
+use crate::models::organization::NewOrganization;
+use crate::models::phone_number::PhoneNumber;
 
 const APILAYER_KEY_KEY: &str = "APILAYER_KEY";
+const MAX_ADDRESS_FIELD_LENGTH: usize = 255;
+const MAX_POSTAL_CODE_LENGTH: usize = 32;
+const MAX_LABEL_LENGTH: usize = 64;
+const MAX_NOTE_LENGTH: usize = 10_000;
+static DOMAIN_REGEX: Lazy<Regex> = lazy_regex!(r"^[a-z0-9]+([\-\.]{1}[a-z0-9]+)*\.[a-z]{2,63}$");
 const APILAYER_BASE_URL: &str = "https://api.apilayer.com/number_verification/validate?number=";
 
 #[async_trait]
-trait Validation {
+pub trait Validation {
     fn is_name_valid(name: String) -> bool;
 
     fn is_email_valid(&self, email: String) -> bool;
 
-    fn is_phone_no_valid_fallback(&self, phone_no: i64) -> bool;
+    fn is_phone_no_valid_fallback(&self, phone_no: &PhoneNumber) -> bool;
+
+    async fn get_valid_phone_no(&self, phone_no: &PhoneNumber) -> Result<bool, Error>;
 
-    async fn get_valid_phone_no(&self, phone_no: i64) -> Result<bool, Error>;
+    fn is_address_valid(address: &Address) -> bool;
+
+    fn is_domain_valid(domain: &str) -> bool;
+
+    fn is_note_valid(body: &str) -> bool;
+
+    /// Runs the checks against a new organization. Returns the messages of the failed ones.
+    fn validate_organization(new_organization: &NewOrganization) -> Vec<String>;
+
+    /// Runs all the checks against a new contact. Returns the messages of the failed ones.
+    async fn validate(&self, new_contact: &NewContact) -> Vec<String>;
 }
 
-struct ValidationMiddleware {
+pub struct ValidationMiddleware {
     email_regex: Lazy<Regex>,
     de_phone_regex: Lazy<Regex>,
     http_client: ClientWithMiddleware,
     api_layer_key: String,
+    api_layer_base_url: String,
 }
 
 impl ValidationMiddleware {
-    fn new() -> Self {
+    pub fn new() -> Self {
         let api_layer_key: String = env::var(APILAYER_KEY_KEY)
             .unwrap_or_else(|_| panic!("Missing environment variable: {APILAYER_KEY_KEY}"));
+        Self::new_with_api_layer(api_layer_key, APILAYER_BASE_URL.to_string())
+    }
 
+    /// Same as `new`, but with the given key and base URL of the number verification API, e.g. in tests.
+    pub fn new_with_api_layer(api_layer_key: String, api_layer_base_url: String) -> Self {
         let retry_policy: ExponentialBackoff =
             ExponentialBackoff::builder().build_with_max_retries(3);
 
@@ -47,11 +75,12 @@ impl ValidationMiddleware {
             email_regex: lazy_regex!(
                 r"^([a-z0-9_+]([a-z0-9_+.]*[a-z0-9_+])?)@([a-z0-9]+([\-\.]{1}[a-z0-9]+)*\.[a-z]{2,6})"
             ),
-            de_phone_regex: lazy_regex!(r"49[0-9]{9,10}"),
+            de_phone_regex: lazy_regex!(r"^\+49[0-9]{9,10}$"),
             http_client: ClientBuilder::new(Client::new())
                 .with(RetryTransientMiddleware::new_with_policy(retry_policy))
                 .build(),
-            api_layer_key: api_layer_key,
+            api_layer_key,
+            api_layer_base_url,
         }
     }
 }
@@ -65,19 +94,64 @@ impl Default for ValidationMiddleware {
 #[async_trait]
 impl Validation for ValidationMiddleware {
     fn is_name_valid(name: String) -> bool {
-        name.is_empty() || name.len() > 255
+        !name.is_empty() && name.len() <= 255
     }
 
     fn is_email_valid(&self, email: String) -> bool {
         self.email_regex.is_match(&email)
     }
 
-    fn is_phone_no_valid_fallback(&self, phone_no: i64) -> bool {
-        self.de_phone_regex.is_match(&phone_no.to_string())
+    fn is_phone_no_valid_fallback(&self, phone_no: &PhoneNumber) -> bool {
+        self.de_phone_regex.is_match(phone_no.e164())
     }
 
-    async fn get_valid_phone_no(&self, phone_no: i64) -> Result<bool, Error> {
-        let url: String = format!("{APILAYER_BASE_URL}{phone_no}");
+    fn is_address_valid(address: &Address) -> bool {
+        [&address.street, &address.locality, &address.region]
+            .iter()
+            .all(|x: &&String| x.len() <= MAX_ADDRESS_FIELD_LENGTH)
+            && address.postal_code.len() <= MAX_POSTAL_CODE_LENGTH
+            && address
+                .label
+                .as_ref()
+                .is_none_or(|x: &String| x.len() <= MAX_LABEL_LENGTH)
+            && !(address.street.is_empty() && address.locality.is_empty())
+    }
+
+    fn is_domain_valid(domain: &str) -> bool {
+        DOMAIN_REGEX.is_match(domain) && domain.len() <= MAX_ADDRESS_FIELD_LENGTH
+    }
+
+    fn is_note_valid(body: &str) -> bool {
+        !body.trim().is_empty() && body.chars().count() <= MAX_NOTE_LENGTH
+    }
+
+    fn validate_organization(new_organization: &NewOrganization) -> Vec<String> {
+        let mut messages: Vec<String> = vec![];
+        if !Self::is_name_valid(new_organization.name.clone()) {
+            messages.push(format!("Invalid name: {}", new_organization.name));
+        }
+        if let Some(domain) = &new_organization.domain {
+            if !Self::is_domain_valid(domain) {
+                messages.push(format!("Invalid domain: {}", domain));
+            }
+        }
+        if let Some(address) = &new_organization.address {
+            if !Self::is_address_valid(address) {
+                messages.push(format!(
+                    "Invalid address: {}, {} {}",
+                    address.street, address.postal_code, address.locality
+                ));
+            }
+        }
+        messages
+    }
+
+    async fn get_valid_phone_no(&self, phone_no: &PhoneNumber) -> Result<bool, Error> {
+        let url: String = format!(
+            "{}{}",
+            self.api_layer_base_url,
+            phone_no.e164().trim_start_matches('+')
+        );
         let result: Response = self
             .http_client
             .get(url)
@@ -104,8 +178,69 @@ impl Validation for ValidationMiddleware {
             }
         }
     }
+
+    async fn validate(&self, new_contact: &NewContact) -> Vec<String> {
+        let mut messages: Vec<String> = vec![];
+        if !Self::is_name_valid(new_contact.name.clone()) {
+            messages.push(format!("Invalid name: {}", new_contact.name));
+        }
+        for email in &new_contact.emails {
+            if !self.is_email_valid(email.address.clone()) {
+                messages.push(format!("Invalid email: {}", email.address));
+            }
+        }
+        for phone in &new_contact.phones {
+            match self.get_valid_phone_no(&phone.number).await {
+                Ok(true) => {}
+                Ok(false) => messages.push(format!("Invalid phone_no: {}", phone.number)),
+                Err(err) => messages.push(err.to_string()),
+            }
+        }
+        for address in &new_contact.addresses {
+            if !Self::is_address_valid(address) {
+                messages.push(format!(
+                    "Invalid address: {}, {} {}",
+                    address.street, address.postal_code, address.locality
+                ));
+            }
+        }
+        for date in &new_contact.dates {
+            if date.label.trim().is_empty() || date.label.len() > MAX_LABEL_LENGTH {
+                messages.push(format!("Invalid date label: {}", date.label));
+            }
+        }
+        if new_contact
+            .phones
+            .iter()
+            .filter(|x: &&Phone| x.primary)
+            .count()
+            > 1
+        {
+            messages.push("More than one primary phone".to_string());
+        }
+        if new_contact
+            .emails
+            .iter()
+            .filter(|x: &&Email| x.primary)
+            .count()
+            > 1
+        {
+            messages.push("More than one primary email".to_string());
+        }
+        if new_contact
+            .addresses
+            .iter()
+            .filter(|x: &&Address| x.primary)
+            .count()
+            > 1
+        {
+            messages.push("More than one primary address".to_string());
+        }
+        messages
+    }
 }
 
+#[allow(dead_code)]
 #[derive(Deserialize, Debug, Clone)]
 struct NumberVerificationAPIResponse {
     valid: bool,
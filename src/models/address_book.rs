@@ -0,0 +1,49 @@
+use std::str::FromStr;
+
+use chrono::DateTime;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::models::errors::Error;
+
+const MAX_NAME_LENGTH: usize = 255;
+
+/// An address book of a user, e.g. of a customer or of a team, holding its own contacts and organizations.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct AddressBook {
+    pub id: AddressBookId,
+    pub name: String,
+    /// The username of the owner.
+    pub owner_id: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AddressBookId(pub i32);
+
+/// Parses the id in the path of the address book routes, e.g. `/address-books/1/contacts`.
+impl FromStr for AddressBookId {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        value.parse::<i32>().map(AddressBookId)
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct NewAddressBook {
+    pub name: String,
+}
+
+impl NewAddressBook {
+    /// Trims the name, then rejects it if empty or too long.
+    pub fn validated(self) -> Result<Self, Error> {
+        let name: String = self.name.trim().to_string();
+        if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+            return Err(Error::InvalidBody(format!(
+                "The name of an address book must have 1 to {MAX_NAME_LENGTH} characters"
+            )));
+        }
+        Ok(NewAddressBook { name })
+    }
+}
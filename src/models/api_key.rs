@@ -0,0 +1,173 @@
+use chrono::DateTime;
+use chrono::Utc;
+use rand_core::OsRng;
+use rand_core::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Digest;
+use sha2::Sha256;
+
+use crate::models::errors::Error;
+use crate::models::role::Role;
+
+/// The prefix of the keys, telling them apart from the other secrets, e.g. in logs or in secret scanners.
+const KEY_PREFIX: &str = "cak_";
+const KEY_BYTES: usize = 32;
+const MAX_NAME_LENGTH: usize = 64;
+
+/// What an API key may be used for. Unlike the roles, the scopes don't imply each other.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ApiKeyScope {
+    #[serde(rename = "contacts:read")]
+    ContactsRead,
+    #[serde(rename = "contacts:write")]
+    ContactsWrite,
+}
+
+impl ApiKeyScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiKeyScope::ContactsRead => "contacts:read",
+            ApiKeyScope::ContactsWrite => "contacts:write",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, Error> {
+        match value {
+            "contacts:read" => Ok(ApiKeyScope::ContactsRead),
+            "contacts:write" => Ok(ApiKeyScope::ContactsWrite),
+            _ => Err(Error::InvalidApiKey(format!(
+                "unknown scope {value}, expected contacts:read or contacts:write"
+            ))),
+        }
+    }
+
+    /// The scope standing for the given role, if any: `contacts:read` for a `reader` and `contacts:write` for an
+    /// `editor`. What only the `admin` role is allowed to do is out of reach of any API key.
+    pub fn for_role(role: Role) -> Option<Self> {
+        match role {
+            Role::Reader => Some(ApiKeyScope::ContactsRead),
+            Role::Editor => Some(ApiKeyScope::ContactsWrite),
+            Role::Admin => None,
+        }
+    }
+}
+
+/// An API key of a service, as listed to the admins. The key itself is only shown once, when minted.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ApiKey {
+    pub id: ApiKeyId,
+    /// What the key is for, e.g. `billing-sync`.
+    pub name: String,
+    pub scopes: Vec<ApiKeyScope>,
+    /// The username of the admin who minted the key.
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ApiKeyId(pub i32);
+
+impl ApiKey {
+    /// The name of the key as the author of what is done with it, e.g. `api-key:billing-sync`,
+    /// never clashing with a username, as the usernames have no `:`.
+    pub fn principal_name(&self) -> String {
+        format!("api-key:{}", self.name)
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at
+            .map(|expires_at: DateTime<Utc>| expires_at <= now)
+            .unwrap_or(false)
+    }
+
+    /// Whether the key may be used to do what the given role is required for.
+    pub fn allows(&self, required: Role) -> bool {
+        ApiKeyScope::for_role(required)
+            .map(|scope: ApiKeyScope| self.scopes.contains(&scope))
+            .unwrap_or(false)
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct NewApiKey {
+    pub name: String,
+    pub scopes: Vec<ApiKeyScope>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl NewApiKey {
+    /// Checks the name is 1 to 64 characters, there is at least one scope and the expiry, if any, is in the future.
+    pub fn validated(mut self) -> Result<Self, Error> {
+        self.name = self.name.trim().to_string();
+        if self.name.is_empty() || self.name.chars().count() > MAX_NAME_LENGTH {
+            return Err(Error::InvalidApiKey(format!(
+                "the name {} is not 1 to {MAX_NAME_LENGTH} characters",
+                self.name
+            )));
+        }
+        if self.scopes.is_empty() {
+            return Err(Error::InvalidApiKey("no scopes".to_string()));
+        }
+        if matches!(self.expires_at, Some(expires_at) if expires_at <= Utc::now()) {
+            return Err(Error::InvalidApiKey(
+                "expires_at is in the past".to_string(),
+            ));
+        }
+        self.scopes.sort();
+        self.scopes.dedup();
+        Ok(self)
+    }
+}
+
+/// A newly minted API key, along with the key itself, which is not stored, so it cannot be shown again.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct MintedApiKey {
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+    pub key: String,
+}
+
+/// A new random key, e.g. `cak_5f0c...`, of 256 bits.
+pub fn generate_key() -> String {
+    let mut bytes: [u8; KEY_BYTES] = [0; KEY_BYTES];
+    OsRng.fill_bytes(&mut bytes);
+    format!("{KEY_PREFIX}{}", hex::encode(bytes))
+}
+
+/// The hash the keys are stored and looked up by. Being random and long, they need no salt nor a slow hash.
+pub fn hash_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allows() {
+        let api_key: ApiKey = ApiKey {
+            id: ApiKeyId(1),
+            name: "billing-sync".to_string(),
+            scopes: vec![ApiKeyScope::ContactsRead],
+            created_by: "admin".to_string(),
+            created_at: Utc::now(),
+            expires_at: None,
+            last_used_at: None,
+        };
+        assert!(api_key.allows(Role::Reader));
+        assert!(!api_key.allows(Role::Editor));
+        assert!(!api_key.allows(Role::Admin));
+        assert_eq!("api-key:billing-sync", api_key.principal_name());
+    }
+
+    #[test]
+    fn test_generate_key() {
+        let key: String = generate_key();
+        assert!(key.starts_with(KEY_PREFIX));
+        assert_ne!(key, generate_key());
+        assert_eq!(hash_key(&key), hash_key(&key));
+        assert_eq!(64, hash_key(&key).len());
+    }
+}
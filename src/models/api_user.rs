@@ -0,0 +1,70 @@
+use chrono::DateTime;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::models::errors::Error;
+use crate::models::role::Role;
+
+const MAX_USERNAME_LENGTH: usize = 64;
+const MIN_PASSWORD_LENGTH: usize = 8;
+
+/// An API user, as listed to the admins. The password is never returned.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ApiUser {
+    pub username: String,
+    pub role: Role,
+    /// A disabled user can neither authenticate nor use the tokens issued to it.
+    pub disabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct NewApiUser {
+    pub username: String,
+    pub password: String,
+    pub role: Role,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct NewPassword {
+    pub password: String,
+}
+
+impl NewApiUser {
+    /// Checks the username is 1 to 64 characters without `:` or whitespace, so it fits an HTTP Basic Auth header,
+    /// and the password is long enough.
+    pub fn validated(self) -> Result<Self, Error> {
+        let is_username_valid: bool = !self.username.is_empty()
+            && self.username.chars().count() <= MAX_USERNAME_LENGTH
+            && !self
+                .username
+                .chars()
+                .any(|c: char| c == ':' || c.is_whitespace() || c.is_control());
+        if !is_username_valid {
+            return Err(Error::InvalidApiUser(format!(
+                "the username {} is not 1 to {MAX_USERNAME_LENGTH} characters other than ':' or whitespace",
+                self.username
+            )));
+        }
+        validated_password(&self.password)?;
+        Ok(self)
+    }
+}
+
+impl NewPassword {
+    pub fn validated(self) -> Result<Self, Error> {
+        validated_password(&self.password)?;
+        Ok(self)
+    }
+}
+
+fn validated_password(password: &str) -> Result<(), Error> {
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        Err(Error::InvalidApiUser(format!(
+            "the password is shorter than {MIN_PASSWORD_LENGTH} characters"
+        )))
+    } else {
+        Ok(())
+    }
+}
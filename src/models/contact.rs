@@ -1,20 +1,268 @@
-use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use chrono::NaiveDate;
+use isocountry::CountryCode;
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::models::custom_field::is_same_value;
+use crate::models::custom_field::CustomFieldValues;
+use crate::models::errors::Error;
+use crate::models::important_date::ImportantDate;
+use crate::models::organization::Affiliation;
+use crate::models::phone_number::PhoneNumber;
+use crate::models::tag::Tag;
+use crate::models::tag::TagMode;
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Contact {
     pub id: ContactId,
     pub name: String,
-    pub phone_no: i64,
-    pub email: String,
+    #[serde(default)]
+    pub phones: Vec<Phone>,
+    #[serde(default)]
+    pub emails: Vec<Email>,
+    #[serde(default)]
+    pub addresses: Vec<Address>,
+    #[serde(default)]
+    pub tags: Vec<Tag>,
+    #[serde(default)]
+    pub organization: Option<Affiliation>,
+    #[serde(default)]
+    pub custom_fields: CustomFieldValues,
+    #[serde(default)]
+    pub birthday: Option<NaiveDate>,
+    #[serde(default)]
+    pub dates: Vec<ImportantDate>,
 }
+
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ContactId(pub i32);
 
+/// The contents of a contact, without its id, e.g. to validate an update the same way as a new contact.
+impl From<Contact> for NewContact {
+    fn from(contact: Contact) -> Self {
+        NewContact {
+            name: contact.name,
+            phones: contact.phones,
+            emails: contact.emails,
+            addresses: contact.addresses,
+            tags: contact.tags,
+            organization: contact.organization,
+            custom_fields: contact.custom_fields,
+            birthday: contact.birthday,
+            dates: contact.dates,
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct NewContact {
     pub name: String,
-    pub phone_no: i64,
-    pub email: String,
+    #[serde(default)]
+    pub phones: Vec<Phone>,
+    #[serde(default)]
+    pub emails: Vec<Email>,
+    #[serde(default)]
+    pub addresses: Vec<Address>,
+    #[serde(default)]
+    pub tags: Vec<Tag>,
+    #[serde(default)]
+    pub organization: Option<Affiliation>,
+    #[serde(default)]
+    pub custom_fields: CustomFieldValues,
+    #[serde(default)]
+    pub birthday: Option<NaiveDate>,
+    #[serde(default)]
+    pub dates: Vec<ImportantDate>,
+}
+
+/// A labeled phone number of a contact.
+/// Serialized along with the national format of the number, which is ignored when deserialized.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Phone {
+    #[serde(rename = "type", default)]
+    pub phone_type: PhoneType,
+    pub number: PhoneNumber,
+    #[serde(default)]
+    pub primary: bool,
+}
+
+impl Serialize for Phone {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Phone", 4)?;
+        state.serialize_field("type", &self.phone_type)?;
+        state.serialize_field("number", &self.number)?;
+        state.serialize_field("national", self.number.national())?;
+        state.serialize_field("primary", &self.primary)?;
+        state.end()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PhoneType {
+    #[default]
+    Mobile,
+    Home,
+    Work,
+    Fax,
+    Other,
+}
+
+impl PhoneType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PhoneType::Mobile => "mobile",
+            PhoneType::Home => "home",
+            PhoneType::Work => "work",
+            PhoneType::Fax => "fax",
+            PhoneType::Other => "other",
+        }
+    }
+}
+
+impl FromStr for PhoneType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "mobile" => Ok(PhoneType::Mobile),
+            "home" => Ok(PhoneType::Home),
+            "work" => Ok(PhoneType::Work),
+            "fax" => Ok(PhoneType::Fax),
+            "other" => Ok(PhoneType::Other),
+            other => Err(format!("Unknown phone type: {}", other)),
+        }
+    }
+}
+
+/// A labeled email of a contact.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Email {
+    #[serde(rename = "type", default)]
+    pub email_type: EmailType,
+    pub address: String,
+    #[serde(default)]
+    pub primary: bool,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum EmailType {
+    Home,
+    Work,
+    #[default]
+    Other,
+}
+
+impl EmailType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EmailType::Home => "home",
+            EmailType::Work => "work",
+            EmailType::Other => "other",
+        }
+    }
+}
+
+impl FromStr for EmailType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "home" => Ok(EmailType::Home),
+            "work" => Ok(EmailType::Work),
+            "other" => Ok(EmailType::Other),
+            other => Err(format!("Unknown email type: {}", other)),
+        }
+    }
+}
+
+/// A postal address of a contact. The country is an ISO 3166 alpha-2 code, e.g. `DE`.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Address {
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub street: String,
+    #[serde(default)]
+    pub locality: String,
+    #[serde(default)]
+    pub region: String,
+    #[serde(default)]
+    pub postal_code: String,
+    pub country: CountryCode,
+    #[serde(default)]
+    pub primary: bool,
+}
+
+/// Reads a country out of its ISO 3166 alpha-2 or alpha-3 code or its English name, ignoring the case.
+pub fn parse_country(value: &str) -> Result<CountryCode, Error> {
+    let value: &str = value.trim();
+    CountryCode::for_alpha2_caseless(value)
+        .or_else(|_| CountryCode::for_alpha3_caseless(value))
+        .ok()
+        .or_else(|| {
+            CountryCode::iter()
+                .find(|country: &&CountryCode| country.name().eq_ignore_ascii_case(value))
+                .copied()
+        })
+        .ok_or_else(|| Error::InvalidCountry(value.to_string()))
+}
+
+/// A phone number, an email or an address, one of which may be flagged as the primary one.
+pub trait ContactPoint {
+    fn is_primary(&self) -> bool;
+
+    fn set_primary(&mut self, primary: bool);
+}
+
+impl ContactPoint for Phone {
+    fn is_primary(&self) -> bool {
+        self.primary
+    }
+
+    fn set_primary(&mut self, primary: bool) {
+        self.primary = primary;
+    }
+}
+
+impl ContactPoint for Email {
+    fn is_primary(&self) -> bool {
+        self.primary
+    }
+
+    fn set_primary(&mut self, primary: bool) {
+        self.primary = primary;
+    }
+}
+
+impl ContactPoint for Address {
+    fn is_primary(&self) -> bool {
+        self.primary
+    }
+
+    fn set_primary(&mut self, primary: bool) {
+        self.primary = primary;
+    }
+}
+
+/// Makes sure a non-empty list has exactly one primary entry, listed first:
+/// the first one flagged as primary or, if none is, the first one.
+pub fn with_single_primary<T: ContactPoint>(mut points: Vec<T>) -> Vec<T> {
+    let primary: usize = points
+        .iter()
+        .position(|point: &T| point.is_primary())
+        .unwrap_or(0);
+    for (index, point) in points.iter_mut().enumerate() {
+        point.set_primary(index == primary);
+    }
+    if primary < points.len() {
+        let point: T = points.remove(primary);
+        points.insert(0, point);
+    }
+    points
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -24,5 +272,66 @@ pub struct UpdateContactEmail {
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct UpdateContactPhoneNo {
-    pub phone_no: i64,
+    pub phone_no: PhoneNumber,
+}
+
+/// Narrows down a list of contacts to the ones having an address in the given country and/or city
+/// and having all (or any, as per the tag mode) of the given tags, as well as the given values of custom fields.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContactFilter {
+    pub country: Option<CountryCode>,
+    pub city: Option<String>,
+    pub tags: Vec<Tag>,
+    pub tag_mode: TagMode,
+    pub custom_fields: CustomFieldValues,
+}
+
+impl ContactFilter {
+    pub fn matches(&self, contact: &Contact) -> bool {
+        self.matches_address(contact)
+            && self.matches_tags(contact)
+            && self.matches_custom_fields(contact)
+    }
+
+    fn matches_custom_fields(&self, contact: &Contact) -> bool {
+        self.custom_fields.iter().all(|(name, value)| {
+            contact
+                .custom_fields
+                .get(name)
+                .is_some_and(|x: &serde_json::Value| is_same_value(x, value))
+        })
+    }
+
+    fn matches_tags(&self, contact: &Contact) -> bool {
+        if self.tags.is_empty() {
+            return true;
+        }
+        let mut tags = self.tags.iter();
+        match self.tag_mode {
+            TagMode::And => tags.all(|tag: &Tag| contact.tags.contains(tag)),
+            TagMode::Or => tags.any(|tag: &Tag| contact.tags.contains(tag)),
+        }
+    }
+
+    fn matches_address(&self, contact: &Contact) -> bool {
+        if self.country.is_none() && self.city.is_none() {
+            return true;
+        }
+        contact.addresses.iter().any(|address: &Address| {
+            self.country
+                .is_none_or(|country: CountryCode| country == address.country)
+                && self
+                    .city
+                    .as_ref()
+                    .is_none_or(|city: &String| city.eq_ignore_ascii_case(&address.locality))
+        })
+    }
+}
+
+/// The contacts added, updated or deleted after a given sync token, along with the latest sync token.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContactChanges {
+    pub sync_token: i64,
+    pub updated: Vec<ContactId>,
+    pub deleted: Vec<ContactId>,
 }
@@ -0,0 +1,220 @@
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use serde_json::Number;
+use serde_json::Value;
+
+use crate::models::errors::Error;
+
+const MAX_NAME_LENGTH: usize = 64;
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// The values of the custom fields of a contact, by the name of the field.
+pub type CustomFieldValues = BTreeMap<String, Value>;
+
+/// An admin-defined field of the contacts, e.g. `department`, along with the type of its values.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct CustomField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub field_type: CustomFieldType,
+    /// The allowed values of an `enum` field. Empty for the other types.
+    #[serde(default)]
+    pub options: Vec<String>,
+}
+
+/// The definition of a custom field, its name being given by the path.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct NewCustomField {
+    #[serde(rename = "type")]
+    pub field_type: CustomFieldType,
+    #[serde(default)]
+    pub options: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CustomFieldType {
+    String,
+    Number,
+    /// An ISO 8601 calendar date, e.g. `2023-05-10`.
+    Date,
+    Bool,
+    Enum,
+}
+
+impl CustomFieldType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CustomFieldType::String => "string",
+            CustomFieldType::Number => "number",
+            CustomFieldType::Date => "date",
+            CustomFieldType::Bool => "bool",
+            CustomFieldType::Enum => "enum",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, Error> {
+        match value {
+            "string" => Ok(CustomFieldType::String),
+            "number" => Ok(CustomFieldType::Number),
+            "date" => Ok(CustomFieldType::Date),
+            "bool" => Ok(CustomFieldType::Bool),
+            "enum" => Ok(CustomFieldType::Enum),
+            other => Err(Error::InvalidCustomField(format!("Unknown type {other}"))),
+        }
+    }
+}
+
+impl CustomField {
+    /// Builds a definition, checking its name is made of 1 to 64 lower case letters, digits or `_`, starting with a letter,
+    /// and that only an `enum` field has options, at least one.
+    pub fn new(name: &str, new_custom_field: NewCustomField) -> Result<Self, Error> {
+        let is_name_valid: bool = name.len() <= MAX_NAME_LENGTH
+            && name.starts_with(|c: char| c.is_ascii_lowercase())
+            && name
+                .chars()
+                .all(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+        if !is_name_valid {
+            return Err(Error::InvalidCustomField(format!("Invalid name {name}")));
+        }
+        let is_enum: bool = new_custom_field.field_type == CustomFieldType::Enum;
+        if is_enum == new_custom_field.options.is_empty() {
+            return Err(Error::InvalidCustomField(format!(
+                "{name}: only an enum field has options, at least one"
+            )));
+        }
+        Ok(CustomField {
+            name: name.to_string(),
+            field_type: new_custom_field.field_type,
+            options: new_custom_field.options,
+        })
+    }
+
+    /// Checks a value is of the type of the field.
+    pub fn validate(&self, value: &Value) -> Result<(), Error> {
+        let is_valid: bool = match (self.field_type, value) {
+            (CustomFieldType::String, Value::String(_)) => true,
+            (CustomFieldType::Number, Value::Number(_)) => true,
+            (CustomFieldType::Date, Value::String(date)) => {
+                NaiveDate::parse_from_str(date, DATE_FORMAT).is_ok()
+            }
+            (CustomFieldType::Bool, Value::Bool(_)) => true,
+            (CustomFieldType::Enum, Value::String(option)) => self.options.contains(option),
+            _ => false,
+        };
+        if is_valid {
+            Ok(())
+        } else {
+            Err(Error::InvalidCustomField(format!(
+                "{}: {} is not a valid {}",
+                self.name,
+                value,
+                self.field_type.as_str()
+            )))
+        }
+    }
+
+    /// Reads a value of the field out of a query parameter, e.g. `42` for a number or `true` for a bool.
+    pub fn parse_value(&self, value: &str) -> Result<Value, Error> {
+        let parsed: Value = match self.field_type {
+            CustomFieldType::Number => value
+                .parse::<i64>()
+                .map(Value::from)
+                .ok()
+                .or_else(|| {
+                    value
+                        .parse::<f64>()
+                        .ok()
+                        .and_then(Number::from_f64)
+                        .map(Value::Number)
+                })
+                .unwrap_or(Value::String(value.to_string())),
+            CustomFieldType::Bool => value
+                .parse::<bool>()
+                .map(Value::Bool)
+                .unwrap_or(Value::String(value.to_string())),
+            _ => Value::String(value.to_string()),
+        };
+        self.validate(&parsed).map(|_| parsed)
+    }
+}
+
+/// Checks the values of the custom fields of a contact against the definitions, dropping the `null` ones.
+pub fn validated(
+    values: CustomFieldValues,
+    definitions: &[CustomField],
+) -> Result<CustomFieldValues, Error> {
+    let mut validated: CustomFieldValues = CustomFieldValues::new();
+    for (name, value) in values {
+        if value.is_null() {
+            continue;
+        }
+        definitions
+            .iter()
+            .find(|definition: &&CustomField| definition.name == name)
+            .ok_or_else(|| Error::InvalidCustomField(format!("Unknown field {name}")))?
+            .validate(&value)?;
+        validated.insert(name, value);
+    }
+    Ok(validated)
+}
+
+/// Whether two values are the same, the numbers being compared by value, e.g. `1` and `1.0`.
+pub fn is_same_value(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64() == b.as_f64(),
+        _ => a == b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validated() {
+        let definitions: Vec<CustomField> = vec![
+            CustomField::new(
+                "level",
+                NewCustomField {
+                    field_type: CustomFieldType::Enum,
+                    options: vec!["gold".to_string(), "silver".to_string()],
+                },
+            )
+            .unwrap(),
+            CustomField::new(
+                "since",
+                NewCustomField {
+                    field_type: CustomFieldType::Date,
+                    options: vec![],
+                },
+            )
+            .unwrap(),
+        ];
+        let values: CustomFieldValues = CustomFieldValues::from([
+            ("level".to_string(), Value::from("gold")),
+            ("since".to_string(), Value::from("2023-05-10")),
+            ("vip".to_string(), Value::Null),
+        ]);
+
+        assert_eq!(2, validated(values, &definitions).unwrap().len());
+        for (name, value) in [
+            ("level", Value::from("bronze")),
+            ("since", Value::from("10.05.2023")),
+            ("vip", Value::from(true)),
+        ] {
+            let values: CustomFieldValues = CustomFieldValues::from([(name.to_string(), value)]);
+            assert!(validated(values, &definitions).is_err());
+        }
+        assert!(CustomField::new(
+            "Level",
+            NewCustomField {
+                field_type: CustomFieldType::Bool,
+                options: vec![],
+            }
+        )
+        .is_err());
+    }
+}
@@ -5,6 +5,9 @@ use std::string::FromUtf8Error;
 
 use base64::DecodeError;
 
+/// The Postgres error code of a unique constraint violation.
+const UNIQUE_VIOLATION: &str = "23505";
+
 #[derive(Debug)]
 pub enum Error {
     /// Pagination info (page_no or page_size) cannot be converted from String to u32
@@ -19,9 +22,47 @@ pub enum Error {
     /// The HTTP Authorization header value is invalid
     InvalidAuthHeader,
 
-    /// The HTTP Authorization header contains a valid value but the scheme is other than `Basic`
+    /// The HTTP Authorization header contains a valid value but the scheme is other than `Basic` or `Bearer`
     InvalidScheme(String),
 
+    /// The HTTP Authorization header is missing or the credentials are not valid
+    Unauthorized,
+
+    /// The API users file cannot be read, or is malformed
+    InvalidUsersFile(String),
+
+    /// There is no API user with the given username
+    UnknownUser(String),
+
+    /// The username or the password of a new API user is not valid
+    InvalidApiUser(String),
+
+    /// The name, the scopes or the expiry of a new API key are not valid
+    InvalidApiKey(String),
+
+    /// The authenticated user's role doesn't allow the operation
+    Forbidden(String),
+
+    /// The username or the client IP is locked out after too many failed logins, for `retry_after` more seconds
+    TooManyAttempts { retry_after: u64 },
+
+    /// The client used up its requests of the route: the burst of `limit` requests, or the daily quota of as many,
+    /// which is refilled in `reset` seconds and allows another request in `retry_after` seconds
+    RateLimited {
+        limit: u64,
+        reset: u64,
+        retry_after: u64,
+    },
+
+    /// The rate limits file cannot be read, or is malformed
+    InvalidRateLimits(String),
+
+    /// The bearer token is malformed, expired, not signed by any of the keys or not meant for the request
+    InvalidToken(String),
+
+    /// The JWT signing or verification keys cannot be loaded
+    InvalidJwtKey(String),
+
     /// The value expected as a base64 encoded `String` is not encoded correctly
     InvalidBase64Value(String),
 
@@ -30,6 +71,69 @@ pub enum Error {
 
     /// The external validation api call failed
     ReqwestMiddleware(String),
+
+    /// The CSV payload cannot be read or written
+    InvalidCsv(String),
+
+    /// The vCard payload or one of its properties is malformed
+    InvalidVCard(String),
+
+    /// The WebDAV XML payload is malformed or not supported
+    InvalidXml(String),
+
+    /// The WebDAV sync-token is not one issued by this server
+    InvalidSyncToken(String),
+
+    /// A conditional request header (If-Match or If-None-Match) doesn't hold
+    PreconditionFailed(String),
+
+    /// None of the media types in the HTTP Accept header can be produced
+    NotAcceptable(String),
+
+    /// The media type in the HTTP Content-Type header cannot be consumed
+    UnsupportedMediaType(String),
+
+    /// The request body cannot be decoded into the expected payload
+    InvalidBody(String),
+
+    /// The phone number cannot be parsed
+    InvalidPhoneNumber(String),
+
+    /// The country is not an ISO 3166 country code or name
+    InvalidCountry(String),
+
+    /// The tag is empty, too long or has characters other than letters, digits, spaces, `-`, `_` or `.`
+    InvalidTag(String),
+
+    /// The tag mode is neither `and` nor `or`
+    InvalidTagMode(String),
+
+    /// The window of the upcoming dates is not a number of days from 0 to 366
+    InvalidDays(String),
+
+    /// The depth of a relation graph is not a number of hops from 1 to 3
+    InvalidDepth(String),
+
+    /// A custom field definition is malformed, or a value doesn't match its definition
+    InvalidCustomField(String),
+
+    /// The photo cannot be decoded, or the requested size of it is unknown
+    InvalidPhoto(String),
+
+    /// The photo storage cannot be read or written
+    Storage(String),
+
+    /// The contact is linked to an organization which doesn't exist
+    UnknownOrganization(i32),
+
+    /// The address book doesn't exist, or is not one the authenticated user may access
+    UnknownAddressBook(i32),
+
+    /// The entity conflicts with an existing one, e.g. an organization with the same domain
+    Conflict(String),
+
+    /// The response body cannot be encoded into the negotiated media type
+    Encoding(String),
 }
 
 impl Display for Error {
@@ -51,8 +155,25 @@ impl Display for Error {
                 "Invalid value provided for the HTTP Authorization header"
             ),
             Error::InvalidScheme(scheme) => {
-                write!(f, "The scheme provided ({}) is not Basic", scheme)
+                write!(f, "The scheme provided ({}) is not Basic or Bearer", scheme)
+            }
+            Error::Unauthorized => write!(f, "Missing or invalid credentials"),
+            Error::InvalidUsersFile(message) => write!(f, "Invalid API users file: {}", message),
+            Error::UnknownUser(username) => write!(f, "There is no API user {}", username),
+            Error::InvalidApiUser(message) => write!(f, "Invalid API user: {}", message),
+            Error::InvalidApiKey(message) => write!(f, "Invalid API key: {}", message),
+            Error::Forbidden(message) => write!(f, "Forbidden: {}", message),
+            Error::TooManyAttempts { retry_after } => write!(
+                f,
+                "Too many failed logins, retry after {} seconds",
+                retry_after
+            ),
+            Error::RateLimited { retry_after, .. } => {
+                write!(f, "Too many requests, retry after {} seconds", retry_after)
             }
+            Error::InvalidRateLimits(message) => write!(f, "Invalid rate limits: {}", message),
+            Error::InvalidToken(message) => write!(f, "Invalid token: {}", message),
+            Error::InvalidJwtKey(message) => write!(f, "Invalid JWT key: {}", message),
             Error::InvalidBase64Value(message) => {
                 write!(f, "The value have an invalid base64 encoding: {}", message)
             }
@@ -60,10 +181,59 @@ impl Display for Error {
             Error::ReqwestMiddleware(message) => {
                 write!(f, "The external validation api call failed {}", message)
             }
+            Error::InvalidCsv(message) => write!(f, "Invalid CSV: {}", message),
+            Error::InvalidVCard(message) => write!(f, "Invalid vCard: {}", message),
+            Error::InvalidXml(message) => write!(f, "Invalid XML: {}", message),
+            Error::InvalidSyncToken(token) => write!(f, "Invalid sync-token: {}", token),
+            Error::PreconditionFailed(message) => write!(f, "Precondition failed: {}", message),
+            Error::NotAcceptable(accept) => {
+                write!(
+                    f,
+                    "None of the accepted media types ({}) is supported",
+                    accept
+                )
+            }
+            Error::UnsupportedMediaType(content_type) => {
+                write!(f, "The media type ({}) is not supported", content_type)
+            }
+            Error::InvalidBody(message) => write!(f, "Invalid request body: {}", message),
+            Error::InvalidPhoneNumber(message) => write!(f, "Invalid phone number {}", message),
+            Error::InvalidCountry(country) => write!(
+                f,
+                "Invalid country {}, expected an ISO 3166 country code",
+                country
+            ),
+            Error::InvalidTag(tag) => write!(
+                f,
+                "Invalid tag {}, expected 1 to 64 letters, digits, spaces, '-', '_' or '.'",
+                tag
+            ),
+            Error::InvalidTagMode(tag_mode) => {
+                write!(f, "Invalid tag_mode {}, expected 'and' or 'or'", tag_mode)
+            }
+            Error::InvalidDays(days) => {
+                write!(f, "Invalid days {}, expected a number from 0 to 366", days)
+            }
+            Error::InvalidDepth(depth) => {
+                write!(f, "Invalid depth {}, expected a number from 1 to 3", depth)
+            }
+            Error::InvalidCustomField(message) => write!(f, "Invalid custom field: {}", message),
+            Error::InvalidPhoto(message) => write!(f, "Invalid photo: {}", message),
+            Error::Storage(message) => write!(f, "Photo storage error: {}", message),
+            Error::UnknownOrganization(id) => {
+                write!(f, "The organization with ID ({}) doesn't exist", id)
+            }
+            Error::UnknownAddressBook(id) => {
+                write!(f, "The address book with ID ({}) doesn't exist", id)
+            }
+            Error::Conflict(message) => write!(f, "Conflict: {}", message),
+            Error::Encoding(message) => write!(f, "The response cannot be encoded: {}", message),
         }
     }
 }
 
+impl std::error::Error for Error {}
+
 impl From<DecodeError> for Error {
     fn from(err: DecodeError) -> Self {
         Error::InvalidBase64Value(err.to_string())
@@ -76,9 +246,26 @@ impl From<FromUtf8Error> for Error {
     }
 }
 
+impl From<jsonwebtoken::errors::Error> for Error {
+    fn from(err: jsonwebtoken::errors::Error) -> Self {
+        Error::InvalidToken(err.to_string())
+    }
+}
+
 impl From<sqlx::Error> for Error {
     fn from(err: sqlx::Error) -> Self {
-        Error::Db(err.to_string())
+        match &err {
+            sqlx::Error::Database(db_err) if db_err.code().as_deref() == Some(UNIQUE_VIOLATION) => {
+                Error::Conflict(db_err.message().to_string())
+            }
+            _ => Error::Db(err.to_string()),
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Storage(err.to_string())
     }
 }
 
@@ -93,3 +280,15 @@ impl From<reqwest::Error> for Error {
         Error::ReqwestMiddleware(err.to_string())
     }
 }
+
+impl From<csv::Error> for Error {
+    fn from(err: csv::Error) -> Self {
+        Error::InvalidCsv(err.to_string())
+    }
+}
+
+impl From<roxmltree::Error> for Error {
+    fn from(err: roxmltree::Error) -> Self {
+        Error::InvalidXml(err.to_string())
+    }
+}
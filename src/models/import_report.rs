@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::contact::NewContact;
+
+/// A single record of an import payload, either parsed into a new contact or with the reasons it couldn't be.
+#[derive(Debug, Clone)]
+pub struct ImportRow {
+    pub row: u64,
+    pub new_contact: Result<NewContact, Vec<String>>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct ImportReport {
+    pub imported: u32,
+    pub failed: u32,
+    pub errors: Vec<ImportRowError>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ImportRowError {
+    pub row: u64,
+    pub messages: Vec<String>,
+}
+
+impl ImportReport {
+    pub fn add_success(&mut self) {
+        self.imported += 1;
+    }
+
+    pub fn add_failure(&mut self, row: u64, messages: Vec<String>) {
+        self.failed += 1;
+        self.errors.push(ImportRowError { row, messages });
+    }
+}
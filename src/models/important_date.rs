@@ -0,0 +1,120 @@
+use chrono::Datelike;
+use chrono::Duration;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::models::contact::Contact;
+
+/// The label of the birthday of a contact among its upcoming dates.
+pub const BIRTHDAY_LABEL: &str = "birthday";
+
+/// A labeled date recurring every year, e.g. a wedding anniversary.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ImportantDate {
+    pub label: String,
+    /// An ISO 8601 calendar date, e.g. `2015-06-20`.
+    pub date: NaiveDate,
+}
+
+/// A date of a contact, the birthday or a labeled one, recurring within the given window.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct UpcomingDate {
+    pub contact: Contact,
+    pub label: String,
+    pub date: NaiveDate,
+    /// When the date recurs next, today included.
+    pub next_date: NaiveDate,
+    pub days_until: i64,
+}
+
+/// The anniversary of a date in the given year, Feb 29 falling on Feb 28 in the common years.
+pub fn anniversary(date: NaiveDate, year: i32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, date.month(), date.day())
+        .or_else(|| NaiveDate::from_ymd_opt(year, 2, 28))
+        .unwrap_or(date)
+}
+
+/// The first anniversary of a date on or after the given day, wrapping around to the next year if needed.
+pub fn next_occurrence(date: NaiveDate, from: NaiveDate) -> NaiveDate {
+    let this_year: NaiveDate = anniversary(date, from.year());
+    if this_year >= from {
+        this_year
+    } else {
+        anniversary(date, from.year() + 1)
+    }
+}
+
+/// The dates of a contact recurring within `days` days from the given day, both ends included.
+pub fn upcoming_dates(contact: &Contact, from: NaiveDate, days: u32) -> Vec<UpcomingDate> {
+    let until: NaiveDate = from + Duration::days(days as i64);
+    contact
+        .birthday
+        .map(|birthday: NaiveDate| (BIRTHDAY_LABEL.to_string(), birthday))
+        .into_iter()
+        .chain(
+            contact
+                .dates
+                .iter()
+                .map(|x: &ImportantDate| (x.label.clone(), x.date)),
+        )
+        .map(|(label, date)| (label, date, next_occurrence(date, from)))
+        .filter(|(_, _, next_date)| *next_date <= until)
+        .map(|(label, date, next_date)| UpcomingDate {
+            contact: contact.clone(),
+            label,
+            date,
+            next_date,
+            days_until: (next_date - from).num_days(),
+        })
+        .collect()
+}
+
+/// Orders upcoming dates by when they recur, then by the name of the contact.
+pub fn sort_upcoming_dates(upcoming_dates: &mut [UpcomingDate]) {
+    upcoming_dates.sort_by(|a: &UpcomingDate, b: &UpcomingDate| {
+        (a.next_date, &a.contact.name, a.contact.id.0).cmp(&(
+            b.next_date,
+            &b.contact.name,
+            b.contact.id.0,
+        ))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_next_occurrence() {
+        // Later this year, today, and wrapped around to the next year
+        assert_eq!(
+            date(2023, 6, 20),
+            next_occurrence(date(1990, 6, 20), date(2023, 5, 14))
+        );
+        assert_eq!(
+            date(2023, 5, 14),
+            next_occurrence(date(1990, 5, 14), date(2023, 5, 14))
+        );
+        assert_eq!(
+            date(2024, 1, 3),
+            next_occurrence(date(1990, 1, 3), date(2023, 12, 20))
+        );
+        // Feb 29 falls on Feb 28 in the common years only
+        assert_eq!(
+            date(2023, 2, 28),
+            next_occurrence(date(2000, 2, 29), date(2023, 2, 1))
+        );
+        assert_eq!(
+            date(2024, 2, 29),
+            next_occurrence(date(2000, 2, 29), date(2023, 3, 1))
+        );
+        assert_eq!(
+            date(2024, 2, 29),
+            next_occurrence(date(2000, 2, 29), date(2024, 2, 29))
+        );
+    }
+}
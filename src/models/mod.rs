@@ -1,2 +1,17 @@
+pub mod address_book;
+pub mod api_key;
+pub mod api_user;
 pub mod contact;
+pub mod custom_field;
 pub mod errors;
+pub mod import_report;
+pub mod important_date;
+pub mod note;
+pub mod organization;
+pub mod phone_number;
+pub mod photo;
+pub mod relation;
+pub mod role;
+pub mod share;
+pub mod tag;
+pub mod token;
@@ -0,0 +1,25 @@
+use chrono::DateTime;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::models::contact::ContactId;
+
+/// A timestamped note on a contact, e.g. the summary of a call, written by an API user.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Note {
+    pub id: NoteId,
+    pub contact_id: ContactId,
+    /// The username of the author.
+    pub author: String,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NoteId(pub i32);
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct NewNote {
+    pub body: String,
+}
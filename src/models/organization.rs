@@ -0,0 +1,58 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::contact::Address;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Organization {
+    pub id: OrganizationId,
+    pub name: String,
+    /// The email domain of the organization, e.g. `example.com`, lower case.
+    #[serde(default)]
+    pub domain: Option<String>,
+    #[serde(default)]
+    pub address: Option<Address>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OrganizationId(pub i32);
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct NewOrganization {
+    pub name: String,
+    #[serde(default)]
+    pub domain: Option<String>,
+    #[serde(default)]
+    pub address: Option<Address>,
+}
+
+impl NewOrganization {
+    /// Trims the domain and makes it lower case, dropping it if empty.
+    pub fn normalized(self) -> Self {
+        NewOrganization {
+            domain: self
+                .domain
+                .map(|domain: String| domain.trim().to_ascii_lowercase())
+                .filter(|domain: &String| !domain.is_empty()),
+            ..self
+        }
+    }
+}
+
+/// Where a contact works: the organization, whose name is read only, and the job title.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Affiliation {
+    pub id: OrganizationId,
+    /// The name of the organization. Ignored when linking a contact to an organization.
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub job_title: Option<String>,
+}
+
+/// Returns the domain of an email address, lower case, e.g. `example.com` for `john@Example.com`.
+pub fn email_domain(email: &str) -> Option<String> {
+    email
+        .rsplit_once('@')
+        .map(|(_, domain)| domain.trim().to_ascii_lowercase())
+        .filter(|domain: &String| !domain.is_empty())
+}
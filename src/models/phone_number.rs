@@ -0,0 +1,168 @@
+use std::env;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use phonenumber::country;
+use phonenumber::Mode;
+use serde::de::Visitor;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+
+use crate::models::errors::Error;
+
+const DEFAULT_PHONE_REGION_KEY: &str = "DEFAULT_PHONE_REGION";
+const DEFAULT_PHONE_REGION: &str = "DE";
+const EXTENSION_SEPARATOR: &str = ";ext=";
+
+/// A phone number normalized to E.164, e.g. `+4915112345678`, with an optional extension.
+/// It is stored and serialized as text, the extension in the RFC 3966 style: `+4930123456;ext=12`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PhoneNumber {
+    e164: String,
+    extension: Option<String>,
+    national: String,
+}
+
+impl PhoneNumber {
+    /// Parses a phone number in any common format. Numbers without a `+` or `00` prefix
+    /// are read as national numbers of the `DEFAULT_PHONE_REGION` (ISO 3166 code, `DE` by default).
+    pub fn parse(value: &str) -> Result<Self, Error> {
+        let value: &str = value.trim();
+        let value: &str = value.strip_prefix("tel:").unwrap_or(value);
+        let (value, extension): (&str, Option<&str>) = match value.split_once(EXTENSION_SEPARATOR) {
+            Some((value, extension)) => (value, Some(extension.trim())),
+            None => (value, None),
+        };
+        let number: phonenumber::PhoneNumber = phonenumber::parse(default_region(), value)
+            .map_err(|err| Error::InvalidPhoneNumber(format!("{}: {}", value, err)))?;
+        let extension: Option<String> = match extension {
+            Some(extension)
+                if !extension.is_empty() && extension.chars().all(|c: char| c.is_ascii_digit()) =>
+            {
+                Some(extension.to_string())
+            }
+            Some(extension) => {
+                return Err(Error::InvalidPhoneNumber(format!(
+                    "{}: invalid extension {}",
+                    value, extension
+                )))
+            }
+            None => number.extension().map(|x| x.to_string()),
+        };
+        let national: String = number.format().mode(Mode::National).to_string();
+        let national: String = match (&extension, number.extension()) {
+            (Some(extension), None) => format!("{} ext. {}", national, extension),
+            _ => national,
+        };
+        Ok(PhoneNumber {
+            e164: number.format().mode(Mode::E164).to_string(),
+            extension,
+            national,
+        })
+    }
+
+    /// The number in E.164, without the extension.
+    pub fn e164(&self) -> &str {
+        &self.e164
+    }
+
+    /// The number in the national format of its country, for display, e.g. `01511 2345678`.
+    pub fn national(&self) -> &str {
+        &self.national
+    }
+}
+
+impl FromStr for PhoneNumber {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PhoneNumber::parse(s)
+    }
+}
+
+/// The number in E.164, followed by the extension, if any.
+impl Display for PhoneNumber {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match &self.extension {
+            Some(extension) => write!(f, "{}{}{}", self.e164, EXTENSION_SEPARATOR, extension),
+            None => write!(f, "{}", self.e164),
+        }
+    }
+}
+
+impl Serialize for PhoneNumber {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for PhoneNumber {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(PhoneNumberVisitor)
+    }
+}
+
+/// Reads a phone number out of a string or, as the API used to take them, out of an integer
+/// holding the country code and the number without the leading `+`.
+struct PhoneNumberVisitor;
+
+impl<'de> Visitor<'de> for PhoneNumberVisitor {
+    type Value = PhoneNumber;
+
+    fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "a phone number, preferably in E.164")
+    }
+
+    fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        PhoneNumber::parse(value).map_err(E::custom)
+    }
+
+    fn visit_i64<E: serde::de::Error>(self, value: i64) -> Result<Self::Value, E> {
+        PhoneNumber::parse(&format!("+{}", value)).map_err(E::custom)
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<Self::Value, E> {
+        PhoneNumber::parse(&format!("+{}", value)).map_err(E::custom)
+    }
+}
+
+fn default_region() -> Option<country::Id> {
+    static DEFAULT_REGION: OnceLock<Option<country::Id>> = OnceLock::new();
+    *DEFAULT_REGION.get_or_init(|| {
+        env::var(DEFAULT_PHONE_REGION_KEY)
+            .unwrap_or(DEFAULT_PHONE_REGION.to_string())
+            .trim()
+            .to_ascii_uppercase()
+            .parse::<country::Id>()
+            .ok()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let number: PhoneNumber = PhoneNumber::parse("0151 12345678").unwrap();
+        assert_eq!("+4915112345678", number.e164());
+        assert_eq!(number, PhoneNumber::parse("+49 (151) 123-45678").unwrap());
+        assert_eq!(number, PhoneNumber::parse("004915112345678").unwrap());
+
+        let number: PhoneNumber = PhoneNumber::parse("tel:+49-30-123456;ext=12").unwrap();
+        assert_eq!("+4930123456;ext=12", number.to_string());
+        assert!(number.national().starts_with("030"));
+        assert_eq!(number, PhoneNumber::parse(&number.to_string()).unwrap());
+
+        assert!(PhoneNumber::parse("n/a").is_err());
+        let number: PhoneNumber = serde_json::from_str("4915112345678").unwrap();
+        assert_eq!(
+            "\"+4915112345678\"",
+            serde_json::to_string(&number).unwrap()
+        );
+    }
+}
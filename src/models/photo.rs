@@ -0,0 +1,188 @@
+use std::io::Cursor;
+use std::str::FromStr;
+
+use image::io::Limits;
+use image::io::Reader;
+use image::DynamicImage;
+use image::ImageFormat;
+use sha2::Digest;
+use sha2::Sha256;
+
+use crate::models::errors::Error;
+
+/// The thumbnails fit into a square of this many pixels, keeping the aspect ratio.
+const THUMBNAIL_SIZE: u32 = 128;
+/// The photos may be at most this many pixels wide and high, so that decoding a small upload can't take up gigabytes.
+const MAX_PHOTO_DIMENSION: u32 = 4096;
+/// What decoding a photo may allocate at most, in bytes: a photo of the max dimensions, as RGBA, and then some.
+const MAX_PHOTO_ALLOCATION: u64 = 128 * 1024 * 1024;
+const JPEG_MAGIC_BYTES: &[u8] = &[0xFF, 0xD8, 0xFF];
+const PNG_MAGIC_BYTES: &[u8] = &[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+/// The image formats accepted for photos, told apart by their magic bytes rather than by the `Content-Type` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageType {
+    Jpeg,
+    Png,
+}
+
+impl ImageType {
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(JPEG_MAGIC_BYTES) {
+            Some(ImageType::Jpeg)
+        } else if bytes.starts_with(PNG_MAGIC_BYTES) {
+            Some(ImageType::Png)
+        } else {
+            None
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ImageType::Jpeg => "image/jpeg",
+            ImageType::Png => "image/png",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageType::Jpeg => "jpg",
+            ImageType::Png => "png",
+        }
+    }
+
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        [ImageType::Jpeg, ImageType::Png]
+            .into_iter()
+            .find(|image_type: &ImageType| image_type.content_type() == content_type)
+    }
+
+    fn format(&self) -> ImageFormat {
+        match self {
+            ImageType::Jpeg => ImageFormat::Jpeg,
+            ImageType::Png => ImageFormat::Png,
+        }
+    }
+}
+
+/// Which rendition of a photo to serve: the uploaded image or its thumbnail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PhotoSize {
+    #[default]
+    Original,
+    Thumbnail,
+}
+
+impl FromStr for PhotoSize {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "original" => Ok(PhotoSize::Original),
+            "thumbnail" => Ok(PhotoSize::Thumbnail),
+            other => Err(Error::InvalidPhoto(format!(
+                "Unknown size {other}, expected 'original' or 'thumbnail'"
+            ))),
+        }
+    }
+}
+
+/// A rendition of the photo of a contact.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Photo {
+    pub image_type: ImageType,
+    pub bytes: Vec<u8>,
+}
+
+impl Photo {
+    /// A strong entity tag of the image, e.g. `"5d41402abc4b2a76b9719d911017c592"`.
+    pub fn etag(&self) -> String {
+        let digest = Sha256::digest(&self.bytes);
+        format!("\"{}\"", hex::encode(&digest[..16]))
+    }
+}
+
+/// The photo of a contact, as uploaded, along with its thumbnail, of the same image type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContactPhoto {
+    pub original: Photo,
+    pub thumbnail: Photo,
+}
+
+impl ContactPhoto {
+    /// Checks an upload is a JPEG or PNG image that can be decoded within the limits, then makes its thumbnail,
+    /// off the async runtime as both take a while.
+    pub async fn from_upload(bytes: Vec<u8>) -> Result<Self, Error> {
+        let image_type: ImageType = ImageType::detect(&bytes).ok_or_else(|| {
+            Error::UnsupportedMediaType("Photos have to be JPEG or PNG images".to_string())
+        })?;
+        tokio::task::spawn_blocking(move || ContactPhoto::decode(bytes, image_type))
+            .await
+            .map_err(|err| Error::InvalidPhoto(err.to_string()))?
+    }
+
+    fn decode(bytes: Vec<u8>, image_type: ImageType) -> Result<Self, Error> {
+        let mut limits: Limits = Limits::default();
+        limits.max_image_width = Some(MAX_PHOTO_DIMENSION);
+        limits.max_image_height = Some(MAX_PHOTO_DIMENSION);
+        limits.max_alloc = Some(MAX_PHOTO_ALLOCATION);
+        let mut reader: Reader<Cursor<&[u8]>> =
+            Reader::with_format(Cursor::new(&bytes), image_type.format());
+        reader.limits(limits);
+        let image: DynamicImage = reader
+            .decode()
+            .map_err(|err: image::ImageError| Error::InvalidPhoto(err.to_string()))?;
+        let mut thumbnail: Cursor<Vec<u8>> = Cursor::new(vec![]);
+        image
+            .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+            .write_to(&mut thumbnail, image_type.format())
+            .map_err(|err: image::ImageError| Error::InvalidPhoto(err.to_string()))?;
+        Ok(ContactPhoto {
+            original: Photo { image_type, bytes },
+            thumbnail: Photo {
+                image_type,
+                bytes: thumbnail.into_inner(),
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbImage;
+
+    #[tokio::test]
+    async fn test_from_upload() {
+        let mut png: Cursor<Vec<u8>> = Cursor::new(vec![]);
+        DynamicImage::ImageRgb8(RgbImage::new(512, 256))
+            .write_to(&mut png, ImageFormat::Png)
+            .unwrap();
+
+        let photo: ContactPhoto = ContactPhoto::from_upload(png.get_ref().clone())
+            .await
+            .unwrap();
+
+        assert_eq!(ImageType::Png, photo.thumbnail.image_type);
+        let thumbnail: DynamicImage = image::load_from_memory(&photo.thumbnail.bytes).unwrap();
+        assert_eq!((128, 64), (thumbnail.width(), thumbnail.height()));
+        assert!(matches!(
+            ContactPhoto::from_upload(b"GIF89a".to_vec()).await,
+            Err(Error::UnsupportedMediaType(_))
+        ));
+        assert!(matches!(
+            ContactPhoto::from_upload(png.get_ref()[..32].to_vec()).await,
+            Err(Error::InvalidPhoto(_))
+        ));
+
+        // Too wide to be decoded, however small the upload
+        let mut wide: Cursor<Vec<u8>> = Cursor::new(vec![]);
+        DynamicImage::ImageRgb8(RgbImage::new(MAX_PHOTO_DIMENSION + 1, 1))
+            .write_to(&mut wide, ImageFormat::Png)
+            .unwrap();
+        assert!(matches!(
+            ContactPhoto::from_upload(wide.into_inner()).await,
+            Err(Error::InvalidPhoto(_))
+        ));
+    }
+}
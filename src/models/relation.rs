@@ -0,0 +1,100 @@
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::contact::ContactId;
+use crate::models::errors::Error;
+
+/// A typed edge between two contacts, e.g. `{"contact_id": 1, "related_id": 2, "type": "reports_to"}` for 1 reports to 2.
+/// A bidirectional relation holds the other way around too.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Relation {
+    pub id: RelationId,
+    pub contact_id: ContactId,
+    pub related_id: ContactId,
+    #[serde(rename = "type")]
+    pub relation_type: RelationType,
+    pub bidirectional: bool,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RelationId(pub i32);
+
+/// A relation from the contact given by the path to another one.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct NewRelation {
+    pub related_id: ContactId,
+    #[serde(rename = "type")]
+    pub relation_type: RelationType,
+    #[serde(default)]
+    pub bidirectional: bool,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RelationType {
+    AssistantOf,
+    /// Always bidirectional.
+    SpouseOf,
+    ReportsTo,
+}
+
+impl RelationType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RelationType::AssistantOf => "assistant_of",
+            RelationType::SpouseOf => "spouse_of",
+            RelationType::ReportsTo => "reports_to",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, Error> {
+        match value {
+            "assistant_of" => Ok(RelationType::AssistantOf),
+            "spouse_of" => Ok(RelationType::SpouseOf),
+            "reports_to" => Ok(RelationType::ReportsTo),
+            other => Err(Error::Db(format!("Unknown relation type {other}"))),
+        }
+    }
+
+    fn is_symmetric(&self) -> bool {
+        *self == RelationType::SpouseOf
+    }
+}
+
+impl NewRelation {
+    /// Makes the relations of the symmetric types bidirectional.
+    pub fn normalized(self) -> Self {
+        NewRelation {
+            bidirectional: self.bidirectional || self.relation_type.is_symmetric(),
+            ..self
+        }
+    }
+}
+
+/// A contact of a relation graph.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct RelationNode {
+    pub id: ContactId,
+    pub name: String,
+}
+
+/// The contacts reachable from a contact through its relations, in either direction, within a number of hops,
+/// along with the relations between them.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct RelationGraph {
+    pub nodes: Vec<RelationNode>,
+    pub edges: Vec<Relation>,
+}
+
+/// The ids of the contacts at the other end of the given relations, sorted, leaving out the already visited ones.
+pub fn next_hop(relations: &[Relation], visited: &BTreeSet<i32>) -> Vec<ContactId> {
+    relations
+        .iter()
+        .flat_map(|relation: &Relation| [relation.contact_id.0, relation.related_id.0])
+        .filter(|id: &i32| !visited.contains(id))
+        .collect::<BTreeSet<i32>>()
+        .into_iter()
+        .map(ContactId)
+        .collect()
+}
@@ -0,0 +1,67 @@
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::errors::Error;
+
+/// The role of an API user. Each role is allowed to do whatever the ones before it are:
+/// a `reader` reads the contacts, an `editor` also adds and updates them and an `admin` also deletes them.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    Reader,
+    Editor,
+    Admin,
+}
+
+impl Role {
+    /// Whether a user having this role may do what the given role is required for.
+    pub fn allows(&self, required: Role) -> bool {
+        *self >= required
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Reader => "reader",
+            Role::Editor => "editor",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+impl Display for Role {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Role {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reader" => Ok(Role::Reader),
+            "editor" => Ok(Role::Editor),
+            "admin" => Ok(Role::Admin),
+            _ => Err(Error::InvalidApiUser(format!(
+                "unknown role {s}, expected reader, editor or admin"
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allows() {
+        assert!(Role::Admin.allows(Role::Reader));
+        assert!(Role::Admin.allows(Role::Admin));
+        assert!(Role::Editor.allows(Role::Editor));
+        assert!(!Role::Editor.allows(Role::Admin));
+        assert!(!Role::Reader.allows(Role::Editor));
+    }
+}
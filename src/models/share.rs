@@ -0,0 +1,171 @@
+use chrono::DateTime;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::models::address_book::AddressBookId;
+use crate::models::errors::Error;
+use crate::models::role::Role;
+
+const MAX_USERNAME_LENGTH: usize = 64;
+
+/// A grant of access to an address book for another user or for a group of users,
+/// e.g. `{"id": 1, "address_book_id": 1, "user": "alice", "permission": "read", "created_at": "..."}`.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Share {
+    pub id: ShareId,
+    pub address_book_id: AddressBookId,
+    #[serde(flatten)]
+    pub grantee: Grantee,
+    pub permission: SharePermission,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ShareId(pub i32);
+
+/// A grant for the address book given by the path, e.g. `{"group": "editor", "permission": "write"}`.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct NewShare {
+    #[serde(flatten)]
+    pub grantee: Grantee,
+    pub permission: SharePermission,
+}
+
+/// Who an address book is shared with: a user, by username, or a group, being all the users having a role.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Grantee {
+    User(String),
+    Group(Role),
+}
+
+/// A `read` grant allows what a `reader` may do in the address book, a `write` one what an `editor` may do.
+/// Deleting the contacts, as well as renaming, deleting and sharing the address book, is left to its owner.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum SharePermission {
+    Read,
+    Write,
+}
+
+impl Grantee {
+    /// Either `user` or `group`, as stored.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Grantee::User(_) => "user",
+            Grantee::Group(_) => "group",
+        }
+    }
+
+    /// The username of the user, or the role of the group.
+    pub fn name(&self) -> &str {
+        match self {
+            Grantee::User(username) => username,
+            Grantee::Group(role) => role.as_str(),
+        }
+    }
+
+    pub fn parse(kind: &str, name: &str) -> Result<Self, Error> {
+        match kind {
+            "user" => Ok(Grantee::User(name.to_string())),
+            "group" => name
+                .parse::<Role>()
+                .map(Grantee::Group)
+                .map_err(|_| Error::Db(format!("Unknown group {name}"))),
+            other => Err(Error::Db(format!("Unknown grantee kind {other}"))),
+        }
+    }
+
+    /// Whether the grant is for the given user, having the given role, if any.
+    pub fn includes(&self, username: &str, role: Option<Role>) -> bool {
+        match self {
+            Grantee::User(grantee) => grantee == username,
+            Grantee::Group(group) => role == Some(*group),
+        }
+    }
+}
+
+impl SharePermission {
+    /// Whether the grant allows what the given role is required for.
+    pub fn allows(&self, required: Role) -> bool {
+        let role: Role = match self {
+            SharePermission::Read => Role::Reader,
+            SharePermission::Write => Role::Editor,
+        };
+        role.allows(required)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SharePermission::Read => "read",
+            SharePermission::Write => "write",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, Error> {
+        match value {
+            "read" => Ok(SharePermission::Read),
+            "write" => Ok(SharePermission::Write),
+            other => Err(Error::Db(format!("Unknown share permission {other}"))),
+        }
+    }
+}
+
+impl NewShare {
+    /// Trims the username of the grantee, then rejects it if empty or too long.
+    pub fn validated(self) -> Result<Self, Error> {
+        match self.grantee {
+            Grantee::User(username) => {
+                let username: String = username.trim().to_string();
+                if username.is_empty() || username.chars().count() > MAX_USERNAME_LENGTH {
+                    return Err(Error::InvalidBody(format!(
+                        "The username of a grantee must have 1 to {MAX_USERNAME_LENGTH} characters"
+                    )));
+                }
+                Ok(NewShare {
+                    grantee: Grantee::User(username),
+                    ..self
+                })
+            }
+            Grantee::Group(_) => Ok(self),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_share_json() {
+        let new_share: NewShare =
+            serde_json::from_str(r#"{"group": "editor", "permission": "write"}"#).unwrap();
+        assert_eq!(Grantee::Group(Role::Editor), new_share.grantee);
+        assert_eq!(SharePermission::Write, new_share.permission);
+
+        let new_share: NewShare =
+            serde_json::from_str(r#"{"user": " alice ", "permission": "read"}"#).unwrap();
+        assert_eq!(
+            Grantee::User("alice".to_string()),
+            new_share.validated().unwrap().grantee
+        );
+        assert!(
+            serde_json::from_str::<NewShare>(r#"{"group": "sales", "permission": "read"}"#)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_grants() {
+        let group: Grantee = Grantee::Group(Role::Editor);
+        assert!(group.includes("alice", Some(Role::Editor)));
+        assert!(!group.includes("alice", Some(Role::Admin)));
+        assert!(!group.includes("alice", None));
+        assert!(Grantee::User("alice".to_string()).includes("alice", None));
+
+        assert!(SharePermission::Read.allows(Role::Reader));
+        assert!(!SharePermission::Read.allows(Role::Editor));
+        assert!(SharePermission::Write.allows(Role::Editor));
+        assert!(!SharePermission::Write.allows(Role::Admin));
+    }
+}
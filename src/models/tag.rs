@@ -0,0 +1,124 @@
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::str::FromStr;
+
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+
+use crate::models::errors::Error;
+
+const MAX_TAG_LENGTH: usize = 64;
+const TAG_MODE_AND: &str = "and";
+const TAG_MODE_OR: &str = "or";
+
+/// A tag categorizing contacts, e.g. `family`. Tags are case-insensitive, so they are kept lower case.
+/// Made of 1 to 64 letters, digits, spaces, `-`, `_` or `.`, so it never clashes with the CSV and vCard separators.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Tag(String);
+
+impl Tag {
+    pub fn parse(value: &str) -> Result<Self, Error> {
+        let value: String = value.trim().to_lowercase();
+        let is_valid: bool = !value.is_empty()
+            && value.chars().count() <= MAX_TAG_LENGTH
+            && value
+                .chars()
+                .all(|c: char| c.is_alphanumeric() || [' ', '-', '_', '.'].contains(&c));
+        if is_valid {
+            Ok(Tag(value))
+        } else {
+            Err(Error::InvalidTag(value))
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for Tag {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Tag::parse(s)
+    }
+}
+
+impl Display for Tag {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Serialize for Tag {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Tag {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value: String = String::deserialize(deserializer)?;
+        Tag::parse(&value).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Sorts the tags of a contact and drops the duplicates.
+pub fn unique_sorted(mut tags: Vec<Tag>) -> Vec<Tag> {
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+/// A tag along with the number of contacts tagged with it.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct TagCount {
+    pub tag: Tag,
+    pub count: i64,
+}
+
+/// Whether a contact has to have all the tags filtered by (`and`) or any of them (`or`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TagMode {
+    #[default]
+    And,
+    Or,
+}
+
+impl FromStr for TagMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            TAG_MODE_AND => Ok(TagMode::And),
+            TAG_MODE_OR => Ok(TagMode::Or),
+            other => Err(Error::InvalidTagMode(other.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            "best friends",
+            Tag::parse(" Best Friends ").unwrap().as_str()
+        );
+        assert!(Tag::parse("").is_err());
+        assert!(Tag::parse("friends;work").is_err());
+        assert!(Tag::parse(&"x".repeat(65)).is_err());
+        assert_eq!(
+            vec![Tag::parse("a").unwrap(), Tag::parse("b").unwrap()],
+            unique_sorted(vec![
+                Tag::parse("b").unwrap(),
+                Tag::parse("A").unwrap(),
+                Tag::parse("a").unwrap(),
+            ])
+        );
+    }
+}
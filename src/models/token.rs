@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::api_key::ApiKeyScope;
+use crate::models::role::Role;
+
+/// The prefix of the usernames of the users of the identity provider, e.g. `oauth2:alice`,
+/// never clashing with the usernames of the API users, as those have no `:`.
+const OAUTH2_USERNAME_PREFIX: &str = "oauth2:";
+
+/// The token type of the issued access tokens, to be sent back as `Authorization: Bearer <access_token>`.
+pub const BEARER_TOKEN_TYPE: &str = "Bearer";
+
+/// The tokens issued to an API user, in the shape of an OAuth 2.0 token response.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    /// The lifetime of the access token, in seconds.
+    pub expires_in: u64,
+    /// Trades for a new pair of tokens at `POST /auth/refresh` once the access token expires.
+    pub refresh_token: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct RefreshTokenRequest {
+    pub refresh_token: String,
+}
+
+/// An access token issued by the identity provider of the company, as validated in the OAuth 2.0 resource-server mode.
+/// Unlike the API users, its user needs not be known to the API: its scopes tell what it may do.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct AccessToken {
+    /// The user the token was issued to, as told by the username claim, e.g. `sub`.
+    pub subject: String,
+    /// The user as known to the API, e.g. owning address books: the subject, prefixed with `oauth2:`.
+    pub username: String,
+    pub scopes: Vec<ApiKeyScope>,
+}
+
+impl AccessToken {
+    pub fn new(subject: &str, scopes: Vec<ApiKeyScope>) -> Self {
+        AccessToken {
+            subject: subject.to_string(),
+            username: format!("{OAUTH2_USERNAME_PREFIX}{subject}"),
+            scopes,
+        }
+    }
+
+    /// Whether the token may be used to do what the given role is required for, the same as an API key having its scopes.
+    pub fn allows(&self, required: Role) -> bool {
+        ApiKeyScope::for_role(required)
+            .map(|scope: ApiKeyScope| self.scopes.contains(&scope))
+            .unwrap_or(false)
+    }
+}
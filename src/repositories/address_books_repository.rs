@@ -0,0 +1,56 @@
+use async_trait::async_trait;
+
+use crate::models::address_book::AddressBook;
+use crate::models::address_book::AddressBookId;
+use crate::models::address_book::NewAddressBook;
+use crate::models::errors::Error;
+use crate::models::role::Role;
+use crate::models::share::NewShare;
+use crate::models::share::Share;
+use crate::models::share::ShareId;
+
+/// Contract for an Address books repository.
+/// Implemented by the contacts repositories, as the contacts are deleted along with their address book.
+#[async_trait]
+pub trait AddressBooksRepository {
+    /// Returns the address books owned by the given user, ordered by id.
+    async fn get_address_books(&self, owner_id: &str) -> Result<Vec<AddressBook>, Error>;
+
+    /// Returns the address books shared with the given user, or with the group of its role, if any,
+    /// other than its own ones, ordered by id.
+    async fn get_shared_address_books(
+        &self,
+        username: &str,
+        role: Option<Role>,
+    ) -> Result<Vec<AddressBook>, Error>;
+
+    /// Return a single address book, if found, otherwise None.
+    async fn get_address_book(&self, id: AddressBookId) -> Result<Option<AddressBook>, Error>;
+
+    /// Adds an address book owned by the given user. Returns the new address book.
+    async fn add_address_book(
+        &mut self,
+        owner_id: &str,
+        new_address_book: NewAddressBook,
+    ) -> Result<AddressBook, Error>;
+
+    /// Renames an address book. Doesn't return anything. Safe for no-ops.
+    async fn update_address_book(
+        &mut self,
+        id: AddressBookId,
+        address_book: NewAddressBook,
+    ) -> Result<(), Error>;
+
+    /// Deletes an address book along with its contacts, organizations and shares. Doesn't return anything. Safe for no-ops.
+    async fn delete_address_book(&mut self, id: AddressBookId) -> Result<(), Error>;
+
+    /// Returns the shares of an address book, ordered by id.
+    async fn get_shares(&self, id: AddressBookId) -> Result<Vec<Share>, Error>;
+
+    /// Shares an address book. Returns the new share.
+    /// Fails with `Error::Conflict` if the address book is already shared with the grantee.
+    async fn add_share(&mut self, id: AddressBookId, new_share: NewShare) -> Result<Share, Error>;
+
+    /// Revokes a share of an address book. Doesn't return anything. Safe for no-ops.
+    async fn delete_share(&mut self, id: AddressBookId, share_id: ShareId) -> Result<(), Error>;
+}
@@ -0,0 +1,29 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::models::api_key::ApiKey;
+use crate::models::api_key::ApiKeyId;
+use crate::models::api_key::NewApiKey;
+use crate::models::errors::Error;
+
+/// The API keys store shared by the routes managing the keys.
+pub type SharedApiKeysRepository = Arc<dyn ApiKeysRepository + Send + Sync>;
+
+/// Contract for managing the API keys of the services, the ones the `AuthMiddleware` authenticates by `X-Api-Key`.
+#[async_trait]
+pub trait ApiKeysRepository {
+    /// Returns all the API keys, ordered by ID.
+    async fn get_api_keys(&self) -> Result<Vec<ApiKey>, Error>;
+
+    /// Adds a new key, stored by its hash only, returning it.
+    async fn add_api_key(
+        &self,
+        new_api_key: NewApiKey,
+        key_hash: String,
+        created_by: &str,
+    ) -> Result<ApiKey, Error>;
+
+    /// Revokes a key, deleting it. Returns `Error::NotFound` if there is no such key.
+    async fn delete_api_key(&self, id: ApiKeyId) -> Result<(), Error>;
+}
@@ -0,0 +1,29 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::models::api_user::ApiUser;
+use crate::models::api_user::NewApiUser;
+use crate::models::errors::Error;
+
+/// The API users store shared by the routes managing the users.
+pub type SharedApiUsersRepository = Arc<dyn ApiUsersRepository + Send + Sync>;
+
+/// Contract for managing the API users, the ones the `AuthMiddleware` authenticates.
+#[async_trait]
+pub trait ApiUsersRepository {
+    /// Returns all the API users, ordered by username.
+    async fn get_api_users(&self) -> Result<Vec<ApiUser>, Error>;
+
+    /// Adds a new user, returning it. Returns `Error::Conflict` if the username is taken.
+    async fn add_api_user(&self, new_api_user: NewApiUser) -> Result<ApiUser, Error>;
+
+    /// Disables or re-enables a user. Returns `Error::UnknownUser` if there is no such user.
+    async fn set_api_user_disabled(&self, username: &str, disabled: bool) -> Result<(), Error>;
+
+    /// Replaces the password of a user. Returns `Error::UnknownUser` if there is no such user.
+    async fn set_api_user_password(&self, username: &str, password: &str) -> Result<(), Error>;
+
+    /// Deletes a user. Returns `Error::UnknownUser` if there is no such user.
+    async fn delete_api_user(&self, username: &str) -> Result<(), Error>;
+}
@@ -1,32 +1,161 @@
 use std::env;
 
+use async_stream::try_stream;
 use async_trait::async_trait;
+use chrono::DateTime;
+use chrono::NaiveDate;
+use chrono::Utc;
+use futures::stream::BoxStream;
+use futures::TryStreamExt;
+use isocountry::CountryCode;
+use serde_json::Value;
+use sqlx::pool::PoolConnection;
 use sqlx::postgres::PgPoolOptions;
 use sqlx::postgres::PgRow;
+use sqlx::types::Json;
 use sqlx::Connection;
 use sqlx::PgConnection;
 use sqlx::Pool;
 use sqlx::Postgres;
 use sqlx::Row;
+use sqlx::Transaction;
 
+use crate::middleware::auth_db::AuthDbMiddleware;
+use crate::models::address_book::AddressBook;
+use crate::models::address_book::AddressBookId;
+use crate::models::address_book::NewAddressBook;
+use crate::models::contact::with_single_primary;
+use crate::models::contact::Address;
 use crate::models::contact::Contact;
+use crate::models::contact::ContactChanges;
+use crate::models::contact::ContactFilter;
 use crate::models::contact::ContactId;
+use crate::models::contact::Email;
+use crate::models::contact::EmailType;
 use crate::models::contact::NewContact;
+use crate::models::contact::Phone;
+use crate::models::contact::PhoneType;
+use crate::models::custom_field::validated;
+use crate::models::custom_field::CustomField;
+use crate::models::custom_field::CustomFieldType;
+use crate::models::custom_field::CustomFieldValues;
 use crate::models::errors::Error;
+use crate::models::important_date::ImportantDate;
+use crate::models::important_date::UpcomingDate;
+use crate::models::important_date::BIRTHDAY_LABEL;
+use crate::models::note::NewNote;
+use crate::models::note::Note;
+use crate::models::note::NoteId;
+use crate::models::organization::Affiliation;
+use crate::models::organization::NewOrganization;
+use crate::models::organization::Organization;
+use crate::models::organization::OrganizationId;
+use crate::models::phone_number::PhoneNumber;
+use crate::models::relation::NewRelation;
+use crate::models::relation::Relation;
+use crate::models::relation::RelationId;
+use crate::models::relation::RelationType;
+use crate::models::role::Role;
+use crate::models::share::Grantee;
+use crate::models::share::NewShare;
+use crate::models::share::Share;
+use crate::models::share::ShareId;
+use crate::models::share::SharePermission;
+use crate::models::tag::unique_sorted;
+use crate::models::tag::Tag;
+use crate::models::tag::TagCount;
+use crate::models::tag::TagMode;
 
+use super::address_books_repository::AddressBooksRepository;
 use super::contacts_repository::get_limit_and_offset;
 use super::contacts_repository::ContactsRepository;
+use super::custom_fields_repository::CustomFieldsRepository;
+use super::notes_repository::NotesRepository;
+use super::organizations_repository::OrganizationsRepository;
+use super::photo_db_storage::PhotoDbStorage;
+use super::relations_repository::RelationsRepository;
 
 const DATABASE_URL_KEY: &str = "DATABASE_URL";
 const MAX_CONNECTIONS: u32 = 5;
 
-const SQL_SELECT_PAGE: &str = "SELECT id, name, phone_no, email FROM contacts LIMIT $1 OFFSET $2;";
-const SQL_SELECT_ONE: &str = "SELECT id, name, phone_no, email FROM contacts WHERE id = $1;";
-const SQL_INSERT: &str = "INSERT INTO contacts(name, phone_no, email) VALUES ($1, $2, $3) RETURNING id, name, phone_no, email;";
-const SQL_UPDATE: &str = "UPDATE contacts SET name = $1, phone_no = $2, email = $3 WHERE id = $4;";
-const SQL_UPDATE_EMAIL: &str = "UPDATE contacts SET email = $1 WHERE id = $2;";
-const SQL_UPDATE_PHONE_NO: &str = "UPDATE contacts SET phone_no = $1 WHERE id = $2;";
-const SQL_DELETE: &str = "DELETE FROM contacts WHERE id = $1;";
+const SQL_SELECT_PAGE: &str = "SELECT id, name, phones, emails, addresses, tags, organization, custom_fields, birthday, dates FROM contacts_view v WHERE (($3::VARCHAR IS NULL AND $4::VARCHAR IS NULL) OR EXISTS (SELECT 1 FROM addresses a WHERE a.contact_id = v.id AND ($3::VARCHAR IS NULL OR a.country = $3) AND ($4::VARCHAR IS NULL OR lower(a.locality) = lower($4)))) AND (cardinality($5::VARCHAR[]) = 0 OR (SELECT COUNT(*) FROM contact_tags ct JOIN tags t ON t.id = ct.tag_id WHERE ct.contact_id = v.id AND t.name = ANY($5::VARCHAR[])) >= CASE WHEN $6::BOOLEAN THEN cardinality($5::VARCHAR[]) ELSE 1 END) AND v.custom_fields @> $7::JSONB AND v.address_book_id = $8 ORDER BY id LIMIT $1 OFFSET $2;";
+const SQL_SELECT_ALL: &str =
+    "SELECT id, name, phones, emails, addresses, tags, organization, custom_fields, birthday, dates FROM contacts_view WHERE address_book_id = $1 ORDER BY id;";
+const SQL_SELECT_ONE: &str =
+    "SELECT id, name, phones, emails, addresses, tags, organization, custom_fields, birthday, dates FROM contacts_view WHERE address_book_id = $1 AND id = $2;";
+const SQL_INSERT: &str = "INSERT INTO contacts(name, organization_id, job_title, custom_fields, birthday, address_book_id) VALUES ($1, $2, $3, $4, $5, $6) RETURNING id;";
+const SQL_UPDATE: &str = "UPDATE contacts SET name = $1, organization_id = $2, job_title = $3, custom_fields = $4, birthday = $5 WHERE id = $6 AND address_book_id = $7;";
+const SQL_TOUCH: &str = "UPDATE contacts SET name = name WHERE id = $1 AND address_book_id = $2;";
+const SQL_DELETE: &str = "DELETE FROM contacts WHERE id = $1 AND address_book_id = $2;";
+const SQL_SELECT_CONTACT_EXISTS: &str =
+    "SELECT EXISTS (SELECT 1 FROM contacts WHERE id = $1 AND address_book_id = $2);";
+const SQL_INSERT_PHONES: &str = "INSERT INTO contact_phones(contact_id, type, number, is_primary) SELECT $1, * FROM UNNEST($2::VARCHAR[], $3::VARCHAR[], $4::BOOLEAN[]);";
+const SQL_DELETE_PHONES: &str = "DELETE FROM contact_phones WHERE contact_id = $1;";
+const SQL_UPDATE_PRIMARY_PHONE: &str =
+    "UPDATE contact_phones SET number = $1 WHERE contact_id = $2 AND is_primary;";
+const SQL_INSERT_EMAILS: &str = "INSERT INTO contact_emails(contact_id, type, address, is_primary) SELECT $1, * FROM UNNEST($2::VARCHAR[], $3::VARCHAR[], $4::BOOLEAN[]);";
+const SQL_DELETE_EMAILS: &str = "DELETE FROM contact_emails WHERE contact_id = $1;";
+const SQL_UPDATE_PRIMARY_EMAIL: &str =
+    "UPDATE contact_emails SET address = $1 WHERE contact_id = $2 AND is_primary;";
+const SQL_INSERT_ADDRESSES: &str = "INSERT INTO addresses(contact_id, label, street, locality, region, postal_code, country, is_primary) SELECT $1, * FROM UNNEST($2::VARCHAR[], $3::VARCHAR[], $4::VARCHAR[], $5::VARCHAR[], $6::VARCHAR[], $7::VARCHAR[], $8::BOOLEAN[]);";
+const SQL_DELETE_ADDRESSES: &str = "DELETE FROM addresses WHERE contact_id = $1;";
+const SQL_INSERT_DATES: &str = "INSERT INTO contact_dates(contact_id, label, date) SELECT $1, * FROM UNNEST($2::VARCHAR[], $3::DATE[]);";
+const SQL_DELETE_DATES: &str = "DELETE FROM contact_dates WHERE contact_id = $1;";
+const SQL_SELECT_UPCOMING_DATES: &str = "SELECT v.id, v.name, v.phones, v.emails, v.addresses, v.tags, v.organization, v.custom_fields, v.birthday, v.dates, d.label, d.date, next_occurrence(d.date, $1) AS next_date FROM (SELECT id AS contact_id, $3::VARCHAR AS label, birthday AS date FROM contacts WHERE birthday IS NOT NULL UNION ALL SELECT contact_id, label, date FROM contact_dates) d JOIN contacts_view v ON v.id = d.contact_id WHERE v.address_book_id = $4 AND next_occurrence(d.date, $1) <= $1 + $2::INTEGER ORDER BY next_date, v.name, v.id;";
+const SQL_INSERT_TAGS: &str =
+    "INSERT INTO tags(name) SELECT * FROM UNNEST($1::VARCHAR[]) ON CONFLICT (name) DO NOTHING;";
+const SQL_INSERT_CONTACT_TAGS: &str = "INSERT INTO contact_tags(contact_id, tag_id) SELECT $1, t.id FROM tags t WHERE t.name = ANY($2::VARCHAR[]) AND EXISTS (SELECT 1 FROM contacts c WHERE c.id = $1) ON CONFLICT DO NOTHING;";
+const SQL_DELETE_CONTACT_TAGS: &str = "DELETE FROM contact_tags WHERE contact_id = $1;";
+const SQL_DELETE_CONTACT_TAG: &str = "DELETE FROM contact_tags WHERE contact_id = $1 AND tag_id IN (SELECT id FROM tags WHERE name = $2);";
+const SQL_SELECT_TAGS: &str = "SELECT t.name, COUNT(*) AS count FROM tags t JOIN contact_tags ct ON ct.tag_id = t.id JOIN contacts c ON c.id = ct.contact_id WHERE c.address_book_id = $1 GROUP BY t.name ORDER BY t.name;";
+const SQL_SELECT_ORGANIZATIONS_PAGE: &str = "SELECT id, name, domain, CASE WHEN country IS NULL THEN NULL ELSE json_build_object('street', street, 'locality', locality, 'region', region, 'postal_code', postal_code, 'country', country) END AS address FROM organizations WHERE address_book_id = $3 ORDER BY name, id LIMIT $1 OFFSET $2;";
+const SQL_SELECT_ORGANIZATION: &str = "SELECT id, name, domain, CASE WHEN country IS NULL THEN NULL ELSE json_build_object('street', street, 'locality', locality, 'region', region, 'postal_code', postal_code, 'country', country) END AS address FROM organizations WHERE id = $1 AND address_book_id = $2;";
+const SQL_SELECT_ORGANIZATION_EXISTS: &str =
+    "SELECT EXISTS (SELECT 1 FROM organizations WHERE id = $1 AND address_book_id = $2);";
+const SQL_INSERT_ORGANIZATION: &str = "INSERT INTO organizations(name, domain, street, locality, region, postal_code, country, address_book_id) VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING id;";
+const SQL_UPDATE_ORGANIZATION: &str = "UPDATE organizations SET name = $1, domain = $2, street = $3, locality = $4, region = $5, postal_code = $6, country = $7 WHERE id = $8 AND address_book_id = $9;";
+const SQL_UNLINK_ORGANIZATION_CONTACTS: &str =
+    "UPDATE contacts SET organization_id = NULL, job_title = NULL WHERE organization_id = $1 AND address_book_id = $2;";
+const SQL_DELETE_ORGANIZATION: &str =
+    "DELETE FROM organizations WHERE id = $1 AND address_book_id = $2;";
+const SQL_SELECT_ORGANIZATION_CONTACTS: &str = "SELECT v.id, v.name, v.phones, v.emails, v.addresses, v.tags, v.organization, v.custom_fields, v.birthday, v.dates FROM contacts_view v JOIN contacts c ON c.id = v.id WHERE c.organization_id = $1 AND c.address_book_id = $2 ORDER BY v.id;";
+const SQL_SELECT_SUGGESTED_CONTACTS: &str = "SELECT v.id, v.name, v.phones, v.emails, v.addresses, v.tags, v.organization, v.custom_fields, v.birthday, v.dates FROM contacts_view v JOIN contacts c ON c.id = v.id WHERE c.address_book_id = $2 AND c.organization_id IS NULL AND EXISTS (SELECT 1 FROM contact_emails e JOIN organizations o ON o.id = $1 AND o.address_book_id = $2 WHERE e.contact_id = c.id AND lower(split_part(e.address, '@', 2)) = o.domain) ORDER BY v.id;";
+const SQL_SELECT_NOTES: &str = "SELECT id, contact_id, author, body, created_at, updated_at FROM contact_notes WHERE contact_id = $1 ORDER BY created_at DESC, id DESC;";
+const SQL_INSERT_NOTE: &str = "INSERT INTO contact_notes(contact_id, author, body) VALUES ($1, $2, $3) RETURNING id, contact_id, author, body, created_at, updated_at;";
+const SQL_UPDATE_NOTE: &str =
+    "UPDATE contact_notes SET body = $1, updated_at = now() WHERE id = $2 AND contact_id = $3;";
+const SQL_DELETE_NOTE: &str = "DELETE FROM contact_notes WHERE id = $1 AND contact_id = $2;";
+const SQL_SELECT_CUSTOM_FIELDS: &str =
+    "SELECT name, type, options FROM custom_fields ORDER BY name;";
+const SQL_UPSERT_CUSTOM_FIELD: &str = "INSERT INTO custom_fields(name, type, options) VALUES ($1, $2, $3) ON CONFLICT (name) DO UPDATE SET type = EXCLUDED.type, options = EXCLUDED.options;";
+const SQL_SELECT_CUSTOM_FIELD_VALUES: &str =
+    "SELECT DISTINCT custom_fields -> $1 AS value FROM contacts WHERE custom_fields ? $1;";
+const SQL_DELETE_CUSTOM_FIELD: &str = "DELETE FROM custom_fields WHERE name = $1;";
+const SQL_DELETE_CUSTOM_FIELD_VALUES: &str =
+    "UPDATE contacts SET custom_fields = custom_fields - $1 WHERE custom_fields ? $1;";
+const SQL_SELECT_RELATIONS: &str = "SELECT id, contact_id, related_id, type, bidirectional FROM contact_relations WHERE contact_id = ANY($1) OR related_id = ANY($1) ORDER BY id;";
+const SQL_INSERT_RELATION: &str = "INSERT INTO contact_relations(contact_id, related_id, type, bidirectional) VALUES ($1, $2, $3, $4) RETURNING id;";
+const SQL_DELETE_RELATION: &str =
+    "DELETE FROM contact_relations WHERE id = $1 AND (contact_id = $2 OR related_id = $2);";
+const SQL_SELECT_SYNC_TOKEN: &str =
+    "SELECT COALESCE(MAX(id), 0) AS sync_token FROM contact_changes WHERE address_book_id = $1;";
+const SQL_SELECT_CHANGES: &str = "SELECT DISTINCT ON (contact_id) contact_id, deleted FROM contact_changes WHERE id > $1 AND id <= $2 AND address_book_id = $3 ORDER BY contact_id, id DESC;";
+const SQL_SELECT_LAST_MODIFIED: &str =
+    "SELECT MAX(changed_at) AS last_modified FROM contact_changes WHERE address_book_id = $1;";
+const SQL_SELECT_LAST_MODIFIED_ONE: &str =
+    "SELECT MAX(changed_at) AS last_modified FROM contact_changes WHERE contact_id = $1 AND address_book_id = $2;";
+const SQL_SELECT_ADDRESS_BOOKS: &str =
+    "SELECT id, name, owner_id, created_at FROM address_books WHERE owner_id = $1 ORDER BY id;";
+const SQL_SELECT_ADDRESS_BOOK: &str =
+    "SELECT id, name, owner_id, created_at FROM address_books WHERE id = $1;";
+const SQL_INSERT_ADDRESS_BOOK: &str = "INSERT INTO address_books(name, owner_id) VALUES ($1, $2) RETURNING id, name, owner_id, created_at;";
+const SQL_UPDATE_ADDRESS_BOOK: &str = "UPDATE address_books SET name = $1 WHERE id = $2;";
+const SQL_DELETE_ADDRESS_BOOK: &str = "DELETE FROM address_books WHERE id = $1;";
+const SQL_SELECT_SHARED_ADDRESS_BOOKS: &str = "SELECT id, name, owner_id, created_at FROM address_books b WHERE owner_id <> $1 AND EXISTS (SELECT 1 FROM address_book_shares s WHERE s.address_book_id = b.id AND ((s.grantee_kind = 'user' AND s.grantee = $1) OR (s.grantee_kind = 'group' AND s.grantee = $2))) ORDER BY id;";
+const SQL_SELECT_SHARES: &str = "SELECT id, address_book_id, grantee_kind, grantee, permission, created_at FROM address_book_shares WHERE address_book_id = $1 ORDER BY id;";
+const SQL_INSERT_SHARE: &str = "INSERT INTO address_book_shares(address_book_id, grantee_kind, grantee, permission) VALUES ($1, $2, $3, $4) RETURNING id, address_book_id, grantee_kind, grantee, permission, created_at;";
+const SQL_DELETE_SHARE: &str =
+    "DELETE FROM address_book_shares WHERE address_book_id = $1 AND id = $2;";
 
 #[derive(Debug, Clone)]
 pub struct ContactsDbRepository {
@@ -48,6 +177,16 @@ impl ContactsDbRepository {
             .unwrap_or_else(|_| panic!("Couldn't establish a DB connection: {db_url}"))
     }
 
+    /// A photo storage sharing the DB connections of the repository.
+    pub fn photo_storage(&self) -> PhotoDbStorage {
+        PhotoDbStorage::new(self.db_pool.clone())
+    }
+
+    /// An API users store sharing the DB connections of the repository.
+    pub fn auth_middleware(&self) -> AuthDbMiddleware {
+        AuthDbMiddleware::new(self.db_pool.clone())
+    }
+
     async fn run_migrations(db_url: &String) {
         let mut db_connection: PgConnection = PgConnection::connect(db_url)
             .await
@@ -64,6 +203,8 @@ impl ContactsDbRepository {
 impl ContactsRepository for ContactsDbRepository {
     async fn get_all(
         &self,
+        address_book_id: AddressBookId,
+        filter: &ContactFilter,
         page_no: Option<u32>,
         page_size: Option<u32>,
     ) -> Result<Vec<Contact>, Error> {
@@ -71,14 +212,41 @@ impl ContactsRepository for ContactsDbRepository {
         sqlx::query(SQL_SELECT_PAGE)
             .bind(limit as i32)
             .bind(offset as i32)
+            .bind(filter.country.map(|country: CountryCode| country.alpha2()))
+            .bind(filter.city.as_deref())
+            .bind(tag_names(&filter.tags))
+            .bind(filter.tag_mode == TagMode::And)
+            .bind(Json(&filter.custom_fields))
+            .bind(address_book_id.0)
             .map(map_row)
             .fetch_all(&self.db_pool)
             .await
             .map_err(|err: sqlx::Error| Error::Db(err.to_string()))
     }
 
-    async fn get(&self, id: ContactId) -> Result<Option<Contact>, Error> {
+    fn get_all_as_stream(
+        &self,
+        address_book_id: AddressBookId,
+    ) -> BoxStream<'static, Result<Contact, Error>> {
+        let db_pool: Pool<Postgres> = self.db_pool.clone();
+        Box::pin(try_stream! {
+            let mut contacts = sqlx::query(SQL_SELECT_ALL)
+                .bind(address_book_id.0)
+                .map(map_row)
+                .fetch(&db_pool);
+            while let Some(contact) = contacts.try_next().await? {
+                yield contact;
+            }
+        })
+    }
+
+    async fn get(
+        &self,
+        address_book_id: AddressBookId,
+        id: ContactId,
+    ) -> Result<Option<Contact>, Error> {
         sqlx::query(SQL_SELECT_ONE)
+            .bind(address_book_id.0)
             .bind(id.0)
             .map(map_row)
             .fetch_one(&self.db_pool)
@@ -90,61 +258,653 @@ impl ContactsRepository for ContactsDbRepository {
             })
     }
 
-    async fn add(&mut self, new_contact: NewContact) -> Result<Contact, Error> {
-        sqlx::query(SQL_INSERT)
+    async fn add(
+        &mut self,
+        address_book_id: AddressBookId,
+        new_contact: NewContact,
+    ) -> Result<Contact, Error> {
+        let mut tx: Transaction<Postgres> = self.db_pool.begin().await?;
+        check_organization(&mut tx, address_book_id, new_contact.organization.as_ref()).await?;
+        let custom_fields: CustomFieldValues = validated(
+            new_contact.custom_fields,
+            &select_custom_fields(&mut tx).await?,
+        )?;
+        let id: i32 = sqlx::query(SQL_INSERT)
             .bind(new_contact.name)
-            .bind(new_contact.phone_no)
-            .bind(new_contact.email)
+            .bind(organization_id(new_contact.organization.as_ref()))
+            .bind(job_title(new_contact.organization.as_ref()))
+            .bind(Json(custom_fields))
+            .bind(new_contact.birthday)
+            .bind(address_book_id.0)
+            .map(|row: PgRow| row.get("id"))
+            .fetch_one(&mut tx)
+            .await?;
+        insert_phones(&mut tx, id, with_single_primary(new_contact.phones)).await?;
+        insert_emails(&mut tx, id, with_single_primary(new_contact.emails)).await?;
+        insert_addresses(&mut tx, id, with_single_primary(new_contact.addresses)).await?;
+        insert_tags(&mut tx, id, unique_sorted(new_contact.tags)).await?;
+        insert_dates(&mut tx, id, new_contact.dates).await?;
+        tx.commit().await?;
+
+        self.get(address_book_id, ContactId(id))
+            .await?
+            .ok_or(Error::NotFound { id })
+    }
+
+    async fn update(
+        &mut self,
+        address_book_id: AddressBookId,
+        contact: Contact,
+        id: ContactId,
+    ) -> Result<(), Error> {
+        let mut tx: Transaction<Postgres> = self.db_pool.begin().await?;
+        check_organization(&mut tx, address_book_id, contact.organization.as_ref()).await?;
+        let custom_fields: CustomFieldValues =
+            validated(contact.custom_fields, &select_custom_fields(&mut tx).await?)?;
+        let updated: u64 = sqlx::query(SQL_UPDATE)
+            .bind(contact.name)
+            .bind(organization_id(contact.organization.as_ref()))
+            .bind(job_title(contact.organization.as_ref()))
+            .bind(Json(custom_fields))
+            .bind(contact.birthday)
+            .bind(id.0)
+            .bind(address_book_id.0)
+            .execute(&mut tx)
+            .await?
+            .rows_affected();
+        if updated == 0 {
+            return Ok(());
+        }
+        sqlx::query(SQL_DELETE_PHONES)
+            .bind(id.0)
+            .execute(&mut tx)
+            .await?;
+        sqlx::query(SQL_DELETE_EMAILS)
+            .bind(id.0)
+            .execute(&mut tx)
+            .await?;
+        sqlx::query(SQL_DELETE_ADDRESSES)
+            .bind(id.0)
+            .execute(&mut tx)
+            .await?;
+        sqlx::query(SQL_DELETE_CONTACT_TAGS)
+            .bind(id.0)
+            .execute(&mut tx)
+            .await?;
+        sqlx::query(SQL_DELETE_DATES)
+            .bind(id.0)
+            .execute(&mut tx)
+            .await?;
+        insert_phones(&mut tx, id.0, with_single_primary(contact.phones)).await?;
+        insert_emails(&mut tx, id.0, with_single_primary(contact.emails)).await?;
+        insert_addresses(&mut tx, id.0, with_single_primary(contact.addresses)).await?;
+        insert_tags(&mut tx, id.0, unique_sorted(contact.tags)).await?;
+        insert_dates(&mut tx, id.0, contact.dates).await?;
+        tx.commit()
+            .await
+            .map_err(|err: sqlx::Error| Error::Db(err.to_string()))
+    }
+
+    async fn update_email(
+        &mut self,
+        address_book_id: AddressBookId,
+        new_email: String,
+        id: ContactId,
+    ) -> Result<(), Error> {
+        let mut tx: Transaction<Postgres> = self.db_pool.begin().await?;
+        let touched: u64 = sqlx::query(SQL_TOUCH)
+            .bind(id.0)
+            .bind(address_book_id.0)
+            .execute(&mut tx)
+            .await?
+            .rows_affected();
+        if touched == 0 {
+            return Ok(());
+        }
+        let updated: u64 = sqlx::query(SQL_UPDATE_PRIMARY_EMAIL)
+            .bind(&new_email)
+            .bind(id.0)
+            .execute(&mut tx)
+            .await?
+            .rows_affected();
+        if updated == 0 {
+            let email: Email = Email {
+                email_type: EmailType::default(),
+                address: new_email,
+                primary: true,
+            };
+            insert_emails(&mut tx, id.0, vec![email]).await?;
+        }
+        tx.commit()
+            .await
+            .map_err(|err: sqlx::Error| Error::Db(err.to_string()))
+    }
+
+    async fn update_phone_no(
+        &mut self,
+        address_book_id: AddressBookId,
+        new_phone_no: PhoneNumber,
+        id: ContactId,
+    ) -> Result<(), Error> {
+        let mut tx: Transaction<Postgres> = self.db_pool.begin().await?;
+        let touched: u64 = sqlx::query(SQL_TOUCH)
+            .bind(id.0)
+            .bind(address_book_id.0)
+            .execute(&mut tx)
+            .await?
+            .rows_affected();
+        if touched == 0 {
+            return Ok(());
+        }
+        let updated: u64 = sqlx::query(SQL_UPDATE_PRIMARY_PHONE)
+            .bind(new_phone_no.to_string())
+            .bind(id.0)
+            .execute(&mut tx)
+            .await?
+            .rows_affected();
+        if updated == 0 {
+            let phone: Phone = Phone {
+                phone_type: PhoneType::default(),
+                number: new_phone_no,
+                primary: true,
+            };
+            insert_phones(&mut tx, id.0, vec![phone]).await?;
+        }
+        tx.commit()
+            .await
+            .map_err(|err: sqlx::Error| Error::Db(err.to_string()))
+    }
+
+    async fn add_tag(
+        &mut self,
+        address_book_id: AddressBookId,
+        id: ContactId,
+        tag: Tag,
+    ) -> Result<(), Error> {
+        let mut tx: Transaction<Postgres> = self.db_pool.begin().await?;
+        if !contact_exists(&mut tx, address_book_id, &id).await? {
+            return Ok(());
+        }
+        let added: u64 = insert_tags(&mut tx, id.0, vec![tag]).await?;
+        if added > 0 {
+            sqlx::query(SQL_TOUCH)
+                .bind(id.0)
+                .bind(address_book_id.0)
+                .execute(&mut tx)
+                .await?;
+        }
+        tx.commit()
+            .await
+            .map_err(|err: sqlx::Error| Error::Db(err.to_string()))
+    }
+
+    async fn remove_tag(
+        &mut self,
+        address_book_id: AddressBookId,
+        id: ContactId,
+        tag: Tag,
+    ) -> Result<(), Error> {
+        let mut tx: Transaction<Postgres> = self.db_pool.begin().await?;
+        if !contact_exists(&mut tx, address_book_id, &id).await? {
+            return Ok(());
+        }
+        let removed: u64 = sqlx::query(SQL_DELETE_CONTACT_TAG)
+            .bind(id.0)
+            .bind(tag.as_str())
+            .execute(&mut tx)
+            .await?
+            .rows_affected();
+        if removed > 0 {
+            sqlx::query(SQL_TOUCH)
+                .bind(id.0)
+                .bind(address_book_id.0)
+                .execute(&mut tx)
+                .await?;
+        }
+        tx.commit()
+            .await
+            .map_err(|err: sqlx::Error| Error::Db(err.to_string()))
+    }
+
+    async fn get_tags(&self, address_book_id: AddressBookId) -> Result<Vec<TagCount>, Error> {
+        let rows: Vec<(String, i64)> = sqlx::query(SQL_SELECT_TAGS)
+            .bind(address_book_id.0)
+            .map(|row: PgRow| (row.get("name"), row.get("count")))
+            .fetch_all(&self.db_pool)
+            .await?;
+        rows.into_iter()
+            .map(|(name, count)| Tag::parse(&name).map(|tag: Tag| TagCount { tag, count }))
+            .collect()
+    }
+
+    async fn delete(&mut self, address_book_id: AddressBookId, id: ContactId) -> Result<(), Error> {
+        sqlx::query(SQL_DELETE)
+            .bind(id.0)
+            .bind(address_book_id.0)
+            .execute(&self.db_pool)
+            .await
+            .map(|_| ())
+            .map_err(|err: sqlx::Error| Error::Db(err.to_string()))
+    }
+
+    async fn get_changes(
+        &self,
+        address_book_id: AddressBookId,
+        sync_token: Option<i64>,
+    ) -> Result<ContactChanges, Error> {
+        let latest_sync_token: i64 = sqlx::query(SQL_SELECT_SYNC_TOKEN)
+            .bind(address_book_id.0)
+            .map(|row: PgRow| row.get("sync_token"))
+            .fetch_one(&self.db_pool)
+            .await?;
+        let mut changes: ContactChanges = ContactChanges {
+            sync_token: latest_sync_token,
+            ..ContactChanges::default()
+        };
+        let Some(sync_token) = sync_token else {
+            return Ok(changes);
+        };
+        if sync_token > latest_sync_token || sync_token < 0 {
+            return Err(Error::InvalidSyncToken(sync_token.to_string()));
+        }
+
+        let rows: Vec<(ContactId, bool)> = sqlx::query(SQL_SELECT_CHANGES)
+            .bind(sync_token)
+            .bind(latest_sync_token)
+            .bind(address_book_id.0)
+            .map(|row: PgRow| (ContactId(row.get("contact_id")), row.get("deleted")))
+            .fetch_all(&self.db_pool)
+            .await?;
+        for (id, deleted) in rows {
+            if deleted {
+                changes.deleted.push(id);
+            } else {
+                changes.updated.push(id);
+            }
+        }
+        Ok(changes)
+    }
+
+    async fn get_last_modified(
+        &self,
+        address_book_id: AddressBookId,
+        id: Option<ContactId>,
+    ) -> Result<Option<DateTime<Utc>>, Error> {
+        let query = match id {
+            Some(id) => sqlx::query(SQL_SELECT_LAST_MODIFIED_ONE)
+                .bind(id.0)
+                .bind(address_book_id.0),
+            None => sqlx::query(SQL_SELECT_LAST_MODIFIED).bind(address_book_id.0),
+        };
+        query
+            .map(|row: PgRow| row.get("last_modified"))
+            .fetch_one(&self.db_pool)
+            .await
+            .map_err(|err: sqlx::Error| Error::Db(err.to_string()))
+    }
+
+    async fn get_upcoming_dates(
+        &self,
+        address_book_id: AddressBookId,
+        from: NaiveDate,
+        days: u32,
+    ) -> Result<Vec<UpcomingDate>, Error> {
+        sqlx::query(SQL_SELECT_UPCOMING_DATES)
+            .bind(from)
+            .bind(days as i32)
+            .bind(BIRTHDAY_LABEL)
+            .bind(address_book_id.0)
+            .map(|row: PgRow| {
+                let next_date: NaiveDate = row.get("next_date");
+                UpcomingDate {
+                    label: row.get("label"),
+                    date: row.get("date"),
+                    next_date,
+                    days_until: (next_date - from).num_days(),
+                    contact: map_row(row),
+                }
+            })
+            .fetch_all(&self.db_pool)
+            .await
+            .map_err(|err: sqlx::Error| Error::Db(err.to_string()))
+    }
+}
+
+#[async_trait]
+impl OrganizationsRepository for ContactsDbRepository {
+    async fn get_all_organizations(
+        &self,
+        address_book_id: AddressBookId,
+        page_no: Option<u32>,
+        page_size: Option<u32>,
+    ) -> Result<Vec<Organization>, Error> {
+        let (limit, offset): (u32, u32) = get_limit_and_offset(page_no, page_size);
+        sqlx::query(SQL_SELECT_ORGANIZATIONS_PAGE)
+            .bind(limit as i32)
+            .bind(offset as i32)
+            .bind(address_book_id.0)
+            .map(map_organization_row)
+            .fetch_all(&self.db_pool)
+            .await
+            .map_err(|err: sqlx::Error| Error::Db(err.to_string()))
+    }
+
+    async fn get_organization(
+        &self,
+        address_book_id: AddressBookId,
+        id: OrganizationId,
+    ) -> Result<Option<Organization>, Error> {
+        sqlx::query(SQL_SELECT_ORGANIZATION)
+            .bind(id.0)
+            .bind(address_book_id.0)
+            .map(map_organization_row)
+            .fetch_optional(&self.db_pool)
+            .await
+            .map_err(|err: sqlx::Error| Error::Db(err.to_string()))
+    }
+
+    async fn add_organization(
+        &mut self,
+        address_book_id: AddressBookId,
+        new_organization: NewOrganization,
+    ) -> Result<Organization, Error> {
+        let address: Option<&Address> = new_organization.address.as_ref();
+        let id: i32 = sqlx::query(SQL_INSERT_ORGANIZATION)
+            .bind(&new_organization.name)
+            .bind(&new_organization.domain)
+            .bind(address.map(|address: &Address| address.street.as_str()))
+            .bind(address.map(|address: &Address| address.locality.as_str()))
+            .bind(address.map(|address: &Address| address.region.as_str()))
+            .bind(address.map(|address: &Address| address.postal_code.as_str()))
+            .bind(address.map(|address: &Address| address.country.alpha2()))
+            .bind(address_book_id.0)
+            .map(|row: PgRow| row.get("id"))
+            .fetch_one(&self.db_pool)
+            .await?;
+
+        self.get_organization(address_book_id, OrganizationId(id))
+            .await?
+            .ok_or(Error::UnknownOrganization(id))
+    }
+
+    async fn update_organization(
+        &mut self,
+        address_book_id: AddressBookId,
+        organization: NewOrganization,
+        id: OrganizationId,
+    ) -> Result<(), Error> {
+        let address: Option<&Address> = organization.address.as_ref();
+        sqlx::query(SQL_UPDATE_ORGANIZATION)
+            .bind(&organization.name)
+            .bind(&organization.domain)
+            .bind(address.map(|address: &Address| address.street.as_str()))
+            .bind(address.map(|address: &Address| address.locality.as_str()))
+            .bind(address.map(|address: &Address| address.region.as_str()))
+            .bind(address.map(|address: &Address| address.postal_code.as_str()))
+            .bind(address.map(|address: &Address| address.country.alpha2()))
+            .bind(id.0)
+            .bind(address_book_id.0)
+            .execute(&self.db_pool)
+            .await
+            .map(|_| ())
+            .map_err(Error::from)
+    }
+
+    async fn delete_organization(
+        &mut self,
+        address_book_id: AddressBookId,
+        id: OrganizationId,
+    ) -> Result<(), Error> {
+        // Unlinking explicitly, rather than relying on ON DELETE SET NULL, records the change of the contacts
+        let mut tx: Transaction<Postgres> = self.db_pool.begin().await?;
+        sqlx::query(SQL_UNLINK_ORGANIZATION_CONTACTS)
+            .bind(id.0)
+            .bind(address_book_id.0)
+            .execute(&mut tx)
+            .await?;
+        sqlx::query(SQL_DELETE_ORGANIZATION)
+            .bind(id.0)
+            .bind(address_book_id.0)
+            .execute(&mut tx)
+            .await?;
+        tx.commit()
+            .await
+            .map_err(|err: sqlx::Error| Error::Db(err.to_string()))
+    }
+
+    async fn get_organization_contacts(
+        &self,
+        address_book_id: AddressBookId,
+        id: OrganizationId,
+    ) -> Result<Vec<Contact>, Error> {
+        sqlx::query(SQL_SELECT_ORGANIZATION_CONTACTS)
+            .bind(id.0)
+            .bind(address_book_id.0)
             .map(map_row)
+            .fetch_all(&self.db_pool)
+            .await
+            .map_err(|err: sqlx::Error| Error::Db(err.to_string()))
+    }
+
+    async fn get_suggested_contacts(
+        &self,
+        address_book_id: AddressBookId,
+        id: OrganizationId,
+    ) -> Result<Vec<Contact>, Error> {
+        sqlx::query(SQL_SELECT_SUGGESTED_CONTACTS)
+            .bind(id.0)
+            .bind(address_book_id.0)
+            .map(map_row)
+            .fetch_all(&self.db_pool)
+            .await
+            .map_err(|err: sqlx::Error| Error::Db(err.to_string()))
+    }
+}
+
+#[async_trait]
+impl AddressBooksRepository for ContactsDbRepository {
+    async fn get_address_books(&self, owner_id: &str) -> Result<Vec<AddressBook>, Error> {
+        sqlx::query(SQL_SELECT_ADDRESS_BOOKS)
+            .bind(owner_id)
+            .map(map_address_book_row)
+            .fetch_all(&self.db_pool)
+            .await
+            .map_err(|err: sqlx::Error| Error::Db(err.to_string()))
+    }
+
+    async fn get_shared_address_books(
+        &self,
+        username: &str,
+        role: Option<Role>,
+    ) -> Result<Vec<AddressBook>, Error> {
+        sqlx::query(SQL_SELECT_SHARED_ADDRESS_BOOKS)
+            .bind(username)
+            .bind(role.map(|role: Role| role.as_str()))
+            .map(map_address_book_row)
+            .fetch_all(&self.db_pool)
+            .await
+            .map_err(|err: sqlx::Error| Error::Db(err.to_string()))
+    }
+
+    async fn get_address_book(&self, id: AddressBookId) -> Result<Option<AddressBook>, Error> {
+        sqlx::query(SQL_SELECT_ADDRESS_BOOK)
+            .bind(id.0)
+            .map(map_address_book_row)
+            .fetch_optional(&self.db_pool)
+            .await
+            .map_err(|err: sqlx::Error| Error::Db(err.to_string()))
+    }
+
+    async fn add_address_book(
+        &mut self,
+        owner_id: &str,
+        new_address_book: NewAddressBook,
+    ) -> Result<AddressBook, Error> {
+        sqlx::query(SQL_INSERT_ADDRESS_BOOK)
+            .bind(new_address_book.name)
+            .bind(owner_id)
+            .map(map_address_book_row)
             .fetch_one(&self.db_pool)
             .await
             .map_err(|err: sqlx::Error| Error::Db(err.to_string()))
     }
 
-    async fn update(&mut self, contact: Contact, id: ContactId) -> Result<(), Error> {
-        sqlx::query(SQL_UPDATE)
-            .bind(contact.name)
-            .bind(contact.phone_no)
-            .bind(contact.email)
+    async fn update_address_book(
+        &mut self,
+        id: AddressBookId,
+        address_book: NewAddressBook,
+    ) -> Result<(), Error> {
+        sqlx::query(SQL_UPDATE_ADDRESS_BOOK)
+            .bind(address_book.name)
             .bind(id.0)
             .execute(&self.db_pool)
             .await
             .map(|_| ())
-            .map_err(|err: sqlx::Error| match err {
-                sqlx::Error::RowNotFound => Error::NotFound { id: id.0 },
-                _ => Error::Db(err.to_string()),
-            })
+            .map_err(|err: sqlx::Error| Error::Db(err.to_string()))
     }
 
-    async fn update_email(&mut self, new_email: String, id: ContactId) -> Result<(), Error> {
-        sqlx::query(SQL_UPDATE_EMAIL)
-            .bind(new_email)
+    async fn delete_address_book(&mut self, id: AddressBookId) -> Result<(), Error> {
+        sqlx::query(SQL_DELETE_ADDRESS_BOOK)
             .bind(id.0)
             .execute(&self.db_pool)
             .await
             .map(|_| ())
-            .map_err(|err: sqlx::Error| match err {
-                sqlx::Error::RowNotFound => Error::NotFound { id: id.0 },
-                _ => Error::Db(err.to_string()),
-            })
+            .map_err(|err: sqlx::Error| Error::Db(err.to_string()))
+    }
+
+    async fn get_shares(&self, id: AddressBookId) -> Result<Vec<Share>, Error> {
+        sqlx::query(SQL_SELECT_SHARES)
+            .bind(id.0)
+            .fetch_all(&self.db_pool)
+            .await?
+            .into_iter()
+            .map(map_share_row)
+            .collect()
+    }
+
+    async fn add_share(&mut self, id: AddressBookId, new_share: NewShare) -> Result<Share, Error> {
+        let row: PgRow = sqlx::query(SQL_INSERT_SHARE)
+            .bind(id.0)
+            .bind(new_share.grantee.kind())
+            .bind(new_share.grantee.name())
+            .bind(new_share.permission.as_str())
+            .fetch_one(&self.db_pool)
+            .await
+            .map_err(|err: sqlx::Error| match Error::from(err) {
+                Error::Conflict(_) => Error::Conflict(format!(
+                    "The address book is already shared with the {} {}",
+                    new_share.grantee.kind(),
+                    new_share.grantee.name()
+                )),
+                other => other,
+            })?;
+        map_share_row(row)
     }
 
-    async fn update_phone_no(&mut self, new_phone_no: i64, id: ContactId) -> Result<(), Error> {
-        sqlx::query(SQL_UPDATE_PHONE_NO)
-            .bind(new_phone_no)
+    async fn delete_share(&mut self, id: AddressBookId, share_id: ShareId) -> Result<(), Error> {
+        sqlx::query(SQL_DELETE_SHARE)
             .bind(id.0)
+            .bind(share_id.0)
             .execute(&self.db_pool)
             .await
             .map(|_| ())
-            .map_err(|err: sqlx::Error| match err {
-                sqlx::Error::RowNotFound => Error::NotFound { id: id.0 },
-                _ => Error::Db(err.to_string()),
-            })
+            .map_err(|err: sqlx::Error| Error::Db(err.to_string()))
     }
+}
 
-    async fn delete(&mut self, id: ContactId) -> Result<(), Error> {
-        sqlx::query(SQL_DELETE)
+#[async_trait]
+impl CustomFieldsRepository for ContactsDbRepository {
+    async fn get_custom_fields(&self) -> Result<Vec<CustomField>, Error> {
+        let mut db_connection: PoolConnection<Postgres> = self.db_pool.acquire().await?;
+        select_custom_fields(&mut db_connection).await
+    }
+
+    async fn put_custom_field(&mut self, custom_field: CustomField) -> Result<(), Error> {
+        let mut tx: Transaction<Postgres> = self.db_pool.begin().await?;
+        let values: Vec<Json<Value>> = sqlx::query(SQL_SELECT_CUSTOM_FIELD_VALUES)
+            .bind(&custom_field.name)
+            .map(|row: PgRow| row.get("value"))
+            .fetch_all(&mut tx)
+            .await?;
+        for value in values {
+            custom_field
+                .validate(&value.0)
+                .map_err(|err: Error| Error::Conflict(err.to_string()))?;
+        }
+        sqlx::query(SQL_UPSERT_CUSTOM_FIELD)
+            .bind(&custom_field.name)
+            .bind(custom_field.field_type.as_str())
+            .bind(&custom_field.options)
+            .execute(&mut tx)
+            .await?;
+        tx.commit()
+            .await
+            .map_err(|err: sqlx::Error| Error::Db(err.to_string()))
+    }
+
+    async fn delete_custom_field(&mut self, name: String) -> Result<(), Error> {
+        let mut tx: Transaction<Postgres> = self.db_pool.begin().await?;
+        sqlx::query(SQL_DELETE_CUSTOM_FIELD_VALUES)
+            .bind(&name)
+            .execute(&mut tx)
+            .await?;
+        sqlx::query(SQL_DELETE_CUSTOM_FIELD)
+            .bind(&name)
+            .execute(&mut tx)
+            .await?;
+        tx.commit()
+            .await
+            .map_err(|err: sqlx::Error| Error::Db(err.to_string()))
+    }
+}
+
+#[async_trait]
+impl NotesRepository for ContactsDbRepository {
+    async fn get_notes(&self, contact_id: ContactId) -> Result<Vec<Note>, Error> {
+        sqlx::query(SQL_SELECT_NOTES)
+            .bind(contact_id.0)
+            .map(map_note_row)
+            .fetch_all(&self.db_pool)
+            .await
+            .map_err(|err: sqlx::Error| Error::Db(err.to_string()))
+    }
+
+    async fn add_note(
+        &mut self,
+        contact_id: ContactId,
+        author: String,
+        new_note: NewNote,
+    ) -> Result<Note, Error> {
+        sqlx::query(SQL_INSERT_NOTE)
+            .bind(contact_id.0)
+            .bind(author)
+            .bind(new_note.body)
+            .map(map_note_row)
+            .fetch_one(&self.db_pool)
+            .await
+            .map_err(|err: sqlx::Error| Error::Db(err.to_string()))
+    }
+
+    async fn update_note(
+        &mut self,
+        contact_id: ContactId,
+        id: NoteId,
+        new_note: NewNote,
+    ) -> Result<(), Error> {
+        sqlx::query(SQL_UPDATE_NOTE)
+            .bind(new_note.body)
+            .bind(id.0)
+            .bind(contact_id.0)
+            .execute(&self.db_pool)
+            .await
+            .map(|_| ())
+            .map_err(|err: sqlx::Error| Error::Db(err.to_string()))
+    }
+
+    async fn delete_note(&mut self, contact_id: ContactId, id: NoteId) -> Result<(), Error> {
+        sqlx::query(SQL_DELETE_NOTE)
             .bind(id.0)
+            .bind(contact_id.0)
             .execute(&self.db_pool)
             .await
             .map(|_| ())
@@ -152,11 +912,363 @@ impl ContactsRepository for ContactsDbRepository {
     }
 }
 
+#[async_trait]
+impl RelationsRepository for ContactsDbRepository {
+    async fn get_relations(&self, contact_ids: &[ContactId]) -> Result<Vec<Relation>, Error> {
+        let rows: Vec<(i32, i32, i32, String, bool)> = sqlx::query(SQL_SELECT_RELATIONS)
+            .bind(
+                contact_ids
+                    .iter()
+                    .map(|id: &ContactId| id.0)
+                    .collect::<Vec<i32>>(),
+            )
+            .map(|row: PgRow| {
+                (
+                    row.get("id"),
+                    row.get("contact_id"),
+                    row.get("related_id"),
+                    row.get("type"),
+                    row.get("bidirectional"),
+                )
+            })
+            .fetch_all(&self.db_pool)
+            .await?;
+        rows.into_iter()
+            .map(
+                |(id, contact_id, related_id, relation_type, bidirectional)| {
+                    Ok(Relation {
+                        id: RelationId(id),
+                        contact_id: ContactId(contact_id),
+                        related_id: ContactId(related_id),
+                        relation_type: RelationType::parse(&relation_type)?,
+                        bidirectional,
+                    })
+                },
+            )
+            .collect()
+    }
+
+    async fn add_relation(
+        &mut self,
+        contact_id: ContactId,
+        new_relation: NewRelation,
+    ) -> Result<Relation, Error> {
+        let id: i32 = sqlx::query(SQL_INSERT_RELATION)
+            .bind(contact_id.0)
+            .bind(new_relation.related_id.0)
+            .bind(new_relation.relation_type.as_str())
+            .bind(new_relation.bidirectional)
+            .map(|row: PgRow| row.get("id"))
+            .fetch_one(&self.db_pool)
+            .await
+            .map_err(|err: sqlx::Error| match Error::from(err) {
+                Error::Conflict(_) => Error::Conflict(format!(
+                    "The contacts are already related as {}",
+                    new_relation.relation_type.as_str()
+                )),
+                other => other,
+            })?;
+        Ok(Relation {
+            id: RelationId(id),
+            contact_id,
+            related_id: new_relation.related_id,
+            relation_type: new_relation.relation_type,
+            bidirectional: new_relation.bidirectional,
+        })
+    }
+
+    async fn delete_relation(
+        &mut self,
+        contact_id: ContactId,
+        id: RelationId,
+    ) -> Result<(), Error> {
+        let deleted: u64 = sqlx::query(SQL_DELETE_RELATION)
+            .bind(id.0)
+            .bind(contact_id.0)
+            .execute(&self.db_pool)
+            .await
+            .map_err(|err: sqlx::Error| Error::Db(err.to_string()))?
+            .rows_affected();
+        if deleted == 0 {
+            return Err(Error::NotFound { id: id.0 });
+        }
+        Ok(())
+    }
+}
+
+/// Fails with `Error::UnknownOrganization` if a contact is linked to an organization
+/// that doesn't exist in its address book.
+async fn check_organization(
+    tx: &mut Transaction<'_, Postgres>,
+    address_book_id: AddressBookId,
+    affiliation: Option<&Affiliation>,
+) -> Result<(), Error> {
+    let Some(affiliation) = affiliation else {
+        return Ok(());
+    };
+    let exists: bool = sqlx::query(SQL_SELECT_ORGANIZATION_EXISTS)
+        .bind(affiliation.id.0)
+        .bind(address_book_id.0)
+        .map(|row: PgRow| row.get(0))
+        .fetch_one(&mut *tx)
+        .await?;
+    if exists {
+        Ok(())
+    } else {
+        Err(Error::UnknownOrganization(affiliation.id.0))
+    }
+}
+
+async fn contact_exists(
+    tx: &mut Transaction<'_, Postgres>,
+    address_book_id: AddressBookId,
+    id: &ContactId,
+) -> Result<bool, sqlx::Error> {
+    sqlx::query(SQL_SELECT_CONTACT_EXISTS)
+        .bind(id.0)
+        .bind(address_book_id.0)
+        .map(|row: PgRow| row.get(0))
+        .fetch_one(&mut *tx)
+        .await
+}
+
+async fn select_custom_fields(db_connection: &mut PgConnection) -> Result<Vec<CustomField>, Error> {
+    let rows: Vec<(String, String, Vec<String>)> = sqlx::query(SQL_SELECT_CUSTOM_FIELDS)
+        .map(|row: PgRow| (row.get("name"), row.get("type"), row.get("options")))
+        .fetch_all(&mut *db_connection)
+        .await?;
+    rows.into_iter()
+        .map(|(name, field_type, options)| {
+            Ok(CustomField {
+                name,
+                field_type: CustomFieldType::parse(&field_type)?,
+                options,
+            })
+        })
+        .collect()
+}
+
+fn organization_id(affiliation: Option<&Affiliation>) -> Option<i32> {
+    affiliation.map(|affiliation: &Affiliation| affiliation.id.0)
+}
+
+fn job_title(affiliation: Option<&Affiliation>) -> Option<&str> {
+    affiliation.and_then(|affiliation: &Affiliation| affiliation.job_title.as_deref())
+}
+
+async fn insert_phones(
+    tx: &mut Transaction<'_, Postgres>,
+    contact_id: i32,
+    phones: Vec<Phone>,
+) -> Result<(), sqlx::Error> {
+    if phones.is_empty() {
+        return Ok(());
+    }
+    sqlx::query(SQL_INSERT_PHONES)
+        .bind(contact_id)
+        .bind(
+            phones
+                .iter()
+                .map(|phone: &Phone| phone.phone_type.as_str())
+                .collect::<Vec<&str>>(),
+        )
+        .bind(
+            phones
+                .iter()
+                .map(|phone: &Phone| phone.number.to_string())
+                .collect::<Vec<String>>(),
+        )
+        .bind(
+            phones
+                .iter()
+                .map(|phone: &Phone| phone.primary)
+                .collect::<Vec<bool>>(),
+        )
+        .execute(&mut *tx)
+        .await
+        .map(|_| ())
+}
+
+async fn insert_emails(
+    tx: &mut Transaction<'_, Postgres>,
+    contact_id: i32,
+    emails: Vec<Email>,
+) -> Result<(), sqlx::Error> {
+    if emails.is_empty() {
+        return Ok(());
+    }
+    sqlx::query(SQL_INSERT_EMAILS)
+        .bind(contact_id)
+        .bind(
+            emails
+                .iter()
+                .map(|email: &Email| email.email_type.as_str())
+                .collect::<Vec<&str>>(),
+        )
+        .bind(
+            emails
+                .iter()
+                .map(|email: &Email| email.address.as_str())
+                .collect::<Vec<&str>>(),
+        )
+        .bind(
+            emails
+                .iter()
+                .map(|email: &Email| email.primary)
+                .collect::<Vec<bool>>(),
+        )
+        .execute(&mut *tx)
+        .await
+        .map(|_| ())
+}
+
+async fn insert_addresses(
+    tx: &mut Transaction<'_, Postgres>,
+    contact_id: i32,
+    addresses: Vec<Address>,
+) -> Result<(), sqlx::Error> {
+    if addresses.is_empty() {
+        return Ok(());
+    }
+    let column =
+        |field: fn(&Address) -> &str| -> Vec<&str> { addresses.iter().map(field).collect() };
+    sqlx::query(SQL_INSERT_ADDRESSES)
+        .bind(contact_id)
+        .bind(
+            addresses
+                .iter()
+                .map(|address: &Address| address.label.as_deref())
+                .collect::<Vec<Option<&str>>>(),
+        )
+        .bind(column(|address: &Address| address.street.as_str()))
+        .bind(column(|address: &Address| address.locality.as_str()))
+        .bind(column(|address: &Address| address.region.as_str()))
+        .bind(column(|address: &Address| address.postal_code.as_str()))
+        .bind(column(|address: &Address| address.country.alpha2()))
+        .bind(
+            addresses
+                .iter()
+                .map(|address: &Address| address.primary)
+                .collect::<Vec<bool>>(),
+        )
+        .execute(&mut *tx)
+        .await
+        .map(|_| ())
+}
+
+/// Tags a contact, adding the tags not used so far. Returns how many tags the contact didn't have.
+async fn insert_dates(
+    tx: &mut Transaction<'_, Postgres>,
+    contact_id: i32,
+    dates: Vec<ImportantDate>,
+) -> Result<(), sqlx::Error> {
+    if dates.is_empty() {
+        return Ok(());
+    }
+    sqlx::query(SQL_INSERT_DATES)
+        .bind(contact_id)
+        .bind(
+            dates
+                .iter()
+                .map(|x: &ImportantDate| x.label.as_str())
+                .collect::<Vec<&str>>(),
+        )
+        .bind(
+            dates
+                .iter()
+                .map(|x: &ImportantDate| x.date)
+                .collect::<Vec<NaiveDate>>(),
+        )
+        .execute(&mut *tx)
+        .await
+        .map(|_| ())
+}
+
+async fn insert_tags(
+    tx: &mut Transaction<'_, Postgres>,
+    contact_id: i32,
+    tags: Vec<Tag>,
+) -> Result<u64, sqlx::Error> {
+    if tags.is_empty() {
+        return Ok(0);
+    }
+    sqlx::query(SQL_INSERT_TAGS)
+        .bind(tag_names(&tags))
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(SQL_INSERT_CONTACT_TAGS)
+        .bind(contact_id)
+        .bind(tag_names(&tags))
+        .execute(&mut *tx)
+        .await
+        .map(|result| result.rows_affected())
+}
+
+fn tag_names(tags: &[Tag]) -> Vec<&str> {
+    tags.iter().map(|tag: &Tag| tag.as_str()).collect()
+}
+
 fn map_row(row: PgRow) -> Contact {
+    let phones: Json<Vec<Phone>> = row.get("phones");
+    let emails: Json<Vec<Email>> = row.get("emails");
+    let addresses: Json<Vec<Address>> = row.get("addresses");
+    let tags: Json<Vec<Tag>> = row.get("tags");
+    let organization: Option<Json<Affiliation>> = row.get("organization");
+    let custom_fields: Json<CustomFieldValues> = row.get("custom_fields");
+    let dates: Json<Vec<ImportantDate>> = row.get("dates");
     Contact {
         id: ContactId(row.get("id")),
         name: row.get("name"),
-        phone_no: row.get("phone_no"),
-        email: row.get("email"),
+        phones: phones.0,
+        emails: emails.0,
+        addresses: addresses.0,
+        tags: tags.0,
+        organization: organization.map(|organization: Json<Affiliation>| organization.0),
+        custom_fields: custom_fields.0,
+        birthday: row.get("birthday"),
+        dates: dates.0,
+    }
+}
+
+fn map_note_row(row: PgRow) -> Note {
+    Note {
+        id: NoteId(row.get("id")),
+        contact_id: ContactId(row.get("contact_id")),
+        author: row.get("author"),
+        body: row.get("body"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+fn map_address_book_row(row: PgRow) -> AddressBook {
+    AddressBook {
+        id: AddressBookId(row.get("id")),
+        name: row.get("name"),
+        owner_id: row.get("owner_id"),
+        created_at: row.get("created_at"),
+    }
+}
+
+fn map_share_row(row: PgRow) -> Result<Share, Error> {
+    let grantee_kind: String = row.get("grantee_kind");
+    let grantee: String = row.get("grantee");
+    let permission: String = row.get("permission");
+    Ok(Share {
+        id: ShareId(row.get("id")),
+        address_book_id: AddressBookId(row.get("address_book_id")),
+        grantee: Grantee::parse(&grantee_kind, &grantee)?,
+        permission: SharePermission::parse(&permission)?,
+        created_at: row.get("created_at"),
+    })
+}
+
+fn map_organization_row(row: PgRow) -> Organization {
+    let address: Option<Json<Address>> = row.get("address");
+    Organization {
+        id: OrganizationId(row.get("id")),
+        name: row.get("name"),
+        domain: row.get("domain"),
+        address: address.map(|address: Json<Address>| address.0),
     }
 }
@@ -1,24 +1,228 @@
+use async_stream::stream;
 use async_trait::async_trait;
+use chrono::DateTime;
+use chrono::NaiveDate;
+use chrono::Utc;
+use futures::stream::BoxStream;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+use crate::models::address_book::AddressBook;
+use crate::models::address_book::AddressBookId;
+use crate::models::address_book::NewAddressBook;
+use crate::models::contact::with_single_primary;
 use crate::models::contact::Contact;
+use crate::models::contact::ContactChanges;
+use crate::models::contact::ContactFilter;
 use crate::models::contact::ContactId;
+use crate::models::contact::Email;
+use crate::models::contact::EmailType;
 use crate::models::contact::NewContact;
+use crate::models::contact::Phone;
+use crate::models::contact::PhoneType;
+use crate::models::custom_field::validated;
+use crate::models::custom_field::CustomField;
+use crate::models::custom_field::CustomFieldValues;
 use crate::models::errors::Error;
+use crate::models::important_date::sort_upcoming_dates;
+use crate::models::important_date::upcoming_dates;
+use crate::models::important_date::UpcomingDate;
+use crate::models::note::NewNote;
+use crate::models::note::Note;
+use crate::models::note::NoteId;
+use crate::models::organization::email_domain;
+use crate::models::organization::Affiliation;
+use crate::models::organization::NewOrganization;
+use crate::models::organization::Organization;
+use crate::models::organization::OrganizationId;
+use crate::models::phone_number::PhoneNumber;
+use crate::models::relation::NewRelation;
+use crate::models::relation::Relation;
+use crate::models::relation::RelationId;
+use crate::models::role::Role;
+use crate::models::share::NewShare;
+use crate::models::share::Share;
+use crate::models::share::ShareId;
+use crate::models::tag::unique_sorted;
+use crate::models::tag::Tag;
+use crate::models::tag::TagCount;
 use crate::repositories::contacts_repository::get_limit_and_offset;
 
+use super::address_books_repository::AddressBooksRepository;
 use super::contacts_repository::ContactsRepository;
+use super::custom_fields_repository::CustomFieldsRepository;
+use super::notes_repository::NotesRepository;
+use super::organizations_repository::OrganizationsRepository;
+use super::relations_repository::RelationsRepository;
 
+#[derive(Debug, Clone)]
 pub struct ContactsInMemoryRepository {
     data: Arc<RwLock<HashMap<ContactId, Contact>>>,
+    /// The address book of each contact.
+    contact_books: Arc<RwLock<HashMap<ContactId, AddressBookId>>>,
+    /// Log of the changed contacts, the sync token being the number of changes so far.
+    changes: Arc<RwLock<Vec<ContactChange>>>,
+    organizations: Arc<RwLock<HashMap<OrganizationId, Organization>>>,
+    /// The address book of each organization.
+    organization_books: Arc<RwLock<HashMap<OrganizationId, AddressBookId>>>,
+    /// The notes on the contacts, in the order they were added.
+    notes: Arc<RwLock<Vec<Note>>>,
+    custom_fields: Arc<RwLock<BTreeMap<String, CustomField>>>,
+    /// The relations between contacts, in the order they were added.
+    relations: Arc<RwLock<Vec<Relation>>>,
+    address_books: Arc<RwLock<BTreeMap<AddressBookId, AddressBook>>>,
+    /// The shares of the address books, in the order they were added.
+    shares: Arc<RwLock<Vec<Share>>>,
+}
+
+/// An entry of the change log: which contact of which address book, whether it was deleted and when.
+#[derive(Debug, Clone)]
+struct ContactChange {
+    address_book_id: AddressBookId,
+    id: ContactId,
+    deleted: bool,
+    changed_at: DateTime<Utc>,
 }
 
 impl ContactsInMemoryRepository {
     pub fn new() -> Self {
         ContactsInMemoryRepository {
             data: Arc::new(RwLock::new(HashMap::new())),
+            contact_books: Arc::new(RwLock::new(HashMap::new())),
+            changes: Arc::new(RwLock::new(vec![])),
+            organizations: Arc::new(RwLock::new(HashMap::new())),
+            organization_books: Arc::new(RwLock::new(HashMap::new())),
+            notes: Arc::new(RwLock::new(vec![])),
+            custom_fields: Arc::new(RwLock::new(BTreeMap::new())),
+            relations: Arc::new(RwLock::new(vec![])),
+            address_books: Arc::new(RwLock::new(BTreeMap::new())),
+            shares: Arc::new(RwLock::new(vec![])),
+        }
+    }
+
+    async fn log_change(&self, address_book_id: AddressBookId, id: ContactId, deleted: bool) {
+        self.changes.write().await.push(ContactChange {
+            address_book_id,
+            id,
+            deleted,
+            changed_at: Utc::now(),
+        });
+    }
+
+    /// The ids of the contacts of the address book.
+    async fn contact_ids(&self, address_book_id: AddressBookId) -> HashSet<ContactId> {
+        self.contact_books
+            .read()
+            .await
+            .iter()
+            .filter(|(_, book_id)| **book_id == address_book_id)
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+
+    async fn has_contact(&self, address_book_id: AddressBookId, id: &ContactId) -> bool {
+        self.contact_books.read().await.get(id) == Some(&address_book_id)
+    }
+
+    async fn has_organization(&self, address_book_id: AddressBookId, id: &OrganizationId) -> bool {
+        self.organization_books.read().await.get(id) == Some(&address_book_id)
+    }
+
+    /// The contacts of the address book matching the predicate, in no particular order.
+    async fn contacts_where(
+        &self,
+        address_book_id: AddressBookId,
+        predicate: impl Fn(&Contact) -> bool,
+    ) -> Vec<Contact> {
+        let ids: HashSet<ContactId> = self.contact_ids(address_book_id).await;
+        self.data
+            .read()
+            .await
+            .values()
+            .filter(|contact: &&Contact| ids.contains(&contact.id) && predicate(contact))
+            .cloned()
+            .collect()
+    }
+
+    /// Checks the values of the custom fields of a contact against the definitions.
+    async fn validate_custom_fields(
+        &self,
+        values: CustomFieldValues,
+    ) -> Result<CustomFieldValues, Error> {
+        let definitions: Vec<CustomField> =
+            self.custom_fields.read().await.values().cloned().collect();
+        validated(values, &definitions)
+    }
+
+    /// Fills in the name of the organization a contact is linked to,
+    /// failing if it doesn't exist in the address book of the contact.
+    async fn resolve_affiliation(
+        &self,
+        address_book_id: AddressBookId,
+        affiliation: Option<Affiliation>,
+    ) -> Result<Option<Affiliation>, Error> {
+        let Some(affiliation) = affiliation else {
+            return Ok(None);
+        };
+        if !self
+            .has_organization(address_book_id, &affiliation.id)
+            .await
+        {
+            return Err(Error::UnknownOrganization(affiliation.id.0));
+        }
+        match self.organizations.read().await.get(&affiliation.id) {
+            Some(organization) => Ok(Some(Affiliation {
+                name: organization.name.clone(),
+                ..affiliation
+            })),
+            None => Err(Error::UnknownOrganization(affiliation.id.0)),
+        }
+    }
+
+    /// Fails with `Error::Conflict` if an organization of the address book, other than the given one,
+    /// has the same domain.
+    async fn check_domain(
+        &self,
+        address_book_id: AddressBookId,
+        domain: Option<&String>,
+        id: Option<&OrganizationId>,
+    ) -> Result<(), Error> {
+        let Some(domain) = domain else {
+            return Ok(());
+        };
+        let organization_books = self.organization_books.read().await;
+        let taken: bool =
+            self.organizations
+                .read()
+                .await
+                .values()
+                .any(|organization: &Organization| {
+                    organization_books.get(&organization.id) == Some(&address_book_id)
+                        && organization.domain.as_ref() == Some(domain)
+                        && Some(&organization.id) != id
+                });
+        if taken {
+            Err(Error::Conflict(format!("The domain {domain} is taken")))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Deletes a contact along with its notes and relations, logging the change if it existed.
+    async fn remove_contact(&self, address_book_id: AddressBookId, id: ContactId) {
+        if self.data.write().await.remove_entry(&id).is_some() {
+            self.contact_books.write().await.remove(&id);
+            self.notes
+                .write()
+                .await
+                .retain(|note: &Note| note.contact_id != id);
+            self.relations.write().await.retain(|relation: &Relation| {
+                relation.contact_id != id && relation.related_id != id
+            });
+            self.log_change(address_book_id, id, true).await;
         }
     }
 }
@@ -33,15 +237,19 @@ impl Default for ContactsInMemoryRepository {
 impl ContactsRepository for ContactsInMemoryRepository {
     async fn get_all(
         &self,
+        address_book_id: AddressBookId,
+        filter: &ContactFilter,
         page_no: Option<u32>,
         page_size: Option<u32>,
     ) -> Result<Vec<Contact>, Error> {
         let (limit, offset): (u32, u32) = get_limit_and_offset(page_no, page_size);
+        let ids: HashSet<ContactId> = self.contact_ids(address_book_id).await;
         let contacts: Vec<Contact> = self
             .data
             .read()
             .await
             .values()
+            .filter(|contact: &&Contact| ids.contains(&contact.id) && filter.matches(contact))
             .skip(offset as usize)
             .take(limit as usize)
             .cloned()
@@ -49,14 +257,55 @@ impl ContactsRepository for ContactsInMemoryRepository {
         Ok(contacts)
     }
 
-    async fn get(&self, id: ContactId) -> Result<Option<Contact>, Error> {
+    fn get_all_as_stream(
+        &self,
+        address_book_id: AddressBookId,
+    ) -> BoxStream<'static, Result<Contact, Error>> {
+        let data: Arc<RwLock<HashMap<ContactId, Contact>>> = self.data.clone();
+        let contact_books: Arc<RwLock<HashMap<ContactId, AddressBookId>>> =
+            self.contact_books.clone();
+        Box::pin(stream! {
+            let mut ids: Vec<i32> = contact_books
+                .read()
+                .await
+                .iter()
+                .filter(|(_, book_id)| **book_id == address_book_id)
+                .map(|(id, _)| id.0)
+                .collect();
+            ids.sort_unstable();
+            for id in ids {
+                if let Some(contact) = data.read().await.get(&ContactId(id)).cloned() {
+                    yield Ok(contact);
+                }
+            }
+        })
+    }
+
+    async fn get(
+        &self,
+        address_book_id: AddressBookId,
+        id: ContactId,
+    ) -> Result<Option<Contact>, Error> {
+        if !self.has_contact(address_book_id, &id).await {
+            return Ok(None);
+        }
         match self.data.read().await.get(&id) {
             Some(contact) => Ok(Some(contact.to_owned())),
             None => Ok(None),
         }
     }
 
-    async fn add(&mut self, new_contact: NewContact) -> Result<Contact, Error> {
+    async fn add(
+        &mut self,
+        address_book_id: AddressBookId,
+        new_contact: NewContact,
+    ) -> Result<Contact, Error> {
+        let organization: Option<Affiliation> = self
+            .resolve_affiliation(address_book_id, new_contact.organization)
+            .await?;
+        let custom_fields: CustomFieldValues = self
+            .validate_custom_fields(new_contact.custom_fields)
+            .await?;
         let count: usize = self.data.read().await.values().count();
         let mut id: i32 = count as i32;
         while self.data.read().await.contains_key(&ContactId(id)) {
@@ -66,50 +315,1367 @@ impl ContactsRepository for ContactsInMemoryRepository {
         let contact: Contact = Contact {
             id: ContactId(id),
             name: new_contact.name,
-            phone_no: new_contact.phone_no,
-            email: new_contact.email,
+            phones: with_single_primary(new_contact.phones),
+            emails: with_single_primary(new_contact.emails),
+            addresses: with_single_primary(new_contact.addresses),
+            tags: unique_sorted(new_contact.tags),
+            organization,
+            custom_fields,
+            birthday: new_contact.birthday,
+            dates: new_contact.dates,
         };
         self.data
             .write()
             .await
             .insert(ContactId(id), contact.clone());
+        self.contact_books
+            .write()
+            .await
+            .insert(ContactId(id), address_book_id);
+        self.log_change(address_book_id, ContactId(id), false).await;
 
         Ok(contact)
     }
 
-    async fn update(&mut self, contact: Contact, id: ContactId) -> Result<(), Error> {
-        self.data.write().await.insert(id, contact.clone());
+    async fn update(
+        &mut self,
+        address_book_id: AddressBookId,
+        contact: Contact,
+        id: ContactId,
+    ) -> Result<(), Error> {
+        let organization: Option<Affiliation> = self
+            .resolve_affiliation(address_book_id, contact.organization)
+            .await?;
+        let custom_fields: CustomFieldValues =
+            self.validate_custom_fields(contact.custom_fields).await?;
+        if !self.has_contact(address_book_id, &id).await {
+            return Ok(());
+        }
+        // Stored under the id of the path, whatever the id of the body
+        let contact: Contact = Contact {
+            id: id.clone(),
+            phones: with_single_primary(contact.phones),
+            emails: with_single_primary(contact.emails),
+            addresses: with_single_primary(contact.addresses),
+            tags: unique_sorted(contact.tags),
+            organization,
+            custom_fields,
+            ..contact
+        };
+        self.data.write().await.insert(id.clone(), contact);
+        self.log_change(address_book_id, id, false).await;
+        Ok(())
+    }
+
+    async fn update_email(
+        &mut self,
+        address_book_id: AddressBookId,
+        new_email: String,
+        id: ContactId,
+    ) -> Result<(), Error> {
+        if !self.has_contact(address_book_id, &id).await {
+            return Ok(());
+        }
+        if let Some(contact) = self.data.write().await.get_mut(&id) {
+            match contact
+                .emails
+                .iter_mut()
+                .find(|email: &&mut Email| email.primary)
+            {
+                Some(email) => email.address = new_email,
+                None => contact.emails.insert(
+                    0,
+                    Email {
+                        email_type: EmailType::default(),
+                        address: new_email,
+                        primary: true,
+                    },
+                ),
+            }
+        } else {
+            return Ok(());
+        }
+        self.log_change(address_book_id, id, false).await;
+        Ok(())
+    }
+
+    async fn update_phone_no(
+        &mut self,
+        address_book_id: AddressBookId,
+        new_phone_no: PhoneNumber,
+        id: ContactId,
+    ) -> Result<(), Error> {
+        if !self.has_contact(address_book_id, &id).await {
+            return Ok(());
+        }
+        if let Some(contact) = self.data.write().await.get_mut(&id) {
+            match contact
+                .phones
+                .iter_mut()
+                .find(|phone: &&mut Phone| phone.primary)
+            {
+                Some(phone) => phone.number = new_phone_no,
+                None => contact.phones.insert(
+                    0,
+                    Phone {
+                        phone_type: PhoneType::default(),
+                        number: new_phone_no,
+                        primary: true,
+                    },
+                ),
+            }
+        } else {
+            return Ok(());
+        }
+        self.log_change(address_book_id, id, false).await;
+        Ok(())
+    }
+
+    async fn add_tag(
+        &mut self,
+        address_book_id: AddressBookId,
+        id: ContactId,
+        tag: Tag,
+    ) -> Result<(), Error> {
+        if !self.has_contact(address_book_id, &id).await {
+            return Ok(());
+        }
+        if let Some(contact) = self.data.write().await.get_mut(&id) {
+            if contact.tags.contains(&tag) {
+                return Ok(());
+            }
+            contact.tags.push(tag);
+            contact.tags.sort();
+        } else {
+            return Ok(());
+        }
+        self.log_change(address_book_id, id, false).await;
+        Ok(())
+    }
+
+    async fn remove_tag(
+        &mut self,
+        address_book_id: AddressBookId,
+        id: ContactId,
+        tag: Tag,
+    ) -> Result<(), Error> {
+        if !self.has_contact(address_book_id, &id).await {
+            return Ok(());
+        }
+        if let Some(contact) = self.data.write().await.get_mut(&id) {
+            let count: usize = contact.tags.len();
+            contact.tags.retain(|x: &Tag| *x != tag);
+            if contact.tags.len() == count {
+                return Ok(());
+            }
+        } else {
+            return Ok(());
+        }
+        self.log_change(address_book_id, id, false).await;
+        Ok(())
+    }
+
+    async fn get_tags(&self, address_book_id: AddressBookId) -> Result<Vec<TagCount>, Error> {
+        let mut counts: BTreeMap<Tag, i64> = BTreeMap::new();
+        for contact in self.contacts_where(address_book_id, |_| true).await {
+            for tag in contact.tags {
+                *counts.entry(tag).or_default() += 1;
+            }
+        }
+        Ok(counts
+            .into_iter()
+            .map(|(tag, count)| TagCount { tag, count })
+            .collect())
+    }
+
+    async fn delete(&mut self, address_book_id: AddressBookId, id: ContactId) -> Result<(), Error> {
+        if self.has_contact(address_book_id, &id).await {
+            self.remove_contact(address_book_id, id).await;
+        }
+        Ok(())
+    }
+
+    async fn get_changes(
+        &self,
+        address_book_id: AddressBookId,
+        sync_token: Option<i64>,
+    ) -> Result<ContactChanges, Error> {
+        let changes = self.changes.read().await;
+        let latest_sync_token: i64 = changes
+            .iter()
+            .rposition(|change: &ContactChange| change.address_book_id == address_book_id)
+            .map_or(0, |index: usize| index as i64 + 1);
+        let mut contact_changes: ContactChanges = ContactChanges {
+            sync_token: latest_sync_token,
+            ..ContactChanges::default()
+        };
+        let Some(sync_token) = sync_token else {
+            return Ok(contact_changes);
+        };
+        if sync_token > contact_changes.sync_token || sync_token < 0 {
+            return Err(Error::InvalidSyncToken(sync_token.to_string()));
+        }
+
+        let mut latest: HashMap<ContactId, bool> = HashMap::new();
+        for change in changes
+            .iter()
+            .skip(sync_token as usize)
+            .filter(|change: &&ContactChange| change.address_book_id == address_book_id)
+        {
+            latest.insert(change.id.clone(), change.deleted);
+        }
+        for (id, deleted) in latest {
+            if deleted {
+                contact_changes.deleted.push(id);
+            } else {
+                contact_changes.updated.push(id);
+            }
+        }
+        Ok(contact_changes)
+    }
+
+    async fn get_last_modified(
+        &self,
+        address_book_id: AddressBookId,
+        id: Option<ContactId>,
+    ) -> Result<Option<DateTime<Utc>>, Error> {
+        Ok(self
+            .changes
+            .read()
+            .await
+            .iter()
+            .filter(|change: &&ContactChange| {
+                change.address_book_id == address_book_id
+                    && id.as_ref().is_none_or(|id: &ContactId| *id == change.id)
+            })
+            .map(|change: &ContactChange| change.changed_at)
+            .max())
+    }
+
+    async fn get_upcoming_dates(
+        &self,
+        address_book_id: AddressBookId,
+        from: NaiveDate,
+        days: u32,
+    ) -> Result<Vec<UpcomingDate>, Error> {
+        let mut upcoming: Vec<UpcomingDate> = self
+            .contacts_where(address_book_id, |_| true)
+            .await
+            .iter()
+            .flat_map(|contact: &Contact| upcoming_dates(contact, from, days))
+            .collect();
+        sort_upcoming_dates(&mut upcoming);
+        Ok(upcoming)
+    }
+}
+
+#[async_trait]
+impl OrganizationsRepository for ContactsInMemoryRepository {
+    async fn get_all_organizations(
+        &self,
+        address_book_id: AddressBookId,
+        page_no: Option<u32>,
+        page_size: Option<u32>,
+    ) -> Result<Vec<Organization>, Error> {
+        let (limit, offset): (u32, u32) = get_limit_and_offset(page_no, page_size);
+        let organization_books = self.organization_books.read().await;
+        let mut organizations: Vec<Organization> = self
+            .organizations
+            .read()
+            .await
+            .values()
+            .filter(|organization: &&Organization| {
+                organization_books.get(&organization.id) == Some(&address_book_id)
+            })
+            .cloned()
+            .collect();
+        organizations.sort_by(|a: &Organization, b: &Organization| {
+            (&a.name, a.id.0).cmp(&(&b.name, b.id.0))
+        });
+        Ok(organizations
+            .into_iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .collect())
+    }
+
+    async fn get_organization(
+        &self,
+        address_book_id: AddressBookId,
+        id: OrganizationId,
+    ) -> Result<Option<Organization>, Error> {
+        if !self.has_organization(address_book_id, &id).await {
+            return Ok(None);
+        }
+        Ok(self.organizations.read().await.get(&id).cloned())
+    }
+
+    async fn add_organization(
+        &mut self,
+        address_book_id: AddressBookId,
+        new_organization: NewOrganization,
+    ) -> Result<Organization, Error> {
+        self.check_domain(address_book_id, new_organization.domain.as_ref(), None)
+            .await?;
+        let mut id: i32 = self.organizations.read().await.len() as i32 + 1;
+        while self
+            .organizations
+            .read()
+            .await
+            .contains_key(&OrganizationId(id))
+        {
+            id += 1;
+        }
+
+        let organization: Organization = Organization {
+            id: OrganizationId(id),
+            name: new_organization.name,
+            domain: new_organization.domain,
+            address: new_organization.address,
+        };
+        self.organizations
+            .write()
+            .await
+            .insert(OrganizationId(id), organization.clone());
+        self.organization_books
+            .write()
+            .await
+            .insert(OrganizationId(id), address_book_id);
+        Ok(organization)
+    }
+
+    async fn update_organization(
+        &mut self,
+        address_book_id: AddressBookId,
+        organization: NewOrganization,
+        id: OrganizationId,
+    ) -> Result<(), Error> {
+        if !self.has_organization(address_book_id, &id).await {
+            return Ok(());
+        }
+        self.check_domain(address_book_id, organization.domain.as_ref(), Some(&id))
+            .await?;
+        if let Some(existing) = self.organizations.write().await.get_mut(&id) {
+            existing.name = organization.name.clone();
+            existing.domain = organization.domain;
+            existing.address = organization.address;
+        } else {
+            return Ok(());
+        }
+        for contact in self.data.write().await.values_mut() {
+            if let Some(affiliation) = contact.organization.as_mut() {
+                if affiliation.id == id {
+                    affiliation.name = organization.name.clone();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn delete_organization(
+        &mut self,
+        address_book_id: AddressBookId,
+        id: OrganizationId,
+    ) -> Result<(), Error> {
+        if !self.has_organization(address_book_id, &id).await {
+            return Ok(());
+        }
+        self.organization_books.write().await.remove(&id);
+        if self.organizations.write().await.remove(&id).is_none() {
+            return Ok(());
+        }
+        let mut unlinked: Vec<ContactId> = vec![];
+        for contact in self.data.write().await.values_mut() {
+            if contact
+                .organization
+                .as_ref()
+                .is_some_and(|affiliation: &Affiliation| affiliation.id == id)
+            {
+                contact.organization = None;
+                unlinked.push(contact.id.clone());
+            }
+        }
+        for contact_id in unlinked {
+            self.log_change(address_book_id, contact_id, false).await;
+        }
+        Ok(())
+    }
+
+    async fn get_organization_contacts(
+        &self,
+        address_book_id: AddressBookId,
+        id: OrganizationId,
+    ) -> Result<Vec<Contact>, Error> {
+        let mut contacts: Vec<Contact> = self
+            .contacts_where(address_book_id, |contact: &Contact| {
+                contact
+                    .organization
+                    .as_ref()
+                    .is_some_and(|affiliation: &Affiliation| affiliation.id == id)
+            })
+            .await;
+        contacts.sort_by_key(|contact: &Contact| contact.id.0);
+        Ok(contacts)
+    }
+
+    async fn get_suggested_contacts(
+        &self,
+        address_book_id: AddressBookId,
+        id: OrganizationId,
+    ) -> Result<Vec<Contact>, Error> {
+        let Some(domain) = self
+            .get_organization(address_book_id, id)
+            .await?
+            .and_then(|organization: Organization| organization.domain)
+        else {
+            return Ok(vec![]);
+        };
+        let mut contacts: Vec<Contact> = self
+            .contacts_where(address_book_id, |contact: &Contact| {
+                contact.organization.is_none()
+                    && contact
+                        .emails
+                        .iter()
+                        .any(|email: &Email| email_domain(&email.address).as_ref() == Some(&domain))
+            })
+            .await;
+        contacts.sort_by_key(|contact: &Contact| contact.id.0);
+        Ok(contacts)
+    }
+}
+
+#[async_trait]
+impl AddressBooksRepository for ContactsInMemoryRepository {
+    async fn get_address_books(&self, owner_id: &str) -> Result<Vec<AddressBook>, Error> {
+        Ok(self
+            .address_books
+            .read()
+            .await
+            .values()
+            .filter(|address_book: &&AddressBook| address_book.owner_id == owner_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_shared_address_books(
+        &self,
+        username: &str,
+        role: Option<Role>,
+    ) -> Result<Vec<AddressBook>, Error> {
+        let shared_ids: HashSet<AddressBookId> = self
+            .shares
+            .read()
+            .await
+            .iter()
+            .filter(|share: &&Share| share.grantee.includes(username, role))
+            .map(|share: &Share| share.address_book_id)
+            .collect();
+        Ok(self
+            .address_books
+            .read()
+            .await
+            .values()
+            .filter(|address_book: &&AddressBook| {
+                address_book.owner_id != username && shared_ids.contains(&address_book.id)
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn get_address_book(&self, id: AddressBookId) -> Result<Option<AddressBook>, Error> {
+        Ok(self.address_books.read().await.get(&id).cloned())
+    }
+
+    async fn add_address_book(
+        &mut self,
+        owner_id: &str,
+        new_address_book: NewAddressBook,
+    ) -> Result<AddressBook, Error> {
+        let mut address_books = self.address_books.write().await;
+        let id: AddressBookId = AddressBookId(
+            address_books
+                .keys()
+                .last()
+                .map_or(1, |id: &AddressBookId| id.0 + 1),
+        );
+        let address_book: AddressBook = AddressBook {
+            id,
+            name: new_address_book.name,
+            owner_id: owner_id.to_string(),
+            created_at: Utc::now(),
+        };
+        address_books.insert(id, address_book.clone());
+        Ok(address_book)
+    }
+
+    async fn update_address_book(
+        &mut self,
+        id: AddressBookId,
+        address_book: NewAddressBook,
+    ) -> Result<(), Error> {
+        if let Some(existing) = self.address_books.write().await.get_mut(&id) {
+            existing.name = address_book.name;
+        }
+        Ok(())
+    }
+
+    async fn delete_address_book(&mut self, id: AddressBookId) -> Result<(), Error> {
+        if self.address_books.write().await.remove(&id).is_none() {
+            return Ok(());
+        }
+        self.shares
+            .write()
+            .await
+            .retain(|share: &Share| share.address_book_id != id);
+        let mut contact_ids: Vec<ContactId> = self.contact_ids(id).await.into_iter().collect();
+        contact_ids.sort_by_key(|contact_id: &ContactId| contact_id.0);
+        for contact_id in contact_ids {
+            self.remove_contact(id, contact_id).await;
+        }
+        let mut organization_books = self.organization_books.write().await;
+        let mut organizations = self.organizations.write().await;
+        organization_books.retain(
+            |organization_id: &OrganizationId, book_id: &mut AddressBookId| {
+                if *book_id == id {
+                    organizations.remove(organization_id);
+                    false
+                } else {
+                    true
+                }
+            },
+        );
         Ok(())
     }
 
-    async fn update_email(&mut self, new_email: String, id: ContactId) -> Result<(), Error> {
-        if let Some(contact) = self.data.read().await.get(&id) {
-            self.data.write().await.insert(
-                id,
+    async fn get_shares(&self, id: AddressBookId) -> Result<Vec<Share>, Error> {
+        Ok(self
+            .shares
+            .read()
+            .await
+            .iter()
+            .filter(|share: &&Share| share.address_book_id == id)
+            .cloned()
+            .collect())
+    }
+
+    async fn add_share(&mut self, id: AddressBookId, new_share: NewShare) -> Result<Share, Error> {
+        let mut shares = self.shares.write().await;
+        if shares
+            .iter()
+            .any(|share: &Share| share.address_book_id == id && share.grantee == new_share.grantee)
+        {
+            return Err(Error::Conflict(format!(
+                "The address book is already shared with the {} {}",
+                new_share.grantee.kind(),
+                new_share.grantee.name()
+            )));
+        }
+        let share: Share = Share {
+            id: ShareId(shares.last().map_or(1, |share: &Share| share.id.0 + 1)),
+            address_book_id: id,
+            grantee: new_share.grantee,
+            permission: new_share.permission,
+            created_at: Utc::now(),
+        };
+        shares.push(share.clone());
+        Ok(share)
+    }
+
+    async fn delete_share(&mut self, id: AddressBookId, share_id: ShareId) -> Result<(), Error> {
+        self.shares
+            .write()
+            .await
+            .retain(|share: &Share| share.address_book_id != id || share.id != share_id);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl CustomFieldsRepository for ContactsInMemoryRepository {
+    async fn get_custom_fields(&self) -> Result<Vec<CustomField>, Error> {
+        Ok(self.custom_fields.read().await.values().cloned().collect())
+    }
+
+    async fn put_custom_field(&mut self, custom_field: CustomField) -> Result<(), Error> {
+        for contact in self.data.read().await.values() {
+            if let Some(value) = contact.custom_fields.get(&custom_field.name) {
+                custom_field
+                    .validate(value)
+                    .map_err(|err: Error| Error::Conflict(err.to_string()))?;
+            }
+        }
+        self.custom_fields
+            .write()
+            .await
+            .insert(custom_field.name.clone(), custom_field);
+        Ok(())
+    }
+
+    async fn delete_custom_field(&mut self, name: String) -> Result<(), Error> {
+        self.custom_fields.write().await.remove(&name);
+        let mut changed: Vec<ContactId> = vec![];
+        for contact in self.data.write().await.values_mut() {
+            if contact.custom_fields.remove(&name).is_some() {
+                changed.push(contact.id.clone());
+            }
+        }
+        for id in changed {
+            let address_book_id: Option<AddressBookId> =
+                self.contact_books.read().await.get(&id).copied();
+            if let Some(address_book_id) = address_book_id {
+                self.log_change(address_book_id, id, false).await;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl NotesRepository for ContactsInMemoryRepository {
+    async fn get_notes(&self, contact_id: ContactId) -> Result<Vec<Note>, Error> {
+        Ok(self
+            .notes
+            .read()
+            .await
+            .iter()
+            .rev()
+            .filter(|note: &&Note| note.contact_id == contact_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn add_note(
+        &mut self,
+        contact_id: ContactId,
+        author: String,
+        new_note: NewNote,
+    ) -> Result<Note, Error> {
+        if !self.data.read().await.contains_key(&contact_id) {
+            return Err(Error::NotFound { id: contact_id.0 });
+        }
+        let mut notes = self.notes.write().await;
+        let id: i32 = notes.last().map_or(1, |note: &Note| note.id.0 + 1);
+        let now: DateTime<Utc> = Utc::now();
+        let note: Note = Note {
+            id: NoteId(id),
+            contact_id,
+            author,
+            body: new_note.body,
+            created_at: now,
+            updated_at: now,
+        };
+        notes.push(note.clone());
+        Ok(note)
+    }
+
+    async fn update_note(
+        &mut self,
+        contact_id: ContactId,
+        id: NoteId,
+        new_note: NewNote,
+    ) -> Result<(), Error> {
+        if let Some(note) = self
+            .notes
+            .write()
+            .await
+            .iter_mut()
+            .find(|note: &&mut Note| note.id == id && note.contact_id == contact_id)
+        {
+            note.body = new_note.body;
+            note.updated_at = Utc::now();
+        }
+        Ok(())
+    }
+
+    async fn delete_note(&mut self, contact_id: ContactId, id: NoteId) -> Result<(), Error> {
+        self.notes
+            .write()
+            .await
+            .retain(|note: &Note| !(note.id == id && note.contact_id == contact_id));
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RelationsRepository for ContactsInMemoryRepository {
+    async fn get_relations(&self, contact_ids: &[ContactId]) -> Result<Vec<Relation>, Error> {
+        Ok(self
+            .relations
+            .read()
+            .await
+            .iter()
+            .filter(|relation: &&Relation| {
+                contact_ids.contains(&relation.contact_id)
+                    || contact_ids.contains(&relation.related_id)
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn add_relation(
+        &mut self,
+        contact_id: ContactId,
+        new_relation: NewRelation,
+    ) -> Result<Relation, Error> {
+        for id in [&contact_id, &new_relation.related_id] {
+            if !self.data.read().await.contains_key(id) {
+                return Err(Error::NotFound { id: id.0 });
+            }
+        }
+        let mut relations = self.relations.write().await;
+        let taken: bool = relations.iter().any(|relation: &Relation| {
+            relation.relation_type == new_relation.relation_type
+                && ((relation.contact_id == contact_id
+                    && relation.related_id == new_relation.related_id)
+                    || (relation.bidirectional
+                        && new_relation.bidirectional
+                        && relation.contact_id == new_relation.related_id
+                        && relation.related_id == contact_id))
+        });
+        if taken {
+            return Err(Error::Conflict(format!(
+                "The contacts are already related as {}",
+                new_relation.relation_type.as_str()
+            )));
+        }
+        let id: i32 = relations
+            .last()
+            .map_or(1, |relation: &Relation| relation.id.0 + 1);
+        let relation: Relation = Relation {
+            id: RelationId(id),
+            contact_id,
+            related_id: new_relation.related_id,
+            relation_type: new_relation.relation_type,
+            bidirectional: new_relation.bidirectional,
+        };
+        relations.push(relation.clone());
+        Ok(relation)
+    }
+
+    async fn delete_relation(
+        &mut self,
+        contact_id: ContactId,
+        id: RelationId,
+    ) -> Result<(), Error> {
+        let mut relations = self.relations.write().await;
+        let count: usize = relations.len();
+        relations.retain(|relation: &Relation| {
+            !(relation.id == id
+                && (relation.contact_id == contact_id || relation.related_id == contact_id))
+        });
+        if relations.len() == count {
+            return Err(Error::NotFound { id: id.0 });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::contact::Address;
+    use crate::models::important_date::ImportantDate;
+    use crate::models::relation::RelationType;
+    use crate::models::share::Grantee;
+    use crate::models::share::SharePermission;
+    use crate::models::tag::TagMode;
+    use isocountry::CountryCode;
+
+    const BOOK: AddressBookId = AddressBookId(1);
+
+    #[tokio::test]
+    async fn test_get_changes() {
+        let mut repository: ContactsInMemoryRepository = ContactsInMemoryRepository::new();
+        let new_contact: NewContact = NewContact {
+            name: "John".to_string(),
+            phones: vec![],
+            emails: vec![],
+            addresses: vec![],
+            tags: vec![],
+            organization: None,
+            custom_fields: CustomFieldValues::new(),
+            birthday: None,
+            dates: vec![],
+        };
+        let first: Contact = repository.add(BOOK, new_contact.clone()).await.unwrap();
+        let sync_token: i64 = repository.get_changes(BOOK, None).await.unwrap().sync_token;
+        let second: Contact = repository.add(BOOK, new_contact).await.unwrap();
+        repository.delete(BOOK, first.id.clone()).await.unwrap();
+
+        let changes: ContactChanges = repository
+            .get_changes(BOOK, Some(sync_token))
+            .await
+            .unwrap();
+
+        assert_eq!(3, changes.sync_token);
+        assert_eq!(vec![second.id], changes.updated);
+        assert_eq!(vec![first.id], changes.deleted);
+        assert!(repository.get_changes(BOOK, Some(4)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_update_keeps_the_id_of_the_path() {
+        let mut repository: ContactsInMemoryRepository = ContactsInMemoryRepository::new();
+        let contact: Contact = repository
+            .add(
+                BOOK,
+                NewContact {
+                    name: "John".to_string(),
+                    phones: vec![],
+                    emails: vec![],
+                    addresses: vec![],
+                    tags: vec![],
+                    organization: None,
+                    custom_fields: CustomFieldValues::new(),
+                    birthday: None,
+                    dates: vec![],
+                },
+            )
+            .await
+            .unwrap();
+
+        repository
+            .update(
+                BOOK,
                 Contact {
-                    email: new_email,
+                    id: ContactId(7),
+                    name: "Jim".to_string(),
                     ..contact.clone()
                 },
-            );
+                contact.id.clone(),
+            )
+            .await
+            .unwrap();
+
+        let updated: Contact = repository
+            .get(BOOK, contact.id.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(("Jim", contact.id), (updated.name.as_str(), updated.id));
+        assert!(repository.get(BOOK, ContactId(7)).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_get_all_filtered_by_address() {
+        let mut repository: ContactsInMemoryRepository = ContactsInMemoryRepository::new();
+        for (name, locality, country) in [
+            ("John", "Berlin", CountryCode::DEU),
+            ("Jane", "Paris", CountryCode::FRA),
+        ] {
+            let address: Address = Address {
+                label: None,
+                street: "Main St. 1".to_string(),
+                locality: locality.to_string(),
+                region: String::new(),
+                postal_code: String::new(),
+                country,
+                primary: false,
+            };
+            let new_contact: NewContact = NewContact {
+                name: name.to_string(),
+                phones: vec![],
+                emails: vec![],
+                addresses: vec![address],
+                tags: vec![],
+                organization: None,
+                custom_fields: CustomFieldValues::new(),
+                birthday: None,
+                dates: vec![],
+            };
+            repository.add(BOOK, new_contact).await.unwrap();
         }
-        Ok(())
+
+        let filter: ContactFilter = ContactFilter {
+            city: Some("paris".to_string()),
+            ..ContactFilter::default()
+        };
+        let contacts: Vec<Contact> = repository.get_all(BOOK, &filter, None, None).await.unwrap();
+
+        assert_eq!(1, contacts.len());
+        assert_eq!("Jane", contacts[0].name);
+        assert!(contacts[0].addresses[0].primary);
+        let filter: ContactFilter = ContactFilter {
+            country: Some(CountryCode::FRA),
+            city: Some("Berlin".to_string()),
+            ..ContactFilter::default()
+        };
+        assert!(repository
+            .get_all(BOOK, &filter, None, None)
+            .await
+            .unwrap()
+            .is_empty());
     }
 
-    async fn update_phone_no(&mut self, new_phone_no: i64, id: ContactId) -> Result<(), Error> {
-        if let Some(contact) = self.data.read().await.get(&id) {
-            self.data.write().await.insert(
-                id,
+    #[tokio::test]
+    async fn test_tags() {
+        let mut repository: ContactsInMemoryRepository = ContactsInMemoryRepository::new();
+        let family: Tag = Tag::parse("family").unwrap();
+        let work: Tag = Tag::parse("work").unwrap();
+        let mut ids: Vec<ContactId> = vec![];
+        for name in ["John", "Jane", "Jim"] {
+            let new_contact: NewContact = NewContact {
+                name: name.to_string(),
+                phones: vec![],
+                emails: vec![],
+                addresses: vec![],
+                tags: vec![family.clone()],
+                organization: None,
+                custom_fields: CustomFieldValues::new(),
+                birthday: None,
+                dates: vec![],
+            };
+            ids.push(repository.add(BOOK, new_contact).await.unwrap().id);
+        }
+        repository
+            .add_tag(BOOK, ids[0].clone(), work.clone())
+            .await
+            .unwrap();
+        repository
+            .add_tag(BOOK, ids[1].clone(), work.clone())
+            .await
+            .unwrap();
+        repository
+            .remove_tag(BOOK, ids[1].clone(), family.clone())
+            .await
+            .unwrap();
+
+        let tags: Vec<TagCount> = repository.get_tags(BOOK).await.unwrap();
+        assert_eq!(
+            vec![
+                TagCount {
+                    tag: family.clone(),
+                    count: 2
+                },
+                TagCount {
+                    tag: work.clone(),
+                    count: 2
+                }
+            ],
+            tags
+        );
+        for (tag_mode, expected) in [(TagMode::And, 1), (TagMode::Or, 3)] {
+            let filter: ContactFilter = ContactFilter {
+                tags: vec![family.clone(), work.clone()],
+                tag_mode,
+                ..ContactFilter::default()
+            };
+            let contacts: Vec<Contact> =
+                repository.get_all(BOOK, &filter, None, None).await.unwrap();
+            assert_eq!(expected, contacts.len());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_organizations() {
+        let mut repository: ContactsInMemoryRepository = ContactsInMemoryRepository::new();
+        let new_organization: NewOrganization = NewOrganization {
+            name: "ACME".to_string(),
+            domain: Some("acme.com".to_string()),
+            address: None,
+        };
+        let organization: Organization = repository
+            .add_organization(BOOK, new_organization.clone())
+            .await
+            .unwrap();
+        assert!(repository
+            .add_organization(BOOK, new_organization)
+            .await
+            .is_err());
+        let mut ids: Vec<ContactId> = vec![];
+        for (name, address) in [("John", "john@ACME.com"), ("Jane", "jane@doe.com")] {
+            let new_contact: NewContact = NewContact {
+                name: name.to_string(),
+                phones: vec![],
+                emails: vec![Email {
+                    email_type: EmailType::Work,
+                    address: address.to_string(),
+                    primary: true,
+                }],
+                addresses: vec![],
+                tags: vec![],
+                organization: None,
+                custom_fields: CustomFieldValues::new(),
+                birthday: None,
+                dates: vec![],
+            };
+            ids.push(repository.add(BOOK, new_contact).await.unwrap().id);
+        }
+
+        let suggested: Vec<Contact> = repository
+            .get_suggested_contacts(BOOK, organization.id.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            vec![ids[0].clone()],
+            suggested
+                .iter()
+                .map(|x: &Contact| x.id.clone())
+                .collect::<Vec<ContactId>>()
+        );
+        let affiliation: Affiliation = Affiliation {
+            id: organization.id.clone(),
+            name: String::new(),
+            job_title: Some("CEO".to_string()),
+        };
+        let contact: Contact = Contact {
+            organization: Some(affiliation),
+            ..suggested[0].clone()
+        };
+        repository
+            .update(BOOK, contact, ids[0].clone())
+            .await
+            .unwrap();
+        let contacts: Vec<Contact> = repository
+            .get_organization_contacts(BOOK, organization.id.clone())
+            .await
+            .unwrap();
+        assert_eq!(1, contacts.len());
+        assert_eq!("ACME", contacts[0].organization.as_ref().unwrap().name);
+        assert!(repository
+            .get_suggested_contacts(BOOK, organization.id.clone())
+            .await
+            .unwrap()
+            .is_empty());
+
+        repository
+            .delete_organization(BOOK, organization.id.clone())
+            .await
+            .unwrap();
+        let contact: Contact = repository.get(BOOK, ids[0].clone()).await.unwrap().unwrap();
+        assert!(contact.organization.is_none());
+        assert!(repository
+            .update(
+                BOOK,
                 Contact {
-                    phone_no: new_phone_no,
-                    ..contact.clone()
+                    organization: Some(Affiliation {
+                        id: organization.id,
+                        name: String::new(),
+                        job_title: None,
+                    }),
+                    ..contact
                 },
+                ids[0].clone()
+            )
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_notes() {
+        let mut repository: ContactsInMemoryRepository = ContactsInMemoryRepository::new();
+        let new_contact: NewContact = NewContact {
+            name: "John".to_string(),
+            phones: vec![],
+            emails: vec![],
+            addresses: vec![],
+            tags: vec![],
+            organization: None,
+            custom_fields: CustomFieldValues::new(),
+            birthday: None,
+            dates: vec![],
+        };
+        let contact: Contact = repository.add(BOOK, new_contact).await.unwrap();
+        for body in ["Called", "Met"] {
+            let new_note: NewNote = NewNote {
+                body: body.to_string(),
+            };
+            repository
+                .add_note(contact.id.clone(), "admin".to_string(), new_note)
+                .await
+                .unwrap();
+        }
+
+        let notes: Vec<Note> = repository.get_notes(contact.id.clone()).await.unwrap();
+        assert_eq!(
+            vec!["Met", "Called"],
+            notes
+                .iter()
+                .map(|note: &Note| note.body.as_str())
+                .collect::<Vec<&str>>()
+        );
+        assert!(repository
+            .add_note(
+                ContactId(42),
+                "admin".to_string(),
+                NewNote {
+                    body: "Lost".to_string()
+                }
+            )
+            .await
+            .is_err());
+        repository.delete(BOOK, contact.id.clone()).await.unwrap();
+        assert!(repository.get_notes(contact.id).await.unwrap().is_empty());
+    }
+    #[tokio::test]
+    async fn test_relations() {
+        let mut repository: ContactsInMemoryRepository = ContactsInMemoryRepository::new();
+        let mut ids: Vec<ContactId> = vec![];
+        for name in ["John", "Jane", "Jim"] {
+            let new_contact: NewContact = NewContact {
+                name: name.to_string(),
+                phones: vec![],
+                emails: vec![],
+                addresses: vec![],
+                tags: vec![],
+                organization: None,
+                custom_fields: CustomFieldValues::new(),
+                birthday: None,
+                dates: vec![],
+            };
+            ids.push(repository.add(BOOK, new_contact).await.unwrap().id);
+        }
+        let new_relation = |related_id: &ContactId, relation_type: RelationType| {
+            NewRelation {
+                related_id: related_id.clone(),
+                relation_type,
+                bidirectional: false,
+            }
+            .normalized()
+        };
+        repository
+            .add_relation(
+                ids[0].clone(),
+                new_relation(&ids[1], RelationType::ReportsTo),
+            )
+            .await
+            .unwrap();
+        repository
+            .add_relation(
+                ids[1].clone(),
+                new_relation(&ids[2], RelationType::SpouseOf),
+            )
+            .await
+            .unwrap();
+        let relation: Relation = repository
+            .add_relation(
+                ids[0].clone(),
+                new_relation(&ids[2], RelationType::AssistantOf),
+            )
+            .await
+            .unwrap();
+
+        // Only from or to the contact, and only once
+        assert!(matches!(
+            repository
+                .delete_relation(ids[1].clone(), relation.id.clone())
+                .await,
+            Err(Error::NotFound { .. })
+        ));
+        repository
+            .delete_relation(ids[2].clone(), relation.id.clone())
+            .await
+            .unwrap();
+        assert!(matches!(
+            repository
+                .delete_relation(ids[0].clone(), relation.id)
+                .await,
+            Err(Error::NotFound { .. })
+        ));
+
+        for (id, related_id, relation_type) in [
+            (&ids[0], &ids[1], RelationType::ReportsTo),
+            (&ids[2], &ids[1], RelationType::SpouseOf),
+        ] {
+            assert!(matches!(
+                repository
+                    .add_relation(id.clone(), new_relation(related_id, relation_type))
+                    .await,
+                Err(Error::Conflict(_))
+            ));
+        }
+        assert_eq!(
+            2,
+            repository
+                .get_relations(&[ids[1].clone()])
+                .await
+                .unwrap()
+                .len()
+        );
+        repository.delete(BOOK, ids[1].clone()).await.unwrap();
+        assert!(repository.get_relations(&ids).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_upcoming_dates() {
+        let mut repository: ContactsInMemoryRepository = ContactsInMemoryRepository::new();
+        let date =
+            |year: i32, month: u32, day: u32| NaiveDate::from_ymd_opt(year, month, day).unwrap();
+        for (name, birthday, dates) in [
+            ("John", Some(date(1990, 1, 3)), vec![]),
+            (
+                "Jane",
+                Some(date(2000, 2, 29)),
+                vec![ImportantDate {
+                    label: "anniversary".to_string(),
+                    date: date(2015, 12, 24),
+                }],
+            ),
+            ("Jim", Some(date(1985, 6, 1)), vec![]),
+        ] {
+            let new_contact: NewContact = NewContact {
+                name: name.to_string(),
+                phones: vec![],
+                emails: vec![],
+                addresses: vec![],
+                tags: vec![],
+                organization: None,
+                custom_fields: CustomFieldValues::new(),
+                birthday,
+                dates,
+            };
+            repository.add(BOOK, new_contact).await.unwrap();
+        }
+
+        let upcoming: Vec<UpcomingDate> = repository
+            .get_upcoming_dates(BOOK, date(2022, 12, 20), 70)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            vec![
+                ("Jane", "anniversary", date(2022, 12, 24), 4),
+                ("John", "birthday", date(2023, 1, 3), 14),
+                ("Jane", "birthday", date(2023, 2, 28), 70),
+            ],
+            upcoming
+                .iter()
+                .map(|x: &UpcomingDate| (
+                    x.contact.name.as_str(),
+                    x.label.as_str(),
+                    x.next_date,
+                    x.days_until
+                ))
+                .collect::<Vec<(&str, &str, NaiveDate, i64)>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_address_books() {
+        let mut repository: ContactsInMemoryRepository = ContactsInMemoryRepository::new();
+        let mut books: Vec<AddressBookId> = vec![];
+        for (owner_id, name) in [("admin", "Customers"), ("jane", "Team")] {
+            let new_address_book: NewAddressBook = NewAddressBook {
+                name: name.to_string(),
+            };
+            books.push(
+                repository
+                    .add_address_book(owner_id, new_address_book)
+                    .await
+                    .unwrap()
+                    .id,
             );
         }
-        Ok(())
+        let new_contact: NewContact = NewContact {
+            name: "John".to_string(),
+            phones: vec![],
+            emails: vec![],
+            addresses: vec![],
+            tags: vec![Tag::parse("family").unwrap()],
+            organization: None,
+            custom_fields: CustomFieldValues::new(),
+            birthday: None,
+            dates: vec![],
+        };
+        let contact: Contact = repository.add(books[0], new_contact).await.unwrap();
+
+        // The contacts of an address book are out of reach of the other ones
+        assert!(repository
+            .get(books[1], contact.id.clone())
+            .await
+            .unwrap()
+            .is_none());
+        assert!(repository.get_tags(books[1]).await.unwrap().is_empty());
+        assert_eq!(
+            0,
+            repository
+                .get_changes(books[1], None)
+                .await
+                .unwrap()
+                .sync_token
+        );
+        repository
+            .delete(books[1], contact.id.clone())
+            .await
+            .unwrap();
+        assert!(repository
+            .get(books[0], contact.id.clone())
+            .await
+            .unwrap()
+            .is_some());
+        assert_eq!(
+            vec!["Team"],
+            repository
+                .get_address_books("jane")
+                .await
+                .unwrap()
+                .iter()
+                .map(|x: &AddressBook| x.name.as_str())
+                .collect::<Vec<&str>>()
+        );
+
+        repository.delete_address_book(books[0]).await.unwrap();
+        assert!(repository
+            .get(books[0], contact.id.clone())
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            vec![contact.id],
+            repository
+                .get_changes(books[0], Some(1))
+                .await
+                .unwrap()
+                .deleted
+        );
     }
 
-    async fn delete(&mut self, id: ContactId) -> Result<(), Error> {
-        self.data.write().await.remove_entry(&id);
-        Ok(())
+    #[tokio::test]
+    async fn test_shares() {
+        let mut repository: ContactsInMemoryRepository = ContactsInMemoryRepository::new();
+        let new_address_book: NewAddressBook = NewAddressBook {
+            name: "Customers".to_string(),
+        };
+        let book: AddressBookId = repository
+            .add_address_book("admin", new_address_book)
+            .await
+            .unwrap()
+            .id;
+        let new_share: NewShare = NewShare {
+            grantee: Grantee::Group(Role::Editor),
+            permission: SharePermission::Read,
+        };
+        let share: Share = repository.add_share(book, new_share.clone()).await.unwrap();
+        assert!(matches!(
+            repository.add_share(book, new_share).await,
+            Err(Error::Conflict(_))
+        ));
+
+        // Shared with the editors, but not with the readers, nor with the owner, who has the address book anyway
+        for (username, role, expected) in [
+            ("jane", Some(Role::Editor), 1),
+            ("john", Some(Role::Reader), 0),
+            ("admin", Some(Role::Editor), 0),
+        ] {
+            assert_eq!(
+                expected,
+                repository
+                    .get_shared_address_books(username, role)
+                    .await
+                    .unwrap()
+                    .len()
+            );
+        }
+
+        repository
+            .delete_share(AddressBookId(book.0 + 1), share.id)
+            .await
+            .unwrap();
+        assert_eq!(
+            vec![share.clone()],
+            repository.get_shares(book).await.unwrap()
+        );
+        repository.delete_share(book, share.id).await.unwrap();
+        assert!(repository.get_shares(book).await.unwrap().is_empty());
     }
 }
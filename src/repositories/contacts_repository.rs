@@ -1,9 +1,20 @@
 use async_trait::async_trait;
+use chrono::DateTime;
+use chrono::NaiveDate;
+use chrono::Utc;
+use futures::stream::BoxStream;
 
+use crate::models::address_book::AddressBookId;
 use crate::models::contact::Contact;
+use crate::models::contact::ContactChanges;
+use crate::models::contact::ContactFilter;
 use crate::models::contact::ContactId;
 use crate::models::contact::NewContact;
 use crate::models::errors::Error;
+use crate::models::important_date::UpcomingDate;
+use crate::models::phone_number::PhoneNumber;
+use crate::models::tag::Tag;
+use crate::models::tag::TagCount;
 
 /// Default page number.
 pub const DEFAULT_PAGE_NO: u32 = 1;
@@ -11,34 +22,110 @@ pub const DEFAULT_PAGE_NO: u32 = 1;
 /// Default page size.
 pub const DEFAULT_PAGE_SIZE: u32 = 5;
 
-/// Contract for a Contacts repository
+/// Contract for a Contacts repository.
+/// Every method is scoped by an address book, the contacts of the other address books being out of its reach.
 #[async_trait]
 pub trait ContactsRepository {
-    /// Returns all contacts, considering a page_no and page_size.
+    /// Returns all contacts matching the filter, considering a page_no and page_size.
     /// If no page_no or no page_size, defaults will be used.
     async fn get_all(
         &self,
+        address_book_id: AddressBookId,
+        filter: &ContactFilter,
         page_no: Option<u32>,
         page_size: Option<u32>,
     ) -> Result<Vec<Contact>, Error>;
 
+    /// Returns all contacts ordered by id, one at a time, without loading them all in memory.
+    fn get_all_as_stream(
+        &self,
+        address_book_id: AddressBookId,
+    ) -> BoxStream<'static, Result<Contact, Error>>;
+
     /// Return a single contact, if found, otherwise None.
-    async fn get(&self, id: ContactId) -> Result<Option<Contact>, Error>;
+    async fn get(
+        &self,
+        address_book_id: AddressBookId,
+        id: ContactId,
+    ) -> Result<Option<Contact>, Error>;
 
-    /// Adds a contact to the repository. Returns the new contact.
-    async fn add(&mut self, new_contact: NewContact) -> Result<Contact, Error>;
+    /// Adds a contact to the address book. Returns the new contact.
+    async fn add(
+        &mut self,
+        address_book_id: AddressBookId,
+        new_contact: NewContact,
+    ) -> Result<Contact, Error>;
 
     /// Updates an existing contact. Doesn't return anything. Safe for no-ops.
-    async fn update(&mut self, contact: Contact, id: ContactId) -> Result<(), Error>;
+    async fn update(
+        &mut self,
+        address_book_id: AddressBookId,
+        contact: Contact,
+        id: ContactId,
+    ) -> Result<(), Error>;
 
     /// Updates only the email of a contact. Doesn't return anything. Safe for no-ops.
-    async fn update_email(&mut self, new_email: String, id: ContactId) -> Result<(), Error>;
+    async fn update_email(
+        &mut self,
+        address_book_id: AddressBookId,
+        new_email: String,
+        id: ContactId,
+    ) -> Result<(), Error>;
 
     /// Updates only the phone_no of a contact. Doesn't return anything. Safe for no-ops.
-    async fn update_phone_no(&mut self, new_phone_no: i64, id: ContactId) -> Result<(), Error>;
+    async fn update_phone_no(
+        &mut self,
+        address_book_id: AddressBookId,
+        new_phone_no: PhoneNumber,
+        id: ContactId,
+    ) -> Result<(), Error>;
+
+    /// Tags a contact. Doesn't return anything. Safe for no-ops.
+    async fn add_tag(
+        &mut self,
+        address_book_id: AddressBookId,
+        id: ContactId,
+        tag: Tag,
+    ) -> Result<(), Error>;
+
+    /// Removes a tag from a contact. Doesn't return anything. Safe for no-ops.
+    async fn remove_tag(
+        &mut self,
+        address_book_id: AddressBookId,
+        id: ContactId,
+        tag: Tag,
+    ) -> Result<(), Error>;
+
+    /// Returns the tags in use in the address book, sorted, along with the number of its contacts tagged with each.
+    async fn get_tags(&self, address_book_id: AddressBookId) -> Result<Vec<TagCount>, Error>;
 
     /// Deletes a contact. Doesn't return anything. Safe for no-ops.
-    async fn delete(&mut self, id: ContactId) -> Result<(), Error>;
+    async fn delete(&mut self, address_book_id: AddressBookId, id: ContactId) -> Result<(), Error>;
+
+    /// Returns the latest sync token and the contacts changed after the given one.
+    /// Without a sync token, only the latest sync token is returned.
+    async fn get_changes(
+        &self,
+        address_book_id: AddressBookId,
+        sync_token: Option<i64>,
+    ) -> Result<ContactChanges, Error>;
+
+    /// Returns when the given contact, or any contact if no id is given, was last added, updated or deleted.
+    /// Returns None if there were no such changes.
+    async fn get_last_modified(
+        &self,
+        address_book_id: AddressBookId,
+        id: Option<ContactId>,
+    ) -> Result<Option<DateTime<Utc>>, Error>;
+
+    /// Returns the birthdays and labeled dates recurring within `days` days from the given day, both ends included,
+    /// ordered by when they recur. Feb 29 recurs on Feb 28 in the common years.
+    async fn get_upcoming_dates(
+        &self,
+        address_book_id: AddressBookId,
+        from: NaiveDate,
+        days: u32,
+    ) -> Result<Vec<UpcomingDate>, Error>;
 }
 
 pub fn get_limit_and_offset(page_no: Option<u32>, page_size: Option<u32>) -> (u32, u32) {
@@ -0,0 +1,19 @@
+use async_trait::async_trait;
+
+use crate::models::custom_field::CustomField;
+use crate::models::errors::Error;
+
+/// Contract for a repository of the custom field definitions.
+/// Implemented by the contacts repositories, as the values of the custom fields are stored along with the contacts.
+#[async_trait]
+pub trait CustomFieldsRepository {
+    /// Returns all the custom field definitions, ordered by name.
+    async fn get_custom_fields(&self) -> Result<Vec<CustomField>, Error>;
+
+    /// Adds a custom field definition or replaces the one with the same name.
+    /// Fails with `Error::Conflict` if a contact has a value not matching the new definition.
+    async fn put_custom_field(&mut self, custom_field: CustomField) -> Result<(), Error>;
+
+    /// Deletes a custom field definition along with its values. Doesn't return anything. Safe for no-ops.
+    async fn delete_custom_field(&mut self, name: String) -> Result<(), Error>;
+}
@@ -1,3 +1,13 @@
+pub mod address_books_repository;
+pub mod api_keys_repository;
+pub mod api_users_repository;
 pub mod contacts_db_repository;
 pub mod contacts_in_memory_repository;
 pub mod contacts_repository;
+pub mod custom_fields_repository;
+pub mod notes_repository;
+pub mod organizations_repository;
+pub mod photo_db_storage;
+pub mod photo_fs_storage;
+pub mod photo_storage;
+pub mod relations_repository;
@@ -0,0 +1,34 @@
+use async_trait::async_trait;
+
+use crate::models::contact::ContactId;
+use crate::models::errors::Error;
+use crate::models::note::NewNote;
+use crate::models::note::Note;
+use crate::models::note::NoteId;
+
+/// Contract for a repository of the notes on contacts.
+/// Implemented by the contacts repositories, as the notes are deleted along with their contact.
+#[async_trait]
+pub trait NotesRepository {
+    /// Returns the notes on a contact, the latest first.
+    async fn get_notes(&self, contact_id: ContactId) -> Result<Vec<Note>, Error>;
+
+    /// Adds a note on a contact, written by the given author. Returns the new note.
+    async fn add_note(
+        &mut self,
+        contact_id: ContactId,
+        author: String,
+        new_note: NewNote,
+    ) -> Result<Note, Error>;
+
+    /// Replaces the body of a note on a contact. Doesn't return anything. Safe for no-ops.
+    async fn update_note(
+        &mut self,
+        contact_id: ContactId,
+        id: NoteId,
+        new_note: NewNote,
+    ) -> Result<(), Error>;
+
+    /// Deletes a note on a contact. Doesn't return anything. Safe for no-ops.
+    async fn delete_note(&mut self, contact_id: ContactId, id: NoteId) -> Result<(), Error>;
+}
@@ -0,0 +1,68 @@
+use async_trait::async_trait;
+
+use crate::models::address_book::AddressBookId;
+use crate::models::contact::Contact;
+use crate::models::errors::Error;
+use crate::models::organization::NewOrganization;
+use crate::models::organization::Organization;
+use crate::models::organization::OrganizationId;
+
+/// Contract for an Organizations repository.
+/// Implemented by the contacts repositories, as the contacts are linked to the organizations of their address book.
+/// Every method is scoped by an address book.
+#[async_trait]
+pub trait OrganizationsRepository {
+    /// Returns all organizations ordered by name, considering a page_no and page_size.
+    /// If no page_no or no page_size, defaults will be used.
+    async fn get_all_organizations(
+        &self,
+        address_book_id: AddressBookId,
+        page_no: Option<u32>,
+        page_size: Option<u32>,
+    ) -> Result<Vec<Organization>, Error>;
+
+    /// Return a single organization, if found, otherwise None.
+    async fn get_organization(
+        &self,
+        address_book_id: AddressBookId,
+        id: OrganizationId,
+    ) -> Result<Option<Organization>, Error>;
+
+    /// Adds an organization to the repository. Returns the new organization.
+    /// Fails with `Error::Conflict` if another organization of the address book has the same domain.
+    async fn add_organization(
+        &mut self,
+        address_book_id: AddressBookId,
+        new_organization: NewOrganization,
+    ) -> Result<Organization, Error>;
+
+    /// Updates an existing organization. Doesn't return anything. Safe for no-ops.
+    /// Fails with `Error::Conflict` if another organization of the address book has the same domain.
+    async fn update_organization(
+        &mut self,
+        address_book_id: AddressBookId,
+        organization: NewOrganization,
+        id: OrganizationId,
+    ) -> Result<(), Error>;
+
+    /// Deletes an organization, unlinking its contacts. Doesn't return anything. Safe for no-ops.
+    async fn delete_organization(
+        &mut self,
+        address_book_id: AddressBookId,
+        id: OrganizationId,
+    ) -> Result<(), Error>;
+
+    /// Returns the contacts working at an organization, ordered by id.
+    async fn get_organization_contacts(
+        &self,
+        address_book_id: AddressBookId,
+        id: OrganizationId,
+    ) -> Result<Vec<Contact>, Error>;
+
+    /// Returns the contacts not linked to any organization yet, having an email at the domain of the given one.
+    async fn get_suggested_contacts(
+        &self,
+        address_book_id: AddressBookId,
+        id: OrganizationId,
+    ) -> Result<Vec<Contact>, Error>;
+}
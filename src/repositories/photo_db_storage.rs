@@ -0,0 +1,75 @@
+use async_trait::async_trait;
+use sqlx::postgres::PgRow;
+use sqlx::Pool;
+use sqlx::Postgres;
+use sqlx::Row;
+
+use crate::models::contact::ContactId;
+use crate::models::errors::Error;
+use crate::models::photo::ContactPhoto;
+use crate::models::photo::ImageType;
+use crate::models::photo::Photo;
+use crate::models::photo::PhotoSize;
+
+use super::photo_storage::PhotoStorage;
+
+const SQL_SELECT_ORIGINAL: &str =
+    "SELECT content_type, original AS bytes FROM contact_photos WHERE contact_id = $1;";
+const SQL_SELECT_THUMBNAIL: &str =
+    "SELECT content_type, thumbnail AS bytes FROM contact_photos WHERE contact_id = $1;";
+const SQL_UPSERT: &str = "INSERT INTO contact_photos(contact_id, content_type, original, thumbnail) VALUES ($1, $2, $3, $4) ON CONFLICT (contact_id) DO UPDATE SET content_type = EXCLUDED.content_type, original = EXCLUDED.original, thumbnail = EXCLUDED.thumbnail;";
+const SQL_DELETE: &str = "DELETE FROM contact_photos WHERE contact_id = $1;";
+
+/// Stores the photos in the `contact_photos` table, so they are deleted along with their contact.
+#[derive(Debug, Clone)]
+pub struct PhotoDbStorage {
+    db_pool: Pool<Postgres>,
+}
+
+impl PhotoDbStorage {
+    pub fn new(db_pool: Pool<Postgres>) -> Self {
+        PhotoDbStorage { db_pool }
+    }
+}
+
+#[async_trait]
+impl PhotoStorage for PhotoDbStorage {
+    async fn get_photo(&self, id: ContactId, size: PhotoSize) -> Result<Option<Photo>, Error> {
+        let sql: &str = match size {
+            PhotoSize::Original => SQL_SELECT_ORIGINAL,
+            PhotoSize::Thumbnail => SQL_SELECT_THUMBNAIL,
+        };
+        let row: Option<(String, Vec<u8>)> = sqlx::query(sql)
+            .bind(id.0)
+            .map(|row: PgRow| (row.get("content_type"), row.get("bytes")))
+            .fetch_optional(&self.db_pool)
+            .await?;
+        row.map(|(content_type, bytes)| {
+            ImageType::from_content_type(&content_type)
+                .map(|image_type: ImageType| Photo { image_type, bytes })
+                .ok_or_else(|| Error::Db(format!("Unexpected photo type {content_type}")))
+        })
+        .transpose()
+    }
+
+    async fn put_photo(&self, id: ContactId, photo: ContactPhoto) -> Result<(), Error> {
+        sqlx::query(SQL_UPSERT)
+            .bind(id.0)
+            .bind(photo.original.image_type.content_type())
+            .bind(photo.original.bytes)
+            .bind(photo.thumbnail.bytes)
+            .execute(&self.db_pool)
+            .await
+            .map(|_| ())
+            .map_err(|err: sqlx::Error| Error::Db(err.to_string()))
+    }
+
+    async fn delete_photo(&self, id: ContactId) -> Result<(), Error> {
+        sqlx::query(SQL_DELETE)
+            .bind(id.0)
+            .execute(&self.db_pool)
+            .await
+            .map(|_| ())
+            .map_err(|err: sqlx::Error| Error::Db(err.to_string()))
+    }
+}
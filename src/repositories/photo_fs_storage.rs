@@ -0,0 +1,93 @@
+use std::env;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use tokio::fs;
+
+use crate::models::contact::ContactId;
+use crate::models::errors::Error;
+use crate::models::photo::ContactPhoto;
+use crate::models::photo::ImageType;
+use crate::models::photo::Photo;
+use crate::models::photo::PhotoSize;
+
+use super::photo_storage::PhotoStorage;
+
+const PHOTOS_DIR_KEY: &str = "PHOTOS_DIR";
+const DEFAULT_PHOTOS_DIR: &str = "photos";
+
+/// Stores the photos as files named after the contact, e.g. `42.jpg` and `42.thumbnail.jpg`.
+#[derive(Debug, Clone)]
+pub struct PhotoFsStorage {
+    dir: PathBuf,
+}
+
+impl PhotoFsStorage {
+    pub async fn new() -> Self {
+        let dir: String = env::var(PHOTOS_DIR_KEY).unwrap_or(DEFAULT_PHOTOS_DIR.to_string());
+        fs::create_dir_all(&dir)
+            .await
+            .unwrap_or_else(|_| panic!("Cannot create the photos directory {dir}"));
+        PhotoFsStorage {
+            dir: PathBuf::from(dir),
+        }
+    }
+
+    fn path(&self, id: &ContactId, size: PhotoSize, image_type: ImageType) -> PathBuf {
+        let file_name: String = match size {
+            PhotoSize::Original => format!("{}.{}", id.0, image_type.extension()),
+            PhotoSize::Thumbnail => format!("{}.thumbnail.{}", id.0, image_type.extension()),
+        };
+        self.dir.join(file_name)
+    }
+
+    /// Writes to a temporary file first, so a photo is never read half written.
+    async fn write(&self, path: PathBuf, photo: &Photo) -> Result<(), Error> {
+        let tmp_path: PathBuf = path.with_extension("tmp");
+        fs::write(&tmp_path, &photo.bytes).await?;
+        fs::rename(&tmp_path, &path).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl PhotoStorage for PhotoFsStorage {
+    async fn get_photo(&self, id: ContactId, size: PhotoSize) -> Result<Option<Photo>, Error> {
+        for image_type in [ImageType::Jpeg, ImageType::Png] {
+            match fs::read(self.path(&id, size, image_type)).await {
+                Ok(bytes) => return Ok(Some(Photo { image_type, bytes })),
+                Err(err) if err.kind() == ErrorKind::NotFound => continue,
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(None)
+    }
+
+    async fn put_photo(&self, id: ContactId, photo: ContactPhoto) -> Result<(), Error> {
+        self.delete_photo(id.clone()).await?;
+        let image_type: ImageType = photo.original.image_type;
+        self.write(
+            self.path(&id, PhotoSize::Thumbnail, image_type),
+            &photo.thumbnail,
+        )
+        .await?;
+        self.write(
+            self.path(&id, PhotoSize::Original, image_type),
+            &photo.original,
+        )
+        .await
+    }
+
+    async fn delete_photo(&self, id: ContactId) -> Result<(), Error> {
+        for image_type in [ImageType::Jpeg, ImageType::Png] {
+            for size in [PhotoSize::Original, PhotoSize::Thumbnail] {
+                match fs::remove_file(self.path(&id, size, image_type)).await {
+                    Err(err) if err.kind() != ErrorKind::NotFound => return Err(err.into()),
+                    _ => {}
+                }
+            }
+        }
+        Ok(())
+    }
+}
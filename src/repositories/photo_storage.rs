@@ -0,0 +1,25 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::models::contact::ContactId;
+use crate::models::errors::Error;
+use crate::models::photo::ContactPhoto;
+use crate::models::photo::Photo;
+use crate::models::photo::PhotoSize;
+
+/// The photo storage shared by the routes serving or deleting photos.
+pub type SharedPhotoStorage = Arc<dyn PhotoStorage + Send + Sync>;
+
+/// Contract for the storage of the photos of the contacts, one per contact.
+#[async_trait]
+pub trait PhotoStorage {
+    /// Returns the given rendition of the photo of a contact, if any, otherwise None.
+    async fn get_photo(&self, id: ContactId, size: PhotoSize) -> Result<Option<Photo>, Error>;
+
+    /// Stores the photo of a contact, replacing the existing one. Doesn't return anything.
+    async fn put_photo(&self, id: ContactId, photo: ContactPhoto) -> Result<(), Error>;
+
+    /// Deletes the photo of a contact. Doesn't return anything. Safe for no-ops.
+    async fn delete_photo(&self, id: ContactId) -> Result<(), Error>;
+}
@@ -0,0 +1,27 @@
+use async_trait::async_trait;
+
+use crate::models::contact::ContactId;
+use crate::models::errors::Error;
+use crate::models::relation::NewRelation;
+use crate::models::relation::Relation;
+use crate::models::relation::RelationId;
+
+/// Contract for a repository of the relations between contacts.
+/// Implemented by the contacts repositories, as the relations are deleted along with either of their contacts.
+#[async_trait]
+pub trait RelationsRepository {
+    /// Returns the relations from or to any of the given contacts, ordered by id.
+    async fn get_relations(&self, contact_ids: &[ContactId]) -> Result<Vec<Relation>, Error>;
+
+    /// Adds a relation from a contact to another one. Returns the new relation.
+    /// Fails with `Error::Conflict` if the contacts are already related the same way.
+    async fn add_relation(
+        &mut self,
+        contact_id: ContactId,
+        new_relation: NewRelation,
+    ) -> Result<Relation, Error>;
+
+    /// Deletes a relation from or to a contact. Returns `Error::NotFound` if there is no such relation.
+    async fn delete_relation(&mut self, contact_id: ContactId, id: RelationId)
+        -> Result<(), Error>;
+}